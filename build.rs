@@ -50,5 +50,7 @@ fn main() {
         res.compile().unwrap();
     }
     
-    slint_build::compile("ui/app-window.slint").unwrap();
+    // @tr strings are looked up in translations/<lang>/LC_MESSAGES/gamemode.po and bundled into the exe
+    let config = slint_build::CompilerConfiguration::new().with_bundled_translations("translations");
+    slint_build::compile_with_config("ui/app-window.slint", config).unwrap();
 }
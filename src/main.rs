@@ -13,10 +13,58 @@ mod services;
 use services::{
     settings::SettingsService,
     options::GameModeOptions,
-    gamemode::GameModeService,
+    gamemode::{GameModeService, GameModeState},
+    process::{ProcessService, TerminatedProcess},
+    power::PowerService,
+    detector::GameDetector,
+    library::LibraryService,
     update::UpdateService,
     revi_tweaks::ReviTweaksService,
+    custom_tweaks::CustomTweaksService,
     advanced_modules::AdvancedModulesService,
+    game_config::GameConfigService,
+    network::NetworkService,
+    gpu::GpuService,
+    capabilities::Capabilities,
+    recommend,
+    latency::{LatencyMonitor, LatencyReport},
+    dpc::{self, DpcMonitor, DpcReport},
+    thermal::{ThermalMonitor, ThrottleCause, ThrottleWarning},
+    migration::MigrationService,
+    preset::PresetService,
+    drift::DriftService,
+    journal::{self, SessionJournal},
+    hotkey,
+    clipboard,
+    specs::{SpecsFormat, SystemSpecs},
+    modified::{self, ModifiedItem},
+    status,
+    i18n,
+    theme,
+    profiles,
+    schedule,
+    session,
+    notify,
+    obs::ObsService,
+    tray::{self, TrayCommand, TrayStatus},
+    instance::{self, InstanceCommand},
+    protocol,
+    startup,
+    shutdown,
+    lock_memory,
+    interrupts,
+    bcdedit,
+    affinity,
+    conflicts,
+    vbs,
+    write_audit,
+    tasks,
+    crash,
+    connections,
+    bandwidth,
+    trim,
+    frontend::FrontendService,
+    controller::{self, ControllerTrigger},
 };
 
 slint::include_modules!();
@@ -37,49 +85,6 @@ fn is_process_running(pid: u32) -> bool {
 }
 
 
-/// Fetch GPU info using DXGI for accurate VRAM reporting
-fn get_gpu_info() -> String {
-    use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1};
-
-
-    unsafe {
-        let factory: Result<IDXGIFactory1, _> = CreateDXGIFactory1();
-        if let Ok(factory) = factory {
-            let mut i = 0;
-            let mut gpus = Vec::new();
-
-            while let Ok(adapter) = factory.EnumAdapters1(i) {
-                if let Ok(desc) = adapter.GetDesc1() {
-                    let name = String::from_utf16_lossy(&desc.Description)
-                        .trim_matches('\0')
-                        .to_string();
-                    
-                    let vram_gb = desc.DedicatedVideoMemory as f64 / 1073741824.0;
-                    
-                    // Filter out Microsoft Basic Render Driver unless it's the only one
-                    // and only show if it has some VRAM or meaningful name
-                    if name != "Microsoft Basic Render Driver" || vram_gb > 0.0 {
-                        if vram_gb > 0.1 {
-                             gpus.push(format!("{} ({:.1} GB)", name, vram_gb));
-                        } else {
-                             gpus.push(name);
-                        }
-                    }
-                }
-                i += 1;
-            }
-
-            if gpus.is_empty() {
-                 "Unknown".to_string()
-            } else {
-                 gpus.join("\n       ")
-            }
-        } else {
-            "Unknown (DXGI Init Failed)".to_string()
-        }
-    }
-}
-
 /// Enable Windows 11 Efficiency Mode (EcoQoS)
 /// Enable Windows 11 Efficiency Mode (EcoQoS)
 fn enable_efficiency_mode() {
@@ -121,13 +126,704 @@ fn trim_own_memory() {
     }
 }
 
+/// Show permanent tweaks and reboot-pending reverts that remain after exit, before anything is restored
+/// Returns None if the user cancelled the exit, else the items to revert once the session has ended
+fn confirm_restore_obligations(
+    gamemode: &Mutex<GameModeService>,
+    advanced_modules: &AdvancedModulesService,
+) -> Option<Vec<ModifiedItem>> {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNOCANCEL, MB_ICONWARNING, IDYES, IDCANCEL};
+    use windows::core::HSTRING;
+
+    // Everything that outlives the session; "Revert and exit" undoes the revertable ones
+    let items: Vec<ModifiedItem> = match gamemode.lock() {
+        Ok(svc) => modified::collect(&svc, advanced_modules).into_iter().filter(|item| !item.session).collect(),
+        Err(_) => modified::permanent(advanced_modules),
+    };
+    if items.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut msg = tr!("These changes will remain on your system after exit:\n\n");
+    for item in &items {
+        msg.push_str(&format!("  - {}\n", item.label));
+    }
+    msg.push_str(&tr!("\nYes = Revert and exit\nNo = Exit and keep changes\nCancel = Stay open"));
+
+    let result = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Before you exit")), MB_YESNOCANCEL | MB_ICONWARNING)
+    };
+
+    match result {
+        IDCANCEL => None,
+        IDYES => Some(items.into_iter().filter(|item| item.revertable).collect()),
+        _ => Some(Vec::new()),
+    }
+}
+
+/// "Revert and exit": undo the permanent items picked in `confirm_restore_obligations`
+fn revert_restore_obligations(
+    items: &[ModifiedItem],
+    settings: &Arc<Mutex<services::settings::AppSettings>>,
+    settings_service: &SettingsService,
+    gamemode: &Mutex<GameModeService>,
+    advanced_modules: &AdvancedModulesService,
+    ui_weak: &slint::Weak<AppWindow>,
+) {
+    let mut reverted = Vec::new();
+    if let Ok(svc) = gamemode.lock() {
+        for item in items {
+            if modified::revert(&item.id, &svc, advanced_modules) {
+                reverted.push(item.id.as_str());
+            } else {
+                log_line!("[Exit] Could not revert: {}", item.label);
+            }
+        }
+    }
+
+    // Permanent toggles are mirrored in settings and the UI
+    if reverted.contains(&"perm.mpo") {
+        let mut guard = settings.lock().unwrap();
+        guard.disable_mpo = false;
+        settings_service.save(&guard);
+        drop(guard);
+        let _ = ui_weak.upgrade_in_event_loop(|ui| {
+            let mut s = ui.get_settings();
+            s.disable_mpo = false;
+            ui.set_settings(s);
+        });
+    }
+    if reverted.contains(&"perm.autotuning") {
+        let _ = ui_weak.upgrade_in_event_loop(|ui| ui.set_bufferbloat_active(false));
+    }
+    if !reverted.is_empty() {
+        DriftService::note_permanent_change();
+    }
+}
+
+/// Start the watchdog helper (this exe with --watchdog <pid>) once per app run
+/// It is armed by the session journal: the journal exists only while a session is applied
+fn arm_watchdog() {
+    use std::os::windows::process::CommandExt;
+    static SPAWNED: AtomicBool = AtomicBool::new(false);
+
+    if SPAWNED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        // The watchdog reads the session journal, so it has to look in the same folder
+        let portable = services::settings::is_portable().then_some("--portable");
+        let _ = std::process::Command::new(exe)
+            .args(["--watchdog", &std::process::id().to_string()])
+            .args(portable)
+            .creation_flags(0x08000000)
+            .spawn();
+    }
+}
+
+/// Wait for the app to exit; if it died with a session applied, revert it from the journal
+fn run_watchdog(parent_pid: u32) {
+    use windows::Win32::System::Threading::{OpenProcess, WaitForSingleObject, INFINITE, PROCESS_SYNCHRONIZE};
+    use windows::Win32::Foundation::CloseHandle;
+
+    unsafe {
+        let Ok(handle) = OpenProcess(PROCESS_SYNCHRONIZE, false, parent_pid) else { return };
+        WaitForSingleObject(handle, INFINITE);
+        let _ = CloseHandle(handle);
+    }
+
+    if let Some(session) = journal::read() {
+        if session.app_pid == parent_pid {
+            journal::recover(&session);
+        }
+    }
+}
+
+/// Start the game given after --launch (Steam's %command%: exe path, then its arguments)
+fn launch_game(command: &[String]) -> Option<std::process::Child> {
+    let (program, args) = command.split_first()?;
+    match std::process::Command::new(program).args(args).spawn() {
+        Ok(child) => {
+            log_line!("[Launch] Started {} (PID {})", program, child.id());
+            Some(child)
+        }
+        Err(e) => {
+            log_line!("[Launch] Could not start {}: {}", program, e);
+            notify::attention(&tr!("Game not started"), &tr!("{} could not be started: {}", program, e));
+            None
+        }
+    }
+}
+
+/// Carry out a command from a later instance or a xillygamemode:// link
+fn run_instance_command(ui: &AppWindow, command: InstanceCommand, monitored_pid: &AtomicU32, is_active: &AtomicBool) {
+    match command {
+        InstanceCommand::Show => {
+            let _ = ui.show();
+            ui.window().set_minimized(false);
+        }
+        InstanceCommand::Launch { pid } => start_launch_session(ui, pid, monitored_pid, is_active),
+        InstanceCommand::Enable { profile } => {
+            if is_active.load(Ordering::SeqCst) {
+                log_line!("[Instance] Game mode already active, enable ignored");
+                return;
+            }
+            if let Some(name) = profile {
+                use slint::Model;
+                match ui.get_profile_names().iter().find(|p| p.eq_ignore_ascii_case(&name)) {
+                    // Only this session runs with it; the saved pick stays as it was
+                    Some(found) => session::set_profile(&found),
+                    None => log_line!("[Instance] Unknown profile '{}', keeping the active one", name),
+                }
+            }
+            ui.invoke_toggle_game_mode(true);
+        }
+        InstanceCommand::Disable => {
+            if is_active.load(Ordering::SeqCst) {
+                ui.invoke_toggle_game_mode(false);
+            }
+        }
+        InstanceCommand::Toggle => ui.invoke_toggle_game_mode(!is_active.load(Ordering::SeqCst)),
+    }
+}
+
+/// Run the session for a game started through --launch: its PID is monitored directly, no fullscreen detection
+fn start_launch_session(ui: &AppWindow, pid: u32, monitored_pid: &AtomicU32, is_active: &AtomicBool) {
+    if is_active.load(Ordering::SeqCst) {
+        ui.invoke_pick_game_window(pid as i32);
+    } else {
+        monitored_pid.store(pid, Ordering::SeqCst);
+        ui.invoke_toggle_game_mode(true);
+    }
+}
+
+/// Write the specs report to Documents\XillyGameMode-Specs.<txt|md|json>
+fn save_specs_report(report: &str, format: SpecsFormat) -> Option<std::path::PathBuf> {
+    let path = dirs::document_dir()
+        .or_else(dirs::desktop_dir)?
+        .join(format!("XillyGameMode-Specs.{}", format.extension()));
+    std::fs::write(&path, report).ok()?;
+    log_line!("[Specs] Report saved to {}", path.display());
+    Some(path)
+}
+
+/// Collect the modified-state list and show it in the Modified State page
+fn push_modified_items(
+    ui_weak: &slint::Weak<AppWindow>,
+    gamemode: &Arc<Mutex<GameModeService>>,
+    advanced: &AdvancedModulesService,
+) {
+    let items = match gamemode.lock() {
+        Ok(svc) => modified::collect(&svc, advanced),
+        Err(_) => Vec::new(),
+    };
+    
+    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+        let entries: Vec<ModifiedEntry> = items.into_iter()
+            .map(|item| ModifiedEntry {
+                id: item.id.into(),
+                label: item.label.into(),
+                scope: if item.session { "Session" } else { "Permanent" }.into(),
+                revertable: item.revertable,
+            })
+            .collect();
+        ui.set_modified_items(slint::ModelRc::new(slint::VecModel::from(entries)));
+    });
+}
+
+/// Replace the settings with imported ones (preset file or community preset) and refresh the UI
+fn apply_imported_settings(
+    mut imported: services::settings::AppSettings,
+    current: &services::settings::AppSettings,
+    settings: &Arc<Mutex<services::settings::AppSettings>>,
+    settings_service: &SettingsService,
+    ui_weak: &slint::Weak<AppWindow>,
+) {
+    // Page state is taken from the preset; MPO keeps its current value in settings so the
+    // Settings Changed handler sees the transition and applies it like a manual toggle
+    let page = settings_ui(&imported);
+    let advanced_page = advanced_settings_ui(&imported);
+    let profile_names: Vec<String> = imported.profiles.iter().map(|p| p.name.clone()).collect();
+    imported.disable_mpo = current.disable_mpo;
+    imported.mpo_game_display_only = current.mpo_game_display_only;
+    imported.acknowledged_conflicts = current.acknowledged_conflicts.clone();
+    {
+        let mut guard = settings.lock().unwrap();
+        *guard = imported;
+        settings_service.save(&guard);
+    }
+
+    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+        let names: Vec<slint::SharedString> = profile_names.into_iter().map(Into::into).collect();
+        ui.set_profile_names(slint::ModelRc::new(slint::VecModel::from(names)));
+        ui.set_advanced_settings(advanced_page);
+        ui.set_settings(page.clone());
+        ui.invoke_settings_changed(page);
+    });
+}
+
+/// Read back live values and show them in the System Status page
+fn push_status_rows(
+    ui_weak: &slint::Weak<AppWindow>,
+    gamemode: &Arc<Mutex<GameModeService>>,
+    advanced: &AdvancedModulesService,
+    session_active: bool,
+) {
+    let rows = match gamemode.lock() {
+        Ok(svc) => status::collect(&svc, advanced, session_active),
+        Err(_) => Vec::new(),
+    };
+
+    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+        let entries: Vec<StatusEntry> = rows.into_iter()
+            .map(|row| StatusEntry {
+                label: row.label.into(),
+                current: row.current.into(),
+                expected: row.expected.into(),
+                ok: row.ok,
+            })
+            .collect();
+        ui.set_status_rows(slint::ModelRc::new(slint::VecModel::from(entries)));
+    });
+}
+
+/// Walk the user through changes left by an older version that this one no longer manages
+/// Runs before the UI so reverted values are in place before anything reads them
+fn run_migration_wizard() {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_YESNOCANCEL, MB_ICONINFORMATION, MB_ICONQUESTION, IDYES, IDCANCEL};
+    use windows::core::HSTRING;
+
+    if let Some(pending) = MigrationService::pending() {
+        if !pending.leftovers.is_empty() {
+            let mut msg = tr!(
+                "Updated from v{}. The old version left {} change(s) that this version no longer manages:\n\n",
+                pending.previous_version,
+                pending.leftovers.len()
+            );
+            for leftover in &pending.leftovers {
+                msg.push_str(&format!("  - {}\n", leftover.describe()));
+            }
+            msg.push_str(&tr!("\nReview them one by one now?\nNo = Keep all of them as they are"));
+
+            let review = unsafe {
+                MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Update cleanup")), MB_YESNO | MB_ICONINFORMATION)
+            };
+
+            if review == IDYES {
+                for leftover in &pending.leftovers {
+                    let msg = tr!(
+                        "{}\n\nYes = Undo this change\nNo = Keep it\nCancel = Keep this and all remaining",
+                        leftover.describe()
+                    );
+                    let result = unsafe {
+                        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Update cleanup")), MB_YESNOCANCEL | MB_ICONQUESTION)
+                    };
+                    if result == IDCANCEL {
+                        break;
+                    }
+                    if result == IDYES {
+                        leftover.revert();
+                    }
+                }
+            }
+        }
+    }
+
+    // Kept leftovers are now the user's own settings; don't ask again
+    MigrationService::record_current();
+}
+
+/// Offer to repair session changes still applied outside a session (crash, interrupted restore)
+/// Runs before the UI, after the migration wizard has handled older versions' leftovers
+fn run_drift_check() {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONWARNING, IDYES};
+    use windows::core::HSTRING;
+
+    let drift = DriftService::detect();
+    if !drift.is_empty() {
+        let mut msg = tr!(
+            "{} change(s) from an earlier session are still applied, but no session is running:\n\n",
+            drift.len()
+        );
+        for item in &drift {
+            msg.push_str(&format!("  - {}\n", item.describe()));
+        }
+        msg.push_str(&tr!("\nYes = Repair all (restore the values from before the session)\nNo = Keep them"));
+
+        let repair = unsafe {
+            MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Leftover changes")), MB_YESNO | MB_ICONWARNING)
+        };
+        if repair == IDYES {
+            for item in &drift {
+                item.repair();
+            }
+        }
+    }
+
+    // Kept leftovers are now the user's own settings; don't ask again
+    DriftService::record_baseline();
+}
+
+/// Warn about other optimizers fighting over the same settings and offer to switch off the
+/// overlapping modules; each product is only asked about once. Returns true if settings changed
+fn run_conflict_check(settings: &mut services::settings::AppSettings) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_OK, MB_YESNO, MB_ICONWARNING, IDYES};
+    use windows::core::HSTRING;
+
+    let found: Vec<_> = conflicts::detect()
+        .into_iter()
+        .filter(|c| !settings.acknowledged_conflicts.iter().any(|a| a == c.product))
+        .collect();
+    if found.is_empty() {
+        return false;
+    }
+    for conflict in &found {
+        log_line!("[Conflicts] {} is running", conflict.describe());
+    }
+
+    let mut msg = tr!("Other optimizers are running that manage the same things as Game Mode:\n\n");
+    for conflict in &found {
+        msg.push_str(&format!("  - {}\n", conflict.describe()));
+    }
+    msg.push_str(&tr!("\nTwo tools changing the same settings undo each other's changes and can cause the stutter both try to prevent."));
+
+    let overlapping = found.iter().any(|c| c.overlaps_enabled(&settings.advanced_modules));
+    if overlapping {
+        msg.push_str(&tr!("\n\nYes = Turn off the overlapping Game Mode modules\nNo = Keep both"));
+    }
+    let buttons = if overlapping { MB_YESNO } else { MB_OK };
+    let answer = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Conflicting software")), buttons | MB_ICONWARNING)
+    };
+    if overlapping && answer == IDYES && conflicts::disable_overlapping(&found, &mut settings.advanced_modules) {
+        log_line!("[Conflicts] Overlapping modules turned off");
+    }
+
+    settings.acknowledged_conflicts.extend(found.iter().map(|c| c.product.to_string()));
+    true
+}
+
+/// Settings page state for `settings`
+fn settings_ui(settings: &services::settings::AppSettings) -> AppSettings {
+    AppSettings {
+        suspend_explorer: settings.suspend_explorer,
+        suspend_browsers: settings.suspend_browsers,
+        suspend_launchers: settings.suspend_launchers,
+        firewall_block_background: settings.firewall_block_background,
+        advanced_tweaks: settings.advanced_tweaks,
+        disable_mpo: settings.disable_mpo,
+        mpo_game_display_only: settings.mpo_game_display_only,
+        run_on_startup: settings.run_on_startup,
+        active_profile: settings.active_profile.as_str().into(),
+        auto_activate_profiles: settings.auto_activate_profiles,
+    }
+}
+
+/// Colors and density for `settings`
+fn apply_theme(ui: &AppWindow, settings: &services::settings::AppSettings) {
+    let resolved = theme::resolve(settings.theme, &settings.accent_color);
+    let (r, g, b) = resolved.accent;
+    let ui_theme = ui.global::<Theme>();
+    ui_theme.set_dark(resolved.dark);
+    ui_theme.set_accent(slint::Color::from_rgb_u8(r, g, b));
+    ui_theme.set_compact(settings.compact_window);
+}
+
+/// Advanced Modules popup state for `settings` (modules plus the active profile's priority separation)
+fn advanced_settings_ui(settings: &services::settings::AppSettings) -> AdvancedSettings {
+    let modules = &settings.advanced_modules;
+    AdvancedSettings {
+        disable_core_parking: modules.disable_core_parking,
+        enable_large_pages: modules.enable_large_pages,
+        lock_memory_privilege: modules.lock_memory_privilege,
+        raise_game_working_set: modules.raise_game_working_set,
+        pin_device_interrupts: modules.pin_device_interrupts,
+        mmcss_priority_boost: modules.mmcss_priority_boost,
+        enable_hags: modules.enable_hags,
+        process_idle_demotion: modules.process_idle_demotion,
+        lower_bufferbloat: modules.lower_bufferbloat,
+        disable_ecn: modules.disable_ecn,
+        disable_rsc: modules.disable_rsc,
+        flush_dns_cache: modules.flush_dns_cache,
+        dns_override: modules.dns_override,
+        dns_servers: modules.dns_servers.join(", ").into(),
+        tune_network_adapter: modules.tune_network_adapter,
+        dscp_tagging: modules.dscp_tagging,
+        target_gpu: modules.target_gpu.as_str().into(),
+        latency_monitor: modules.latency_monitor,
+        latency_target: modules.latency_target.as_str().into(),
+        latency_spike_ms: modules.latency_spike_ms as i32,
+        bandwidth_watch: modules.bandwidth_watch,
+        dpc_monitor: modules.dpc_monitor,
+        thermal_monitor: modules.thermal_monitor,
+        demote_telemetry: modules.demote_telemetry,
+        demote_sync: modules.demote_sync,
+        demote_search: modules.demote_search,
+        demote_security: modules.demote_security,
+        demote_background: modules.demote_background,
+        demote_custom: modules.demote_custom.join(", ").into(),
+        pause_windows_update: modules.pause_windows_update,
+        defender_game_exclusion: modules.defender_game_exclusion,
+        windows_game_mode_coop: modules.windows_game_mode_coop,
+        kill_process_trees: modules.kill_process_trees,
+        flush_working_sets: modules.flush_working_sets,
+        flush_exclusions: modules.flush_exclusions.join(", ").into(),
+        periodic_trim: modules.periodic_trim,
+        disable_usb_suspend: modules.disable_usb_suspend,
+        disable_pcie_aspm: modules.disable_pcie_aspm,
+        disable_mouse_acceleration: modules.disable_mouse_acceleration,
+        raise_mouse_queue_size: modules.raise_mouse_queue_size,
+        suppress_accessibility_hotkeys: modules.suppress_accessibility_hotkeys,
+        block_windows_key: modules.block_windows_key,
+        disable_audio_enhancements: modules.disable_audio_enhancements,
+        disable_comms_ducking: modules.disable_comms_ducking,
+        enable_hdr: modules.enable_hdr,
+        enable_auto_hdr: modules.enable_auto_hdr,
+        suspend_night_light: modules.suspend_night_light,
+        best_performance_visuals: modules.best_performance_visuals,
+        disable_secondary_displays: modules.disable_secondary_displays,
+        disable_game_dvr: modules.disable_game_dvr,
+        priority_separation: settings.effective_priority_separation() as i32,
+    }
+}
+
+/// Ask before adding SeLockMemoryPrivilege to the signed-in user's account
+fn confirm_lock_memory_grant() -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONQUESTION, IDYES};
+    use windows::core::HSTRING;
+
+    let msg = tr!(
+        "Large pages need the \"Lock pages in memory\" right, which Windows does not give to user accounts by default.\n\n\
+        Add it to your account now? It is removed again when you turn this option off.\n\
+        Games can only use it after you sign out and back in."
+    );
+    let answer = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Lock Pages in Memory")), MB_YESNO | MB_ICONQUESTION)
+    };
+    answer == IDYES
+}
+
+/// Warn before switching the GPU and network adapter to MSI mode
+fn confirm_msi_mode(devices: &[interrupts::InterruptDevice]) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONWARNING, MB_DEFBUTTON2, IDYES};
+    use windows::core::HSTRING;
+
+    let mut msg = tr!(
+        "This switches the following devices to message signaled interrupts (MSI) and, if enabled, pins their interrupts to fixed cores:\n\n"
+    );
+    for device in devices {
+        msg.push_str(&format!("  - {}\n", device.name));
+    }
+    msg.push_str(&tr!(
+        "\nA driver that does not support MSI can fail to start, leaving you without display output or network until the change is reverted (from Safe Mode if necessary). \
+        The original values are backed up and restored with the same button.\n\nThe change applies after a restart. Continue?"
+    ));
+    let answer = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("MSI Mode")), MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2)
+    };
+    answer == IDYES
+}
+
+/// Warn before changing the boot configuration's timer settings
+fn confirm_platform_timer() -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONWARNING, MB_DEFBUTTON2, IDYES};
+    use windows::core::HSTRING;
+
+    let msg = tr!(
+        "This changes the boot configuration (bcdedit) for the current Windows installation:\n\n  \
+        - disabledynamictick yes: the timer keeps ticking while idle\n  \
+        - useplatformtick yes: ticks come from the hardware timer\n  \
+        - useplatformclock removed: HPET is not forced as the clock\n\n\
+        Results depend on the hardware: some systems get smoother frame times, others higher idle power use or none of the benefit. \
+        If BitLocker is on, Windows may ask for the recovery key at the next boot.\n\n\
+        The current values are recorded and put back with the same button. The change applies after a restart. Continue?"
+    );
+    let answer = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Platform Timer")), MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2)
+    };
+    answer == IDYES
+}
+
+/// Warn before turning off Memory Integrity and VBS, showing what is running now
+fn confirm_core_isolation(state: &vbs::VbsState) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONWARNING, MB_DEFBUTTON2, IDYES};
+    use windows::core::HSTRING;
+
+    let msg = tr!(
+        "Current state: {}\n\n\
+        This turns off Memory Integrity (HVCI) and virtualization-based security for the next boot. \
+        Games can gain a few percent, but Windows loses protection against malicious and vulnerable drivers, \
+        and some anti-cheats or Windows 11 features may require it.\n\n\
+        The current values are recorded and put back with the same button. The change applies after a restart. Continue?",
+        state.describe()
+    );
+    let answer = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Core Isolation")), MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2)
+    };
+    answer == IDYES
+}
+
+/// Show the core isolation state read from WMI and whether the app turned it off
+fn refresh_core_isolation(ui_weak: &slint::Weak<AppWindow>) {
+    let text = vbs::state().map(|s| s.describe()).unwrap_or_default();
+    let disabled = vbs::is_disabled_by_app();
+    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+        ui.set_vbs_state_text(text.into());
+        ui.set_vbs_disabled(disabled);
+    });
+}
+
+/// Point at the driver behind the session's longest DPC/ISR when it was long enough to stutter
+fn notify_driver_latency(report: &DpcReport) {
+    if let Some((driver, us)) = report.worst().filter(|(_, us)| *us >= dpc::LONG_EXECUTION_US) {
+        notify::attention(
+            &tr!("Driver latency detected"),
+            &tr!("{} ran for {} µs at once during the session. See the log for the worst drivers.", driver, us),
+        );
+    }
+}
+
+/// Ping summary of the session that just ended
+fn notify_latency(report: &LatencyReport) {
+    if report.sent == 0 {
+        return;
+    }
+    notify::info(
+        &tr!("Session ping to {}", report.endpoint),
+        &tr!(
+            "{} ms average, {} ms worst, {} ms jitter, {}% loss, {} spikes",
+            format!("{:.0}", report.avg_ms),
+            report.max_ms,
+            format!("{:.1}", report.jitter_ms),
+            format!("{:.1}", report.loss_percent()),
+            report.spikes,
+        ),
+    );
+}
+
+/// Apps the session closed are not restarted: name them once it ends
+fn notify_closed_apps(closed: &[TerminatedProcess]) {
+    let mut names: Vec<&str> = Vec::new();
+    for process in closed {
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(&process.name)) {
+            names.push(&process.name);
+        }
+    }
+    if !names.is_empty() {
+        notify::info(&tr!("Apps closed during the session"), &tr!("Not restarted: {}", names.join(", ")));
+    }
+}
+
+/// The CPU stayed throttled for a while: point at the hardware rather than the settings
+fn notify_throttling(warning: ThrottleWarning) {
+    let temperature = warning.temperature_c.map(|c| format!(" ({:.0} °C)", c)).unwrap_or_default();
+    match warning.cause {
+        ThrottleCause::Thermal => notify::attention(
+            &tr!("CPU thermal throttling"),
+            &tr!("The CPU is being slowed down to cool off{}, clock limited to {}%. Check cooling and airflow; no setting can fix this.", temperature, warning.clock_percent),
+        ),
+        ThrottleCause::Clock => notify::attention(
+            &tr!("CPU clock limited"),
+            &tr!("The CPU clock has been held at {}% of its maximum for 30 seconds{}. Check the power plan, power limits or laptop power mode.", warning.clock_percent, temperature),
+        ),
+    }
+}
+
+/// Ask to restart now for changes that only apply at boot
+fn offer_restart() {
+    use std::os::windows::process::CommandExt;
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONQUESTION, IDYES};
+    use windows::core::HSTRING;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let answer = unsafe {
+        MessageBoxW(
+            None,
+            &HSTRING::from(tr!("The change takes effect after a restart. Restart now?")),
+            &HSTRING::from(tr!("Restart required")),
+            MB_YESNO | MB_ICONQUESTION,
+        )
+    };
+    if answer == IDYES {
+        // Shutdown handling restores an active session before Windows ends the process
+        let _ = std::process::Command::new("shutdown")
+            .args(["/r", "/t", "0"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn();
+    }
+}
+
+/// First run: show detected hardware and offer the recommended preset
+/// Returns the settings to start with and whether the user wants to customize them
+fn run_first_run_wizard() -> (services::settings::AppSettings, bool) {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONINFORMATION, IDYES};
+    use windows::core::HSTRING;
+
+    let recommendation = recommend::recommend(&services::settings::AppSettings::default());
+    let msg = tr!(
+        "Welcome to Xilly Game Mode!\n\nDetected: {}\n\nRecommended for this PC:\n{}\n\n\
+         Yes = Use the recommended settings\nNo = Start with defaults and choose modules yourself",
+        recommendation.hardware.join(", "),
+        recommendation.changes.iter().map(|c| format!("  - {}", c)).collect::<Vec<_>>().join("\n"),
+    );
+    let accept = unsafe {
+        MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("First-time setup")), MB_YESNO | MB_ICONINFORMATION)
+    };
+
+    if accept == IDYES {
+        log_line!("[Setup] Recommended settings accepted");
+        (recommendation.settings, false)
+    } else {
+        log_line!("[Setup] Recommended settings declined");
+        (services::settings::AppSettings::default(), true)
+    }
+}
+
 fn main() -> Result<(), slint::PlatformError> {
+    // Watchdog helper: no UI, only waits for the app to exit
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--watchdog") {
+        if let Some(parent_pid) = args.get(pos + 1).and_then(|p| p.parse().ok()) {
+            run_watchdog(parent_pid);
+        }
+        return Ok(());
+    }
+
+    // Steam launch options: gamemode.exe --launch %command%
+    let launch_command = args.iter().position(|a| a == "--launch").map(|pos| args[pos + 1..].to_vec());
+    // xillygamemode://... links (browser dashboards, Playnite, Stream Deck)
+    let url_command = args.iter().skip(1).find_map(|a| protocol::parse(a));
+    let is_primary = instance::acquire();
+    let mut launched_game = None;
+    if let Some(command) = launch_command {
+        let Some(mut child) = launch_game(&command) else { return Ok(()) };
+        // The running instance runs the session; this one stays alive as the process Steam tracks
+        if !is_primary && instance::send(&InstanceCommand::Launch { pid: child.id() }) {
+            let _ = child.wait();
+            return Ok(());
+        }
+        launched_game = Some(child);
+    } else if !is_primary && instance::send(&url_command.clone().unwrap_or(InstanceCommand::Show)) {
+        return Ok(());
+    }
+    if is_primary {
+        protocol::register();
+        // Re-register autostart so a moved exe or a changed startup_method takes effect
+        let startup_settings = SettingsService::new().load();
+        if startup_settings.run_on_startup {
+            tasks::spawn("startup-apply", move |_| startup::apply(true, startup_settings.startup_method));
+        }
+    }
+
+    // Minidump + log on a crash, and restore the session before the process dies
+    crash::install();
+
     // Enable Efficiency Mode
     enable_efficiency_mode();
 
     // Periodically trim memory to keep footprint minimal (every 5s)
-    thread::spawn(|| {
-        loop {
+    tasks::spawn("trim-own-memory", |cancel| {
+        while !cancel.is_cancelled() {
             trim_own_memory();
             thread::sleep(std::time::Duration::from_secs(5));
         }
@@ -137,41 +833,130 @@ fn main() -> Result<(), slint::PlatformError> {
     std::env::set_var("SLINT_FONT_HINTING", "none");
     std::env::set_var("SLINT_ENABLE_SUBPIXEL_RENDERING", "1");
     
+    // UI language ("language" in settings.json, otherwise Windows'); the startup dialogs below already use it
+    i18n::init(&SettingsService::new().load().language);
+
+    // Clean up after older versions before anything is applied
+    run_migration_wizard();
+
+    // A previous run that died mid-session (e.g. power loss, watchdog gone too) left its power plan applied
+    if let Some(marker) = PowerService::stale_marker() {
+        if !is_process_running(marker.app_pid) {
+            PowerService::restore_marker(&marker);
+        }
+    }
+
+    // Session changes a crash or interrupted restore left behind
+    run_drift_check();
+
+    // Started by the update script after it could not swap in the new exe (the old one was put back)
+    if args.iter().any(|a| a == "--update-failed") {
+        notify::attention(&tr!("Update not installed"), &tr!("The new version could not replace this one, so the previous version was kept. Try updating again later."));
+    }
+
+    // Track focused windows so game detection doesn't have to scan every process
+    GameDetector::start_foreground_hook();
+
     let ui = AppWindow::new()?;
     let ui_handle = ui.as_weak();
+    i18n::select_ui_language();
 
     // 1. Load Settings
     let settings_service = SettingsService::new();
-    let loaded_settings = settings_service.load();
+    let (mut loaded_settings, customize_modules) = if settings_service.exists() {
+        (settings_service.load(), false)
+    } else {
+        let (settings, customize) = run_first_run_wizard();
+        settings_service.save(&settings);
+        (settings, customize)
+    };
+    // Other optimizers managing core parking / priorities / the standby list
+    if run_conflict_check(&mut loaded_settings) {
+        settings_service.save(&loaded_settings);
+    }
     let app_settings = Arc::new(Mutex::new(loaded_settings.clone()));
 
     // 2. Initialize UI State from Settings (including advanced_tweaks and disable_mpo)
-    let initial_settings_ui = AppSettings {
-        suspend_explorer: loaded_settings.suspend_explorer,
-        suspend_browsers: loaded_settings.suspend_browsers,
-        suspend_launchers: loaded_settings.suspend_launchers,
-        advanced_tweaks: loaded_settings.advanced_tweaks,
-        disable_mpo: loaded_settings.disable_mpo,
-        run_on_startup: loaded_settings.run_on_startup,
-    };
-    ui.set_settings(initial_settings_ui);
+    ui.set_settings(settings_ui(&loaded_settings));
+    
+    let profile_names: Vec<slint::SharedString> = loaded_settings.profiles
+        .iter()
+        .map(|p| p.name.as_str().into())
+        .collect();
+    ui.set_profile_names(slint::ModelRc::new(slint::VecModel::from(profile_names)));
     
     // Initialize Advanced Module Settings
-    let initial_advanced_ui = AdvancedSettings {
-        disable_core_parking: loaded_settings.advanced_modules.disable_core_parking,
-        enable_large_pages: loaded_settings.advanced_modules.enable_large_pages,
-        mmcss_priority_boost: loaded_settings.advanced_modules.mmcss_priority_boost,
-        enable_hags: loaded_settings.advanced_modules.enable_hags,
-        process_idle_demotion: loaded_settings.advanced_modules.process_idle_demotion,
-        lower_bufferbloat: loaded_settings.advanced_modules.lower_bufferbloat,
-    };
-    ui.set_advanced_settings(initial_advanced_ui);
+    ui.set_advanced_settings(advanced_settings_ui(&loaded_settings));
+    // Declined the first-run preset: start on the module list
+    if customize_modules {
+        ui.set_show_advanced_popup(true);
+    }
+
+    // Windows dark/light mode and accent color unless settings.json overrides them, kept in sync with Windows
+    apply_theme(&ui, &loaded_settings);
+    let ui_handle_theme = ui.as_weak();
+    let settings_for_theme = app_settings.clone();
+    theme::watch(move || {
+        let settings = settings_for_theme.lock().unwrap().clone();
+        let _ = ui_handle_theme.upgrade_in_event_loop(move |ui| apply_theme(&ui, &settings));
+    });
     
     // Initialize bufferbloat status from current system state
     ui.set_bufferbloat_active(AdvancedModulesService::get_bufferbloat_status());
     
+    // GPU selection is only offered when more than one discrete GPU is installed
+    let hardware_gpus = GpuService::hardware_adapters();
+    if hardware_gpus.iter().filter(|g| g.is_discrete()).count() > 1 {
+        let names: Vec<slint::SharedString> = std::iter::once(slint::SharedString::from("Auto"))
+            .chain(hardware_gpus.iter().map(|g| slint::SharedString::from(g.name.as_str())))
+            .collect();
+        ui.set_gpu_names(slint::ModelRc::new(slint::VecModel::from(names)));
+    }
+    
+    // Probe hardware capabilities so unsupported modules are shown disabled with a reason
+    let ui_handle_caps = ui.as_weak();
+    tasks::spawn("probe-capabilities", move |_| {
+        let caps = Capabilities::get();
+        let support = ModuleSupport {
+            core_parking: caps.core_parking().unwrap_or_default().into(),
+            large_pages: caps.large_pages().unwrap_or_default().into(),
+            hags: caps.hags().unwrap_or_default().into(),
+            update_pause: caps.update_pause().unwrap_or_default().into(),
+            game_mode_coop: caps.game_mode_coop().unwrap_or_default().into(),
+            auto_hdr: caps.auto_hdr().unwrap_or_default().into(),
+            mpo: caps.mpo().unwrap_or_default().into(),
+        };
+        let _ = ui_handle_caps.upgrade_in_event_loop(move |ui| {
+            ui.set_module_support(support);
+        });
+    });
+    
+    // Detect the adapter carrying the default route (PowerShell, so off the UI thread)
+    let ui_handle_adapter = ui.as_weak();
+    tasks::spawn("detect-adapter", move |_| {
+        let adapter = NetworkService::active_adapter();
+        let _ = ui_handle_adapter.upgrade_in_event_loop(move |ui| {
+            match adapter {
+                Some(a) => {
+                    ui.set_active_adapter_label(a.label().into());
+                    ui.set_active_adapter_wireless(a.is_wireless);
+                }
+                None => ui.set_active_adapter_label("Not connected".into()),
+            }
+        });
+    });
+    
     // Create advanced modules service
     let advanced_modules_service = Arc::new(AdvancedModulesService::new());
+    
+    // Ping/jitter/loss monitor (only runs while game mode is active)
+    let latency_monitor = Arc::new(LatencyMonitor::new());
+
+    // DPC/ISR driver trace (only runs while game mode is active)
+    let dpc_monitor = Arc::new(DpcMonitor::new());
+
+    // CPU temperature/clock limit monitor (only runs while game mode is active)
+    let thermal_monitor = Arc::new(ThermalMonitor::new());
 
     // 3. Window Moving Logic
     let ui_handle_copy = ui_handle.clone();
@@ -209,9 +994,16 @@ fn main() -> Result<(), slint::PlatformError> {
     let is_monitoring_for_thread = is_monitoring.clone();
     let advanced_modules_for_monitor = advanced_modules_service.clone();
     let is_active_for_monitor = is_game_mode_active.clone();
+    let latency_for_monitor = latency_monitor.clone();
+    let dpc_for_monitor = dpc_monitor.clone();
+    let thermal_for_monitor = thermal_monitor.clone();
     
-    thread::spawn(move || {
-        loop {
+    tasks::spawn("game-monitor", move |cancel| {
+        // Start time and exe name of the monitored process, captured while it is alive
+        // so a relaunch or launcher handoff can be recognized after it exits
+        let mut tracked: Option<(u32, Option<u64>, Option<String>)> = None;
+        
+        while !cancel.is_cancelled() {
             // Adaptive sleep: 2s when monitoring, 5s when idle to save resources
             let sleep_secs = if is_monitoring_for_thread.load(Ordering::Relaxed) { 2 } else { 5 };
             thread::sleep(std::time::Duration::from_secs(sleep_secs));
@@ -225,64 +1017,292 @@ fn main() -> Result<(), slint::PlatformError> {
                 continue;
             }
             
+            if tracked.as_ref().map(|t| t.0) != Some(pid) && is_process_running(pid) {
+                let exe_name = ProcessService::executable_path(pid).and_then(|path| {
+                    std::path::Path::new(&path).file_stem().map(|s| s.to_string_lossy().into_owned())
+                });
+                tracked = Some((pid, ProcessService::process_start_time(pid), exe_name));
+            }
+            
             if !is_process_running(pid) {
-                is_monitoring_for_thread.store(false, Ordering::Release);
-                monitored_pid_for_thread.store(0, Ordering::Release);
-                
-                // Extract settings once, avoid repeated clones
-                let (options, advanced, advanced_modules) = {
+                // Non-game workloads run as a chain of processes (cl -> link), follow the next one
+                let (next_trigger, grace_secs) = {
                     let guard = settings_for_monitor.lock().unwrap();
-                    (
-                        GameModeOptions {
-                            suspend_explorer: guard.suspend_explorer,
-                            suspend_browsers: guard.suspend_browsers,
-                            suspend_launchers: guard.suspend_launchers,
-                            isolate_network: guard.isolate_network,
-                        },
-                        guard.advanced_tweaks,
-                        guard.advanced_modules.clone(),
-                    )
+                    let next = profiles::find(&guard.profiles, &guard.session_profile())
+                        .filter(|p| !p.detect_fullscreen)
+                        .and_then(|p| p.running_trigger());
+                    (next, guard.relaunch_grace_secs)
                 };
+                if let Some(next_pid) = next_trigger {
+                    monitored_pid_for_thread.store(next_pid, Ordering::Release);
+                    continue;
+                }
                 
-                if let Ok(svc) = gamemode_for_monitor.lock() {
-                    svc.disable_game_mode(&options);
+                // Games relaunch themselves (anti-cheat) or hand off from a launcher:
+                // give the successor a grace window before restoring everything
+                let (start_time, exe_name) = match tracked.take() {
+                    Some((tracked_pid, start, name)) if tracked_pid == pid => (start, name),
+                    _ => (None, None),
+                };
+                let mut successor = None;
+                for _ in 0..grace_secs {
+                    if !is_monitoring_for_thread.load(Ordering::Acquire) {
+                        break;
+                    }
+                    successor = GameDetector::find_successor(pid, start_time, exe_name.as_deref());
+                    if successor.is_some() {
+                        break;
+                    }
+                    thread::sleep(std::time::Duration::from_secs(1));
+                }
+                if !is_monitoring_for_thread.load(Ordering::Acquire) {
+                    continue;
+                }
+                if let Some(next_pid) = successor {
+                    log_line!("[Monitor] PID {} exited, following successor PID {}", pid, next_pid);
+                    monitored_pid_for_thread.store(next_pid, Ordering::Release);
+                    continue;
                 }
                 
-                // Restore ReviOS tweaks if they were enabled
-                if advanced {
-                    ReviTweaksService::disable();
+                is_monitoring_for_thread.store(false, Ordering::Release);
+                monitored_pid_for_thread.store(0, Ordering::Release);
+                
+                // Restore on the session queue, behind any enable/disable the user started
+                let settings = settings_for_monitor.clone();
+                let service = gamemode_for_monitor.clone();
+                let advanced_svc = advanced_modules_for_monitor.clone();
+                let active_flag = is_active_for_monitor.clone();
+                let latency = latency_for_monitor.clone();
+                let dpc = dpc_for_monitor.clone();
+                let thermal = thermal_for_monitor.clone();
+                let ui_weak = ui_handle_monitor.clone();
+                tasks::session().submit("game exit restore", move |_| {
+                    // Turned off by hand in the meantime
+                    if !active_flag.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    
+                    // Extract settings once, avoid repeated clones
+                    let (options, advanced, advanced_modules) = {
+                        let guard = settings.lock().unwrap();
+                        (
+                            GameModeOptions::from_settings(&guard),
+                            guard.advanced_tweaks,
+                            guard.effective_advanced_modules(),
+                        )
+                    };
+                    
+                    // Read before the disable clears it
+                    let closed = match service.lock() {
+                        Ok(svc) => {
+                            let closed = svc.terminated_processes();
+                            svc.disable_game_mode(&options);
+                            closed
+                        }
+                        Err(_) => Vec::new(),
+                    };
+                    
+                    // Restore ReviOS tweaks if they were enabled
+                    if advanced {
+                        ReviTweaksService::disable();
+                    }
+                    CustomTweaksService::disable();
+                    
+                    // Restore advanced modules
+                    advanced_svc.disable(&advanced_modules);
+                    affinity::restore();
+                    bandwidth::stop();
+                    trim::stop();
+                    if let Some(report) = latency.stop() {
+                        notify_latency(&report);
+                    }
+                    if let Some(report) = dpc.stop() {
+                        notify_driver_latency(&report);
+                    }
+                    thermal.stop();
+                    ObsService::stop();
+                    log_line!("[Session] Ended (game exited)");
+                    session::end();
+                    journal::clear();
+                    notify::info(&tr!("Game exited"), &tr!("System restored"));
+                    notify_closed_apps(&closed);
+                    
+                    // Clear active flag
+                    active_flag.store(false, Ordering::SeqCst);
+                    
+                    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                        ui.set_active(false);
+                        ui.window().show().unwrap();
+                        let _ = ui.window().set_minimized(false);
+                    });
+                });
+            }
+        }
+    });
+
+    // 5b. Profile auto-activation - start a workload profile when its trigger process,
+    // a known (library) game or a game launched from a front-end appears
+    let ui_handle_profiles = ui.as_weak();
+    let settings_for_profiles = app_settings.clone();
+    let is_active_for_profiles = is_game_mode_active.clone();
+    let monitored_pid_for_profiles = monitored_pid.clone();
+    // Set by the controller trigger (5d) while a pad is connected in "detect" mode
+    let controller_armed = Arc::new(AtomicBool::new(false));
+    let controller_armed_for_profiles = controller_armed.clone();
+    
+    tasks::spawn("profile-triggers", move |cancel| {
+        // Installed Steam/Epic/GOG games become known games (detection + auto-activation)
+        let library = LibraryService::scan();
+        GameDetector::set_library_games(LibraryService::executables(&library));
+        
+        // Trigger PID we already activated for, so a manual deactivate is not undone
+        let mut handled_pid = 0u32;
+        while !cancel.is_cancelled() {
+            let armed = controller_armed_for_profiles.load(Ordering::Acquire);
+            thread::sleep(std::time::Duration::from_secs(if armed { 2 } else { 5 }));
+            
+            if is_active_for_profiles.load(Ordering::Acquire) {
+                continue;
+            }
+            
+            // Front-end launches are tied to their game's PID (no fullscreen detection needed)
+            let (triggered, from_frontend) = {
+                let guard = settings_for_profiles.lock().unwrap();
+                let frontend = guard.frontend_integration
+                    .then(FrontendService::launched_game)
+                    .flatten()
+                    .filter(|game| game.pid != handled_pid);
+                if let Some(game) = frontend {
+                    log_line!("[Frontend] {} started {}", game.frontend, game.exe);
+                    (Some((FrontendService::profile_for(&guard.profiles, &game.exe), game.pid)), true)
+                } else if guard.auto_activate_profiles || armed {
+                    let triggered = profiles::detect_triggered(&guard.profiles)
+                        .map(|(p, pid)| (p.name.clone(), pid))
+                        // A known game from the libraries starts the Gaming profile
+                        .or_else(|| GameDetector::find_known_game().map(|pid| (profiles::GAMING_PROFILE.to_string(), pid)));
+                    (triggered, false)
+                } else {
+                    continue;
+                }
+            };
+            
+            let Some((name, pid)) = triggered else { continue };
+            if pid == handled_pid {
+                continue;
+            }
+            handled_pid = pid;
+            
+            log_line!("[Profiles] {} detected, activating {} profile", pid, name);
+            if from_frontend {
+                monitored_pid_for_profiles.store(pid, Ordering::SeqCst);
+            }
+            // Only this session runs with the triggered profile; the saved pick stays as it was
+            session::set_profile(&name);
+            let _ = ui_handle_profiles.upgrade_in_event_loop(|ui| ui.invoke_toggle_game_mode(true));
+        }
+    });
+
+    // 5c. Scheduler - time-window activation and the maximum session timer
+    let ui_handle_schedule = ui.as_weak();
+    let settings_for_schedule = app_settings.clone();
+    let is_active_for_schedule = is_game_mode_active.clone();
+    
+    tasks::spawn("scheduler", move |cancel| {
+        // The current window was already acted on, so a manual deactivate inside it sticks
+        let mut window_handled = false;
+        // The running session was started by the schedule and ends with its window
+        let mut started_by_schedule = false;
+        let mut session_start: Option<std::time::Instant> = None;
+        
+        while !cancel.is_cancelled() {
+            thread::sleep(std::time::Duration::from_secs(30));
+            
+            let active = is_active_for_schedule.load(Ordering::Acquire);
+            let (in_window, max_session_hours) = {
+                let guard = settings_for_schedule.lock().unwrap();
+                (schedule::is_scheduled_now(&guard.schedule), guard.max_session_hours)
+            };
+            
+            if !active {
+                session_start = None;
+                started_by_schedule = false;
+            } else if session_start.is_none() {
+                session_start = Some(std::time::Instant::now());
+            }
+            
+            // Safety timer applies to every session, however it was started
+            let limit = std::time::Duration::from_secs(max_session_hours as u64 * 3600);
+            if max_session_hours > 0 && session_start.is_some_and(|start| start.elapsed() >= limit) {
+                log_line!("[Schedule] Session exceeded {} h, reverting", max_session_hours);
+                session_start = None;
+                started_by_schedule = false;
+                let _ = ui_handle_schedule.upgrade_in_event_loop(|ui| ui.invoke_toggle_game_mode(false));
+                continue;
+            }
+            
+            if in_window {
+                if !window_handled && !active {
+                    log_line!("[Schedule] Activation window started");
+                    started_by_schedule = true;
+                    let _ = ui_handle_schedule.upgrade_in_event_loop(|ui| ui.invoke_toggle_game_mode(true));
+                }
+                window_handled = true;
+            } else {
+                if window_handled && started_by_schedule && active {
+                    log_line!("[Schedule] Activation window ended");
+                    started_by_schedule = false;
+                    let _ = ui_handle_schedule.upgrade_in_event_loop(|ui| ui.invoke_toggle_game_mode(false));
+                }
+                window_handled = false;
+            }
+        }
+    });
+
+    // 5d. Controller trigger - picking up a pad enables game mode or arms detection
+    let ui_handle_controller = ui.as_weak();
+    let settings_for_controller = app_settings.clone();
+    let is_active_for_controller = is_game_mode_active.clone();
+    
+    tasks::spawn("controller-trigger", move |cancel| {
+        // Only a new connection triggers; a pad already connected at startup does not
+        let mut was_connected: Option<bool> = None;
+        while !cancel.is_cancelled() {
+            thread::sleep(std::time::Duration::from_secs(3));
+            
+            let trigger = settings_for_controller.lock().unwrap().controller_trigger;
+            if trigger == ControllerTrigger::Off {
+                controller_armed.store(false, Ordering::Release);
+                was_connected = None;
+                continue;
+            }
+            
+            let connected = controller::connected();
+            controller_armed.store(connected && trigger == ControllerTrigger::Detect, Ordering::Release);
+            if connected && was_connected == Some(false) {
+                log_line!("[Controller] Controller connected ({:?})", trigger);
+                if trigger == ControllerTrigger::Enable && !is_active_for_controller.load(Ordering::Acquire) {
+                    let _ = ui_handle_controller.upgrade_in_event_loop(|ui| ui.invoke_toggle_game_mode(true));
                 }
-                
-                // Restore advanced modules
-                advanced_modules_for_monitor.disable(&advanced_modules);
-                
-                // Clear active flag
-                is_active_for_monitor.store(false, Ordering::SeqCst);
-                
-                let ui_weak = ui_handle_monitor.clone();
-                let _ = ui_weak.upgrade_in_event_loop(move |ui| {
-                    ui.set_active(false);
-                    ui.window().show().unwrap();
-                    let _ = ui.window().set_minimized(false);
-                });
             }
+            was_connected = Some(connected);
         }
     });
 
     // 6. Toggle Game Mode (with ReviOS tweaks support and advanced modules)
     let advanced_modules_toggle = advanced_modules_clone.clone();
     let is_active_for_toggle = is_game_mode_active.clone();
+    let latency_for_toggle = latency_monitor.clone();
+    let dpc_for_toggle = dpc_monitor.clone();
+    let thermal_for_toggle = thermal_monitor.clone();
     ui.on_toggle_game_mode(move |active| {
         let ui_weak = ui_handle.clone();
         let guard = settings_clone.lock().unwrap();
-        let options = GameModeOptions {
-            suspend_explorer: guard.suspend_explorer,
-            suspend_browsers: guard.suspend_browsers,
-            suspend_launchers: guard.suspend_launchers,
-            isolate_network: guard.isolate_network,
-        };
+        let options = GameModeOptions::from_settings(&guard);
+        let profile = profiles::find(&guard.profiles, &guard.session_profile()).cloned();
         let advanced = guard.advanced_tweaks;
-        let advanced_modules = guard.advanced_modules.clone();
+        let advanced_modules = guard.effective_advanced_modules();
+        let game_flags = guard.game_flags.clone();
+        let obs = guard.obs.clone();
         drop(guard);
         
         let service = gm_clone.clone();
@@ -290,26 +1310,183 @@ fn main() -> Result<(), slint::PlatformError> {
         let monitoring_ref = is_monitoring_clone.clone();
         let advanced_svc = advanced_modules_toggle.clone();
         let active_flag = is_active_for_toggle.clone();
+        let latency = latency_for_toggle.clone();
+        let dpc = dpc_for_toggle.clone();
+        let thermal = thermal_for_toggle.clone();
 
-        thread::spawn(move || {
+        // Rapid toggling: whatever is still queued is superseded by this request
+        tasks::session().cancel_pending();
+        tasks::session().submit(if active { "enable" } else { "disable" }, move |cancel| {
+            // on/off/on collapses to the state that was asked for last
+            if active_flag.load(Ordering::SeqCst) == active {
+                session::clear_profile();
+                let _ = ui_weak.upgrade_in_event_loop(move |ui| ui.set_active(active));
+                return;
+            }
+            
             if active {
+                // A session still being restored would refuse the enable: apply nothing at all
+                if !service.lock().is_ok_and(|svc| svc.is_idle()) {
+                    log_line!("[Session] Previous session is still being restored, not enabling");
+                    session::clear_profile();
+                    let _ = ui_weak.upgrade_in_event_loop(|ui| ui.set_active(false));
+                    return;
+                }
+                
+                // A --launch session already knows its game, a non-game profile its trigger process;
+                // the memory flush spares it like a detected game
+                let launched_pid = pid_ref.load(Ordering::SeqCst);
+                let mut options = options;
+                options.workload_pid = if launched_pid != 0 {
+                    Some(launched_pid)
+                } else if !options.detect_game {
+                    profile.as_ref().and_then(|p| p.running_trigger())
+                } else {
+                    None
+                };
+                
                 // Set active flag immediately
                 active_flag.store(true, Ordering::SeqCst);
                 
+                // Idle state to compare against if this session never gets restored
+                DriftService::record_baseline();
+
+                // Every log line until disable carries this session ID
+                session::begin();
+                write_audit::clear();
+                log_line!("[Session] Started ({} profile)", profile.as_ref().map_or(profiles::GAMING_PROFILE, |p| p.name.as_str()));
+                
+                // Watchdog first, then the journal after every step: a crash part-way through
+                // the enable still gets reverted
+                arm_watchdog();
+                let persist = |game_mode: bool, modules: bool| {
+                    let mut session_journal = if game_mode {
+                        service.lock().map(|svc| svc.journal(&options)).unwrap_or_default()
+                    } else {
+                        SessionJournal::default()
+                    };
+                    session_journal.app_pid = std::process::id();
+                    session_journal.revi_state = ReviTweaksService::journal_state();
+                    session_journal.custom_tweaks_state = CustomTweaksService::journal_state();
+                    session_journal.dns_originals = NetworkService::dns_originals();
+                    if modules {
+                        session_journal.advanced_modules = Some(advanced_modules.clone());
+                        session_journal.module_originals = Some(advanced_svc.originals());
+                    }
+                    journal::write(&session_journal);
+                };
+                
                 // Apply ReviOS tweaks FIRST if enabled (saves original state)
                 if advanced {
                     ReviTweaksService::enable();
+                    persist(false, false);
                 }
                 
+                // User modules from tweaks.json, saved and restored the same way
+                CustomTweaksService::enable();
+                persist(false, false);
+                
                 // Apply advanced modules
                 advanced_svc.enable(&advanced_modules);
+                persist(false, true);
                 
-                if let Ok(mut svc) = service.lock() {
-                    svc.enable_game_mode(&options);
-                    if let Some((game_pid, _hwnd)) = svc.detect_game() {
-                        pid_ref.store(game_pid, Ordering::SeqCst);
-                        monitoring_ref.store(true, Ordering::SeqCst);
+                // OBS must survive the kill lists while the integration is on
+                ProcessService::set_protected(ObsService::protected_processes(&obs));
+                
+                let enabled = match service.lock() {
+                    Ok(mut svc) if svc.enable_game_mode(&options) => {
+                        // Games are found by fullscreen window, other workloads by their trigger process
+                        let workload_pid = options.workload_pid.or_else(|| {
+                            options.detect_game.then(|| svc.detect_game()).flatten().map(|(game_pid, _hwnd)| game_pid)
+                        });
+                        if let Some(pid) = workload_pid {
+                            pid_ref.store(pid, Ordering::SeqCst);
+                            monitoring_ref.store(true, Ordering::SeqCst);
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                let cancelled = enabled && cancel.is_cancelled();
+                if !enabled || cancelled {
+                    if cancelled {
+                        // Superseded while enabling (toggled off, panic hotkey): undo instead of persisting or announcing
+                        log_line!("[Session] Enable was cancelled, rolling back");
+                        monitoring_ref.store(false, Ordering::SeqCst);
+                        pid_ref.store(0, Ordering::SeqCst);
+                        if let Ok(svc) = service.lock() {
+                            svc.disable_game_mode(&options);
+                        }
+                    } else {
+                        // Refused (a session is still running or being restored): undo this job's tweaks
+                        log_line!("[Session] Game mode did not start, reverting the tweaks applied so far");
                     }
+                    advanced_svc.disable(&advanced_modules);
+                    CustomTweaksService::disable();
+                    if advanced {
+                        ReviTweaksService::disable();
+                    }
+                    ProcessService::set_protected(&[]);
+                    journal::clear();
+                    session::end();
+                    active_flag.store(false, Ordering::SeqCst);
+                    let _ = ui_weak.upgrade_in_event_loop(|ui| ui.set_active(false));
+                    return;
+                }
+                persist(true, true);
+                
+                let game_pid = pid_ref.load(Ordering::SeqCst);
+                if game_pid != 0 {
+                    advanced_svc.enable_for_game(&advanced_modules, game_pid);
+                    GameConfigService::apply_for_pid(&game_flags, game_pid);
+                    persist(true, true);
+                }
+                ObsService::start(&obs);
+                // Already toggled off again: the queued disable follows, don't announce the session
+                match (game_pid != 0).then(|| ProcessService::executable_name(game_pid)).flatten() {
+                    _ if cancel.is_cancelled() => {}
+                    Some(game) => notify::info(&tr!("Game detected: {}", game), &tr!("Game Mode enabled")),
+                    None if options.detect_game => notify::info(&tr!("Game Mode enabled"), &tr!("No game detected yet. Use Pick Game to choose one.")),
+                    None => notify::info(
+                        &tr!("Game Mode enabled"),
+                        &tr!("{} profile", profile.as_ref().map_or(profiles::GAMING_PROFILE, |p| p.name.as_str())),
+                    ),
+                }
+                
+                // Ping monitor targets the game's server when one was detected
+                if advanced_modules.latency_monitor {
+                    let ui_latency = ui_weak.clone();
+                    latency.start(
+                        &advanced_modules.latency_target,
+                        game_pid,
+                        advanced_modules.latency_spike_ms,
+                        move |sample| {
+                            let text = match sample.rtt_ms {
+                                Some(ms) => format!("Ping {} ms · Jitter {:.0} ms · Loss {:.0}%", ms, sample.jitter_ms, sample.loss_percent),
+                                None => format!("Ping timeout · Loss {:.0}%", sample.loss_percent),
+                            };
+                            let _ = ui_latency.upgrade_in_event_loop(move |ui| {
+                                ui.set_latency_text(text.into());
+                                ui.set_latency_spike(sample.is_spike || sample.rtt_ms.is_none());
+                            });
+                        },
+                    );
+                }
+                if advanced_modules.dpc_monitor {
+                    dpc.start();
+                }
+                if advanced_modules.bandwidth_watch {
+                    bandwidth::start(pid_ref.clone(), advanced_modules.bandwidth_alert_kbps);
+                }
+                if advanced_modules.periodic_trim {
+                    trim::start(
+                        pid_ref.clone(),
+                        advanced_modules.periodic_trim_minutes,
+                        advanced_modules.flush_exclusions.clone(),
+                    );
+                }
+                if advanced_modules.thermal_monitor {
+                    thermal.start(notify_throttling);
                 }
                 let _ = ui_weak.upgrade_in_event_loop(move |ui| {
                     ui.set_active(true);
@@ -318,17 +1495,39 @@ fn main() -> Result<(), slint::PlatformError> {
                 monitoring_ref.store(false, Ordering::SeqCst);
                 pid_ref.store(0, Ordering::SeqCst);
                 
-                if let Ok(svc) = service.lock() {
-                    svc.disable_game_mode(&options);
-                }
+                // Read before the disable clears it
+                let closed = match service.lock() {
+                    Ok(svc) => {
+                        let closed = svc.terminated_processes();
+                        svc.disable_game_mode(&options);
+                        closed
+                    }
+                    Err(_) => Vec::new(),
+                };
                 
                 // Restore ReviOS tweaks (restores original state)
                 if advanced {
                     ReviTweaksService::disable();
                 }
+                CustomTweaksService::disable();
                 
                 // Restore advanced modules
                 advanced_svc.disable(&advanced_modules);
+                affinity::restore();
+                bandwidth::stop();
+                trim::stop();
+                if let Some(report) = latency.stop() {
+                    notify_latency(&report);
+                }
+                if let Some(report) = dpc.stop() {
+                    notify_driver_latency(&report);
+                }
+                thermal.stop();
+                ObsService::stop();
+                log_line!("[Session] Ended");
+                session::end();
+                journal::clear();
+                notify_closed_apps(&closed);
                 
                 // Clear active flag after cleanup
                 active_flag.store(false, Ordering::SeqCst);
@@ -352,50 +1551,184 @@ fn main() -> Result<(), slint::PlatformError> {
         guard.suspend_explorer = new_settings.suspend_explorer;
         guard.suspend_browsers = new_settings.suspend_browsers;
         guard.suspend_launchers = new_settings.suspend_launchers;
+        guard.firewall_block_background = new_settings.firewall_block_background;
         guard.advanced_tweaks = new_settings.advanced_tweaks;
         
         // Handle MPO toggle - apply immediately when changed
-        if new_settings.disable_mpo != guard.disable_mpo {
-            guard.disable_mpo = new_settings.disable_mpo;
-            if new_settings.disable_mpo {
+        // When scoped to the game display, MPO is only disabled during sessions (see GameModeService)
+        let mpo_was_global = guard.disable_mpo && !guard.mpo_game_display_only;
+        guard.disable_mpo = new_settings.disable_mpo;
+        guard.mpo_game_display_only = new_settings.mpo_game_display_only;
+        let mpo_is_global = guard.disable_mpo && !guard.mpo_game_display_only;
+        // Registry only, picked up when DWM restarts: no mode change to preserve color around
+        if mpo_is_global != mpo_was_global {
+            if mpo_is_global {
                 // Disable MPO
                 GameModeService::set_mpo_disabled();
             } else {
                 // Enable MPO + OverlayMinFPS=0
                 GameModeService::set_mpo_enabled();
             }
+            DriftService::note_permanent_change();
         }
         
+        guard.active_profile = new_settings.active_profile.to_string();
+        guard.auto_activate_profiles = new_settings.auto_activate_profiles;
+        
         if new_settings.run_on_startup != guard.run_on_startup {
              guard.run_on_startup = new_settings.run_on_startup;
-             if let Ok(auto) = auto_launch::AutoLaunchBuilder::new()
-                .set_app_name("XillyGameMode")
-                .set_app_path(&std::env::current_exe().unwrap_or_default().to_string_lossy())
-                .build() 
-             {
-                 if guard.run_on_startup {
-                     let _ = auto.enable();
-                 } else {
-                     let _ = auto.disable();
-                 }
-             }
+             startup::apply(guard.run_on_startup, guard.startup_method);
         }
-        ss_clone.save(&guard);
+        ss_clone.save_debounced(&guard);
+    });
+
+    // 7a. Advanced Modules opened - the active profile may have changed since the page was filled
+    let settings_for_advanced_open = app_settings.clone();
+    let ui_handle_advanced_open = ui.as_weak();
+    ui.on_advanced_opened(move || {
+        let Some(ui) = ui_handle_advanced_open.upgrade() else { return };
+        let mut page = ui.get_advanced_settings();
+        page.priority_separation = settings_for_advanced_open.lock().unwrap().effective_priority_separation() as i32;
+        ui.set_advanced_settings(page);
     });
 
     // 7b. Advanced Settings Changed
     let settings_clone_3 = app_settings.clone();
     let ss_clone_2 = settings_service_arc.clone();
+    let advanced_modules_hot = advanced_modules_service.clone();
+    let is_active_for_hot = is_game_mode_active.clone();
+    let settings_for_lock_memory = app_settings.clone();
+    let ss_for_lock_memory = settings_service_arc.clone();
+    let ui_handle_lock_memory = ui.as_weak();
     
     ui.on_advanced_settings_changed(move |new_advanced| {
         let mut guard = settings_clone_3.lock().unwrap();
+        let lock_memory_was = guard.advanced_modules.enable_large_pages && guard.advanced_modules.lock_memory_privilege;
         guard.advanced_modules.disable_core_parking = new_advanced.disable_core_parking;
         guard.advanced_modules.enable_large_pages = new_advanced.enable_large_pages;
+        guard.advanced_modules.lock_memory_privilege = new_advanced.lock_memory_privilege;
+        guard.advanced_modules.raise_game_working_set = new_advanced.raise_game_working_set;
+        guard.advanced_modules.pin_device_interrupts = new_advanced.pin_device_interrupts;
         guard.advanced_modules.mmcss_priority_boost = new_advanced.mmcss_priority_boost;
         guard.advanced_modules.enable_hags = new_advanced.enable_hags;
         guard.advanced_modules.process_idle_demotion = new_advanced.process_idle_demotion;
         guard.advanced_modules.lower_bufferbloat = new_advanced.lower_bufferbloat;
-        ss_clone_2.save(&guard);
+        guard.advanced_modules.disable_ecn = new_advanced.disable_ecn;
+        guard.advanced_modules.disable_rsc = new_advanced.disable_rsc;
+        guard.advanced_modules.flush_dns_cache = new_advanced.flush_dns_cache;
+        guard.advanced_modules.dns_override = new_advanced.dns_override;
+        guard.advanced_modules.dns_servers = new_advanced.dns_servers
+            .split(',')
+            .map(|server| server.trim().to_string())
+            .filter(|server| !server.is_empty())
+            .collect();
+        guard.advanced_modules.tune_network_adapter = new_advanced.tune_network_adapter;
+        guard.advanced_modules.dscp_tagging = new_advanced.dscp_tagging;
+        guard.advanced_modules.target_gpu = new_advanced.target_gpu.to_string();
+        guard.advanced_modules.latency_monitor = new_advanced.latency_monitor;
+        guard.advanced_modules.latency_target = new_advanced.latency_target.trim().to_string();
+        guard.advanced_modules.latency_spike_ms = new_advanced.latency_spike_ms.max(0) as u32;
+        guard.advanced_modules.bandwidth_watch = new_advanced.bandwidth_watch;
+        guard.advanced_modules.dpc_monitor = new_advanced.dpc_monitor;
+        guard.advanced_modules.thermal_monitor = new_advanced.thermal_monitor;
+        guard.advanced_modules.demote_telemetry = new_advanced.demote_telemetry;
+        guard.advanced_modules.demote_sync = new_advanced.demote_sync;
+        guard.advanced_modules.demote_search = new_advanced.demote_search;
+        guard.advanced_modules.demote_security = new_advanced.demote_security;
+        guard.advanced_modules.demote_background = new_advanced.demote_background;
+        guard.advanced_modules.demote_custom = new_advanced.demote_custom
+            .split(',')
+            .map(|name| name.trim().trim_end_matches(".exe").to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        guard.advanced_modules.pause_windows_update = new_advanced.pause_windows_update;
+        guard.advanced_modules.defender_game_exclusion = new_advanced.defender_game_exclusion;
+        guard.advanced_modules.windows_game_mode_coop = new_advanced.windows_game_mode_coop;
+        guard.advanced_modules.kill_process_trees = new_advanced.kill_process_trees;
+        guard.advanced_modules.flush_working_sets = new_advanced.flush_working_sets;
+        guard.advanced_modules.periodic_trim = new_advanced.periodic_trim;
+        guard.advanced_modules.flush_exclusions = new_advanced.flush_exclusions
+            .split(',')
+            .map(|name| name.trim().trim_end_matches(".exe").to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        guard.advanced_modules.disable_usb_suspend = new_advanced.disable_usb_suspend;
+        guard.advanced_modules.disable_pcie_aspm = new_advanced.disable_pcie_aspm;
+        guard.advanced_modules.disable_mouse_acceleration = new_advanced.disable_mouse_acceleration;
+        guard.advanced_modules.raise_mouse_queue_size = new_advanced.raise_mouse_queue_size;
+        guard.advanced_modules.suppress_accessibility_hotkeys = new_advanced.suppress_accessibility_hotkeys;
+        guard.advanced_modules.block_windows_key = new_advanced.block_windows_key;
+        guard.advanced_modules.disable_audio_enhancements = new_advanced.disable_audio_enhancements;
+        guard.advanced_modules.disable_comms_ducking = new_advanced.disable_comms_ducking;
+        guard.advanced_modules.enable_hdr = new_advanced.enable_hdr;
+        guard.advanced_modules.enable_auto_hdr = new_advanced.enable_auto_hdr;
+        guard.advanced_modules.suspend_night_light = new_advanced.suspend_night_light;
+        guard.advanced_modules.best_performance_visuals = new_advanced.best_performance_visuals;
+        guard.advanced_modules.disable_secondary_displays = new_advanced.disable_secondary_displays;
+        guard.advanced_modules.disable_game_dvr = new_advanced.disable_game_dvr;
+        // Priority separation belongs to the active profile; used from the next session on
+        let priority_separation = new_advanced.priority_separation as u32;
+        if priority_separation != guard.effective_priority_separation() {
+            let active = guard.active_profile.clone();
+            if let Some(profile) = profiles::find_mut(&mut guard.profiles, &active) {
+                profile.priority_separation = Some(priority_separation);
+            }
+        }
+        ss_clone_2.save_debounced(&guard);
+        
+        // Lock Pages in Memory is a persistent account right, not a session tweak: grant it (after
+        // asking) while the option is on and remove it again when turned off
+        let lock_memory_now = guard.advanced_modules.enable_large_pages && guard.advanced_modules.lock_memory_privilege;
+        if lock_memory_now != lock_memory_was {
+            let settings_arc = settings_for_lock_memory.clone();
+            let settings_service = ss_for_lock_memory.clone();
+            let ui_weak = ui_handle_lock_memory.clone();
+            tasks::spawn("lock-memory", move |_| {
+                if !lock_memory_now {
+                    lock_memory::revoke_if_granted();
+                } else if lock_memory::is_held() {
+                    log_line!("[LockMemory] Already held by the current user");
+                } else if confirm_lock_memory_grant() {
+                    match lock_memory::grant() {
+                        Ok(true) => notify::info(&tr!("Lock Pages in Memory granted"), &tr!("Sign out and back in for games to use large pages.")),
+                        Ok(false) => log_line!("[LockMemory] Already held by the current user"),
+                        Err(e) => notify::attention(&tr!("Lock Pages in Memory not granted"), &e),
+                    }
+                } else {
+                    // Declined: switch the option back off
+                    let mut guard = settings_arc.lock().unwrap();
+                    guard.advanced_modules.lock_memory_privilege = false;
+                    settings_service.save(&guard);
+                    drop(guard);
+                    let _ = ui_weak.upgrade_in_event_loop(|ui| {
+                        let mut page = ui.get_advanced_settings();
+                        page.lock_memory_privilege = false;
+                        ui.set_advanced_settings(page);
+                    });
+                }
+            });
+        }
+        
+        // Hot-toggle: apply/revert just the changed modules in the running session
+        if is_active_for_hot.load(Ordering::SeqCst) {
+            let modules = guard.effective_advanced_modules();
+            let advanced_svc = advanced_modules_hot.clone();
+            let active_flag = is_active_for_hot.clone();
+            // Queued behind enable/disable so a restore can't race it
+            tasks::session().submit("hot-toggle-modules", move |_| {
+                // Turned off by hand in the meantime
+                if !active_flag.load(Ordering::SeqCst) {
+                    return;
+                }
+                if advanced_svc.apply_changes(&modules) {
+                    if let Some(mut session_journal) = journal::read() {
+                        session_journal.advanced_modules = Some(modules);
+                        session_journal.module_originals = Some(advanced_svc.originals());
+                        journal::write(&session_journal);
+                    }
+                }
+            });
+        }
     });
 
     // 7c. Permanent Bufferbloat Toggle (On/Off button)
@@ -409,285 +1742,680 @@ fn main() -> Result<(), slint::PlatformError> {
             // Currently OFF, turn it ON
             AdvancedModulesService::set_bufferbloat_enabled();
         }
+        DriftService::note_permanent_change();
         // Update UI state
         let _ = ui_handle_bufferbloat.upgrade_in_event_loop(move |ui| {
             ui.set_bufferbloat_active(!current_state);
         });
     });
 
-    // 8. Updates
-    ui.on_check_updates(move || {
-        UpdateService::check_for_updates();
+    // 7d. Modified State page - list and per-item revert
+    let ui_handle_modified = ui.as_weak();
+    let gamemode_for_modified = gamemode_service.clone();
+    let advanced_for_modified = advanced_modules_service.clone();
+    ui.on_refresh_modified(move || {
+        let ui_weak = ui_handle_modified.clone();
+        let gamemode = gamemode_for_modified.clone();
+        let advanced = advanced_for_modified.clone();
+        // The game mode lock is held while a session starts, don't wait for it on the UI thread
+        tasks::spawn("refresh-modified", move |_| {
+            push_modified_items(&ui_weak, &gamemode, &advanced);
+        });
     });
 
-    // 9. Export Specs - Comprehensive hardware info
-    ui.on_export_specs(move || {
-        thread::spawn(move || {
-            use std::process::Command;
-            use std::os::windows::process::CommandExt;
-            const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let ui_handle_revert = ui.as_weak();
+    let gamemode_for_revert = gamemode_service.clone();
+    let advanced_for_revert = advanced_modules_service.clone();
+    let settings_for_revert = app_settings.clone();
+    let ss_for_revert = settings_service_arc.clone();
+    ui.on_revert_modified(move |id| {
+        let ui_weak = ui_handle_revert.clone();
+        let gamemode = gamemode_for_revert.clone();
+        let advanced = advanced_for_revert.clone();
+        let settings = settings_for_revert.clone();
+        let settings_service = ss_for_revert.clone();
+        let id = id.to_string();
+        tasks::spawn("revert-modified", move |_| {
+            let reverted = match gamemode.lock() {
+                Ok(svc) => modified::revert(&id, &svc, &advanced),
+                Err(_) => false,
+            };
             
-            // CPU: Name, Cores, Threads
-            let cpu_info = Command::new("wmic")
-                .args(["cpu", "get", "name,NumberOfCores,NumberOfLogicalProcessors", "/format:list"])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .map(|o| {
-                    let s = String::from_utf8_lossy(&o.stdout);
-                    let mut name = String::new();
-                    let mut cores = String::new();
-                    let mut threads = String::new();
-                    for line in s.lines() {
-                        let line = line.trim();
-                        if let Some(v) = line.strip_prefix("Name=") {
-                            name = v.trim().to_string();
-                        } else if let Some(v) = line.strip_prefix("NumberOfCores=") {
-                            cores = v.trim().to_string();
-                        } else if let Some(v) = line.strip_prefix("NumberOfLogicalProcessors=") {
-                            threads = v.trim().to_string();
-                        }
-                    }
-                    if !name.is_empty() {
-                        format!("{} ({} cores / {} threads)", name, cores, threads)
-                    } else {
-                        "Unknown".to_string()
-                    }
-                })
-                .unwrap_or_else(|_| "Unknown".to_string());
-
-            // GPUs: All video controllers (iGPU + dGPU)
-            // GPUs: All video controllers (iGPU + dGPU) using DXGI for accurate VRAM
-            let gpus = get_gpu_info();
-
-            // RAM: Total capacity and speed
-            let ram_info = Command::new("wmic")
-                .args(["memorychip", "get", "Capacity,Speed", "/format:list"])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .map(|o| {
-                    let s = String::from_utf8_lossy(&o.stdout);
-                    let mut total_capacity: u64 = 0;
-                    let mut speed: u32 = 0;
-                    let mut stick_count = 0;
-                    
-                    for line in s.lines() {
-                        let line = line.trim();
-                        if let Some(v) = line.strip_prefix("Capacity=") {
-                            if let Ok(cap) = v.trim().parse::<u64>() {
-                                total_capacity += cap;
-                                stick_count += 1;
-                            }
-                        } else if let Some(v) = line.strip_prefix("Speed=") {
-                            if let Ok(spd) = v.trim().parse::<u32>() {
-                                if spd > speed { speed = spd; }
-                            }
-                        }
-                    }
-                    
-                    let gb = total_capacity as f64 / 1073741824.0;
-                    if speed > 0 {
-                        format!("{:.0} GB ({} sticks @ {} MHz)", gb, stick_count, speed)
-                    } else {
-                        format!("{:.0} GB ({} sticks)", gb, stick_count)
-                    }
+            // Permanent toggles are mirrored in settings and the UI
+            if reverted && id == "perm.mpo" {
+                let mut guard = settings.lock().unwrap();
+                guard.disable_mpo = false;
+                settings_service.save(&guard);
+                drop(guard);
+                let _ = ui_weak.upgrade_in_event_loop(|ui| {
+                    let mut s = ui.get_settings();
+                    s.disable_mpo = false;
+                    ui.set_settings(s);
+                });
+            }
+            if reverted && id == "perm.autotuning" {
+                let _ = ui_weak.upgrade_in_event_loop(|ui| ui.set_bufferbloat_active(false));
+            }
+            if reverted && id.starts_with("perm.") {
+                DriftService::note_permanent_change();
+            }
+            
+            push_modified_items(&ui_weak, &gamemode, &advanced);
+        });
+    });
+
+    // 7e. System Status page - live values vs. what the session expects
+    let ui_handle_status = ui.as_weak();
+    let gamemode_for_status = gamemode_service.clone();
+    let advanced_for_status = advanced_modules_service.clone();
+    let is_active_for_status = is_game_mode_active.clone();
+    ui.on_refresh_status(move || {
+        let ui_weak = ui_handle_status.clone();
+        let gamemode = gamemode_for_status.clone();
+        let advanced = advanced_for_status.clone();
+        let session_active = is_active_for_status.load(Ordering::SeqCst);
+        // netsh / powercfg calls take a moment, keep them off the UI thread
+        tasks::spawn("refresh-status", move |_| {
+            push_status_rows(&ui_weak, &gamemode, &advanced, session_active);
+        });
+    });
+
+    // Game connections - open endpoints of the detected game during a session
+    let ui_handle_connections = ui.as_weak();
+    let monitored_pid_for_connections = monitored_pid.clone();
+    ui.on_refresh_connections(move || {
+        let ui_weak = ui_handle_connections.clone();
+        let pid = monitored_pid_for_connections.load(Ordering::SeqCst);
+        // Reverse DNS and pings take a moment, keep them off the UI thread
+        tasks::spawn("refresh-connections", move |_| {
+            let rows = if pid != 0 { connections::collect(pid) } else { Vec::new() };
+            let note = if pid == 0 {
+                tr!("No game detected yet")
+            } else if rows.is_empty() {
+                tr!("The game has no open connections")
+            } else {
+                String::new()
+            };
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                let entries: Vec<ConnectionEntry> = rows.iter()
+                    .map(|c| ConnectionEntry {
+                        endpoint: c.endpoint().into(),
+                        state: c.state.into(),
+                        host: c.host.clone().unwrap_or_default().into(),
+                        ping: match (c.remote, c.rtt_ms) {
+                            (None, _) => String::new(),
+                            (Some(_), Some(ms)) => format!("{} ms", ms),
+                            (Some(_), None) => tr!("no reply"),
+                        }.into(),
+                    })
+                    .collect();
+                ui.set_connection_rows(slint::ModelRc::new(slint::VecModel::from(entries)));
+                ui.set_connections_note(note.into());
+            });
+        });
+    });
+
+    // 7f. Manual game selection - picked PID feeds the process monitor
+    let ui_handle_windows = ui.as_weak();
+    ui.on_refresh_windows(move || {
+        let ui_weak = ui_handle_windows.clone();
+        tasks::spawn("refresh-windows", move |_| {
+            let windows: Vec<WindowEntry> = GameDetector::list_windows()
+                .into_iter()
+                .map(|w| WindowEntry {
+                    pid: w.pid as i32,
+                    title: w.title.into(),
+                    process: w.process_name.into(),
                 })
-                .unwrap_or_else(|_| "Unknown".to_string());
-
-            // OS: Caption + Build
-            let os_info = Command::new("wmic")
-                .args(["os", "get", "caption,BuildNumber,OSArchitecture", "/format:list"])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .map(|o| {
-                    let s = String::from_utf8_lossy(&o.stdout);
-                    let mut caption = String::new();
-                    let mut build = String::new();
-                    let mut arch = String::new();
-                    
-                    for line in s.lines() {
-                        let line = line.trim();
-                        if let Some(v) = line.strip_prefix("Caption=") {
-                            caption = v.trim().to_string();
-                        } else if let Some(v) = line.strip_prefix("BuildNumber=") {
-                            build = v.trim().to_string();
-                        } else if let Some(v) = line.strip_prefix("OSArchitecture=") {
-                            arch = v.trim().to_string();
-                        }
+                .collect();
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                ui.set_picker_windows(slint::ModelRc::new(slint::VecModel::from(windows)));
+            });
+        });
+    });
+
+    let monitored_pid_for_pick = monitored_pid.clone();
+    let is_monitoring_for_pick = is_monitoring.clone();
+    let is_active_for_pick = is_game_mode_active.clone();
+    let settings_for_pick = app_settings.clone();
+    let advanced_for_pick = advanced_modules_service.clone();
+    ui.on_pick_game_window(move |pid| {
+        let pid = pid as u32;
+        if !is_active_for_pick.load(Ordering::SeqCst) || !is_process_running(pid) {
+            return;
+        }
+        
+        let previous = monitored_pid_for_pick.swap(pid, Ordering::SeqCst);
+        is_monitoring_for_pick.store(true, Ordering::SeqCst);
+        log_line!("[Session] Game picked manually (PID {})", pid);
+        tray::refresh();
+        
+        if let Some(window) = GameDetector::list_windows().into_iter().find(|w| w.pid == pid) {
+            GameDetector::focus_window(window.hwnd);
+        }
+        
+        // Game-bound modules were skipped if detection found nothing
+        if previous == 0 {
+            let (modules, game_flags) = {
+                let guard = settings_for_pick.lock().unwrap();
+                (guard.effective_advanced_modules(), guard.game_flags.clone())
+            };
+            let advanced_svc = advanced_for_pick.clone();
+            tasks::spawn("pick-game", move |_| {
+                advanced_svc.enable_for_game(&modules, pid);
+                GameConfigService::apply_for_pid(&game_flags, pid);
+            });
+        }
+    });
+
+    // 7g. Panic hotkey - force-restore everything, trusting only the persisted journal
+    let ui_panic = ui.as_weak();
+    let gm_for_panic = gamemode_service.clone();
+    let state_for_panic = gamemode_service.lock().unwrap().state_handle();
+    let advanced_for_panic = advanced_modules_service.clone();
+    let latency_for_panic = latency_monitor.clone();
+    let dpc_for_panic = dpc_monitor.clone();
+    let thermal_for_panic = thermal_monitor.clone();
+    let monitored_pid_for_panic = monitored_pid.clone();
+    let is_monitoring_for_panic = is_monitoring.clone();
+    let is_active_for_panic = is_game_mode_active.clone();
+    hotkey::start_panic_hotkey(move || {
+        log_line!("[Panic] Emergency restore requested");
+        // Nothing queued may re-apply the session after the restore
+        tasks::session().cancel_pending();
+        is_monitoring_for_panic.store(false, Ordering::SeqCst);
+        monitored_pid_for_panic.store(0, Ordering::SeqCst);
+        
+        GameModeService::force_restore();
+        // Modules are only restored if a session applied them; while idle the configured set
+        // would restore guessed defaults over the user's own values
+        if let Some(modules) = advanced_for_panic.session_modules() {
+            advanced_for_panic.disable(&modules);
+        }
+        affinity::restore();
+        bandwidth::stop();
+        trim::stop();
+        latency_for_panic.stop();
+        dpc_for_panic.stop();
+        thermal_for_panic.stop();
+        ObsService::stop();
+        
+        // A hung enable/disable may hold the lock, its state is already restored from the journal;
+        // reset the session state directly so the next enable isn't refused
+        match gm_for_panic.try_lock() {
+            Ok(svc) => svc.forget_session(),
+            Err(_) => {
+                log_line!("[Panic] Game mode service is busy, resetting its session state");
+                *state_for_panic.lock().unwrap_or_else(|e| e.into_inner()) = GameModeState::Idle;
+            }
+        }
+        
+        log_line!("[Panic] Emergency restore complete");
+        session::end();
+        is_active_for_panic.store(false, Ordering::SeqCst);
+        
+        let _ = ui_panic.upgrade_in_event_loop(|ui| {
+            ui.set_active(false);
+            ui.window().show().unwrap();
+            let _ = ui.window().set_minimized(false);
+        });
+    });
+
+    // 7h. MSI mode / interrupt affinity - permanent device settings, applied after a warning and a reboot
+    let settings_for_msi = app_settings.clone();
+    let ui_handle_msi = ui.as_weak();
+    ui.on_toggle_msi_mode(move || {
+        let settings = settings_for_msi.lock().unwrap().advanced_modules.clone();
+        let ui_weak = ui_handle_msi.clone();
+        tasks::spawn("toggle-msi-mode", move |_| {
+            let changed = if interrupts::is_applied() {
+                interrupts::restore()
+            } else {
+                let devices = interrupts::targets(&settings.target_gpu);
+                if devices.is_empty() {
+                    notify::attention(&tr!("MSI mode not applied"), &tr!("No PCI graphics card or network adapter was found."));
+                    false
+                } else if !confirm_msi_mode(&devices) {
+                    false
+                } else if let Err(e) = interrupts::apply(&devices, settings.pin_device_interrupts) {
+                    // Leave nothing half-applied
+                    interrupts::restore();
+                    notify::attention(&tr!("MSI mode not applied"), &e);
+                    false
+                } else {
+                    true
+                }
+            };
+            let active = interrupts::is_applied();
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| ui.set_msi_mode_active(active));
+            if changed {
+                offer_restart();
+            }
+        });
+    });
+    ui.set_msi_mode_active(interrupts::is_applied());
+
+    // 7i. Platform timer (bcdedit) - same flow as MSI mode
+    let ui_handle_timer = ui.as_weak();
+    ui.on_toggle_platform_timer(move || {
+        let ui_weak = ui_handle_timer.clone();
+        tasks::spawn("toggle-platform-timer", move |_| {
+            let changed = if bcdedit::is_applied() {
+                bcdedit::restore()
+            } else if !confirm_platform_timer() {
+                false
+            } else if let Err(e) = bcdedit::apply() {
+                bcdedit::restore();
+                notify::attention(&tr!("Timer tweaks not applied"), &e);
+                false
+            } else {
+                true
+            };
+            let active = bcdedit::is_applied();
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| ui.set_platform_timer_active(active));
+            if changed {
+                offer_restart();
+            }
+        });
+    });
+    ui.set_platform_timer_active(bcdedit::is_applied());
+
+    // 7j. Core isolation (VBS / Memory Integrity) - guided off/on, the running state comes from WMI
+    let ui_handle_vbs = ui.as_weak();
+    ui.on_toggle_core_isolation(move || {
+        let ui_weak = ui_handle_vbs.clone();
+        tasks::spawn("toggle-core-isolation", move |_| {
+            let changed = if vbs::is_disabled_by_app() {
+                vbs::restore()
+            } else {
+                match vbs::state() {
+                    None => {
+                        notify::attention(&tr!("Core isolation not changed"), &tr!("The core isolation state could not be read."));
+                        false
                     }
-                    
-                    format!("{} (Build {}) {}", caption, build, arch)
-                })
-                .unwrap_or_else(|_| "Windows".to_string());
-
-            // Motherboard
-            let mobo = Command::new("wmic")
-                .args(["baseboard", "get", "Manufacturer,Product", "/format:list"])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .map(|o| {
-                    let s = String::from_utf8_lossy(&o.stdout);
-                    let mut manufacturer = String::new();
-                    let mut product = String::new();
-                    
-                    for line in s.lines() {
-                        let line = line.trim();
-                        if let Some(v) = line.strip_prefix("Manufacturer=") {
-                            manufacturer = v.trim().to_string();
-                        } else if let Some(v) = line.strip_prefix("Product=") {
-                            product = v.trim().to_string();
-                        }
+                    Some(state) if !state.vbs_running && !state.hvci_running => {
+                        notify::info(&tr!("Core isolation already off"), &tr!("Neither VBS nor Memory Integrity is running."));
+                        false
                     }
-                    format!("{} {}", manufacturer, product)
-                })
-                .unwrap_or_else(|_| "Unknown".to_string());
-
-            // Storage drives
-            let storage = Command::new("wmic")
-                .args(["diskdrive", "get", "Model,Size,MediaType", "/format:list"])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .map(|o| {
-                    let s = String::from_utf8_lossy(&o.stdout);
-                    let mut drives: Vec<String> = Vec::new();
-                    let mut current_model = String::new();
-                    let mut current_size: u64 = 0;
-                    let mut current_type = String::new();
-                    
-                    for line in s.lines() {
-                        let line = line.trim();
-                        if let Some(v) = line.strip_prefix("Model=") {
-                            if !current_model.is_empty() {
-                                let gb = current_size as f64 / 1000000000.0;
-                                let type_str = if current_type.contains("SSD") || current_type.contains("Solid") { 
-                                    "SSD" 
-                                } else if current_type.contains("Fixed") {
-                                    "HDD"
-                                } else {
-                                    ""
-                                };
-                                drives.push(format!("{} ({:.0} GB) {}", current_model, gb, type_str).trim().to_string());
-                            }
-                            current_model = v.trim().to_string();
-                            current_size = 0;
-                            current_type.clear();
-                        } else if let Some(v) = line.strip_prefix("Size=") {
-                            current_size = v.trim().parse().unwrap_or(0);
-                        } else if let Some(v) = line.strip_prefix("MediaType=") {
-                            current_type = v.trim().to_string();
+                    Some(state) if !confirm_core_isolation(&state) => false,
+                    Some(state) => match vbs::disable(&state) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            vbs::restore();
+                            notify::attention(&tr!("Core isolation not changed"), &e);
+                            false
                         }
-                    }
-                    if !current_model.is_empty() {
-                        let gb = current_size as f64 / 1000000000.0;
-                        let type_str = if current_type.contains("SSD") || current_type.contains("Solid") { 
-                            "SSD" 
-                        } else if current_type.contains("Fixed") {
-                            "HDD"
-                        } else {
-                            ""
-                        };
-                        drives.push(format!("{} ({:.0} GB) {}", current_model, gb, type_str).trim().to_string());
-                    }
-                    
-                    if drives.is_empty() {
-                        "Unknown".to_string()
-                    } else {
-                        drives.join("\n           ")
-                    }
-                })
-                .unwrap_or_else(|_| "Unknown".to_string());
-
-            let report = format!(
-                "System Specs:\n\
-                 CPU:     {}\n\
-                 GPU:     {}\n\
-                 RAM:     {}\n\
-                 Mobo:    {}\n\
-                 Storage: {}\n\
-                 OS:      {}",
-                cpu_info, gpus, ram_info, mobo, storage, os_info
-            );
+                    },
+                }
+            };
+            refresh_core_isolation(&ui_weak);
+            if changed {
+                offer_restart();
+            }
+        });
+    });
+    // WMI is slow to answer, read the state off the UI thread
+    let ui_handle_vbs_state = ui.as_weak();
+    tasks::spawn("core-isolation-state", move |_| refresh_core_isolation(&ui_handle_vbs_state));
+
+    // 8. Updates - checks only surface the update (badge, tray, toast); nothing downloads before "Update" is clicked
+    let settings_for_updates = app_settings.clone();
+    let ui_handle_updates = ui.as_weak();
+    ui.on_check_updates(move || {
+        let channel = settings_for_updates.lock().unwrap().update_channel;
+        let ui_handle = ui_handle_updates.clone();
+        tasks::spawn("check-updates", move |_| {
+            let version = UpdateService::check(channel);
+            match &version {
+                Some(v) => notify::info(&tr!("Update available"), &tr!("Version {} is ready to install", v)),
+                None => notify::info(&tr!("No update available"), &tr!("You are running the latest version")),
+            }
+            tray::refresh();
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| ui.set_update_version(version.unwrap_or_default().into()));
+        });
+    });
+    let ui_handle_install = ui.as_weak();
+    ui.on_install_update(move || {
+        let ui_handle = ui_handle_install.clone();
+        UpdateService::install_available(move |fraction| {
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| ui.set_update_progress(fraction.unwrap_or(-1.0)));
+        });
+    });
+
+    // Background check every `update_check_hours` (0 = off), skipped while a session runs
+    let settings_for_update_check = app_settings.clone();
+    let is_active_for_updates = is_game_mode_active.clone();
+    let ui_handle_update_check = ui.as_weak();
+    tasks::spawn("update-check", move |cancel| {
+        let mut last_check: Option<std::time::Instant> = None;
+        // Each version is announced once, not on every check
+        let mut announced: Option<String> = None;
+
+        while !cancel.is_cancelled() {
+            thread::sleep(std::time::Duration::from_secs(60));
+
+            let (hours, channel) = {
+                let guard = settings_for_update_check.lock().unwrap();
+                (guard.update_check_hours, guard.update_channel)
+            };
+            let interval = std::time::Duration::from_secs(hours as u64 * 3600);
+            if hours == 0
+                || is_active_for_updates.load(Ordering::Acquire)
+                || last_check.is_some_and(|t| t.elapsed() < interval)
+            {
+                continue;
+            }
+            last_check = Some(std::time::Instant::now());
+
+            let Some(version) = UpdateService::check(channel) else { continue };
+            if announced.as_deref() == Some(version.as_str()) {
+                continue;
+            }
+            notify::info(&tr!("Update available"), &tr!("Version {} is ready to install from the app or the tray menu", version));
+            tray::refresh();
+            let badge = version.clone();
+            let _ = ui_handle_update_check.upgrade_in_event_loop(move |ui| ui.set_update_version(badge.into()));
+            announced = Some(version);
+        }
+    });
+
+    // 9. Export Specs - Comprehensive hardware info plus tweak status
+    let gm_for_specs = gamemode_service.clone();
+    let advanced_for_specs = advanced_modules_service.clone();
+    let settings_for_specs = app_settings.clone();
+    let is_active_for_specs = is_game_mode_active.clone();
+    ui.on_export_specs(move |target| {
+        let target = target.to_string();
+        let (format, profile) = {
+            let guard = settings_for_specs.lock().unwrap();
+            (SpecsFormat::from_name(&guard.specs_format), guard.active_profile.clone())
+        };
+        let gamemode = gm_for_specs.clone();
+        let advanced = advanced_for_specs.clone();
+        let active = is_active_for_specs.load(Ordering::SeqCst);
+        tasks::spawn("specs-report", move |_| {
+            use std::process::Command;
             
-            let escaped = report.replace("\"", "`\"").replace("\n", "`n");
-            let _ = Command::new("powershell")
-                .args(["-Command", &format!("Set-Clipboard -Value \"{}\"", escaped)])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output();
-
-            use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_OK, MB_ICONINFORMATION};
-            use windows::Win32::Foundation::HWND;
-            use windows::core::HSTRING;
-            unsafe {
-                MessageBoxW(HWND::default(), &HSTRING::from("System specs copied to clipboard!"), &HSTRING::from("Specs Copied"), MB_OK | MB_ICONINFORMATION);
+            let mut tweaks = vec![format!("Game mode {} ({} profile)", if active { "active" } else { "inactive" }, profile)];
+            if let Ok(svc) = gamemode.lock() {
+                tweaks.extend(modified::collect(&svc, &advanced).into_iter().map(|item| item.label));
+            }
+            let report = SystemSpecs::collect(tweaks).render(format);
+            
+            // Always copied; "file" also saves the report and opens it in Notepad
+            let copied = clipboard::set_text(&report);
+            if target == "file" {
+                if let Some(path) = save_specs_report(&report, format) {
+                    let _ = Command::new("notepad").arg(path).spawn();
+                    return;
+                }
+            }
+
+            if copied {
+                notify::info(&tr!("Specs Copied"), &tr!("System specs copied to clipboard!"));
+            } else {
+                notify::attention(&tr!("Specs Not Copied"), &tr!("The clipboard is in use by another application. Try again or use Save Specs."));
+            }
+        });
+    });
+
+    // 9b. Settings presets - the full configuration exported to / imported from a JSON file
+    let settings_for_export = app_settings.clone();
+    ui.on_export_preset(move || {
+        let settings = settings_for_export.lock().unwrap().clone();
+        tasks::spawn("export-preset", move |_| {
+            let Some(path) = PresetService::pick_export_path() else {
+                return; // Cancelled
+            };
+            if let Err(error) = PresetService::export(&settings, &path) {
+                notify::attention(&tr!("Preset Not Exported"), &error);
             }
         });
     });
 
-    // 10. Clean Close Handler - Deactivate game mode, wait, then exit
+    let ui_handle_import = ui.as_weak();
+    let settings_for_import = app_settings.clone();
+    let ss_for_import = settings_service_arc.clone();
+    ui.on_import_preset(move || {
+        let ui_weak = ui_handle_import.clone();
+        let settings = settings_for_import.clone();
+        let settings_service = ss_for_import.clone();
+        tasks::spawn("import-preset", move |_| {
+            let Some(path) = PresetService::pick_import_path() else {
+                return; // Cancelled
+            };
+            let current = settings.lock().unwrap().clone();
+            let imported = match PresetService::import(&path, &current) {
+                Ok(imported) => imported,
+                Err(error) => {
+                    notify::attention(&tr!("Preset Not Imported"), &error);
+                    return;
+                }
+            };
+
+            apply_imported_settings(imported, &current, &settings, &settings_service, &ui_weak);
+            notify::info(&tr!("Preset Imported"), &tr!("It applies from the next game mode session."));
+        });
+    });
+
+    // 9c. Community presets - curated list from the repo, hash-verified, reviewed as a dry run
+    // Listed presets and the verified settings of the last previewed one (with its list index)
+    let community: Arc<Mutex<(Vec<services::preset::CommunityPreset>, Option<(i32, services::settings::AppSettings)>)>> =
+        Arc::new(Mutex::new((Vec::new(), None)));
+
+    let ui_handle_community = ui.as_weak();
+    let community_for_list = community.clone();
+    ui.on_refresh_community(move || {
+        let ui_weak = ui_handle_community.clone();
+        let community = community_for_list.clone();
+        tasks::spawn("community-index", move |_| {
+            let result = PresetService::community_index();
+            let (entries, status) = match &result {
+                Ok(presets) => (
+                    presets.iter()
+                        .map(|p| CommunityEntry {
+                            name: p.name.clone().into(),
+                            author: p.author.clone().into(),
+                            description: p.description.clone().into(),
+                        })
+                        .collect::<Vec<_>>(),
+                    if presets.is_empty() { "No presets published yet".to_string() } else { String::new() },
+                ),
+                Err(error) => (Vec::new(), error.clone()),
+            };
+            *community.lock().unwrap() = (result.unwrap_or_default(), None);
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                ui.set_community_presets(slint::ModelRc::new(slint::VecModel::from(entries)));
+                ui.set_community_selected(-1);
+                ui.set_community_changes(slint::ModelRc::default());
+                ui.set_community_can_import(false);
+                ui.set_community_status(status.into());
+            });
+        });
+    });
+
+    let ui_handle_preview = ui.as_weak();
+    let community_for_preview = community.clone();
+    let settings_for_preview = app_settings.clone();
+    ui.on_preview_community(move |index| {
+        let ui_weak = ui_handle_preview.clone();
+        let community = community_for_preview.clone();
+        let settings = settings_for_preview.clone();
+        tasks::spawn("community-preview", move |_| {
+            let Some(preset) = community.lock().unwrap().0.get(index as usize).cloned() else {
+                return;
+            };
+            let current = settings.lock().unwrap().clone();
+            let (changes, status, verified) = match PresetService::fetch_community(&preset, &current) {
+                Ok(imported) => {
+                    let changes = PresetService::changes(&current, &imported);
+                    let status = if changes.is_empty() {
+                        "Download intact (SHA-256 matches the list). Matches your current settings.".to_string()
+                    } else {
+                        format!(
+                            "Download intact (SHA-256 matches the list, not a signature). Hooks are never imported. {} change(s):",
+                            changes.len()
+                        )
+                    };
+                    let importable = !changes.is_empty();
+                    community.lock().unwrap().1 = Some((index, imported));
+                    (changes, status, importable)
+                }
+                Err(error) => {
+                    community.lock().unwrap().1 = None;
+                    (Vec::new(), error, false)
+                }
+            };
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                // Ignore a late result for a preset that is no longer selected
+                if ui.get_community_selected() != index {
+                    return;
+                }
+                let lines: Vec<slint::SharedString> = changes.into_iter().map(Into::into).collect();
+                ui.set_community_changes(slint::ModelRc::new(slint::VecModel::from(lines)));
+                ui.set_community_status(status.into());
+                ui.set_community_can_import(verified);
+            });
+        });
+    });
+
+    let ui_handle_community_import = ui.as_weak();
+    let community_for_import = community.clone();
+    let settings_for_community = app_settings.clone();
+    let ss_for_community = settings_service_arc.clone();
+    ui.on_import_community(move || {
+        let Some(ui) = ui_handle_community_import.upgrade() else {
+            return;
+        };
+        // Only the preset on screen may be imported, not a slower earlier preview
+        let imported = match community_for_import.lock().unwrap().1.take() {
+            Some((index, imported)) if index == ui.get_community_selected() => imported,
+            _ => return,
+        };
+        ui.set_show_community_popup(false);
+        let current = settings_for_community.lock().unwrap().clone();
+        apply_imported_settings(imported, &current, &settings_for_community, &ss_for_community, &ui_handle_community_import);
+        notify::info(&tr!("Preset Imported"), &tr!("It applies from the next game mode session."));
+    });
+
+    // 10. Clean Close Handler - Deactivate game mode, show what stays changed, then exit
     let is_active_for_close = is_game_mode_active.clone();
     let settings_for_close = app_settings.clone();
     let gamemode_for_close = gamemode_service.clone();
     let advanced_modules_for_close = advanced_modules_service.clone();
     let monitored_pid_for_close = monitored_pid.clone();
     let is_monitoring_for_close = is_monitoring.clone();
+    let ss_for_close = settings_service_arc.clone();
+    let latency_for_close = latency_monitor.clone();
+    let dpc_for_close = dpc_monitor.clone();
+    let thermal_for_close = thermal_monitor.clone();
     
+    // Full disable path, shared by closing the app and Windows shutting down / logging off;
+    // returns false if no session was active
+    let exit_lock = Mutex::new(());
+    let end_session_on_exit = Arc::new(move |reason: &str| -> bool {
+        // Close and shutdown can overlap: the second caller waits, then finds nothing to restore
+        let _exiting = exit_lock.lock().unwrap();
+        
+        // Let a running enable/disable finish instead of restoring underneath it; queued ones are dropped
+        tasks::session().cancel_pending();
+        if !tasks::session().wait_idle(std::time::Duration::from_secs(15)) {
+            log_line!("[Session] Pending enable/disable still running, restoring anyway");
+        }
+        if !is_active_for_close.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        // Stop monitoring
+        is_monitoring_for_close.store(false, Ordering::SeqCst);
+        monitored_pid_for_close.store(0, Ordering::SeqCst);
+        
+        // Extract settings
+        let (options, advanced, advanced_modules) = {
+            let guard = settings_for_close.lock().unwrap();
+            (
+                GameModeOptions::from_settings(&guard),
+                guard.advanced_tweaks,
+                guard.effective_advanced_modules(),
+            )
+        };
+        
+        // Disable game mode
+        if let Ok(svc) = gamemode_for_close.lock() {
+            svc.disable_game_mode(&options);
+        }
+        
+        // Restore ReviOS tweaks if they were enabled
+        if advanced {
+            ReviTweaksService::disable();
+        }
+        CustomTweaksService::disable();
+        
+        // Restore advanced modules
+        advanced_modules_for_close.disable(&advanced_modules);
+        affinity::restore();
+        bandwidth::stop();
+        trim::stop();
+        latency_for_close.stop();
+        dpc_for_close.stop();
+        thermal_for_close.stop();
+        ObsService::stop();
+        log_line!("[Session] Ended ({})", reason);
+        session::end();
+        journal::clear();
+        
+        // Clear active flag
+        is_active_for_close.store(false, Ordering::SeqCst);
+        true
+    });
+
+    let end_session_for_close = end_session_on_exit.clone();
+    let settings_for_confirm = app_settings.clone();
+    let advanced_modules_for_confirm = advanced_modules_service.clone();
+    let gamemode_for_confirm = gamemode_service.clone();
+    let ui_handle_close = ui.as_weak();
     ui.on_close_app(move || {
-        if is_active_for_close.load(Ordering::SeqCst) {
-            // Game mode is active - deactivate first, then wait 3 seconds, then exit
-            let settings_clone = settings_for_close.clone();
-            let gamemode_clone = gamemode_for_close.clone();
-            let advanced_modules_clone = advanced_modules_for_close.clone();
-            let active_flag = is_active_for_close.clone();
-            let pid_ref = monitored_pid_for_close.clone();
-            let monitoring_ref = is_monitoring_for_close.clone();
+        let end_session = end_session_for_close.clone();
+        let settings_clone = settings_for_confirm.clone();
+        let advanced_modules_clone = advanced_modules_for_confirm.clone();
+        let gamemode_clone = gamemode_for_confirm.clone();
+        let settings_service = ss_for_close.clone();
+        let ui_weak = ui_handle_close.clone();
+        
+        tasks::spawn("close-app", move |_| {
+            // Ask before restoring anything: Cancel keeps the session running
+            let Some(revert) = confirm_restore_obligations(&gamemode_clone, &advanced_modules_clone) else {
+                return; // User chose to keep the app open
+            };
             
-            thread::spawn(move || {
-                // Stop monitoring
-                monitoring_ref.store(false, Ordering::SeqCst);
-                pid_ref.store(0, Ordering::SeqCst);
-                
-                // Extract settings
-                let (options, advanced, advanced_modules) = {
-                    let guard = settings_clone.lock().unwrap();
-                    (
-                        GameModeOptions {
-                            suspend_explorer: guard.suspend_explorer,
-                            suspend_browsers: guard.suspend_browsers,
-                            suspend_launchers: guard.suspend_launchers,
-                            isolate_network: guard.isolate_network,
-                        },
-                        guard.advanced_tweaks,
-                        guard.advanced_modules.clone(),
-                    )
-                };
-                
-                // Disable game mode
-                if let Ok(svc) = gamemode_clone.lock() {
-                    svc.disable_game_mode(&options);
-                }
-                
-                // Restore ReviOS tweaks if they were enabled
-                if advanced {
-                    ReviTweaksService::disable();
-                }
-                
-                // Restore advanced modules
-                advanced_modules_clone.disable(&advanced_modules);
-                
-                // Clear active flag
-                active_flag.store(false, Ordering::SeqCst);
+            if end_session("app closed") {
+                let _ = ui_weak.upgrade_in_event_loop(|ui| ui.set_active(false));
                 
                 // Wait 3 seconds after deactivation to ensure clean state
                 thread::sleep(std::time::Duration::from_secs(3));
-                
-                // Exit cleanly
-                std::process::exit(0);
-            });
-        } else {
-            // Game mode not active - exit immediately
+            }
+            
+            if !revert.is_empty() {
+                revert_restore_obligations(&revert, &settings_clone, &settings_service, &gamemode_clone, &advanced_modules_clone, &ui_weak);
+            }
+            
+            // Exit cleanly; monitors must not start another session on the way out
+            tasks::cancel_background();
+            services::settings::flush_pending();
+            tray::remove();
             std::process::exit(0);
-        }
+        });
     });
 
+    // 10b. Shutdown / logoff - restore before Windows ends the process
+    let is_active_for_shutdown = is_game_mode_active.clone();
+    shutdown::watch(
+        move || is_active_for_shutdown.load(Ordering::SeqCst),
+        move || {
+            end_session_on_exit("Windows shutdown or logoff");
+        },
+    );
+
     // 11. DWM Transparency Fix
     let ui_handle_dwm = ui.as_weak();
     slint::Timer::single_shot(std::time::Duration::from_millis(100), move || {
@@ -714,5 +2442,96 @@ fn main() -> Result<(), slint::PlatformError> {
         });
     });
 
+    // 12. Tray icon - status tooltip, activation, profile and quick-toggle menu
+    ui.on_active_changed(|_| tray::refresh());
+
+    let settings_for_tray = app_settings.clone();
+    let is_active_for_tray = is_game_mode_active.clone();
+    let monitored_pid_for_tray = monitored_pid.clone();
+    let settings_for_tray_cmd = app_settings.clone();
+    let ss_for_tray = settings_service_arc.clone();
+    let ui_handle_tray = ui.as_weak();
+    tray::start(
+        move || {
+            let guard = settings_for_tray.lock().unwrap();
+            let pid = monitored_pid_for_tray.load(Ordering::SeqCst);
+            TrayStatus {
+                active: is_active_for_tray.load(Ordering::SeqCst),
+                game: (pid != 0).then(|| ProcessService::executable_name(pid)).flatten(),
+                elapsed: session::elapsed(),
+                profiles: guard.profiles.iter().map(|p| p.name.clone()).collect(),
+                active_profile: guard.active_profile.clone(),
+                suspend_browsers: guard.suspend_browsers,
+                isolate_network: guard.isolate_network,
+                update: UpdateService::available(),
+            }
+        },
+        move |command| {
+            // Network isolation has no switch on the main page, so it goes straight to settings.json
+            if let TrayCommand::ToggleNetworkIsolation = command {
+                let mut guard = settings_for_tray_cmd.lock().unwrap();
+                guard.isolate_network = !guard.isolate_network;
+                log_line!("[Tray] Network isolation {}", if guard.isolate_network { "on" } else { "off" });
+                ss_for_tray.save_debounced(&guard);
+                return;
+            }
+
+            let _ = ui_handle_tray.upgrade_in_event_loop(move |ui| match command {
+                TrayCommand::Show => {
+                    let _ = ui.show();
+                    ui.window().set_minimized(false);
+                }
+                TrayCommand::ToggleGameMode => ui.invoke_toggle_game_mode(!ui.get_active()),
+                TrayCommand::SelectProfile(name) => {
+                    let mut page = ui.get_settings();
+                    page.active_profile = name.into();
+                    ui.set_settings(page.clone());
+                    ui.invoke_settings_changed(page);
+                }
+                TrayCommand::ToggleSuspendBrowsers => {
+                    let mut page = ui.get_settings();
+                    page.suspend_browsers = !page.suspend_browsers;
+                    ui.set_settings(page.clone());
+                    ui.invoke_settings_changed(page);
+                }
+                TrayCommand::InstallUpdate => ui.invoke_install_update(),
+                TrayCommand::ToggleNetworkIsolation => {}
+                TrayCommand::Exit => ui.invoke_close_app(),
+            });
+        },
+    );
+
+    // 13. Single instance - commands from later starts and links, and the game this run was launched with
+    let ui_handle_instance = ui.as_weak();
+    let monitored_pid_for_instance = monitored_pid.clone();
+    let is_active_for_instance = is_game_mode_active.clone();
+    instance::listen(move |command| {
+        let monitored_pid = monitored_pid_for_instance.clone();
+        let is_active = is_active_for_instance.clone();
+        let _ = ui_handle_instance.upgrade_in_event_loop(move |ui| {
+            run_instance_command(&ui, command, &monitored_pid, &is_active);
+        });
+    });
+    // This run was started by a link (no instance was running)
+    if let Some(command) = url_command {
+        run_instance_command(&ui, command, &monitored_pid, &is_game_mode_active);
+    }
+
+    // Started as the launch wrapper: Steam tracks this process, so exit once the session is restored
+    if let Some(mut child) = launched_game {
+        start_launch_session(&ui, child.id(), &monitored_pid, &is_game_mode_active);
+        let is_active_for_launch = is_game_mode_active.clone();
+        let ui_handle_launch = ui.as_weak();
+        tasks::spawn("launch-wrapper", move |_| {
+            let _ = child.wait();
+            // The monitor restores after the relaunch grace period (or follows a relaunched game)
+            while is_active_for_launch.load(Ordering::SeqCst) {
+                thread::sleep(std::time::Duration::from_secs(1));
+            }
+            log_line!("[Launch] Game exited and session restored, closing");
+            let _ = ui_handle_launch.upgrade_in_event_loop(|ui| ui.invoke_close_app());
+        });
+    }
+
     ui.run()
 }
@@ -1,17 +1,222 @@
 //! Advanced Modules Service
 //! Hardware-aware tweaks for 1% lows optimization
-//! Each tweak is toggleable and only active when game mode is active
+//! Each tweak is toggleable and only active when game mode is active;
+//! toggles made during a session are applied/reverted immediately
 
 use crate::services::settings::AdvancedModuleSettings;
-use windows::Win32::System::Registry::*;
-use windows::core::{PCWSTR, HSTRING};
-use std::sync::Mutex;
+use crate::services::capabilities::Capabilities;
+use crate::services::network::NetworkService;
+use crate::services::audio::AudioService;
+use crate::services::display::{ColorSnapshot, DisplayService, DisplayTopology, HdrTarget};
+use crate::services::gpu::GpuService;
+use crate::services::process::ProcessService;
+use crate::services::modified::ModifiedItem;
+use crate::services::power::{guid_from_hex, guid_to_hex};
+use crate::services::system::{Hive, SystemBackend, Win32};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETMOUSE, SPI_SETMOUSE, SPIF_SENDCHANGE, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    SPI_GETSTICKYKEYS, SPI_SETSTICKYKEYS, SPI_GETFILTERKEYS, SPI_SETFILTERKEYS, FKF_HOTKEYACTIVE,
+    SYSTEM_PARAMETERS_INFO_ACTION, ANIMATIONINFO, SPI_GETANIMATION, SPI_SETANIMATION,
+    SPI_GETMENUANIMATION, SPI_SETMENUANIMATION, SPI_GETCOMBOBOXANIMATION, SPI_SETCOMBOBOXANIMATION,
+    SPI_GETLISTBOXSMOOTHSCROLLING, SPI_SETLISTBOXSMOOTHSCROLLING, SPI_GETTOOLTIPANIMATION, SPI_SETTOOLTIPANIMATION,
+    SPI_GETSELECTIONFADE, SPI_SETSELECTIONFADE, SPI_GETCURSORSHADOW, SPI_SETCURSORSHADOW,
+    SPI_GETDROPSHADOW, SPI_SETDROPSHADOW, SPI_GETCLIENTAREAANIMATION, SPI_SETCLIENTAREAANIMATION,
+    SPI_GETDRAGFULLWINDOWS, SPI_SETDRAGFULLWINDOWS,
+};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::UI::Accessibility::{STICKYKEYS, FILTERKEYS, SKF_HOTKEYACTIVE, STICKYKEYS_FLAGS};
+use crate::services::hotkey;
+use crate::services::notify;
+use windows::core::GUID;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use crate::{log_line, tr};
+
+// Process idle demotion categories (background apps that shouldn't compete with games)
+const DEMOTE_TELEMETRY: &[&str] = &["compattelrunner"];
+const DEMOTE_SYNC: &[&str] = &["SettingSyncHost", "OneDrive", "GoogleDriveFS", "Dropbox"];
+const DEMOTE_SEARCH: &[&str] = &["SearchIndexer"];
+const DEMOTE_SECURITY: &[&str] = &["SecurityHealthService", "SgrmBroker", "MsMpEng", "NisSrv"];
+const DEMOTE_BACKGROUND: &[&str] = &[
+    "WmiPrvSE", "spoolsv", "dllhost", "backgroundTaskHost",
+    "RuntimeBroker", "ApplicationFrameHost", "SystemSettings",
+];
+
+// Settings > Display > Graphics global DirectX options (Auto HDR, optimizations for windowed games)
+const DIRECTX_PREFERENCES: &str = r"Software\Microsoft\DirectX\UserGpuPreferences";
+
+// Mouse class driver input buffer (driver default is 100 packets)
+const MOUCLASS_PARAMETERS: &str = r"SYSTEM\CurrentControlSet\Services\mouclass\Parameters";
+const MOUSE_DATA_QUEUE_SIZE: u32 = 200;
+
+// QoS policy carrying the session's DSCP tag
+const QOS_POLICY_NAME: &str = "XillyGameMode Game";
+
+/// On/off visual effect switched through SystemParametersInfo
+struct VisualEffect {
+    name: &'static str,
+    get: SYSTEM_PARAMETERS_INFO_ACTION,
+    set: SYSTEM_PARAMETERS_INFO_ACTION,
+    /// SPI_SETDRAGFULLWINDOWS takes the value in uiParam, the others in pvParam
+    value_in_uiparam: bool,
+}
+
+// What Performance Options > "Adjust for best performance" turns off (font smoothing is kept)
+const VISUAL_EFFECTS: &[VisualEffect] = &[
+    VisualEffect { name: "menu animation", get: SPI_GETMENUANIMATION, set: SPI_SETMENUANIMATION, value_in_uiparam: false },
+    VisualEffect { name: "combo box animation", get: SPI_GETCOMBOBOXANIMATION, set: SPI_SETCOMBOBOXANIMATION, value_in_uiparam: false },
+    VisualEffect { name: "smooth scrolling", get: SPI_GETLISTBOXSMOOTHSCROLLING, set: SPI_SETLISTBOXSMOOTHSCROLLING, value_in_uiparam: false },
+    VisualEffect { name: "tooltip animation", get: SPI_GETTOOLTIPANIMATION, set: SPI_SETTOOLTIPANIMATION, value_in_uiparam: false },
+    VisualEffect { name: "selection fade", get: SPI_GETSELECTIONFADE, set: SPI_SETSELECTIONFADE, value_in_uiparam: false },
+    VisualEffect { name: "cursor shadow", get: SPI_GETCURSORSHADOW, set: SPI_SETCURSORSHADOW, value_in_uiparam: false },
+    VisualEffect { name: "drop shadow", get: SPI_GETDROPSHADOW, set: SPI_SETDROPSHADOW, value_in_uiparam: false },
+    VisualEffect { name: "control animation", get: SPI_GETCLIENTAREAANIMATION, set: SPI_SETCLIENTAREAANIMATION, value_in_uiparam: false },
+    VisualEffect { name: "window contents while dragging", get: SPI_GETDRAGFULLWINDOWS, set: SPI_SETDRAGFULLWINDOWS, value_in_uiparam: true },
+];
+
+const PERSONALIZE: &str = r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+
+/// Visual effect state captured before switching to best performance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VisualEffectsSnapshot {
+    /// (index into VISUAL_EFFECTS, original value) for effects that were on
+    effects: Vec<(usize, bool)>,
+    /// Minimize/maximize animation (ANIMATIONINFO.iMinAnimate), if it was on
+    min_animate: Option<i32>,
+    /// Original EnableTransparency (outer Some = we changed it)
+    transparency: Option<Option<u32>>,
+}
+
+/// Processor power setting in the sub_processor subgroup
+#[derive(PartialEq)]
+struct ProcessorSetting {
+    /// powercfg alias, used in logs
+    alias: &'static str,
+    guid: GUID,
+}
+
+static CPMINCORES: ProcessorSetting = ProcessorSetting { alias: "CPMINCORES", guid: GUID::from_u128(0x0cc5b647_c1df_4637_891a_dec35c318583) };
+static CPMAXCORES: ProcessorSetting = ProcessorSetting { alias: "CPMAXCORES", guid: GUID::from_u128(0xea062031_0e34_4ff1_9b6d_eb1059334028) };
+static PERFINCTHRESHOLD: ProcessorSetting = ProcessorSetting { alias: "PERFINCTHRESHOLD", guid: GUID::from_u128(0x06cadf0e_64ed_448a_8927_ce7bf90eb35d) };
+static PERFDECTHRESHOLD: ProcessorSetting = ProcessorSetting { alias: "PERFDECTHRESHOLD", guid: GUID::from_u128(0x12a0ab44_fe28_4fa9_b3bd_4b64f44960a6) };
+static PROCTHROTTLEMIN: ProcessorSetting = ProcessorSetting { alias: "PROCTHROTTLEMIN", guid: GUID::from_u128(0x893dee8e_2bef_41e0_89c6_b55d0929964c) };
+
+/// Every setting core parking may change, to find a journaled original by its alias
+static PROCESSOR_SETTINGS: [&ProcessorSetting; 5] = [&CPMINCORES, &CPMAXCORES, &PERFINCTHRESHOLD, &PERFDECTHRESHOLD, &PROCTHROTTLEMIN];
+
+/// Device power-saving setting switched off (AC and DC) while game mode is active
+struct DevicePowerSetting {
+    /// Name used in logs
+    label: &'static str,
+    subgroup: GUID,
+    setting: GUID,
+}
+
+// sub_usb / USBSELECTIVESUSPEND: 0 = disabled, 1 = enabled
+static USB_SELECTIVE_SUSPEND: DevicePowerSetting = DevicePowerSetting {
+    label: "USB selective suspend",
+    subgroup: GUID::from_u128(0x2a737441_1930_4402_8d77_b2bebba308a3),
+    setting: GUID::from_u128(0x48e6b7a6_50f5_4782_a5d4_53bb8f07e226),
+};
+
+// sub_pciexpress / ASPM: 0 = off, 1 = moderate, 2 = maximum power savings
+static PCIE_ASPM: DevicePowerSetting = DevicePowerSetting {
+    label: "PCIe link state power management",
+    subgroup: GUID::from_u128(0x501a4d13_42af_4429_9fd1_a8218c268e20),
+    setting: GUID::from_u128(0xee12f906_d277_404b_b6da_e5fa1a576df5),
+};
+
+/// DWORD switched off while Game DVR is disabled
+struct GameDvrValue {
+    hive: Hive,
+    path: &'static str,
+    name: &'static str,
+    off: u32,
+    /// Written back when nothing was captured (crash recovery); None = left as is
+    fallback: Option<u32>,
+}
+
+// Settings > Gaming > Captures, plus the Game Bar presence writer that Windows starts with every game
+// (ActivationType 0 = not activatable, 1 = out of process)
+const GAME_DVR_VALUES: &[GameDvrValue] = &[
+    GameDvrValue { hive: Hive::CurrentUser, path: r"System\GameConfigStore", name: "GameDVR_Enabled", off: 0, fallback: None },
+    GameDvrValue { hive: Hive::CurrentUser, path: r"Software\Microsoft\Windows\CurrentVersion\GameDVR", name: "AppCaptureEnabled", off: 0, fallback: None },
+    GameDvrValue { hive: Hive::CurrentUser, path: r"Software\Microsoft\Windows\CurrentVersion\GameDVR", name: "HistoricalCaptureEnabled", off: 0, fallback: None },
+    GameDvrValue {
+        hive: Hive::LocalMachine,
+        path: r"SOFTWARE\Microsoft\WindowsRuntime\ActivatableClassId\Windows.Gaming.GameBar.PresenceServer.Internal.PresenceWriter",
+        name: "ActivationType",
+        off: 0,
+        fallback: Some(1),
+    },
+];
+
+/// Originals of the applied modules, persisted in the session journal so crash recovery puts
+/// back the user's values instead of defaults (GUIDs as 32 hex digits). Process-bound state
+/// (demoted PIDs, the game's working set) and display state (HDR target, topology) is not kept
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModuleOriginals {
+    core_parking_scheme: Option<String>,
+    processor_indices: Vec<(String, u32)>,
+    device_power: Vec<(String, String, (u32, u32))>,
+    mouse_params: Option<[i32; 3]>,
+    mouse_queue_size: Option<Option<u32>>,
+    accessibility_flags: Option<(u32, u32)>,
+    directx_global_settings: Option<Option<String>>,
+    suspended_night_light: bool,
+    suspended_color_filter: bool,
+    visual_effects: Option<VisualEffectsSnapshot>,
+    system_responsiveness: Option<u32>,
+    no_lazy_mode: Option<u32>,
+    large_pages_enabled: bool,
+    hags_value: Option<u32>,
+    autotuning_level: Option<String>,
+    ecn: Option<bool>,
+    rsc: Option<bool>,
+    update_pause: Option<Vec<(String, Option<String>)>>,
+    do_policy: Option<Vec<(String, Option<u32>)>>,
+    defender_excluded_exe: Option<String>,
+    auto_game_mode: Option<Option<u32>>,
+    game_config_key: Option<String>,
+    game_dvr: Option<Vec<(usize, Option<u32>)>>,
+}
 
 /// Stores original values before applying tweaks for proper restoration
 pub struct AdvancedModulesService {
-    // Core Parking original values
-    original_core_parking_min: Mutex<Option<u32>>,
-    original_core_parking_max: Mutex<Option<u32>>,
+    // Registry and power plan access (Win32, or an in-memory fake)
+    system: Arc<dyn SystemBackend>,
+    
+    // Core parking: power scheme the module wrote to and the original AC index of each setting
+    core_parking_scheme: Mutex<Option<GUID>>,
+    original_processor_indices: Mutex<Vec<(&'static ProcessorSetting, u32)>>,
+    
+    // USB selective suspend / PCIe ASPM: scheme written to and original (AC, DC) indices
+    original_device_power: Mutex<Vec<(String, GUID, (u32, u32))>>,
+    
+    // Mouse - original SPI_GETMOUSE [threshold1, threshold2, acceleration]
+    // and MouseDataQueueSize (outer Some = we changed it)
+    original_mouse_params: Mutex<Option<[i32; 3]>>,
+    original_mouse_queue_size: Mutex<Option<Option<u32>>>,
+    
+    // Sticky Keys / Filter Keys - original flags (shortcut prompts re-enabled from these)
+    original_accessibility_flags: Mutex<Option<(STICKYKEYS_FLAGS, u32)>>,
+    
+    // HDR - display we turned HDR on for (with its SDR calibration) and the original
+    // DirectXUserGlobalSettings string (outer Some = we changed it)
+    hdr_session: Mutex<Option<(HdrTarget, ColorSnapshot)>>,
+    original_directx_global_settings: Mutex<Option<Option<String>>>,
+    
+    // Night Light / color filters - which of the two we switched off
+    suspended_night_light: Mutex<bool>,
+    suspended_color_filter: Mutex<bool>,
+    
+    // Visual effects - original values of everything switched off
+    original_visual_effects: Mutex<Option<VisualEffectsSnapshot>>,
+    
+    // Secondary displays - topology before they were switched off
+    // (outer Some = module applied this run, inner None = nothing was switched off)
+    saved_topology: Mutex<Option<Option<DisplayTopology>>>,
     
     // MMCSS original values
     original_system_responsiveness: Mutex<Option<u32>>,
@@ -20,6 +225,9 @@ pub struct AdvancedModulesService {
     // Large Pages - track if we enabled it
     large_pages_enabled: Mutex<bool>,
     
+    // Game working set before it was raised: (pid, min, max, flags)
+    original_working_set: Mutex<Option<(u32, usize, usize, u32)>>,
+    
     // HAGS original value
     original_hags_value: Mutex<Option<u32>>,
     
@@ -28,27 +236,153 @@ pub struct AdvancedModulesService {
     
     // Bufferbloat - original TCP autotuning level
     original_autotuning_level: Mutex<Option<String>>,
+    
+    // ECN / RSC - original global TCP switches
+    original_ecn: Mutex<Option<bool>>,
+    original_rsc: Mutex<Option<bool>>,
+    
+    // Windows Update pause - original pause timestamps and DO policy values
+    original_update_pause: Mutex<Option<Vec<(String, Option<String>)>>>,
+    original_do_policy: Mutex<Option<Vec<(String, Option<u32>)>>>,
+    
+    // Defender - game exe we added as a process exclusion (None if pre-existing/not added)
+    defender_excluded_exe: Mutex<Option<String>>,
+    
+    // Windows Game Mode - original AutoGameModeEnabled (outer Some = we changed it)
+    // and the GameConfigStore child key we created for the game
+    original_auto_game_mode: Mutex<Option<Option<u32>>>,
+    game_config_key: Mutex<Option<String>>,
+    
+    // Game DVR - (index into GAME_DVR_VALUES, original value) for values we switched off
+    original_game_dvr: Mutex<Option<Vec<(usize, Option<u32>)>>>,
+    
+    // Registry changes written this run that only take effect after a reboot
+    pending_reboot: Mutex<Vec<&'static str>>,
+    
+    // Modules applied in the running session (None outside a session) and the game they target,
+    // so hot-toggles and disable revert exactly what was applied
+    session_modules: Mutex<Option<AdvancedModuleSettings>>,
+    session_game_pid: Mutex<Option<u32>>,
 }
 
 impl AdvancedModulesService {
     pub fn new() -> Self {
+        Self::with_system(Arc::new(Win32))
+    }
+
+    /// Service working against `system` instead of the live machine
+    pub fn with_system(system: Arc<dyn SystemBackend>) -> Self {
         Self {
-            original_core_parking_min: Mutex::new(None),
-            original_core_parking_max: Mutex::new(None),
+            system,
+            core_parking_scheme: Mutex::new(None),
+            original_processor_indices: Mutex::new(Vec::new()),
+            original_device_power: Mutex::new(Vec::new()),
+            original_mouse_params: Mutex::new(None),
+            original_mouse_queue_size: Mutex::new(None),
+            original_accessibility_flags: Mutex::new(None),
+            hdr_session: Mutex::new(None),
+            original_directx_global_settings: Mutex::new(None),
+            suspended_night_light: Mutex::new(false),
+            suspended_color_filter: Mutex::new(false),
+            original_visual_effects: Mutex::new(None),
+            saved_topology: Mutex::new(None),
             original_system_responsiveness: Mutex::new(None),
             original_no_lazy_mode: Mutex::new(None),
             large_pages_enabled: Mutex::new(false),
+            original_working_set: Mutex::new(None),
             original_hags_value: Mutex::new(None),
             // Pre-allocate with reasonable capacity to avoid reallocs
             demoted_processes: Mutex::new(Vec::with_capacity(32)),
             original_autotuning_level: Mutex::new(None),
+            original_ecn: Mutex::new(None),
+            original_rsc: Mutex::new(None),
+            original_update_pause: Mutex::new(None),
+            original_do_policy: Mutex::new(None),
+            defender_excluded_exe: Mutex::new(None),
+            original_auto_game_mode: Mutex::new(None),
+            game_config_key: Mutex::new(None),
+            original_game_dvr: Mutex::new(None),
+            pending_reboot: Mutex::new(Vec::new()),
+            session_modules: Mutex::new(None),
+            session_game_pid: Mutex::new(None),
+        }
+    }
+
+    /// Originals captured so far, for the session journal
+    pub fn originals(&self) -> ModuleOriginals {
+        ModuleOriginals {
+            core_parking_scheme: self.core_parking_scheme.lock().unwrap().as_ref().map(guid_to_hex),
+            processor_indices: self.original_processor_indices.lock().unwrap().iter()
+                .map(|(setting, value)| (setting.alias.to_string(), *value))
+                .collect(),
+            device_power: self.original_device_power.lock().unwrap().iter()
+                .map(|(label, scheme, values)| (label.clone(), guid_to_hex(scheme), *values))
+                .collect(),
+            mouse_params: *self.original_mouse_params.lock().unwrap(),
+            mouse_queue_size: *self.original_mouse_queue_size.lock().unwrap(),
+            accessibility_flags: self.original_accessibility_flags.lock().unwrap().map(|(sticky, filter)| (sticky.0, filter)),
+            directx_global_settings: self.original_directx_global_settings.lock().unwrap().clone(),
+            suspended_night_light: *self.suspended_night_light.lock().unwrap(),
+            suspended_color_filter: *self.suspended_color_filter.lock().unwrap(),
+            visual_effects: self.original_visual_effects.lock().unwrap().clone(),
+            system_responsiveness: *self.original_system_responsiveness.lock().unwrap(),
+            no_lazy_mode: *self.original_no_lazy_mode.lock().unwrap(),
+            large_pages_enabled: *self.large_pages_enabled.lock().unwrap(),
+            hags_value: *self.original_hags_value.lock().unwrap(),
+            autotuning_level: self.original_autotuning_level.lock().unwrap().clone(),
+            ecn: *self.original_ecn.lock().unwrap(),
+            rsc: *self.original_rsc.lock().unwrap(),
+            update_pause: self.original_update_pause.lock().unwrap().clone(),
+            do_policy: self.original_do_policy.lock().unwrap().clone(),
+            defender_excluded_exe: self.defender_excluded_exe.lock().unwrap().clone(),
+            auto_game_mode: *self.original_auto_game_mode.lock().unwrap(),
+            game_config_key: self.game_config_key.lock().unwrap().clone(),
+            game_dvr: self.original_game_dvr.lock().unwrap().clone(),
         }
     }
 
-    /// Apply all enabled advanced modules
+    /// Service that reverts to journaled originals (crash recovery)
+    pub fn from_originals(system: Arc<dyn SystemBackend>, originals: &ModuleOriginals) -> Self {
+        let service = Self::with_system(system);
+        *service.core_parking_scheme.lock().unwrap() = originals.core_parking_scheme.as_deref().and_then(guid_from_hex);
+        *service.original_processor_indices.lock().unwrap() = originals.processor_indices.iter()
+            .filter_map(|(alias, value)| {
+                let setting = PROCESSOR_SETTINGS.iter().find(|s| s.alias == alias.as_str())?;
+                Some((*setting, *value))
+            })
+            .collect();
+        *service.original_device_power.lock().unwrap() = originals.device_power.iter()
+            .filter_map(|(label, scheme, values)| Some((label.clone(), guid_from_hex(scheme)?, *values)))
+            .collect();
+        *service.original_mouse_params.lock().unwrap() = originals.mouse_params;
+        *service.original_mouse_queue_size.lock().unwrap() = originals.mouse_queue_size;
+        *service.original_accessibility_flags.lock().unwrap() =
+            originals.accessibility_flags.map(|(sticky, filter)| (STICKYKEYS_FLAGS(sticky), filter));
+        *service.original_directx_global_settings.lock().unwrap() = originals.directx_global_settings.clone();
+        *service.suspended_night_light.lock().unwrap() = originals.suspended_night_light;
+        *service.suspended_color_filter.lock().unwrap() = originals.suspended_color_filter;
+        *service.original_visual_effects.lock().unwrap() = originals.visual_effects.clone();
+        *service.original_system_responsiveness.lock().unwrap() = originals.system_responsiveness;
+        *service.original_no_lazy_mode.lock().unwrap() = originals.no_lazy_mode;
+        *service.large_pages_enabled.lock().unwrap() = originals.large_pages_enabled;
+        *service.original_hags_value.lock().unwrap() = originals.hags_value;
+        *service.original_autotuning_level.lock().unwrap() = originals.autotuning_level.clone();
+        *service.original_ecn.lock().unwrap() = originals.ecn;
+        *service.original_rsc.lock().unwrap() = originals.rsc;
+        *service.original_update_pause.lock().unwrap() = originals.update_pause.clone();
+        *service.original_do_policy.lock().unwrap() = originals.do_policy.clone();
+        *service.defender_excluded_exe.lock().unwrap() = originals.defender_excluded_exe.clone();
+        *service.original_auto_game_mode.lock().unwrap() = originals.auto_game_mode;
+        *service.game_config_key.lock().unwrap() = originals.game_config_key.clone();
+        *service.original_game_dvr.lock().unwrap() = originals.game_dvr.clone();
+        service
+    }
+
+    /// Apply all enabled advanced modules (minus those this hardware can't use)
     pub fn enable(&self, settings: &AdvancedModuleSettings) {
+        let settings = &Capabilities::get().gate(settings);
         if settings.disable_core_parking {
-            self.disable_core_parking();
+            self.disable_core_parking(settings);
         }
         if settings.mmcss_priority_boost {
             self.enable_mmcss_boost();
@@ -57,18 +391,103 @@ impl AdvancedModulesService {
             self.enable_large_pages();
         }
         if settings.enable_hags {
-            self.enable_hags();
+            self.enable_hags(&settings.target_gpu);
         }
         if settings.process_idle_demotion {
-            self.enable_process_demotion();
+            self.enable_process_demotion(settings);
         }
         if settings.lower_bufferbloat {
             self.enable_lower_bufferbloat();
         }
+        if settings.disable_ecn {
+            self.disable_ecn();
+        }
+        if settings.disable_rsc {
+            self.disable_rsc();
+        }
+        if settings.flush_dns_cache {
+            NetworkService::flush_dns_cache();
+        }
+        if settings.dns_override {
+            NetworkService::apply_dns_override(&settings.dns_servers);
+        }
+        if settings.tune_network_adapter {
+            NetworkService::apply_adapter_tuning();
+        }
+        if settings.pause_windows_update {
+            self.pause_windows_update();
+        }
+        if settings.disable_usb_suspend {
+            self.disable_device_power_saving(&USB_SELECTIVE_SUSPEND);
+        }
+        if settings.disable_pcie_aspm {
+            self.disable_device_power_saving(&PCIE_ASPM);
+        }
+        if settings.disable_mouse_acceleration {
+            self.disable_mouse_acceleration();
+        }
+        if settings.raise_mouse_queue_size {
+            self.raise_mouse_queue_size();
+        }
+        if settings.suppress_accessibility_hotkeys {
+            self.suppress_accessibility_hotkeys();
+        }
+        if settings.disable_audio_enhancements {
+            AudioService::disable_enhancements();
+        }
+        if settings.disable_comms_ducking {
+            AudioService::disable_ducking();
+        }
+        // Auto HDR only works with HDR on
+        if settings.enable_hdr || settings.enable_auto_hdr {
+            self.enable_hdr();
+        }
+        if settings.enable_auto_hdr {
+            self.enable_auto_hdr();
+        }
+        if settings.suspend_night_light {
+            self.suspend_night_light();
+        }
+        if settings.best_performance_visuals {
+            self.enable_best_performance_visuals();
+        }
+        if settings.disable_secondary_displays {
+            self.disable_secondary_displays();
+        }
+        if settings.disable_game_dvr {
+            self.disable_game_dvr();
+        }
+        *self.session_modules.lock().unwrap() = Some(settings.clone());
+    }
+
+    /// Apply modules that need the detected game (called once the game PID is known)
+    pub fn enable_for_game(&self, settings: &AdvancedModuleSettings, game_pid: u32) {
+        let settings = &Capabilities::get().gate(settings);
+        *self.session_game_pid.lock().unwrap() = Some(game_pid);
+        if settings.enable_large_pages && settings.raise_game_working_set {
+            self.raise_game_working_set(game_pid, settings.game_min_working_set_mb);
+        }
+        if settings.defender_game_exclusion {
+            self.add_defender_exclusion(game_pid);
+        }
+        if settings.windows_game_mode_coop {
+            self.enable_windows_game_mode(game_pid);
+        }
+        if settings.block_windows_key {
+            hotkey::block_windows_key(game_pid);
+        }
+        if settings.dscp_tagging {
+            self.add_qos_policy(game_pid, settings.dscp_value);
+        }
     }
 
     /// Restore all tweaks to original values
+    /// Uses the session's module set when known, so modules toggled mid-session are handled
     pub fn disable(&self, settings: &AdvancedModuleSettings) {
+        let applied = self.session_modules.lock().unwrap().take();
+        let settings = applied.as_ref().unwrap_or(settings);
+        *self.session_game_pid.lock().unwrap() = None;
+        
         if settings.disable_core_parking {
             self.restore_core_parking();
         }
@@ -87,77 +506,800 @@ impl AdvancedModulesService {
         if settings.lower_bufferbloat {
             self.restore_bufferbloat();
         }
+        if settings.disable_ecn {
+            self.restore_ecn();
+        }
+        if settings.disable_rsc {
+            self.restore_rsc();
+        }
+        if settings.dns_override {
+            NetworkService::restore_dns_override();
+        }
+        if settings.tune_network_adapter {
+            NetworkService::restore_adapter_tuning();
+        }
+        if settings.pause_windows_update {
+            self.resume_windows_update();
+        }
+        if settings.disable_usb_suspend {
+            self.restore_device_power_saving(&USB_SELECTIVE_SUSPEND);
+        }
+        if settings.disable_pcie_aspm {
+            self.restore_device_power_saving(&PCIE_ASPM);
+        }
+        if settings.disable_mouse_acceleration {
+            self.restore_mouse_acceleration();
+        }
+        if settings.raise_mouse_queue_size {
+            self.restore_mouse_queue_size();
+        }
+        if settings.suppress_accessibility_hotkeys {
+            self.restore_accessibility_hotkeys();
+        }
+        if settings.disable_audio_enhancements {
+            AudioService::restore_enhancements();
+        }
+        if settings.disable_comms_ducking {
+            AudioService::restore_ducking();
+        }
+        if settings.suspend_night_light {
+            self.restore_night_light();
+        }
+        if settings.best_performance_visuals {
+            self.restore_visual_effects();
+        }
+        if settings.disable_secondary_displays {
+            self.restore_secondary_displays();
+        }
+        if settings.disable_game_dvr {
+            self.restore_game_dvr();
+        }
+        if settings.enable_auto_hdr {
+            self.restore_auto_hdr();
+        }
+        if settings.enable_hdr || settings.enable_auto_hdr {
+            self.restore_hdr();
+        }
+        if settings.block_windows_key {
+            hotkey::unblock_windows_key();
+        }
+        if settings.defender_game_exclusion {
+            self.remove_defender_exclusion();
+        }
+        if settings.windows_game_mode_coop {
+            self.restore_windows_game_mode();
+        }
+        if settings.dscp_tagging {
+            self.remove_qos_policy();
+        }
+        if settings.enable_large_pages && settings.raise_game_working_set {
+            self.restore_game_working_set();
+        }
+    }
+
+    /// Apply or revert only the modules that changed while game mode is active
+    /// Returns false outside a session (changes take effect on the next enable)
+    pub fn apply_changes(&self, new: &AdvancedModuleSettings) -> bool {
+        let Some(old) = self.session_modules.lock().unwrap().clone() else {
+            return false;
+        };
+        let new = &Capabilities::get().gate(new);
+        let game_pid = *self.session_game_pid.lock().unwrap();
+
+        // Changed parking values while on: restore the originals, then write the new set
+        let parking_changed = Self::core_parking_targets(&old) != Self::core_parking_targets(new);
+        if old.disable_core_parking && new.disable_core_parking && parking_changed {
+            self.restore_core_parking();
+            self.disable_core_parking(new);
+        }
+        Self::hot_toggle(old.disable_core_parking, new.disable_core_parking,
+            || self.disable_core_parking(new), || self.restore_core_parking());
+        Self::hot_toggle(old.mmcss_priority_boost, new.mmcss_priority_boost,
+            || self.enable_mmcss_boost(), || self.restore_mmcss());
+        Self::hot_toggle(old.enable_large_pages, new.enable_large_pages,
+            || self.enable_large_pages(), || self.restore_large_pages());
+        Self::hot_toggle(old.lower_bufferbloat, new.lower_bufferbloat,
+            || self.enable_lower_bufferbloat(), || self.restore_bufferbloat());
+        Self::hot_toggle(old.disable_ecn, new.disable_ecn,
+            || self.disable_ecn(), || self.restore_ecn());
+        Self::hot_toggle(old.disable_rsc, new.disable_rsc,
+            || self.disable_rsc(), || self.restore_rsc());
+        // Flushing is one-shot, there is nothing to revert
+        Self::hot_toggle(old.flush_dns_cache, new.flush_dns_cache,
+            NetworkService::flush_dns_cache, || {});
+        Self::hot_toggle(old.dns_override, new.dns_override,
+            || NetworkService::apply_dns_override(&new.dns_servers), NetworkService::restore_dns_override);
+        Self::hot_toggle(old.tune_network_adapter, new.tune_network_adapter,
+            NetworkService::apply_adapter_tuning, NetworkService::restore_adapter_tuning);
+        Self::hot_toggle(old.pause_windows_update, new.pause_windows_update,
+            || self.pause_windows_update(), || self.resume_windows_update());
+        Self::hot_toggle(old.disable_usb_suspend, new.disable_usb_suspend,
+            || self.disable_device_power_saving(&USB_SELECTIVE_SUSPEND),
+            || self.restore_device_power_saving(&USB_SELECTIVE_SUSPEND));
+        Self::hot_toggle(old.disable_pcie_aspm, new.disable_pcie_aspm,
+            || self.disable_device_power_saving(&PCIE_ASPM),
+            || self.restore_device_power_saving(&PCIE_ASPM));
+        Self::hot_toggle(old.disable_mouse_acceleration, new.disable_mouse_acceleration,
+            || self.disable_mouse_acceleration(), || self.restore_mouse_acceleration());
+        Self::hot_toggle(old.raise_mouse_queue_size, new.raise_mouse_queue_size,
+            || self.raise_mouse_queue_size(), || self.restore_mouse_queue_size());
+        Self::hot_toggle(old.suppress_accessibility_hotkeys, new.suppress_accessibility_hotkeys,
+            || self.suppress_accessibility_hotkeys(), || self.restore_accessibility_hotkeys());
+        Self::hot_toggle(old.disable_audio_enhancements, new.disable_audio_enhancements,
+            AudioService::disable_enhancements, AudioService::restore_enhancements);
+        Self::hot_toggle(old.disable_comms_ducking, new.disable_comms_ducking,
+            AudioService::disable_ducking, AudioService::restore_ducking);
+        Self::hot_toggle(old.enable_hdr || old.enable_auto_hdr, new.enable_hdr || new.enable_auto_hdr,
+            || self.enable_hdr(), || self.restore_hdr());
+        Self::hot_toggle(old.enable_auto_hdr, new.enable_auto_hdr,
+            || self.enable_auto_hdr(), || self.restore_auto_hdr());
+        Self::hot_toggle(old.suspend_night_light, new.suspend_night_light,
+            || self.suspend_night_light(), || self.restore_night_light());
+        Self::hot_toggle(old.best_performance_visuals, new.best_performance_visuals,
+            || self.enable_best_performance_visuals(), || self.restore_visual_effects());
+        Self::hot_toggle(old.disable_secondary_displays, new.disable_secondary_displays,
+            || self.disable_secondary_displays(), || self.restore_secondary_displays());
+        Self::hot_toggle(old.disable_game_dvr, new.disable_game_dvr,
+            || self.disable_game_dvr(), || self.restore_game_dvr());
+
+        // HAGS targets one GPU; switching GPU means revert then re-apply
+        if old.enable_hags != new.enable_hags || (new.enable_hags && old.target_gpu != new.target_gpu) {
+            if old.enable_hags {
+                self.restore_hags();
+            }
+            if new.enable_hags {
+                self.enable_hags(&new.target_gpu);
+            }
+        }
+
+        // A changed category set re-demotes from scratch
+        let old_list = old.process_idle_demotion.then(|| Self::demotion_list(&old));
+        let new_list = new.process_idle_demotion.then(|| Self::demotion_list(new));
+        if old_list != new_list {
+            if old.process_idle_demotion {
+                self.restore_process_priority();
+            }
+            if new.process_idle_demotion {
+                self.enable_process_demotion(new);
+            }
+        }
+
+        // Game-bound modules can only be applied once the game is known
+        if let Some(pid) = game_pid {
+            Self::hot_toggle(
+                old.enable_large_pages && old.raise_game_working_set,
+                new.enable_large_pages && new.raise_game_working_set,
+                || self.raise_game_working_set(pid, new.game_min_working_set_mb),
+                || self.restore_game_working_set());
+            Self::hot_toggle(old.defender_game_exclusion, new.defender_game_exclusion,
+                || self.add_defender_exclusion(pid), || self.remove_defender_exclusion());
+            Self::hot_toggle(old.windows_game_mode_coop, new.windows_game_mode_coop,
+                || self.enable_windows_game_mode(pid), || self.restore_windows_game_mode());
+            Self::hot_toggle(old.block_windows_key, new.block_windows_key,
+                || hotkey::block_windows_key(pid), hotkey::unblock_windows_key);
+            Self::hot_toggle(old.dscp_tagging, new.dscp_tagging,
+                || self.add_qos_policy(pid, new.dscp_value), || self.remove_qos_policy());
+        }
+
+        *self.session_modules.lock().unwrap() = Some(new.clone());
+        log_line!("[AdvancedModules] Applied module changes to the active session");
+        true
+    }
+
+    /// Modules applied in the running session, for the modified-state page
+    pub fn modified_items(&self) -> Vec<ModifiedItem> {
+        let Some(applied) = self.session_modules.lock().unwrap().clone() else {
+            return Vec::new();
+        };
+        let has_game = self.session_game_pid.lock().unwrap().is_some();
+
+        let modules = [
+            (applied.disable_core_parking, "adv.disable_core_parking", tr!("Core parking disabled")),
+            (applied.mmcss_priority_boost, "adv.mmcss_priority_boost", tr!("MMCSS priority boost")),
+            (applied.enable_large_pages, "adv.enable_large_pages", tr!("Large system pages")),
+            (applied.enable_large_pages && applied.raise_game_working_set && has_game,
+                "adv.raise_game_working_set", tr!("Game minimum working set raised")),
+            (applied.enable_hags, "adv.enable_hags", tr!("Hardware GPU scheduling")),
+            (applied.process_idle_demotion, "adv.process_idle_demotion", tr!("Background processes demoted to idle")),
+            (applied.lower_bufferbloat, "adv.lower_bufferbloat", tr!("TCP autotuning disabled (session)")),
+            (applied.disable_ecn, "adv.disable_ecn", tr!("TCP ECN disabled")),
+            (applied.disable_rsc, "adv.disable_rsc", tr!("Receive Segment Coalescing disabled")),
+            (applied.dns_override, "adv.dns_override", tr!("DNS servers overridden")),
+            (applied.tune_network_adapter, "adv.tune_network_adapter", tr!("Network adapter tuning")),
+            (applied.pause_windows_update, "adv.pause_windows_update", tr!("Windows Update paused")),
+            (applied.disable_usb_suspend, "adv.disable_usb_suspend", tr!("USB selective suspend disabled")),
+            (applied.disable_pcie_aspm, "adv.disable_pcie_aspm", tr!("PCIe link power saving disabled")),
+            (applied.disable_mouse_acceleration, "adv.disable_mouse_acceleration", tr!("Enhance pointer precision off")),
+            (applied.raise_mouse_queue_size, "adv.raise_mouse_queue_size", tr!("Mouse data queue size raised")),
+            (applied.suppress_accessibility_hotkeys, "adv.suppress_accessibility_hotkeys", tr!("Sticky/Filter Keys shortcuts off")),
+            (applied.disable_audio_enhancements, "adv.disable_audio_enhancements", tr!("Audio enhancements disabled")),
+            (applied.disable_comms_ducking, "adv.disable_comms_ducking", tr!("Communications ducking off")),
+            (applied.enable_hdr, "adv.enable_hdr", tr!("HDR turned on")),
+            (applied.enable_auto_hdr, "adv.enable_auto_hdr", tr!("Auto HDR turned on")),
+            (applied.suspend_night_light, "adv.suspend_night_light", tr!("Night Light / color filters off")),
+            (applied.best_performance_visuals, "adv.best_performance_visuals", tr!("Visual effects set to best performance")),
+            (applied.disable_secondary_displays, "adv.disable_secondary_displays", tr!("Secondary displays off")),
+            (applied.disable_game_dvr, "adv.disable_game_dvr", tr!("Game DVR background recording off")),
+            (applied.defender_game_exclusion && has_game, "adv.defender_game_exclusion", tr!("Defender exclusion for the game")),
+            (applied.windows_game_mode_coop && has_game, "adv.windows_game_mode_coop", tr!("Windows Game Mode entry for the game")),
+            (applied.block_windows_key && has_game, "adv.block_windows_key", tr!("Windows key blocked in game")),
+            (applied.dscp_tagging && has_game, "adv.dscp_tagging", tr!("Game traffic tagged with DSCP")),
+        ];
+
+        modules.into_iter()
+            .filter(|(on, _, _)| *on)
+            .map(|(_, id, label)| ModifiedItem::session(id, label))
+            .collect()
+    }
+
+    /// Revert one module for the rest of the session (settings are left unchanged)
+    pub fn revert_module(&self, id: &str) -> bool {
+        let Some(mut modules) = self.session_modules.lock().unwrap().clone() else {
+            return false;
+        };
+
+        match id {
+            "adv.disable_core_parking" => modules.disable_core_parking = false,
+            "adv.mmcss_priority_boost" => modules.mmcss_priority_boost = false,
+            "adv.enable_large_pages" => modules.enable_large_pages = false,
+            "adv.raise_game_working_set" => modules.raise_game_working_set = false,
+            "adv.enable_hags" => modules.enable_hags = false,
+            "adv.process_idle_demotion" => modules.process_idle_demotion = false,
+            "adv.lower_bufferbloat" => modules.lower_bufferbloat = false,
+            "adv.disable_ecn" => modules.disable_ecn = false,
+            "adv.disable_rsc" => modules.disable_rsc = false,
+            "adv.dns_override" => modules.dns_override = false,
+            "adv.tune_network_adapter" => modules.tune_network_adapter = false,
+            "adv.pause_windows_update" => modules.pause_windows_update = false,
+            "adv.disable_usb_suspend" => modules.disable_usb_suspend = false,
+            "adv.disable_pcie_aspm" => modules.disable_pcie_aspm = false,
+            "adv.disable_mouse_acceleration" => modules.disable_mouse_acceleration = false,
+            "adv.raise_mouse_queue_size" => modules.raise_mouse_queue_size = false,
+            "adv.suppress_accessibility_hotkeys" => modules.suppress_accessibility_hotkeys = false,
+            "adv.disable_audio_enhancements" => modules.disable_audio_enhancements = false,
+            "adv.disable_comms_ducking" => modules.disable_comms_ducking = false,
+            "adv.enable_hdr" => modules.enable_hdr = false,
+            "adv.enable_auto_hdr" => modules.enable_auto_hdr = false,
+            "adv.suspend_night_light" => modules.suspend_night_light = false,
+            "adv.best_performance_visuals" => modules.best_performance_visuals = false,
+            "adv.disable_secondary_displays" => modules.disable_secondary_displays = false,
+            "adv.disable_game_dvr" => modules.disable_game_dvr = false,
+            "adv.defender_game_exclusion" => modules.defender_game_exclusion = false,
+            "adv.windows_game_mode_coop" => modules.windows_game_mode_coop = false,
+            "adv.block_windows_key" => modules.block_windows_key = false,
+            "adv.dscp_tagging" => modules.dscp_tagging = false,
+            _ => return false,
+        }
+
+        self.apply_changes(&modules)
+    }
+
+    fn hot_toggle(was: bool, now: bool, apply: impl FnOnce(), revert: impl FnOnce()) {
+        match (was, now) {
+            (false, true) => apply(),
+            (true, false) => revert(),
+            _ => {}
+        }
     }
 
     // =========================================================================
     // 1. CORE PARKING DISABLE
     // Prevents micro-stutter from core wake latency
-    // Registry: HKLM\SYSTEM\CurrentControlSet\Control\Power\PowerSettings\...
+    // Power plan: sub_processor CPMINCORES/CPMAXCORES, optional perf thresholds and frequency floor
     // =========================================================================
 
-    fn disable_core_parking(&self) {
-        // Core parking is controlled via power settings
-        // We set minimum parked cores to 100% (meaning no cores can park)
-        // Path: 54533251-82be-4824-96c1-47b60b740d00\0cc5b647-c1df-4637-891a-dec35c318583
+    /// Processor settings (sub_processor) and the values the module writes
+    fn core_parking_targets(settings: &AdvancedModuleSettings) -> Vec<(&'static ProcessorSetting, u32)> {
+        let mut targets = vec![
+            (&CPMINCORES, settings.parking_min_cores.min(100)),
+            (&CPMAXCORES, settings.parking_max_cores.min(100)),
+        ];
+        let optional = [
+            (&PERFINCTHRESHOLD, settings.perf_increase_threshold),
+            (&PERFDECTHRESHOLD, settings.perf_decrease_threshold),
+            (&PROCTHROTTLEMIN, settings.min_processor_state),
+        ];
+        targets.extend(optional.into_iter().filter_map(|(setting, value)| Some((setting, value?.min(100)))));
+        targets
+    }
+
+    fn disable_core_parking(&self, settings: &AdvancedModuleSettings) {
+        // Pin the scheme: restore must hit the plan we changed even if the active plan switches
+        let mut scheme_guard = self.core_parking_scheme.lock().unwrap();
+        let Some(scheme) = scheme_guard.or_else(|| self.system.active_scheme()) else {
+            log_line!("[AdvancedModules] No active power scheme, skipping core parking");
+            return;
+        };
+        *scheme_guard = Some(scheme);
+        drop(scheme_guard);
         
-        let power_path = r"SYSTEM\CurrentControlSet\Control\Power\PowerSettings\54533251-82be-4824-96c1-47b60b740d00\0cc5b647-c1df-4637-891a-dec35c318583";
+        let mut originals = self.original_processor_indices.lock().unwrap();
+        for (setting, value) in Self::core_parking_targets(settings) {
+            // Keep the first original if the module is re-applied mid-session
+            if !originals.iter().any(|(s, _)| s.alias == setting.alias) {
+                match self.system.read_processor_ac_index(&scheme, &setting.guid) {
+                    Some(original) => originals.push((setting, original)),
+                    None => log_line!("[AdvancedModules] Could not read {}, it will not be restored", setting.alias),
+                }
+            }
+            self.write_processor_index(&scheme, setting, value);
+        }
+        drop(originals);
         
-        // Store original values
-        let original_min = Self::read_registry_dword(HKEY_LOCAL_MACHINE, power_path, "ValueMin");
-        let original_max = Self::read_registry_dword(HKEY_LOCAL_MACHINE, power_path, "ValueMax");
+        self.reapply_scheme(&scheme);
+        log_line!("[AdvancedModules] Core parking disabled");
+    }
+
+    fn restore_core_parking(&self) {
+        let originals = std::mem::take(&mut *self.original_processor_indices.lock().unwrap());
+        let scheme = self.core_parking_scheme.lock().unwrap().take();
+        // Originals and the pinned scheme come from this session or its journal; without them
+        // there is nothing known to write back, and guessed defaults would overwrite the user's plan
+        let Some(scheme) = scheme.filter(|_| !originals.is_empty()) else {
+            log_line!("[AdvancedModules] No core parking originals captured, leaving the power plan as is");
+            return;
+        };
+        for (setting, value) in &originals {
+            self.write_processor_index(&scheme, setting, *value);
+        }
         
-        *self.original_core_parking_min.lock().unwrap() = original_min;
-        *self.original_core_parking_max.lock().unwrap() = original_max;
+        self.reapply_scheme(&scheme);
+        log_line!("[AdvancedModules] Core parking restored");
+    }
+
+    fn write_processor_index(&self, scheme: &GUID, setting: &ProcessorSetting, value: u32) {
+        if !self.system.write_processor_ac_index(scheme, &setting.guid, value) {
+            log_line!("[AdvancedModules] Failed to set {} to {}", setting.alias, value);
+        }
+    }
+
+    /// Re-activate the scheme if it is the active one, so the new indices take effect
+    fn reapply_scheme(&self, scheme: &GUID) {
+        if self.system.active_scheme().as_ref() == Some(scheme) && !self.system.set_active_scheme(scheme) {
+            log_line!("[AdvancedModules] Failed to re-apply the power scheme");
+        }
+    }
+
+    // =========================================================================
+    // 18/19. USB SELECTIVE SUSPEND / PCIE ASPM
+    // Stops USB devices and PCIe links (GPU, NVMe) dropping into low-power states mid-game
+    // Power plan: sub_usb USBSELECTIVESUSPEND, sub_pciexpress ASPM (AC and DC)
+    // =========================================================================
+
+    fn disable_device_power_saving(&self, device: &'static DevicePowerSetting) {
+        let mut originals = self.original_device_power.lock().unwrap();
+        // Already applied this session: write again to the scheme holding the original
+        let pinned = originals.iter().find(|(label, _, _)| *label == device.label).map(|(_, scheme, _)| *scheme);
+        let Some(scheme) = pinned.or_else(|| self.system.active_scheme()) else {
+            log_line!("[AdvancedModules] No active power scheme, skipping {}", device.label);
+            return;
+        };
         
-        // Also need to modify the active power scheme
-        // Use powercfg to set core parking to 100% (disabled)
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        if pinned.is_none() {
+            match self.system.read_value_indices(&scheme, &device.subgroup, &device.setting) {
+                Some(original) => originals.push((device.label.to_string(), scheme, original)),
+                None => {
+                    // Hidden or absent on this plan/hardware: nothing to change
+                    log_line!("[AdvancedModules] Could not read {}, skipping", device.label);
+                    return;
+                }
+            }
+        }
+        drop(originals);
         
-        // Processor performance core parking min cores (AC) - set to 100
-        let _ = Command::new("powercfg")
-            .args(["/setacvalueindex", "scheme_current", "sub_processor", "CPMINCORES", "100"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        if !self.system.write_value_indices(&scheme, &device.subgroup, &device.setting, 0, 0) {
+            log_line!("[AdvancedModules] Failed to disable {}", device.label);
+        }
+        self.reapply_scheme(&scheme);
+        log_line!("[AdvancedModules] {} disabled", device.label);
+    }
+
+    fn restore_device_power_saving(&self, device: &'static DevicePowerSetting) {
+        let mut originals = self.original_device_power.lock().unwrap();
+        let captured = originals.iter().position(|(label, _, _)| *label == device.label)
+            .map(|index| originals.remove(index));
+        drop(originals);
         
-        // Processor performance core parking max cores (AC) - set to 100
-        let _ = Command::new("powercfg")
-            .args(["/setacvalueindex", "scheme_current", "sub_processor", "CPMAXCORES", "100"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        // Nothing captured: the current value may be the user's own, leave it
+        let Some((_, scheme, (ac, dc))) = captured else {
+            log_line!("[AdvancedModules] No original {} captured, leaving it as is", device.label);
+            return;
+        };
+        if !self.system.write_value_indices(&scheme, &device.subgroup, &device.setting, ac, dc) {
+            log_line!("[AdvancedModules] Failed to restore {}", device.label);
+            notify::attention(&tr!("Power setting not restored"), &tr!("{} could not be restored. Check it in Power Options.", device.label));
+        }
+        self.reapply_scheme(&scheme);
+        log_line!("[AdvancedModules] {} restored", device.label);
+    }
+
+    // =========================================================================
+    // 20/21. MOUSE ACCELERATION / DATA QUEUE SIZE
+    // SPI_SETMOUSE (session only, not written to the user profile)
+    // Registry: HKLM\SYSTEM\CurrentControlSet\Services\mouclass\Parameters
+    // =========================================================================
+
+    fn disable_mouse_acceleration(&self) {
+        let mut original = self.original_mouse_params.lock().unwrap();
+        if original.is_none() {
+            let mut params = [0i32; 3];
+            let read = unsafe {
+                SystemParametersInfoW(SPI_GETMOUSE, 0, Some(params.as_mut_ptr() as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))
+            };
+            if read.is_err() {
+                log_line!("[AdvancedModules] Could not read mouse parameters, skipping");
+                return;
+            }
+            *original = Some(params);
+        }
+        drop(original);
         
-        // Apply the changes
-        let _ = Command::new("powercfg")
-            .args(["/setactive", "scheme_current"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        // Acceleration 0 = Enhance pointer precision off
+        if Self::set_mouse_params([0, 0, 0]) {
+            log_line!("[AdvancedModules] Enhance pointer precision disabled");
+        }
+    }
+
+    fn restore_mouse_acceleration(&self) {
+        let Some(params) = self.original_mouse_params.lock().unwrap().take() else {
+            log_line!("[AdvancedModules] No original mouse parameters captured, leaving them as is");
+            return;
+        };
+        if Self::set_mouse_params(params) {
+            log_line!("[AdvancedModules] Mouse acceleration restored");
+        }
+    }
+
+    fn set_mouse_params(mut params: [i32; 3]) -> bool {
+        let result = unsafe {
+            SystemParametersInfoW(SPI_SETMOUSE, 0, Some(params.as_mut_ptr() as *mut _), SPIF_SENDCHANGE)
+        };
+        if result.is_err() {
+            log_line!("[AdvancedModules] Failed to set mouse parameters");
+        }
+        result.is_ok()
+    }
+
+    fn raise_mouse_queue_size(&self) {
+        let mut original = self.original_mouse_queue_size.lock().unwrap();
+        if original.is_none() {
+            *original = Some(self.system.read_dword(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize"));
+        }
+        drop(original);
         
-        println!("[AdvancedModules] Core parking disabled");
+        self.system.write_dword(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize", MOUSE_DATA_QUEUE_SIZE);
+        self.mark_pending_reboot("Mouse data queue size");
+        log_line!("[AdvancedModules] MouseDataQueueSize set to {} (applies after reboot)", MOUSE_DATA_QUEUE_SIZE);
+    }
+
+    fn restore_mouse_queue_size(&self) {
+        // Outer None: nothing captured, the value is left alone; inner None: it was absent (driver default)
+        let Some(original) = self.original_mouse_queue_size.lock().unwrap().take() else {
+            log_line!("[AdvancedModules] No original MouseDataQueueSize captured, leaving it as is");
+            return;
+        };
+        match original {
+            Some(value) => { self.system.write_dword(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize", value); }
+            None => self.system.delete_value(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize"),
+        }
+        self.mark_pending_reboot("Mouse data queue size revert");
+        log_line!("[AdvancedModules] MouseDataQueueSize restored");
+    }
+
+    // =========================================================================
+    // 22. STICKY KEYS / FILTER KEYS SHORTCUT GUARD
+    // SPI_SETSTICKYKEYS / SPI_SETFILTERKEYS hotkey flags (session only)
+    // The Windows key block (23) is a keyboard hook in hotkey.rs
+    // =========================================================================
+
+    fn suppress_accessibility_hotkeys(&self) {
+        let (Some(mut sticky), Some(mut filter)) = (Self::read_sticky_keys(), Self::read_filter_keys()) else {
+            log_line!("[AdvancedModules] Could not read accessibility settings, skipping");
+            return;
+        };
+        
+        let mut original = self.original_accessibility_flags.lock().unwrap();
+        if original.is_none() {
+            *original = Some((sticky.dwFlags, filter.dwFlags));
+        }
+        drop(original);
+        
+        // Only the shortcut is turned off; a feature the user has switched on stays on
+        sticky.dwFlags &= !SKF_HOTKEYACTIVE;
+        filter.dwFlags &= !FKF_HOTKEYACTIVE;
+        Self::write_accessibility(sticky, filter);
+        log_line!("[AdvancedModules] Sticky Keys / Filter Keys shortcuts disabled");
+    }
+
+    fn restore_accessibility_hotkeys(&self) {
+        let (Some(mut sticky), Some(mut filter)) = (Self::read_sticky_keys(), Self::read_filter_keys()) else {
+            return;
+        };
+        
+        match self.original_accessibility_flags.lock().unwrap().take() {
+            Some((sticky_flags, filter_flags)) => {
+                sticky.dwFlags = sticky_flags;
+                filter.dwFlags = filter_flags;
+            }
+            None => {
+                // Nothing captured (crash recovery): re-enable the shortcuts (Windows default)
+                sticky.dwFlags |= SKF_HOTKEYACTIVE;
+                filter.dwFlags |= FKF_HOTKEYACTIVE;
+            }
+        }
+        Self::write_accessibility(sticky, filter);
+        log_line!("[AdvancedModules] Sticky Keys / Filter Keys shortcuts restored");
+    }
+
+    fn read_sticky_keys() -> Option<STICKYKEYS> {
+        let mut sticky = STICKYKEYS { cbSize: std::mem::size_of::<STICKYKEYS>() as u32, ..Default::default() };
+        unsafe {
+            SystemParametersInfoW(SPI_GETSTICKYKEYS, sticky.cbSize, Some(&mut sticky as *mut _ as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))
+                .is_ok().then_some(sticky)
+        }
+    }
+
+    fn read_filter_keys() -> Option<FILTERKEYS> {
+        let mut filter = FILTERKEYS { cbSize: std::mem::size_of::<FILTERKEYS>() as u32, ..Default::default() };
+        unsafe {
+            SystemParametersInfoW(SPI_GETFILTERKEYS, filter.cbSize, Some(&mut filter as *mut _ as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))
+                .is_ok().then_some(filter)
+        }
+    }
+
+    fn write_accessibility(mut sticky: STICKYKEYS, mut filter: FILTERKEYS) {
+        unsafe {
+            if SystemParametersInfoW(SPI_SETSTICKYKEYS, sticky.cbSize, Some(&mut sticky as *mut _ as *mut _), SPIF_SENDCHANGE).is_err() {
+                log_line!("[AdvancedModules] Failed to set Sticky Keys flags");
+            }
+            if SystemParametersInfoW(SPI_SETFILTERKEYS, filter.cbSize, Some(&mut filter as *mut _ as *mut _), SPIF_SENDCHANGE).is_err() {
+                log_line!("[AdvancedModules] Failed to set Filter Keys flags");
+            }
+        }
+    }
+
+    // =========================================================================
+    // 26/27. HDR / AUTO HDR
+    // Display configuration API advanced color state on the primary display
+    // Registry: HKCU\Software\Microsoft\DirectX\UserGpuPreferences DirectXUserGlobalSettings
+    // =========================================================================
+
+    fn enable_hdr(&self) {
+        let mut session = self.hdr_session.lock().unwrap();
+        if session.is_some() {
+            return; // Already applied
+        }
+        let Some(target) = DisplayService::primary_hdr_target() else {
+            log_line!("[AdvancedModules] Could not query the primary display, skipping HDR");
+            return;
+        };
+        if !target.supported {
+            log_line!("[AdvancedModules] Primary display does not support HDR, skipping");
+            return;
+        }
+        if target.enabled {
+            log_line!("[AdvancedModules] HDR already on, left alone");
+            return;
+        }
+        
+        // Calibration loaders' SDR gamma ramp is lost across the HDR switch
+        let snapshot = DisplayService::snapshot_color();
+        if DisplayService::set_hdr(&target, true) {
+            *session = Some((target, snapshot));
+            log_line!("[AdvancedModules] HDR enabled on the primary display");
+        } else {
+            log_line!("[AdvancedModules] Failed to enable HDR");
+        }
+    }
+
+    fn restore_hdr(&self) {
+        let Some((target, snapshot)) = self.hdr_session.lock().unwrap().take() else {
+            return; // HDR was already on (or unsupported), nothing to turn off
+        };
+        if DisplayService::set_hdr(&target, false) {
+            DisplayService::restore_color(&snapshot);
+            log_line!("[AdvancedModules] HDR restored to off");
+        } else {
+            log_line!("[AdvancedModules] Failed to turn HDR back off");
+            notify::attention(&tr!("HDR still on"), &tr!("HDR could not be turned back off. Use Settings > Display to switch it off."));
+        }
+    }
+
+    fn enable_auto_hdr(&self) {
+        let current = self.system.read_string(Hive::CurrentUser, DIRECTX_PREFERENCES, "DirectXUserGlobalSettings");
+        let updated = Self::with_auto_hdr(current.as_deref().unwrap_or(""), true);
+        if current.as_deref() == Some(updated.as_str()) {
+            return; // Already on
+        }
+        
+        let mut original = self.original_directx_global_settings.lock().unwrap();
+        if original.is_none() {
+            *original = Some(current);
+        }
+        self.system.write_string(Hive::CurrentUser, DIRECTX_PREFERENCES, "DirectXUserGlobalSettings", &updated);
+        log_line!("[AdvancedModules] Auto HDR enabled");
+    }
+
+    fn restore_auto_hdr(&self) {
+        // Outer None: we didn't change it; crash recovery just switches Auto HDR back off
+        let original = self.original_directx_global_settings.lock().unwrap().take();
+        let restored = match original {
+            Some(original) => original,
+            None => self.system.read_string(Hive::CurrentUser, DIRECTX_PREFERENCES, "DirectXUserGlobalSettings")
+                .map(|current| Self::with_auto_hdr(&current, false)),
+        };
+        match restored.filter(|value| !value.is_empty()) {
+            Some(value) => { self.system.write_string(Hive::CurrentUser, DIRECTX_PREFERENCES, "DirectXUserGlobalSettings", &value); }
+            None => self.system.delete_value(Hive::CurrentUser, DIRECTX_PREFERENCES, "DirectXUserGlobalSettings"),
+        }
+        log_line!("[AdvancedModules] Auto HDR restored");
+    }
+
+    /// "Key=Value;" list with AutoHDREnable set (or removed), other settings kept in order
+    fn with_auto_hdr(settings: &str, enabled: bool) -> String {
+        let mut result: String = settings.split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty() && !entry.starts_with("AutoHDREnable="))
+            .map(|entry| format!("{};", entry))
+            .collect();
+        if enabled {
+            result.push_str("AutoHDREnable=1;");
+        }
+        result
+    }
+
+    // =========================================================================
+    // 28. NIGHT LIGHT / COLOR FILTER SUSPENSION
+    // Night Light CloudStore state and HKCU\Software\Microsoft\ColorFiltering
+    // Only what was on is switched off, so only that is switched back on
+    // =========================================================================
+
+    fn suspend_night_light(&self) {
+        if DisplayService::night_light_enabled() == Some(true) {
+            if DisplayService::set_night_light(false) {
+                *self.suspended_night_light.lock().unwrap() = true;
+                log_line!("[AdvancedModules] Night Light suspended");
+            } else {
+                log_line!("[AdvancedModules] Failed to turn Night Light off");
+            }
+        }
+        if DisplayService::color_filter_active() {
+            if DisplayService::set_color_filter(false) {
+                *self.suspended_color_filter.lock().unwrap() = true;
+                log_line!("[AdvancedModules] Color filter suspended");
+            } else {
+                log_line!("[AdvancedModules] Failed to turn the color filter off");
+            }
+        }
+    }
+
+    fn restore_night_light(&self) {
+        if std::mem::take(&mut *self.suspended_night_light.lock().unwrap()) {
+            DisplayService::set_night_light(true);
+            log_line!("[AdvancedModules] Night Light restored");
+        }
+        if std::mem::take(&mut *self.suspended_color_filter.lock().unwrap()) {
+            DisplayService::set_color_filter(true);
+            log_line!("[AdvancedModules] Color filter restored");
+        }
+    }
+
+    // =========================================================================
+    // 29. VISUAL EFFECTS: BEST PERFORMANCE
+    // SystemParametersInfo animations/shadows (session only, not written to the profile)
+    // Registry: HKCU\...\Themes\Personalize EnableTransparency
+    // =========================================================================
+
+    fn enable_best_performance_visuals(&self) {
+        let mut original = self.original_visual_effects.lock().unwrap();
+        if original.is_some() {
+            return; // Already applied
+        }
+        
+        let mut snapshot = VisualEffectsSnapshot { effects: Vec::new(), min_animate: None, transparency: None };
+        for (index, effect) in VISUAL_EFFECTS.iter().enumerate() {
+            if Self::read_visual_effect(effect) == Some(true) && Self::write_visual_effect(effect, false) {
+                snapshot.effects.push((index, true));
+            }
+        }
+        
+        let mut animation = ANIMATIONINFO { cbSize: std::mem::size_of::<ANIMATIONINFO>() as u32, iMinAnimate: 0 };
+        unsafe {
+            let read = SystemParametersInfoW(SPI_GETANIMATION, animation.cbSize, Some(&mut animation as *mut _ as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0));
+            if read.is_ok() && animation.iMinAnimate != 0 {
+                snapshot.min_animate = Some(animation.iMinAnimate);
+                let mut off = ANIMATIONINFO { cbSize: animation.cbSize, iMinAnimate: 0 };
+                let _ = SystemParametersInfoW(SPI_SETANIMATION, off.cbSize, Some(&mut off as *mut _ as *mut _), SPIF_SENDCHANGE);
+            }
+        }
+        
+        // Transparency defaults to on when the value is absent
+        let transparency = self.system.read_dword(Hive::CurrentUser, PERSONALIZE, "EnableTransparency");
+        if transparency != Some(0) {
+            snapshot.transparency = Some(transparency);
+            self.system.write_dword(Hive::CurrentUser, PERSONALIZE, "EnableTransparency", 0);
+        }
+        
+        log_line!("[AdvancedModules] Visual effects set to best performance ({} effects off)", snapshot.effects.len());
+        *original = Some(snapshot);
+    }
+
+    fn restore_visual_effects(&self) {
+        let Some(snapshot) = self.original_visual_effects.lock().unwrap().take() else {
+            return; // Nothing to restore
+        };
+        
+        for (index, value) in &snapshot.effects {
+            Self::write_visual_effect(&VISUAL_EFFECTS[*index], *value);
+        }
+        if let Some(min_animate) = snapshot.min_animate {
+            let mut animation = ANIMATIONINFO { cbSize: std::mem::size_of::<ANIMATIONINFO>() as u32, iMinAnimate: min_animate };
+            unsafe {
+                let _ = SystemParametersInfoW(SPI_SETANIMATION, animation.cbSize, Some(&mut animation as *mut _ as *mut _), SPIF_SENDCHANGE);
+            }
+        }
+        match snapshot.transparency {
+            Some(Some(value)) => { self.system.write_dword(Hive::CurrentUser, PERSONALIZE, "EnableTransparency", value); }
+            Some(None) => self.system.delete_value(Hive::CurrentUser, PERSONALIZE, "EnableTransparency"),
+            None => {}
+        }
+        
+        log_line!("[AdvancedModules] Visual effects restored");
+    }
+
+    fn read_visual_effect(effect: &VisualEffect) -> Option<bool> {
+        let mut value = BOOL(0);
+        unsafe {
+            SystemParametersInfoW(effect.get, 0, Some(&mut value as *mut _ as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))
+                .is_ok().then_some(value.as_bool())
+        }
+    }
+
+    fn write_visual_effect(effect: &VisualEffect, enabled: bool) -> bool {
+        let result = unsafe {
+            if effect.value_in_uiparam {
+                SystemParametersInfoW(effect.set, enabled as u32, None, SPIF_SENDCHANGE)
+            } else {
+                // Boolean passed as the pointer value itself
+                SystemParametersInfoW(effect.set, 0, Some(enabled as usize as *mut _), SPIF_SENDCHANGE)
+            }
+        };
+        if result.is_err() {
+            log_line!("[AdvancedModules] Failed to set {}", effect.name);
+        }
+        result.is_ok()
     }
 
-    fn restore_core_parking(&self) {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        // Restore default values (50% for min, 100% for max is Windows default)
-        let _ = Command::new("powercfg")
-            .args(["/setacvalueindex", "scheme_current", "sub_processor", "CPMINCORES", "50"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        let _ = Command::new("powercfg")
-            .args(["/setacvalueindex", "scheme_current", "sub_processor", "CPMAXCORES", "100"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        let _ = Command::new("powercfg")
-            .args(["/setactive", "scheme_current"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        println!("[AdvancedModules] Core parking restored");
+    // =========================================================================
+    // 30. SECONDARY DISPLAYS OFF
+    // SetDisplayConfig with only the primary path; the reduced topology is never
+    // saved to the display database, so a reboot also brings the displays back
+    // =========================================================================
+
+    fn disable_secondary_displays(&self) {
+        let mut saved = self.saved_topology.lock().unwrap();
+        if saved.is_some() {
+            return; // Already applied
+        }
+        let mut topology = None;
+        // Topology changes can reset calibration gamma ramps
+        DisplayService::preserve_color(|| topology = DisplayService::disable_secondary_displays());
+        *saved = Some(topology);
+    }
+
+    fn restore_secondary_displays(&self) {
+        match self.saved_topology.lock().unwrap().take() {
+            Some(None) => {} // Single display, nothing was switched off
+            Some(Some(topology)) => {
+                DisplayService::preserve_color(|| { DisplayService::restore_topology(Some(&topology)); });
+                log_line!("[AdvancedModules] Secondary displays restored");
+            }
+            None => {
+                // Nothing captured (crash recovery): fall back to the saved display database
+                DisplayService::restore_topology(None);
+                log_line!("[AdvancedModules] Display topology restored from the display database");
+            }
+        }
     }
 
     // =========================================================================
@@ -170,28 +1312,28 @@ impl AdvancedModulesService {
         let mmcss_path = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile";
         
         // Store original SystemResponsiveness (default is usually 20)
-        let original_resp = Self::read_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "SystemResponsiveness");
+        let original_resp = self.system.read_dword(Hive::LocalMachine, mmcss_path, "SystemResponsiveness");
         *self.original_system_responsiveness.lock().unwrap() = original_resp;
         
         // Store original NoLazyMode
-        let original_lazy = Self::read_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "NoLazyMode");
+        let original_lazy = self.system.read_dword(Hive::LocalMachine, mmcss_path, "NoLazyMode");
         *self.original_no_lazy_mode.lock().unwrap() = original_lazy;
         
         // Set SystemResponsiveness to 0 (give maximum CPU to multimedia/games)
         // This means 0% of CPU is reserved for background tasks when MMCSS is active
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "SystemResponsiveness", 0);
+        self.system.write_dword(Hive::LocalMachine, mmcss_path, "SystemResponsiveness", 0);
         
         // Enable NoLazyMode (1) - process MMCSS requests immediately
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "NoLazyMode", 1);
+        self.system.write_dword(Hive::LocalMachine, mmcss_path, "NoLazyMode", 1);
         
         // Also boost the Games task specifically
         let games_path = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games";
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, games_path, "Scheduling Category", 2); // High
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, games_path, "SFIO Priority", 2); // High
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, games_path, "Background Only", 0);
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, games_path, "Clock Rate", 10000); // 1ms
+        self.system.write_dword(Hive::LocalMachine, games_path, "Scheduling Category", 2); // High
+        self.system.write_dword(Hive::LocalMachine, games_path, "SFIO Priority", 2); // High
+        self.system.write_dword(Hive::LocalMachine, games_path, "Background Only", 0);
+        self.system.write_dword(Hive::LocalMachine, games_path, "Clock Rate", 10000); // 1ms
         
-        println!("[AdvancedModules] MMCSS priority boost enabled");
+        log_line!("[AdvancedModules] MMCSS priority boost enabled");
     }
 
     fn restore_mmcss(&self) {
@@ -199,13 +1341,13 @@ impl AdvancedModulesService {
         
         // Restore SystemResponsiveness (default 20)
         let original = self.original_system_responsiveness.lock().unwrap().unwrap_or(20);
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "SystemResponsiveness", original);
+        self.system.write_dword(Hive::LocalMachine, mmcss_path, "SystemResponsiveness", original);
         
         // Restore NoLazyMode (default 0)
         let original_lazy = self.original_no_lazy_mode.lock().unwrap().unwrap_or(0);
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mmcss_path, "NoLazyMode", original_lazy);
+        self.system.write_dword(Hive::LocalMachine, mmcss_path, "NoLazyMode", original_lazy);
         
-        println!("[AdvancedModules] MMCSS priority restored");
+        log_line!("[AdvancedModules] MMCSS priority restored");
     }
 
     // =========================================================================
@@ -218,15 +1360,15 @@ impl AdvancedModulesService {
         let mem_path = r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management";
         
         // LargeSystemCache: 1 = System favors system cache working set
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mem_path, "LargeSystemCache", 1);
+        self.system.write_dword(Hive::LocalMachine, mem_path, "LargeSystemCache", 1);
         
         // LargePageMinimum - helps with large page allocation
-        // Note: Actual large page support also requires SeLockMemoryPrivilege
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mem_path, "LargePageMinimum", 1);
+        // Note: Actual large page support also requires SeLockMemoryPrivilege (see lock_memory)
+        self.system.write_dword(Hive::LocalMachine, mem_path, "LargePageMinimum", 1);
         
         *self.large_pages_enabled.lock().unwrap() = true;
         
-        println!("[AdvancedModules] Large pages enabled (requires reboot for full effect)");
+        log_line!("[AdvancedModules] Large pages enabled (requires reboot for full effect)");
     }
 
     fn restore_large_pages(&self) {
@@ -237,11 +1379,65 @@ impl AdvancedModulesService {
         let mem_path = r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management";
         
         // Restore defaults
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, mem_path, "LargeSystemCache", 0);
+        self.system.write_dword(Hive::LocalMachine, mem_path, "LargeSystemCache", 0);
         
         *self.large_pages_enabled.lock().unwrap() = false;
+        self.mark_pending_reboot("Large pages revert");
         
-        println!("[AdvancedModules] Large pages disabled");
+        log_line!("[AdvancedModules] Large pages disabled");
+    }
+
+    /// Hard minimum working set for the game so its pages (and large-page allocations) aren't trimmed
+    fn raise_game_working_set(&self, game_pid: u32, min_mb: u32) {
+        use windows::Win32::System::Memory::{
+            GetProcessWorkingSetSizeEx, SetProcessWorkingSetSizeEx, QUOTA_LIMITS_HARDWS_MAX_DISABLE,
+            QUOTA_LIMITS_HARDWS_MIN_ENABLE, SETPROCESSWORKINGSETSIZEEX_FLAGS,
+        };
+        use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_SET_QUOTA};
+        use windows::Win32::Foundation::CloseHandle;
+        const MB: usize = 1024 * 1024;
+
+        unsafe {
+            let Ok(handle) = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_SET_QUOTA, false, game_pid) else {
+                log_line!("[AdvancedModules] Game working set unchanged (cannot open the game process)");
+                return;
+            };
+            let (mut min, mut max, mut flags) = (0usize, 0usize, 0u32);
+            if GetProcessWorkingSetSizeEx(handle, &mut min, &mut max, &mut flags).as_bool() {
+                let new_min = min_mb as usize * MB;
+                let new_max = max.max(new_min + 64 * MB);
+                // Hard minimum only; the maximum stays soft so the game can still grow
+                let new_flags = SETPROCESSWORKINGSETSIZEEX_FLAGS(
+                    QUOTA_LIMITS_HARDWS_MIN_ENABLE.0 | QUOTA_LIMITS_HARDWS_MAX_DISABLE.0,
+                );
+                match SetProcessWorkingSetSizeEx(handle, new_min, new_max, new_flags) {
+                    Ok(()) => {
+                        *self.original_working_set.lock().unwrap() = Some((game_pid, min, max, flags));
+                        log_line!("[AdvancedModules] Game minimum working set raised to {} MB", min_mb);
+                    }
+                    Err(e) => log_line!("[AdvancedModules] Could not raise the game working set: {}", e),
+                }
+            }
+            let _ = CloseHandle(handle);
+        }
+    }
+
+    fn restore_game_working_set(&self) {
+        use windows::Win32::System::Memory::{SetProcessWorkingSetSizeEx, SETPROCESSWORKINGSETSIZEEX_FLAGS};
+        use windows::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA};
+        use windows::Win32::Foundation::CloseHandle;
+
+        let Some((pid, min, max, flags)) = self.original_working_set.lock().unwrap().take() else {
+            return;
+        };
+        // The game may already have exited; nothing to restore then
+        unsafe {
+            if let Ok(handle) = OpenProcess(PROCESS_SET_QUOTA, false, pid) {
+                let _ = SetProcessWorkingSetSizeEx(handle, min, max, SETPROCESSWORKINGSETSIZEEX_FLAGS(flags));
+                let _ = CloseHandle(handle);
+                log_line!("[AdvancedModules] Game working set restored");
+            }
+        }
     }
 
     // =========================================================================
@@ -249,19 +1445,28 @@ impl AdvancedModulesService {
     // Registry: HKLM\SYSTEM\CurrentControlSet\Control\GraphicsDrivers
     // =========================================================================
 
-    fn enable_hags(&self) {
+    fn enable_hags(&self, target_gpu: &str) {
+        // HwSchMode is system-wide, but only worth writing if the GPU the game runs on is real hardware
+        match GpuService::target_adapter(target_gpu) {
+            Some(gpu) => log_line!("[AdvancedModules] HAGS target GPU: {} ({})", gpu.name, gpu.vendor()),
+            None => {
+                log_line!("[AdvancedModules] No hardware GPU found, skipping HAGS");
+                return;
+            }
+        }
+        
         let gpu_path = r"SYSTEM\CurrentControlSet\Control\GraphicsDrivers";
         
         // Store original value
-        let original = Self::read_registry_dword(HKEY_LOCAL_MACHINE, gpu_path, "HwSchMode");
+        let original = self.system.read_dword(Hive::LocalMachine, gpu_path, "HwSchMode");
         *self.original_hags_value.lock().unwrap() = original;
         
         // HwSchMode: 2 = Hardware-accelerated GPU scheduling enabled
         // 1 = Enabled but not hardware-accelerated
         // 0 = Disabled
-        Self::set_registry_dword(HKEY_LOCAL_MACHINE, gpu_path, "HwSchMode", 2);
+        self.system.write_dword(Hive::LocalMachine, gpu_path, "HwSchMode", 2);
         
-        println!("[AdvancedModules] HAGS enabled (requires reboot)");
+        log_line!("[AdvancedModules] HAGS enabled (requires reboot)");
     }
 
     fn restore_hags(&self) {
@@ -269,8 +1474,9 @@ impl AdvancedModulesService {
         
         if let Some(val) = original {
             let gpu_path = r"SYSTEM\CurrentControlSet\Control\GraphicsDrivers";
-            Self::set_registry_dword(HKEY_LOCAL_MACHINE, gpu_path, "HwSchMode", val);
-            println!("[AdvancedModules] HAGS restored to previous value");
+            self.system.write_dword(Hive::LocalMachine, gpu_path, "HwSchMode", val);
+            self.mark_pending_reboot("HAGS revert");
+            log_line!("[AdvancedModules] HAGS restored to previous value");
         }
     }
 
@@ -279,7 +1485,24 @@ impl AdvancedModulesService {
     // Set non-essential processes to idle priority during game mode
     // =========================================================================
 
-    fn enable_process_demotion(&self) {
+    /// Process names to demote, built from the enabled categories plus custom entries
+    fn demotion_list(settings: &AdvancedModuleSettings) -> Vec<String> {
+        let categories: [(bool, &[&str]); 5] = [
+            (settings.demote_telemetry, DEMOTE_TELEMETRY),
+            (settings.demote_sync, DEMOTE_SYNC),
+            (settings.demote_search, DEMOTE_SEARCH),
+            (settings.demote_security, DEMOTE_SECURITY),
+            (settings.demote_background, DEMOTE_BACKGROUND),
+        ];
+
+        categories.iter()
+            .filter(|(enabled, _)| *enabled)
+            .flat_map(|(_, names)| names.iter().map(|n| n.to_string()))
+            .chain(settings.demote_custom.iter().cloned())
+            .collect()
+    }
+
+    fn enable_process_demotion(&self, settings: &AdvancedModuleSettings) {
         use windows::Win32::System::Threading::{
             OpenProcess, SetPriorityClass, PROCESS_SET_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION,
             IDLE_PRIORITY_CLASS,
@@ -289,14 +1512,10 @@ impl AdvancedModulesService {
         };
         use windows::Win32::Foundation::CloseHandle;
 
-        // Processes to demote (background apps that shouldn't compete with games)
-        const DEMOTE_PROCESSES: &[&str] = &[
-            "SearchIndexer", "SecurityHealthService", "SgrmBroker",
-            "compattelrunner", "MsMpEng", "NisSrv", "WmiPrvSE",
-            "spoolsv", "dllhost", "backgroundTaskHost",
-            "RuntimeBroker", "ApplicationFrameHost", "SystemSettings",
-            "SettingSyncHost", "OneDrive", "GoogleDriveFS", "Dropbox",
-        ];
+        let demote_list = Self::demotion_list(settings);
+        if demote_list.is_empty() {
+            return;
+        }
 
         let current_pid = std::process::id();
         // Pre-allocate to avoid reallocs during iteration
@@ -319,7 +1538,7 @@ impl AdvancedModulesService {
                         let name = Self::extract_process_name(&entry.szExeFile);
                         
                         // Check if this process should be demoted
-                        if DEMOTE_PROCESSES.iter().any(|&p| name.eq_ignore_ascii_case(p)) {
+                        if demote_list.iter().any(|p| name.eq_ignore_ascii_case(p)) && !ProcessService::is_protected(name) {
                             if let Ok(handle) = OpenProcess(
                                 PROCESS_SET_INFORMATION | PROCESS_QUERY_LIMITED_INFORMATION,
                                 false,
@@ -342,7 +1561,7 @@ impl AdvancedModulesService {
 
         let count = demoted.len();
         *self.demoted_processes.lock().unwrap() = demoted;
-        println!("[AdvancedModules] Process idle demotion enabled ({} processes)", count);
+        log_line!("[AdvancedModules] Process idle demotion enabled ({} processes)", count);
     }
 
     fn restore_process_priority(&self) {
@@ -365,7 +1584,7 @@ impl AdvancedModulesService {
         }
         
         // Vec is dropped here, memory freed
-        println!("[AdvancedModules] Process priorities restored ({} processes)", demoted.len());
+        log_line!("[AdvancedModules] Process priorities restored ({} processes)", demoted.len());
     }
 
     // =========================================================================
@@ -375,112 +1594,423 @@ impl AdvancedModulesService {
     // =========================================================================
 
     fn enable_lower_bufferbloat(&self) {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        // Get current autotuning level first
-        let output = Command::new("netsh")
-            .args(["int", "tcp", "show", "global"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        if let Ok(out) = output {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            // Parse the current autotuning level
-            for line in stdout.lines() {
-                if line.to_lowercase().contains("auto-tuning") || line.to_lowercase().contains("autotuning") {
-                    // Extract the value (e.g., "normal", "disabled", "highlyrestricted")
-                    if let Some(level) = line.split(':').nth(1) {
-                        let level = level.trim().to_lowercase();
-                        *self.original_autotuning_level.lock().unwrap() = Some(level);
-                        break;
-                    }
-                }
-            }
+        // Remember the current level (read locale-independently, see NetworkService::tcp_globals)
+        if let Some(level) = NetworkService::tcp_globals().autotuning {
+            *self.original_autotuning_level.lock().unwrap() = Some(level);
         }
         
-        // Set autotuning to disabled
-        let _ = Command::new("netsh")
-            .args(["int", "tcp", "set", "global", "autotuninglevel=disabled"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        // Autotuning is a global TCP setting, so it also applies to wireless links
+        if NetworkService::active_adapter().is_some_and(|a| a.is_wireless) {
+            log_line!("[AdvancedModules] Warning: active adapter is Wi-Fi, disabling autotuning can reduce throughput");
+        }
         
-        println!("[AdvancedModules] Bufferbloat reduction enabled (TCP autotuning disabled)");
+        NetworkService::set_autotuning("disabled");
+        log_line!("[AdvancedModules] Bufferbloat reduction enabled (TCP autotuning disabled)");
     }
 
     fn restore_bufferbloat(&self) {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
         // Restore original autotuning level
         let original = self.original_autotuning_level.lock().unwrap().clone();
         let level = original.unwrap_or_else(|| "normal".to_string());
         
-        let _ = Command::new("netsh")
-            .args(["int", "tcp", "set", "global", &format!("autotuninglevel={}", level)])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        NetworkService::set_autotuning(&level);
+        log_line!("[AdvancedModules] Bufferbloat setting restored (TCP autotuning: {})", level);
+    }
+
+    // =========================================================================
+    // 38. ECN OFF
+    // Some home routers and middleboxes mishandle ECN-marked packets; turning it off rules that out
+    // Command: netsh int tcp set global ecncapability=disabled
+    // =========================================================================
+
+    fn disable_ecn(&self) {
+        let current = NetworkService::tcp_globals().ecn;
+        *self.original_ecn.lock().unwrap() = current;
+        NetworkService::set_ecn(false);
+        log_line!("[AdvancedModules] TCP ECN disabled (was {:?})", current);
+    }
+
+    fn restore_ecn(&self) {
+        // Windows' default differs between builds, so without a captured value leave it alone
+        let Some(original) = self.original_ecn.lock().unwrap().take() else {
+            log_line!("[AdvancedModules] No original ECN setting captured, left as is");
+            return;
+        };
+        NetworkService::set_ecn(original);
+        log_line!("[AdvancedModules] TCP ECN restored ({})", if original { "enabled" } else { "disabled" });
+    }
+
+    // =========================================================================
+    // 39. RSC OFF
+    // Receive Segment Coalescing holds received segments to hand them up in batches;
+    // off delivers each packet as it arrives at a small CPU cost
+    // Command: netsh int tcp set global rsc=disabled
+    // =========================================================================
+
+    fn disable_rsc(&self) {
+        let current = NetworkService::tcp_globals().rsc;
+        *self.original_rsc.lock().unwrap() = current;
+        NetworkService::set_rsc(false);
+        log_line!("[AdvancedModules] Receive Segment Coalescing disabled (was {:?})", current);
+    }
+
+    fn restore_rsc(&self) {
+        // Enabled is the Windows default
+        let original = self.original_rsc.lock().unwrap().take().unwrap_or(true);
+        NetworkService::set_rsc(original);
+        log_line!("[AdvancedModules] Receive Segment Coalescing restored ({})", if original { "enabled" } else { "disabled" });
+    }
+
+    // =========================================================================
+    // 15. WINDOWS UPDATE PAUSE
+    // Pause updates via the Settings app pause keys and throttle Delivery Optimization
+    // Stopping wuauserv/dosvc alone does not stick - the Update Orchestrator restarts them
+    // =========================================================================
+
+    fn pause_windows_update(&self) {
+        const UX_SETTINGS: &str = r"SOFTWARE\Microsoft\WindowsUpdate\UX\Settings";
+        const DO_POLICY: &str = r"SOFTWARE\Policies\Microsoft\Windows\DeliveryOptimization";
+        // Same values the Settings app writes for "Pause updates"
+        const PAUSE_START_KEYS: &[&str] = &[
+            "PauseUpdatesStartTime", "PauseFeatureUpdatesStartTime", "PauseQualityUpdatesStartTime",
+        ];
+        const PAUSE_END_KEYS: &[&str] = &[
+            "PauseUpdatesExpiryTime", "PauseFeatureUpdatesEndTime", "PauseQualityUpdatesEndTime",
+        ];
+        // (value, tuned) - HTTP only (no peer uploads), 5% bandwidth cap
+        const DO_TWEAKS: &[(&str, u32)] = &[
+            ("DODownloadMode", 0),
+            ("DOPercentageMaxBackgroundBandwidth", 5),
+            ("DOPercentageMaxForegroundBandwidth", 5),
+        ];
+
+        let mut pause_originals = self.original_update_pause.lock().unwrap();
+        if pause_originals.is_some() {
+            return; // Already paused
+        }
+
+        // Expiry one day out so a crash never leaves updates paused for long
+        let start = Self::utc_timestamp(0);
+        let end = Self::utc_timestamp(24 * 60 * 60);
+
+        let mut originals = Vec::with_capacity(PAUSE_START_KEYS.len() + PAUSE_END_KEYS.len());
+        for &key in PAUSE_START_KEYS {
+            originals.push((key.to_string(), self.system.read_string(Hive::LocalMachine, UX_SETTINGS, key)));
+            self.system.write_string(Hive::LocalMachine, UX_SETTINGS, key, &start);
+        }
+        for &key in PAUSE_END_KEYS {
+            originals.push((key.to_string(), self.system.read_string(Hive::LocalMachine, UX_SETTINGS, key)));
+            self.system.write_string(Hive::LocalMachine, UX_SETTINGS, key, &end);
+        }
+        *pause_originals = Some(originals);
+
+        let mut do_originals = Vec::with_capacity(DO_TWEAKS.len());
+        for &(key, value) in DO_TWEAKS {
+            do_originals.push((key.to_string(), self.system.read_dword(Hive::LocalMachine, DO_POLICY, key)));
+            self.system.write_dword(Hive::LocalMachine, DO_POLICY, key, value);
+        }
+        *self.original_do_policy.lock().unwrap() = Some(do_originals);
+
+        log_line!("[AdvancedModules] Windows Update paused until {}, Delivery Optimization throttled", end);
+    }
+
+    fn resume_windows_update(&self) {
+        const UX_SETTINGS: &str = r"SOFTWARE\Microsoft\WindowsUpdate\UX\Settings";
+        const DO_POLICY: &str = r"SOFTWARE\Policies\Microsoft\Windows\DeliveryOptimization";
+
+        if let Some(originals) = self.original_update_pause.lock().unwrap().take() {
+            for (key, original) in originals {
+                match original {
+                    Some(value) => { self.system.write_string(Hive::LocalMachine, UX_SETTINGS, &key, &value); }
+                    None => self.system.delete_value(Hive::LocalMachine, UX_SETTINGS, &key),
+                }
+            }
+        }
+
+        if let Some(originals) = self.original_do_policy.lock().unwrap().take() {
+            for (key, original) in originals {
+                match original {
+                    Some(value) => { self.system.write_dword(Hive::LocalMachine, DO_POLICY, &key, value); }
+                    None => self.system.delete_value(Hive::LocalMachine, DO_POLICY, &key),
+                }
+            }
+        }
+
+        log_line!("[AdvancedModules] Windows Update pause lifted");
+    }
+
+    /// UTC time `offset_secs` from now in the format the Settings app uses (2024-01-31T18:00:00Z)
+    fn utc_timestamp(offset_secs: u64) -> String {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+            + offset_secs;
+        let (days, rem) = (secs / 86400, secs % 86400);
+
+        // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+        let z = days as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60
+        )
+    }
+
+    // =========================================================================
+    // 16. DEFENDER GAME EXCLUSION
+    // Temporary real-time scan exclusion for the game process
+    // Command: Add-MpPreference / Remove-MpPreference -ExclusionProcess <exe>
+    // =========================================================================
+
+    fn add_defender_exclusion(&self, game_pid: u32) {
+        let Some(exe) = ProcessService::executable_path(game_pid) else {
+            log_line!("[AdvancedModules] Defender exclusion skipped (game path unavailable)");
+            return;
+        };
+        
+        // Leave user-defined exclusions alone, and never remove them on disable
+        let existing = Self::run_powershell("(Get-MpPreference).ExclusionProcess");
+        if existing.lines().any(|line| line.trim().eq_ignore_ascii_case(&exe)) {
+            log_line!("[AdvancedModules] Defender exclusion already present for {}", exe);
+            return;
+        }
         
-        println!("[AdvancedModules] Bufferbloat setting restored (TCP autotuning: {})", level);
+        Self::run_powershell(&format!("Add-MpPreference -ExclusionProcess '{}'", exe.replace('\'', "''")));
+        *self.defender_excluded_exe.lock().unwrap() = Some(exe.clone());
+        log_line!("[AdvancedModules] Defender exclusion added for {}", exe);
+    }
+
+    fn remove_defender_exclusion(&self) {
+        let Some(exe) = self.defender_excluded_exe.lock().unwrap().take() else {
+            return;
+        };
+        
+        Self::run_powershell(&format!("Remove-MpPreference -ExclusionProcess '{}'", exe.replace('\'', "''")));
+        log_line!("[AdvancedModules] Defender exclusion removed for {}", exe);
     }
 
     // =========================================================================
-    // PERMANENT TOGGLE FUNCTIONS (Can be called without game mode)
+    // 17. WINDOWS GAME MODE COOPERATION
+    // Turn on Windows Game Mode and categorize the game so OS scheduling stacks with our tweaks
+    // Registry: HKCU\Software\Microsoft\GameBar, HKCU\System\GameConfigStore\Children
     // =========================================================================
 
-    /// Get current TCP autotuning status
-    pub fn get_bufferbloat_status() -> bool {
+    fn enable_windows_game_mode(&self, game_pid: u32) {
+        const GAME_BAR: &str = r"Software\Microsoft\GameBar";
+        const CHILDREN: &str = r"System\GameConfigStore\Children";
+
+        // Windows Game Mode itself (Settings > Gaming > Game Mode)
+        let original = self.system.read_dword(Hive::CurrentUser, GAME_BAR, "AutoGameModeEnabled");
+        if original != Some(1) {
+            *self.original_auto_game_mode.lock().unwrap() = Some(original);
+            self.system.write_dword(Hive::CurrentUser, GAME_BAR, "AutoGameModeEnabled", 1);
+        }
+
+        // Register the exe unless Game Bar already knows it
+        let Some(exe) = ProcessService::executable_path(game_pid) else {
+            log_line!("[AdvancedModules] Windows Game Mode enabled (game path unavailable)");
+            return;
+        };
+        if self.game_config_entry_exists(&exe) {
+            log_line!("[AdvancedModules] Windows Game Mode enabled ({} already registered)", exe);
+            return;
+        }
+
+        let Ok(guid) = (unsafe { windows::Win32::System::Com::CoCreateGuid() }) else { return };
+        let key = format!(r"{}\{{{:?}}}", CHILDREN, guid);
+        let parent = std::path::Path::new(&exe)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.system.write_string(Hive::CurrentUser, &key, "MatchedExeFullPath", &exe);
+        self.system.write_string(Hive::CurrentUser, &key, "ExeParentDirectory", &parent);
+        self.system.write_dword(Hive::CurrentUser, &key, "Type", 1); // 1 = game
+        *self.game_config_key.lock().unwrap() = Some(key);
+
+        log_line!("[AdvancedModules] Windows Game Mode enabled, registered {}", exe);
+    }
+
+    fn restore_windows_game_mode(&self) {
+        const GAME_BAR: &str = r"Software\Microsoft\GameBar";
+
+        if let Some(original) = self.original_auto_game_mode.lock().unwrap().take() {
+            match original {
+                Some(value) => { self.system.write_dword(Hive::CurrentUser, GAME_BAR, "AutoGameModeEnabled", value); }
+                None => self.system.delete_value(Hive::CurrentUser, GAME_BAR, "AutoGameModeEnabled"),
+            }
+        }
+
+        if let Some(key) = self.game_config_key.lock().unwrap().take() {
+            self.system.delete_key(Hive::CurrentUser, &key);
+        }
+
+        log_line!("[AdvancedModules] Windows Game Mode settings restored");
+    }
+
+    // =========================================================================
+    // 36. GAME DVR OFF
+    // Killing GameBar / GameBarPresenceWriter alone doesn't last: Windows relaunches them with
+    // the next game, so background recording and the presence writer are switched off instead
+    // Registry: HKCU\System\GameConfigStore, HKCU\...\CurrentVersion\GameDVR, HKLM WindowsRuntime class
+    // =========================================================================
+
+    fn disable_game_dvr(&self) {
+        let mut originals = Vec::new();
+        for (index, value) in GAME_DVR_VALUES.iter().enumerate() {
+            let original = self.system.read_dword(value.hive, value.path, value.name);
+            if original == Some(value.off) {
+                continue;
+            }
+            // Nothing to restore if it didn't stick (the presence writer class is owned by
+            // TrustedInstaller on some builds; the audit has logged it)
+            if !self.system.write_dword(value.hive, value.path, value.name, value.off) {
+                continue;
+            }
+            originals.push((index, original));
+        }
+
+        log_line!("[AdvancedModules] Game DVR off ({} values changed)", originals.len());
+        *self.original_game_dvr.lock().unwrap() = Some(originals);
+    }
+
+    fn restore_game_dvr(&self) {
+        let originals = match self.original_game_dvr.lock().unwrap().take() {
+            Some(originals) => originals,
+            // Nothing captured (crash recovery): only the presence writer goes back to its default,
+            // the capture settings are the user's and stay off
+            None => GAME_DVR_VALUES.iter()
+                .enumerate()
+                .filter_map(|(index, value)| value.fallback.map(|data| (index, Some(data))))
+                .collect(),
+        };
+
+        for (index, original) in originals {
+            let value = &GAME_DVR_VALUES[index];
+            match original {
+                Some(data) => { self.system.write_dword(value.hive, value.path, value.name, data); }
+                None => self.system.delete_value(value.hive, value.path, value.name),
+            }
+        }
+
+        log_line!("[AdvancedModules] Game DVR settings restored");
+    }
+
+    // =========================================================================
+    // 37. DSCP TAGGING
+    // QoS policy marking the game's packets so routers that honor DSCP prioritize them
+    // Command: New-NetQosPolicy / Remove-NetQosPolicy (one fixed name, removable after a crash)
+    // =========================================================================
+
+    fn add_qos_policy(&self, game_pid: u32, dscp: u32) {
+        let Some(exe) = ProcessService::executable_path(game_pid) else {
+            log_line!("[AdvancedModules] DSCP tagging skipped (game path unavailable)");
+            return;
+        };
+        // DSCP is a 6-bit field
+        let dscp = dscp.min(63);
+
+        // A leftover from an interrupted session would make New-NetQosPolicy fail
+        self.remove_qos_policy();
+        Self::run_powershell(&format!(
+            "New-NetQosPolicy -Name '{}' -AppPathNameMatchCondition '{}' -IPProtocolMatchCondition Both \
+             -DSCPAction {} -NetworkProfile All -Confirm:$false | Out-Null",
+            QOS_POLICY_NAME,
+            exe.replace('\'', "''"),
+            dscp
+        ));
+        log_line!("[AdvancedModules] DSCP {} tagging for {}", dscp, exe);
+    }
+
+    fn remove_qos_policy(&self) {
+        Self::run_powershell(&format!(
+            "Remove-NetQosPolicy -Name '{}' -Confirm:$false -ErrorAction SilentlyContinue",
+            QOS_POLICY_NAME
+        ));
+    }
+
+    /// True if a GameConfigStore child already matches this exe
+    fn game_config_entry_exists(&self, exe: &str) -> bool {
+        const CHILDREN: &str = r"System\GameConfigStore\Children";
+
+        self.system.subkeys(Hive::CurrentUser, CHILDREN).iter().any(|name| {
+            self.system.read_string(Hive::CurrentUser, &format!(r"{}\{}", CHILDREN, name), "MatchedExeFullPath")
+                .is_some_and(|path| path.eq_ignore_ascii_case(exe))
+        })
+    }
+
+    fn run_powershell(script: &str) -> String {
         use std::process::Command;
         use std::os::windows::process::CommandExt;
         const CREATE_NO_WINDOW: u32 = 0x08000000;
         
-        let output = Command::new("netsh")
-            .args(["int", "tcp", "show", "global"])
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
             .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        if let Ok(out) = output {
-            let stdout = String::from_utf8_lossy(&out.stdout).to_lowercase();
-            // Parse line by line to ensure we are checking the correct setting
-            for line in stdout.lines() {
-                if line.contains("auto-tuning") || line.contains("autotuning") {
-                    return line.contains("disabled");
-                }
-            }
-            false
-        } else {
-            false
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Modules applied in the running session (None outside a session)
+    pub fn session_modules(&self) -> Option<AdvancedModuleSettings> {
+        self.session_modules.lock().unwrap().clone()
+    }
+
+    /// Live HwSchMode (None = value absent, driver default)
+    pub fn hags_mode() -> Option<u32> {
+        Win32.read_dword(Hive::LocalMachine, r"SYSTEM\CurrentControlSet\Control\GraphicsDrivers", "HwSchMode")
+    }
+
+    /// Live MMCSS SystemResponsiveness (None = value absent, Windows uses 20)
+    pub fn system_responsiveness() -> Option<u32> {
+        Win32.read_dword(
+            Hive::LocalMachine,
+            r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile",
+            "SystemResponsiveness",
+        )
+    }
+
+    /// Reverts written this run that Windows only applies after a reboot
+    pub fn pending_reboot_items(&self) -> Vec<&'static str> {
+        self.pending_reboot.lock().unwrap().clone()
+    }
+
+    fn mark_pending_reboot(&self, item: &'static str) {
+        let mut pending = self.pending_reboot.lock().unwrap();
+        if !pending.contains(&item) {
+            pending.push(item);
         }
     }
 
+    // =========================================================================
+    // PERMANENT TOGGLE FUNCTIONS (Can be called without game mode)
+    // =========================================================================
+
+    /// Get current TCP autotuning status
+    pub fn get_bufferbloat_status() -> bool {
+        NetworkService::tcp_globals().autotuning.as_deref() == Some("disabled")
+    }
+
     /// Permanently enable bufferbloat reduction (disable TCP autotuning)
     pub fn set_bufferbloat_enabled() {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        let _ = Command::new("netsh")
-            .args(["int", "tcp", "set", "global", "autotuninglevel=disabled"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        println!("[AdvancedModules] Bufferbloat reduction permanently enabled");
+        NetworkService::set_autotuning("disabled");
+        log_line!("[AdvancedModules] Bufferbloat reduction permanently enabled");
     }
 
     /// Permanently disable bufferbloat reduction (restore TCP autotuning to normal)
     pub fn set_bufferbloat_disabled() {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        let _ = Command::new("netsh")
-            .args(["int", "tcp", "set", "global", "autotuninglevel=normal"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
-        
-        println!("[AdvancedModules] Bufferbloat reduction permanently disabled (TCP autotuning normal)");
+        NetworkService::set_autotuning("normal");
+        log_line!("[AdvancedModules] Bufferbloat reduction permanently disabled (TCP autotuning normal)");
     }
 
     // =========================================================================
@@ -493,82 +2023,108 @@ impl AdvancedModulesService {
         let name = std::str::from_utf8(bytes).unwrap_or("");
         name.strip_suffix(".exe").or_else(|| name.strip_suffix(".EXE")).unwrap_or(name)
     }
+}
 
-    fn read_registry_dword(root: HKEY, subkey: &str, value_name: &str) -> Option<u32> {
-        unsafe {
-            let mut key_handle = HKEY::default();
-            let subkey_w = HSTRING::from(subkey);
-            
-            if RegOpenKeyExW(root, PCWSTR(subkey_w.as_ptr()), 0, KEY_READ, &mut key_handle).is_ok() {
-                let value_w = HSTRING::from(value_name);
-                let mut data: u32 = 0;
-                let mut data_size: u32 = std::mem::size_of::<u32>() as u32;
-                
-                let result = RegQueryValueExW(
-                    key_handle,
-                    PCWSTR(value_w.as_ptr()),
-                    None,
-                    None,
-                    Some(&mut data as *mut u32 as *mut u8),
-                    Some(&mut data_size),
-                );
-                
-                let _ = RegCloseKey(key_handle);
-                
-                if result.is_ok() {
-                    return Some(data);
-                }
-            }
-            None
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::power::PROCESSOR_SUBGROUP;
+    use crate::services::system::fake::{FakeSystem, Value};
+    use crate::services::system::{PowerBackend, RegistryBackend};
+
+    const BALANCED: GUID = GUID::from_u128(0x381b4222_f694_41f0_9685_ff5bb260df2e);
+    const HIGH_PERFORMANCE: GUID = GUID::from_u128(0x8c5e7fda_e8bf_4a96_9a85_a6e23a8c635c);
+    const MMCSS: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile";
+
+    fn machine() -> Arc<FakeSystem> {
+        let fake = FakeSystem::new();
+        fake.add_scheme(BALANCED, true);
+        fake.add_scheme(HIGH_PERFORMANCE, false);
+        fake.write_value_indices(&BALANCED, &PROCESSOR_SUBGROUP, &CPMINCORES.guid, 10, 10);
+        fake.write_value_indices(&BALANCED, &PROCESSOR_SUBGROUP, &CPMAXCORES.guid, 100, 100);
+        fake.set(Hive::LocalMachine, MMCSS, "SystemResponsiveness", Value::Dword(20));
+        Arc::new(fake)
     }
 
-    fn set_registry_dword(root: HKEY, subkey: &str, value_name: &str, data: u32) {
-        unsafe {
-            let mut key_handle = HKEY::default();
-            let subkey_w = HSTRING::from(subkey);
-            
-            // Try to open existing key first
-            let open_result = RegOpenKeyExW(root, PCWSTR(subkey_w.as_ptr()), 0, KEY_WRITE, &mut key_handle);
-            
-            if open_result.is_ok() {
-                let value_w = HSTRING::from(value_name);
-                let data_bytes = std::slice::from_raw_parts(&data as *const _ as *const u8, std::mem::size_of::<u32>());
-                
-                let _ = RegSetValueExW(
-                    key_handle,
-                    PCWSTR(value_w.as_ptr()),
-                    0,
-                    REG_DWORD,
-                    Some(data_bytes),
-                );
-                let _ = RegCloseKey(key_handle);
-            } else {
-                // Try to create the key
-                if RegCreateKeyExW(
-                    root,
-                    PCWSTR(subkey_w.as_ptr()),
-                    0,
-                    None,
-                    REG_OPTION_NON_VOLATILE,
-                    KEY_WRITE,
-                    None,
-                    &mut key_handle,
-                    None,
-                ).is_ok() {
-                    let value_w = HSTRING::from(value_name);
-                    let data_bytes = std::slice::from_raw_parts(&data as *const _ as *const u8, std::mem::size_of::<u32>());
-                    
-                    let _ = RegSetValueExW(
-                        key_handle,
-                        PCWSTR(value_w.as_ptr()),
-                        0,
-                        REG_DWORD,
-                        Some(data_bytes),
-                    );
-                    let _ = RegCloseKey(key_handle);
-                }
-            }
-        }
+    #[test]
+    fn core_parking_restores_the_pinned_scheme() {
+        let fake = machine();
+        let modules = AdvancedModulesService::with_system(fake.clone());
+
+        modules.disable_core_parking(&AdvancedModuleSettings::default());
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMINCORES.guid), Some(100));
+
+        // The user switching plans mid-session must not redirect the restore
+        fake.set_active_scheme(&HIGH_PERFORMANCE);
+        modules.restore_core_parking();
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMINCORES.guid), Some(10));
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMAXCORES.guid), Some(100));
+        assert_eq!(fake.read_processor_ac_index(&HIGH_PERFORMANCE, &CPMINCORES.guid), None);
+    }
+
+    #[test]
+    fn mmcss_boost_round_trip() {
+        let fake = machine();
+        let modules = AdvancedModulesService::with_system(fake.clone());
+
+        modules.enable_mmcss_boost();
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "SystemResponsiveness"), Some(0));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "NoLazyMode"), Some(1));
+
+        modules.restore_mmcss();
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "SystemResponsiveness"), Some(20));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "NoLazyMode"), Some(0));
+    }
+
+    #[test]
+    fn mouse_queue_size_round_trip() {
+        let fake = machine();
+        let modules = AdvancedModulesService::with_system(fake.clone());
+
+        modules.raise_mouse_queue_size();
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize"), Some(MOUSE_DATA_QUEUE_SIZE));
+
+        // Absent before (driver default), so removed again
+        modules.restore_mouse_queue_size();
+        assert_eq!(fake.get(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize"), None);
+    }
+
+    #[test]
+    fn journaled_originals_restore_in_a_fresh_service() {
+        let fake = machine();
+        let modules = AdvancedModulesService::with_system(fake.clone());
+        modules.disable_core_parking(&AdvancedModuleSettings::default());
+        modules.enable_mmcss_boost();
+
+        // Through the journal's JSON, as a crash recovery in a new process reads it
+        let json = serde_json::to_string(&modules.originals()).unwrap();
+        let originals: ModuleOriginals = serde_json::from_str(&json).unwrap();
+        fake.set_active_scheme(&HIGH_PERFORMANCE);
+        let recovered = AdvancedModulesService::from_originals(fake.clone(), &originals);
+        recovered.restore_core_parking();
+        recovered.restore_mmcss();
+
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMINCORES.guid), Some(10));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "SystemResponsiveness"), Some(20));
+    }
+
+    #[test]
+    fn mouse_queue_size_restore_without_original_writes_nothing() {
+        let fake = machine();
+        fake.set(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize", Value::Dword(50));
+        let modules = AdvancedModulesService::with_system(fake.clone());
+
+        modules.restore_mouse_queue_size();
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MOUCLASS_PARAMETERS, "MouseDataQueueSize"), Some(50));
+    }
+
+    #[test]
+    fn core_parking_restore_without_originals_writes_nothing() {
+        let fake = machine();
+        let modules = AdvancedModulesService::with_system(fake.clone());
+
+        modules.restore_core_parking();
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMINCORES.guid), Some(10));
+        assert_eq!(fake.read_processor_ac_index(&BALANCED, &CPMAXCORES.guid), Some(100));
     }
 }
@@ -0,0 +1,97 @@
+//! Process Affinity
+//! Restricts the game to one logical processor per physical core for titles that run worse with
+//! SMT / Hyper-Threading (per-game flag). The original mask is put back when the session ends;
+//! affinity belongs to the process, so the next launch starts unrestricted anyway.
+
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::SystemInformation::{
+    GetLogicalProcessorInformationEx, RelationProcessorCore, SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX,
+};
+use windows::Win32::System::Threading::{
+    GetProcessAffinityMask, OpenProcess, SetProcessAffinityMask, PROCESS_QUERY_LIMITED_INFORMATION,
+    PROCESS_SET_INFORMATION,
+};
+use crate::log_line;
+
+/// PROCESSOR_RELATIONSHIP.Flags: the core runs more than one logical processor
+const LTP_PC_SMT: u8 = 1;
+
+/// (pid, mask before the change) while a game is restricted
+static ORIGINAL_MASK: Lazy<Mutex<Option<(u32, usize)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Limit `pid` to the first logical processor of every physical core
+pub fn restrict_to_physical_cores(pid: u32) {
+    let Some(core_mask) = physical_core_mask() else {
+        log_line!("[Affinity] No SMT cores found, {} left unrestricted", pid);
+        return;
+    };
+
+    unsafe {
+        let Ok(handle) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_SET_INFORMATION, false, pid) else {
+            log_line!("[Affinity] Cannot open PID {}", pid);
+            return;
+        };
+        let (mut process_mask, mut system_mask) = (0usize, 0usize);
+        if GetProcessAffinityMask(handle, &mut process_mask, &mut system_mask).is_ok() {
+            // Stay within what the process may use (it could already be restricted by the user)
+            let mask = process_mask & core_mask;
+            if mask != 0 && mask != process_mask {
+                match SetProcessAffinityMask(handle, mask) {
+                    Ok(()) => {
+                        *ORIGINAL_MASK.lock().unwrap() = Some((pid, process_mask));
+                        log_line!("[Affinity] PID {} limited to one thread per core ({:#x})", pid, mask);
+                    }
+                    Err(e) => log_line!("[Affinity] Could not set the affinity of PID {}: {}", pid, e),
+                }
+            }
+        }
+        let _ = CloseHandle(handle);
+    }
+}
+
+/// Put back the mask from before `restrict_to_physical_cores`
+pub fn restore() {
+    let Some((pid, mask)) = ORIGINAL_MASK.lock().unwrap().take() else {
+        return;
+    };
+    unsafe {
+        // Nothing to do if the game has exited
+        if let Ok(handle) = OpenProcess(PROCESS_SET_INFORMATION, false, pid) {
+            if SetProcessAffinityMask(handle, mask).is_ok() {
+                log_line!("[Affinity] PID {} affinity restored", pid);
+            }
+            let _ = CloseHandle(handle);
+        }
+    }
+}
+
+/// First logical processor of each physical core in processor group 0 (where SetProcessAffinityMask
+/// applies); None if no core has SMT, since the mask would change nothing
+fn physical_core_mask() -> Option<usize> {
+    unsafe {
+        let mut len = 0u32;
+        let _ = GetLogicalProcessorInformationEx(RelationProcessorCore, None, &mut len);
+        if len == 0 {
+            return None;
+        }
+        // u64 buffer for the entries' alignment; entries have variable size
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let base = buffer.as_mut_ptr().cast::<u8>();
+        GetLogicalProcessorInformationEx(RelationProcessorCore, Some(base.cast()), &mut len).ok()?;
+
+        let (mut mask, mut any_smt, mut offset) = (0usize, false, 0usize);
+        while offset < len as usize {
+            let info = &*base.add(offset).cast::<SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX>();
+            let core = &info.Anonymous.Processor;
+            let group = core.GroupMask[0];
+            if group.Group == 0 && group.Mask != 0 {
+                mask |= group.Mask & group.Mask.wrapping_neg();
+                any_smt |= core.Flags & LTP_PC_SMT != 0;
+            }
+            offset += info.Size as usize;
+        }
+        (any_smt && mask != 0).then_some(mask)
+    }
+}
@@ -0,0 +1,151 @@
+//! Audio Service
+//! Audio enhancements (APO effects) on the default playback device and communications ducking
+//! Original values are captured before the first change and written back on restore
+
+use windows::core::{PCWSTR, HSTRING, PROPVARIANT};
+use windows::Win32::Media::Audio::{
+    IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator, eConsole, eRender, PKEY_AudioEndpoint_Disable_SysFx,
+};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READWRITE,
+};
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use crate::services::notify;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::{log_line, tr};
+
+// Sound control panel > Communications: 0 = mute others, 1 = reduce 80% (default), 2 = reduce 50%, 3 = do nothing
+const DUCKING_KEY: &str = r"Software\Microsoft\Multimedia\Audio";
+const DUCKING_VALUE: &str = "UserDuckingPreference";
+const DUCKING_DO_NOTHING: u32 = 3;
+
+/// Original audio configuration captured before the first change
+#[derive(Default)]
+struct AudioState {
+    /// Endpoint ID we changed and its original Disable_SysFx value (None = never set, enhancements on)
+    enhancements: Option<(String, Option<u32>)>,
+    /// Original UserDuckingPreference (outer Some = we changed it, inner None = value was absent)
+    ducking: Option<Option<u32>>,
+}
+
+static AUDIO_STATE: Lazy<Mutex<AudioState>> = Lazy::new(|| Mutex::new(AudioState::default()));
+
+pub struct AudioService;
+
+impl AudioService {
+    // =========================================================================
+    // AUDIO ENHANCEMENTS
+    // PKEY_AudioEndpoint_Disable_SysFx on the default render endpoint
+    // Applies to streams opened after the change (the game's, if it starts later)
+    // =========================================================================
+
+    /// Disable enhancements on the default playback device, capturing the original first
+    pub fn disable_enhancements() {
+        let mut state = AUDIO_STATE.lock().unwrap();
+        if state.enhancements.is_some() {
+            return; // Already applied
+        }
+
+        let changed = Self::with_com(|| unsafe {
+            let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+            let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole).ok()?;
+            let id = Self::device_id(&device)?;
+            let store = device.OpenPropertyStore(STGM_READWRITE).ok()?;
+
+            let original = store.GetValue(&PKEY_AudioEndpoint_Disable_SysFx).ok()
+                .and_then(|value| u32::try_from(&value).ok());
+            if original == Some(1) {
+                log_line!("[Audio] Enhancements already disabled on the default device");
+                return None;
+            }
+
+            store.SetValue(&PKEY_AudioEndpoint_Disable_SysFx, &PROPVARIANT::from(1u32)).ok()?;
+            store.Commit().ok()?;
+            Some((id, original))
+        });
+
+        match changed {
+            Some(original) => {
+                log_line!("[Audio] Enhancements disabled on the default playback device");
+                state.enhancements = Some(original);
+            }
+            None => log_line!("[Audio] Enhancements left unchanged"),
+        }
+    }
+
+    /// Restore the enhancement setting on the device changed by `disable_enhancements`
+    pub fn restore_enhancements() {
+        let Some((id, original)) = AUDIO_STATE.lock().unwrap().enhancements.take() else {
+            return; // Nothing to restore
+        };
+
+        // A property store can't delete a value: "never set" restores as enabled (0)
+        let value = original.unwrap_or(0);
+        let restored = Self::with_com(|| unsafe {
+            let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+            let id_w = HSTRING::from(id.as_str());
+            let device = enumerator.GetDevice(PCWSTR(id_w.as_ptr())).ok()?;
+            let store = device.OpenPropertyStore(STGM_READWRITE).ok()?;
+            store.SetValue(&PKEY_AudioEndpoint_Disable_SysFx, &PROPVARIANT::from(value)).ok()?;
+            store.Commit().ok()
+        });
+
+        if restored.is_some() {
+            log_line!("[Audio] Enhancements restored on the playback device");
+        } else {
+            log_line!("[Audio] Failed to restore enhancements (device removed?)");
+            notify::attention(&tr!("Audio not restored"), &tr!("Audio enhancements could not be turned back on for the playback device."));
+        }
+    }
+
+    // =========================================================================
+    // COMMUNICATIONS DUCKING
+    // HKCU\Software\Microsoft\Multimedia\Audio UserDuckingPreference
+    // =========================================================================
+
+    /// Set communications ducking to "Do nothing", capturing the original first
+    pub fn disable_ducking() {
+        let mut state = AUDIO_STATE.lock().unwrap();
+        if state.ducking.is_none() {
+            state.ducking = Some(Win32.read_dword(Hive::CurrentUser, DUCKING_KEY, DUCKING_VALUE));
+        }
+        Win32.write_dword(Hive::CurrentUser, DUCKING_KEY, DUCKING_VALUE, DUCKING_DO_NOTHING);
+        log_line!("[Audio] Communications ducking set to do nothing");
+    }
+
+    /// Restore the original communications ducking preference
+    pub fn restore_ducking() {
+        let Some(original) = AUDIO_STATE.lock().unwrap().ducking.take() else {
+            return; // Nothing to restore
+        };
+        match original {
+            Some(value) => { Win32.write_dword(Hive::CurrentUser, DUCKING_KEY, DUCKING_VALUE, value); }
+            None => Win32.delete_value(Hive::CurrentUser, DUCKING_KEY, DUCKING_VALUE),
+        }
+        log_line!("[Audio] Communications ducking restored");
+    }
+
+    // =========================================================================
+    // Helpers
+    // =========================================================================
+
+    /// Run `f` with COM initialized on this thread
+    fn with_com<T>(f: impl FnOnce() -> Option<T>) -> Option<T> {
+        unsafe {
+            let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+            let result = f();
+            if initialized {
+                CoUninitialize();
+            }
+            result
+        }
+    }
+
+    unsafe fn device_id(device: &IMMDevice) -> Option<String> {
+        let id = device.GetId().ok()?;
+        let text = id.to_string().ok();
+        CoTaskMemFree(Some(id.0 as *const _));
+        text
+    }
+}
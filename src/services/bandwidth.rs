@@ -0,0 +1,223 @@
+//! Bandwidth Watch
+//! While a session runs, samples the byte counters of every established TCP connection (EStats)
+//! every few seconds, sums them per process and raises a toast with Suspend / Kill when a
+//! background process keeps pulling a lot of bandwidth (a missed updater, a cloud sync).
+//! UDP has no per-connection counters and is not attributed.
+
+use windows::Win32::Foundation::{BOOL, BOOLEAN};
+use windows::Win32::Networking::WinSock::{IN6_ADDR, IN6_ADDR_0};
+use windows::Win32::NetworkManagement::IpHelper::{
+    GetExtendedTcpTable, GetPerTcp6ConnectionEStats, GetPerTcpConnectionEStats, SetPerTcp6ConnectionEStats,
+    SetPerTcpConnectionEStats, TcpConnectionEstatsData, MIB_TCP6ROW, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_LH,
+    MIB_TCPROW_LH_0, MIB_TCPROW_OWNER_PID, MIB_TCP_STATE, TCP_ESTATS_DATA_ROD_v0, TCP_ESTATS_DATA_RW_v0,
+    TCP_TABLE_OWNER_PID_CONNECTIONS,
+};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use crate::services::connections;
+use crate::services::notify;
+use crate::services::process::{ProcessService, SuspendedProcess};
+use crate::{log_line, tr};
+
+const AF_INET: u32 = 2;
+const AF_INET6: u32 = 23;
+const MIB_TCP_STATE_ESTAB: u32 = 5;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Samples in a row above the threshold before alerting (a short burst is not worth a toast)
+const SUSTAINED_SAMPLES: u32 = 3;
+/// Processes alerted about without Suspend / Kill (freezing a service host takes Windows with it)
+const NO_ACTIONS: &[&str] = &["svchost", "System", "lsass", "services"];
+
+/// Both ends of a connection, stable for its lifetime
+type ConnectionKey = (SocketAddr, SocketAddr);
+
+struct Watch {
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+static WATCH: Lazy<Mutex<Option<Watch>>> = Lazy::new(|| Mutex::new(None));
+// Suspended from a toast; resumed by stop() so nothing stays frozen after the session
+static SUSPENDED: Lazy<Mutex<Vec<SuspendedProcess>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Start watching; `game_pid` is read on every sample so a picked or relaunched game is followed
+pub fn start(game_pid: Arc<AtomicU32>, threshold_kbps: u32) {
+    let mut watch = WATCH.lock().unwrap();
+    if watch.is_some() || threshold_kbps == 0 {
+        return;
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let running_for_thread = running.clone();
+    let handle = thread::spawn(move || {
+        let mut counters: HashMap<ConnectionKey, u64> = HashMap::new();
+        let mut streaks: HashMap<u32, u32> = HashMap::new();
+        let mut alerted: HashSet<u32> = HashSet::new();
+
+        while running_for_thread.load(Ordering::SeqCst) {
+            let per_process = sample(&mut counters);
+            let game = game_pid.load(Ordering::SeqCst);
+            let game_tree: Vec<u32> = if game != 0 { ProcessService::child_processes(game, None) } else { Vec::new() };
+
+            for (pid, bytes) in per_process {
+                let kbps = (bytes / 1024 / SAMPLE_INTERVAL.as_secs()) as u32;
+                if pid == game || pid == std::process::id() || game_tree.contains(&pid) || kbps < threshold_kbps {
+                    streaks.remove(&pid);
+                    continue;
+                }
+                let streak = streaks.entry(pid).or_insert(0);
+                *streak += 1;
+                if *streak >= SUSTAINED_SAMPLES && alerted.insert(pid) {
+                    alert(pid, kbps);
+                }
+            }
+            thread::sleep(SAMPLE_INTERVAL);
+        }
+    });
+
+    log_line!("[Bandwidth] Watching background processes (alert above {} KB/s)", threshold_kbps);
+    *watch = Some(Watch { running, handle });
+}
+
+/// Stop watching and resume anything suspended from an alert
+pub fn stop() {
+    if let Some(watch) = WATCH.lock().unwrap().take() {
+        watch.running.store(false, Ordering::SeqCst);
+        let _ = watch.handle.join();
+    }
+
+    let suspended = std::mem::take(&mut *SUSPENDED.lock().unwrap());
+    if !suspended.is_empty() {
+        ProcessService::resume_suspended(&suspended);
+        log_line!("[Bandwidth] Resumed {} process(es) suspended from an alert", suspended.len());
+    }
+}
+
+fn alert(pid: u32, kbps: u32) {
+    let Some(exe) = ProcessService::executable_name(pid) else { return };
+    let name = exe.strip_suffix(".exe").unwrap_or(&exe).to_string();
+    let rate = if kbps >= 1024 { format!("{:.1} MB/s", kbps as f64 / 1024.0) } else { format!("{} KB/s", kbps) };
+    log_line!("[Bandwidth] {} (PID {}) at {}", name, pid, rate);
+
+    let title = tr!("Background download");
+    let body = tr!("{} is using {} while you play", name, rate);
+    if NO_ACTIONS.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+        notify::info(&title, &body);
+        return;
+    }
+
+    notify::actions(&title, &body, &[(&tr!("Suspend"), "suspend"), (&tr!("Kill"), "kill")], move |action| {
+        // The PID may have been reused since the toast went up
+        if ProcessService::executable_name(pid).as_deref() != Some(exe.as_str()) {
+            return;
+        }
+        match action {
+            "suspend" => {
+                if let Some(process) = ProcessService::suspend_pid(pid) {
+                    log_line!("[Bandwidth] Suspended {} until the session ends", process.name);
+                    SUSPENDED.lock().unwrap().push(process);
+                }
+            }
+            "kill" => {
+                if let Some(process) = ProcessService::kill_pid(pid) {
+                    log_line!("[Bandwidth] Killed {}", process.name);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Bytes moved per PID since the previous sample; `counters` carries the totals between samples
+/// Collection is enabled per connection the first time it is seen (it stays on until it closes)
+fn sample(counters: &mut HashMap<ConnectionKey, u64>) -> HashMap<u32, u64> {
+    let mut per_process: HashMap<u32, u64> = HashMap::new();
+    let mut seen: HashMap<ConnectionKey, u64> = HashMap::with_capacity(counters.len());
+
+    let mut record = |key: ConnectionKey, pid: u32, total: Option<u64>| {
+        let Some(total) = total else { return };
+        if let Some(previous) = counters.get(&key) {
+            *per_process.entry(pid).or_insert(0) += total.saturating_sub(*previous);
+        }
+        seen.insert(key, total);
+    };
+
+    for row in connections::read_table::<MIB_TCPROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedTcpTable(buf, size, BOOL(0), AF_INET, TCP_TABLE_OWNER_PID_CONNECTIONS, 0)
+    }) {
+        if row.dwState != MIB_TCP_STATE_ESTAB {
+            continue;
+        }
+        let local = SocketAddr::new(IpAddr::V4(Ipv4Addr::from(row.dwLocalAddr.to_ne_bytes())), row.dwLocalPort as u16);
+        let remote = SocketAddr::new(IpAddr::V4(Ipv4Addr::from(row.dwRemoteAddr.to_ne_bytes())), row.dwRemotePort as u16);
+        let key = (local, remote);
+        let lh = MIB_TCPROW_LH {
+            Anonymous: MIB_TCPROW_LH_0 { dwState: row.dwState },
+            dwLocalAddr: row.dwLocalAddr,
+            dwLocalPort: row.dwLocalPort,
+            dwRemoteAddr: row.dwRemoteAddr,
+            dwRemotePort: row.dwRemotePort,
+        };
+        let total = unsafe {
+            if !counters.contains_key(&key) {
+                let _ = SetPerTcpConnectionEStats(&lh, TcpConnectionEstatsData, enable_collection(), 0, 0);
+            }
+            let mut rod = [0u8; std::mem::size_of::<TCP_ESTATS_DATA_ROD_v0>()];
+            (GetPerTcpConnectionEStats(&lh, TcpConnectionEstatsData, None, 0, None, 0, Some(&mut rod), 0) == 0)
+                .then(|| data_bytes(&rod))
+        };
+        record(key, row.dwOwningPid, total);
+    }
+
+    for row in connections::read_table::<MIB_TCP6ROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedTcpTable(buf, size, BOOL(0), AF_INET6, TCP_TABLE_OWNER_PID_CONNECTIONS, 0)
+    }) {
+        if row.dwState != MIB_TCP_STATE_ESTAB {
+            continue;
+        }
+        let local = SocketAddr::new(IpAddr::V6(Ipv6Addr::from(row.ucLocalAddr)), row.dwLocalPort as u16);
+        let remote = SocketAddr::new(IpAddr::V6(Ipv6Addr::from(row.ucRemoteAddr)), row.dwRemotePort as u16);
+        let key = (local, remote);
+        let row6 = MIB_TCP6ROW {
+            State: MIB_TCP_STATE(row.dwState as i32),
+            LocalAddr: IN6_ADDR { u: IN6_ADDR_0 { Byte: row.ucLocalAddr } },
+            dwLocalScopeId: row.dwLocalScopeId,
+            dwLocalPort: row.dwLocalPort,
+            RemoteAddr: IN6_ADDR { u: IN6_ADDR_0 { Byte: row.ucRemoteAddr } },
+            dwRemoteScopeId: row.dwRemoteScopeId,
+            dwRemotePort: row.dwRemotePort,
+        };
+        let total = unsafe {
+            if !counters.contains_key(&key) {
+                let _ = SetPerTcp6ConnectionEStats(&row6, TcpConnectionEstatsData, enable_collection(), 0, 0);
+            }
+            let mut rod = [0u8; std::mem::size_of::<TCP_ESTATS_DATA_ROD_v0>()];
+            (GetPerTcp6ConnectionEStats(&row6, TcpConnectionEstatsData, None, 0, None, 0, Some(&mut rod), 0) == 0)
+                .then(|| data_bytes(&rod))
+        };
+        record(key, row.dwOwningPid, total);
+    }
+
+    // Closed connections drop out here
+    *counters = seen;
+    per_process
+}
+
+/// TCP_ESTATS_DATA_RW_v0 { EnableCollection: TRUE } as the byte slice the Set call takes
+fn enable_collection() -> &'static [u8] {
+    static RW: TCP_ESTATS_DATA_RW_v0 = TCP_ESTATS_DATA_RW_v0 { EnableCollection: BOOLEAN(1) };
+    unsafe {
+        std::slice::from_raw_parts(&RW as *const _ as *const u8, std::mem::size_of::<TCP_ESTATS_DATA_RW_v0>())
+    }
+}
+
+/// DataBytesIn + DataBytesOut from a TCP_ESTATS_DATA_ROD_v0 buffer
+fn data_bytes(rod: &[u8]) -> u64 {
+    let data: TCP_ESTATS_DATA_ROD_v0 = unsafe { std::ptr::read_unaligned(rod.as_ptr() as *const _) };
+    data.DataBytesIn + data.DataBytesOut
+}
@@ -0,0 +1,136 @@
+//! Platform Timer (expert)
+//! Boot configuration (bcdedit) for the system timer: no dynamic tick, the platform tick and no
+//! forced HPET. Like the device interrupt settings these are permanent and only apply at boot;
+//! the values from before are kept in bcdedit.json (data folder) for the revert.
+
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::services::settings;
+use crate::log_line;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// (element, value to set; None = delete it so Windows picks the default)
+const TWEAKS: &[(&str, Option<&str>)] = &[
+    // Keep the timer ticking at a fixed rate instead of pausing it while idle
+    ("disabledynamictick", Some("yes")),
+    // Tick from the platform source (invariant TSC / HPET hardware timer) instead of synthetic ticks
+    ("useplatformtick", Some("yes")),
+    // Don't force HPET as the clock source, it is slower to read than the TSC
+    ("useplatformclock", None),
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BcdBackup {
+    /// (element, value shown by bcdedit before the change; None = not set)
+    originals: Vec<(String, Option<String>)>,
+    /// How bcdedit displays "yes" on this system (the output is localized)
+    yes_text: Option<String>,
+}
+
+/// The tweaks are written to the boot entry (possibly waiting for a reboot)
+pub fn is_applied() -> bool {
+    backup_path().exists()
+}
+
+/// Record the current values and write the tweaks
+pub fn apply() -> Result<(), String> {
+    if is_applied() {
+        return Ok(());
+    }
+    let current = read_current().ok_or("Could not read the boot configuration")?;
+    let mut backup = BcdBackup {
+        originals: TWEAKS.iter()
+            .map(|(element, _)| (element.to_string(), current.iter().find(|(e, _)| e == element).map(|(_, v)| v.clone())))
+            .collect(),
+        yes_text: None,
+    };
+    save_backup(&backup);
+
+    for (element, value) in TWEAKS {
+        let ok = match value {
+            Some(value) => bcdedit(&["/set", "{current}", element, value]),
+            // Deleting an element that isn't set fails; only the final state matters
+            None => { bcdedit(&["/deletevalue", "{current}", element]); true }
+        };
+        if !ok {
+            return Err(format!("bcdedit could not set {}", element));
+        }
+        log_line!("[Bcdedit] {} -> {}", element, value.unwrap_or("default"));
+    }
+
+    // Learn the localized "yes" from a value we just set, to tell yes/no apart on restore
+    backup.yes_text = read_current()
+        .and_then(|values| values.into_iter().find(|(e, _)| e == "disabledynamictick").map(|(_, v)| v));
+    save_backup(&backup);
+    Ok(())
+}
+
+/// Put back the values from before `apply`; true if anything was restored
+pub fn restore() -> bool {
+    let Some(backup) = load_backup() else {
+        return false;
+    };
+
+    for (element, original) in &backup.originals {
+        match original {
+            Some(shown) => {
+                let yes = backup.yes_text.as_deref().is_some_and(|yes| yes.eq_ignore_ascii_case(shown))
+                    || shown.eq_ignore_ascii_case("yes");
+                bcdedit(&["/set", "{current}", element, if yes { "yes" } else { "no" }]);
+            }
+            None => { bcdedit(&["/deletevalue", "{current}", element]); }
+        }
+        log_line!("[Bcdedit] {} restored ({})", element, original.as_deref().unwrap_or("not set"));
+    }
+    let _ = fs::remove_file(backup_path());
+    true
+}
+
+/// (element, displayed value) of the tweaked elements set on the current boot entry
+/// Element names are not localized, the displayed values are
+fn read_current() -> Option<Vec<(String, String)>> {
+    let output = Command::new("bcdedit")
+        .args(["/enum", "{current}"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    Some(text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let element = parts.next()?.to_ascii_lowercase();
+            let value = parts.next()?.to_string();
+            TWEAKS.iter().any(|(e, _)| *e == element).then_some((element, value))
+        })
+        .collect())
+}
+
+fn bcdedit(args: &[&str]) -> bool {
+    Command::new("bcdedit")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
+/// bcdedit.json in settings::data_dir()
+fn backup_path() -> PathBuf {
+    settings::data_dir().join("bcdedit.json")
+}
+
+fn load_backup() -> Option<BcdBackup> {
+    serde_json::from_str(&fs::read_to_string(backup_path()).ok()?).ok()
+}
+
+fn save_backup(backup: &BcdBackup) {
+    if let Ok(json) = serde_json::to_string_pretty(backup) {
+        if let Err(e) = fs::write(backup_path(), json) {
+            log_line!("[Bcdedit] Could not save the backup: {}", e);
+        }
+    }
+}
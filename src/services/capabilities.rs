@@ -0,0 +1,284 @@
+//! Hardware Capabilities
+//! Startup probe of what this machine can actually use (Windows build, cores, RAM,
+//! GPU WDDM version / hardware scheduling), so advanced modules that would only write
+//! useless registry values are disabled with a reason instead.
+//! Also holds the build compatibility matrix: the Windows builds each tweak's registry
+//! path is honored on, checked before writing it
+
+use once_cell::sync::Lazy;
+use windows::Wdk::Graphics::Direct3D::{
+    D3DKMTCloseAdapter, D3DKMTOpenAdapterFromLuid, D3DKMTQueryAdapterInfo,
+    D3DKMT_CLOSEADAPTER, D3DKMT_DRIVERVERSION, D3DKMT_OPENADAPTERFROMLUID, D3DKMT_QUERYADAPTERINFO,
+    D3DKMT_WDDM_2_7_CAPS, KMTQAITYPE_DRIVERVERSION, KMTQAITYPE_WDDM_2_7_CAPS,
+};
+use windows::Wdk::System::SystemServices::RtlGetVersion;
+use windows::Win32::System::SystemInformation::{
+    GetLogicalProcessorInformation, GlobalMemoryStatusEx, RelationProcessorCore,
+    MEMORYSTATUSEX, OSVERSIONINFOW, SYSTEM_LOGICAL_PROCESSOR_INFORMATION,
+};
+use crate::services::gpu::{GpuAdapter, GpuService};
+use crate::services::settings::AdvancedModuleSettings;
+use crate::log_line;
+
+/// Windows builds a tweak does something on (inclusive); outside of it the value is ignored
+/// or means something else, so it is skipped instead of written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Builds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Builds {
+    pub const ANY: Builds = Builds { min: 0, max: u32::MAX };
+
+    /// `min` and newer
+    pub const fn from(min: u32) -> Self {
+        Builds { min, max: u32::MAX }
+    }
+
+    /// Up to and including `max`
+    pub const fn until(max: u32) -> Self {
+        Builds { min: 0, max }
+    }
+
+    pub fn contains(&self, build: u32) -> bool {
+        (self.min..=self.max).contains(&build)
+    }
+}
+
+// Compatibility matrix for the registry paths sessions write
+pub const BUILDS_HAGS: Builds = Builds::from(19041); // 2004: HwSchMode
+pub const BUILDS_UPDATE_PAUSE: Builds = Builds::from(18362); // 1903: UX\Settings Pause*Time keys
+pub const BUILDS_GAME_MODE: Builds = Builds::from(15063); // 1703: GameBar AutoGameModeEnabled
+pub const BUILDS_AUTO_HDR: Builds = Builds::from(22000); // Windows 11: AutoHDREnable
+pub const BUILDS_POWER_THROTTLING: Builds = Builds::from(16299); // 1709: Control\Power\PowerThrottling
+pub const BUILDS_MPO_TEST_MODE: Builds = Builds::until(22631); // 24H2 no longer honors Dwm\OverlayTestMode
+
+/// Release names for the reasons shown in the UI
+const RELEASES: &[(u32, &str)] = &[
+    (15063, "Windows 10 1703"),
+    (16299, "Windows 10 1709"),
+    (18362, "Windows 10 1903"),
+    (19041, "Windows 10 2004"),
+    (22000, "Windows 11"),
+    (22631, "Windows 11 23H2"),
+    (26100, "Windows 11 24H2"),
+];
+
+/// WDDM 2.7 as reported by D3DKMT (major * 1000 + minor * 100)
+const WDDM_2_7: i32 = 2700;
+
+// Same thresholds the module descriptions advertise
+const MIN_PARKING_CORES: u32 = 6;
+const MIN_LARGE_PAGES_RAM_GB: f64 = 15.5; // 16 GB kits report slightly less usable memory
+
+/// What the probe found; `None` means the value could not be read (treated as supported)
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub windows_build: Option<u32>,
+    pub physical_cores: Option<u32>,
+    pub total_ram_gb: Option<f64>,
+    /// GPU that HAGS would target: name, WDDM version, hardware scheduling support
+    pub gpu: Option<(String, Option<i32>, Option<bool>)>,
+}
+
+static CAPABILITIES: Lazy<Capabilities> = Lazy::new(Capabilities::probe);
+
+impl Capabilities {
+    /// Probe once per run
+    pub fn get() -> &'static Capabilities {
+        &CAPABILITIES
+    }
+
+    fn probe() -> Self {
+        let capabilities = Self {
+            windows_build: Self::windows_build(),
+            physical_cores: Self::physical_cores(),
+            total_ram_gb: Self::total_ram_gb(),
+            gpu: GpuService::target_adapter("").map(|gpu| {
+                let (wddm, hags) = Self::query_gpu(&gpu);
+                (gpu.name, wddm, hags)
+            }),
+        };
+        log_line!("[Capabilities] {:?}", capabilities);
+        capabilities
+    }
+
+    // =========================================================================
+    // MODULE SUPPORT
+    // =========================================================================
+
+    pub fn core_parking(&self) -> Option<String> {
+        match self.physical_cores {
+            Some(cores) if cores < MIN_PARKING_CORES => {
+                Some(format!("Needs {}+ cores, this CPU has {}", MIN_PARKING_CORES, cores))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn large_pages(&self) -> Option<String> {
+        match self.total_ram_gb {
+            Some(gb) if gb < MIN_LARGE_PAGES_RAM_GB => Some(format!("Needs 16GB+ RAM, this PC has {:.0} GB", gb)),
+            _ => None,
+        }
+    }
+
+    pub fn hags(&self) -> Option<String> {
+        if let Some(reason) = self.build_support(BUILDS_HAGS) {
+            return Some(reason);
+        }
+        let Some((name, wddm, hags)) = &self.gpu else {
+            return Some("No hardware GPU found".to_string());
+        };
+        if let Some(version) = wddm.filter(|v| *v < WDDM_2_7) {
+            return Some(format!("{} driver is WDDM {}.{}, needs 2.7+", name, version / 1000, version % 1000 / 100));
+        }
+        if *hags == Some(false) {
+            return Some(format!("{} does not support hardware scheduling", name));
+        }
+        None
+    }
+
+    pub fn update_pause(&self) -> Option<String> {
+        self.build_support(BUILDS_UPDATE_PAUSE)
+    }
+
+    pub fn game_mode_coop(&self) -> Option<String> {
+        self.build_support(BUILDS_GAME_MODE)
+    }
+
+    pub fn auto_hdr(&self) -> Option<String> {
+        self.build_support(BUILDS_AUTO_HDR)
+    }
+
+    pub fn mpo(&self) -> Option<String> {
+        self.build_support(BUILDS_MPO_TEST_MODE)
+    }
+
+    /// Why a tweak for `builds` does nothing on this Windows build (None = supported or unknown build)
+    pub fn build_support(&self, builds: Builds) -> Option<String> {
+        let release = |build: u32| RELEASES.iter()
+            .find(|(b, _)| *b == build)
+            .map_or_else(|| format!("build {}", build), |(_, name)| name.to_string());
+        match self.windows_build {
+            Some(build) if build < builds.min => Some(format!("Needs {} or newer", release(builds.min))),
+            Some(build) if build > builds.max => Some(format!("Not supported after {}", release(builds.max))),
+            _ => None,
+        }
+    }
+
+    /// This Windows build honors a tweak for `builds` (unknown builds are assumed to)
+    pub fn supports(&self, builds: Builds) -> bool {
+        match self.windows_build {
+            Some(build) => builds.contains(build),
+            None => true,
+        }
+    }
+
+    /// Copy of `settings` with unsupported modules switched off (logged once per apply)
+    pub fn gate(&self, settings: &AdvancedModuleSettings) -> AdvancedModuleSettings {
+        let mut gated = settings.clone();
+        let checks: [(&mut bool, &str, Option<String>); 6] = [
+            (&mut gated.disable_core_parking, "Core parking", self.core_parking()),
+            (&mut gated.enable_large_pages, "Large pages", self.large_pages()),
+            (&mut gated.enable_hags, "HAGS", self.hags()),
+            (&mut gated.pause_windows_update, "Windows Update pause", self.update_pause()),
+            (&mut gated.windows_game_mode_coop, "Windows Game Mode co-op", self.game_mode_coop()),
+            (&mut gated.enable_auto_hdr, "Auto HDR", self.auto_hdr()),
+        ];
+        for (enabled, module, reason) in checks {
+            if let (true, Some(reason)) = (*enabled, reason) {
+                log_line!("[Capabilities] Skipping {}: {}", module, reason);
+                *enabled = false;
+            }
+        }
+        gated
+    }
+
+    // =========================================================================
+    // PROBES
+    // =========================================================================
+
+    /// RtlGetVersion: unlike GetVersionEx it is not capped by the app manifest
+    fn windows_build() -> Option<u32> {
+        let mut info = OSVERSIONINFOW {
+            dwOSVersionInfoSize: std::mem::size_of::<OSVERSIONINFOW>() as u32,
+            ..Default::default()
+        };
+        unsafe { RtlGetVersion(&mut info).is_ok().then_some(info.dwBuildNumber) }
+    }
+
+    fn physical_cores() -> Option<u32> {
+        let cores = Self::core_masks();
+        (!cores.is_empty()).then_some(cores.len() as u32)
+    }
+
+    /// Logical processor mask of each physical core, in order (empty if it could not be read)
+    pub fn core_masks() -> Vec<usize> {
+        unsafe {
+            let mut len = 0u32;
+            let _ = GetLogicalProcessorInformation(None, &mut len);
+            let count = len as usize / std::mem::size_of::<SYSTEM_LOGICAL_PROCESSOR_INFORMATION>();
+            if count == 0 {
+                return Vec::new();
+            }
+
+            let mut buffer = vec![SYSTEM_LOGICAL_PROCESSOR_INFORMATION::default(); count];
+            if GetLogicalProcessorInformation(Some(buffer.as_mut_ptr()), &mut len).is_err() {
+                return Vec::new();
+            }
+            buffer.iter()
+                .filter(|info| info.Relationship == RelationProcessorCore)
+                .map(|info| info.ProcessorMask)
+                .collect()
+        }
+    }
+
+    fn total_ram_gb() -> Option<f64> {
+        let mut status = MEMORYSTATUSEX {
+            dwLength: std::mem::size_of::<MEMORYSTATUSEX>() as u32,
+            ..Default::default()
+        };
+        unsafe { GlobalMemoryStatusEx(&mut status).ok()? };
+        Some(status.ullTotalPhys as f64 / 1073741824.0)
+    }
+
+    /// WDDM version and hardware scheduling support of an adapter, via D3DKMT
+    fn query_gpu(gpu: &GpuAdapter) -> (Option<i32>, Option<bool>) {
+        unsafe {
+            let mut open = D3DKMT_OPENADAPTERFROMLUID { AdapterLuid: gpu.luid, ..Default::default() };
+            if D3DKMTOpenAdapterFromLuid(&mut open).is_err() {
+                return (None, None);
+            }
+
+            let mut version = D3DKMT_DRIVERVERSION::default();
+            let query = D3DKMT_QUERYADAPTERINFO {
+                hAdapter: open.hAdapter,
+                Type: KMTQAITYPE_DRIVERVERSION,
+                pPrivateDriverData: &mut version as *mut _ as *mut _,
+                PrivateDriverDataSize: std::mem::size_of::<D3DKMT_DRIVERVERSION>() as u32,
+            };
+            let wddm = D3DKMTQueryAdapterInfo(&query).is_ok().then_some(version.0);
+
+            // The 2.7 caps query only exists on WDDM 2.7+ drivers
+            let hags = match wddm {
+                Some(v) if v >= WDDM_2_7 => {
+                    let mut caps = D3DKMT_WDDM_2_7_CAPS::default();
+                    let query = D3DKMT_QUERYADAPTERINFO {
+                        hAdapter: open.hAdapter,
+                        Type: KMTQAITYPE_WDDM_2_7_CAPS,
+                        pPrivateDriverData: &mut caps as *mut _ as *mut _,
+                        PrivateDriverDataSize: std::mem::size_of::<D3DKMT_WDDM_2_7_CAPS>() as u32,
+                    };
+                    // Bit 0: HwSchSupported
+                    D3DKMTQueryAdapterInfo(&query).is_ok().then(|| caps.Anonymous.Value & 1 != 0)
+                }
+                _ => None,
+            };
+
+            let _ = D3DKMTCloseAdapter(&D3DKMT_CLOSEADAPTER { hAdapter: open.hAdapter });
+            (wddm, hags)
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Clipboard
+//! Direct Win32 clipboard access (no PowerShell Set-Clipboard round trip)
+
+use windows::Win32::Foundation::{GlobalFree, HANDLE};
+use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use crate::log_line;
+
+/// Standard clipboard format for UTF-16 text
+const CF_UNICODETEXT: u32 = 13;
+
+/// Put `text` on the clipboard as Unicode text; false if the clipboard is busy or allocation failed
+pub fn set_text(text: &str) -> bool {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+
+    unsafe {
+        if OpenClipboard(None).is_err() {
+            log_line!("[Clipboard] Clipboard is in use by another application");
+            return false;
+        }
+
+        let copied = (|| {
+            EmptyClipboard().ok()?;
+            let memory = GlobalAlloc(GMEM_MOVEABLE, wide.len() * std::mem::size_of::<u16>()).ok()?;
+
+            let target = GlobalLock(memory) as *mut u16;
+            if target.is_null() {
+                let _ = GlobalFree(memory);
+                return None;
+            }
+            std::ptr::copy_nonoverlapping(wide.as_ptr(), target, wide.len());
+            // Reports an error once the lock count hits zero, which is the expected outcome
+            let _ = GlobalUnlock(memory);
+
+            // On success the clipboard owns the memory
+            if SetClipboardData(CF_UNICODETEXT, HANDLE(memory.0)).is_err() {
+                let _ = GlobalFree(memory);
+                return None;
+            }
+            Some(())
+        })()
+        .is_some();
+
+        let _ = CloseClipboard();
+        copied
+    }
+}
@@ -0,0 +1,101 @@
+//! Conflicting Software
+//! Other optimizers that manage the same things as the session (core parking, priorities, the
+//! standby list) undo each other's changes and can cause the stutter both try to prevent;
+//! running ones are detected at startup so the overlapping modules can be switched off
+
+use crate::services::process::ProcessService;
+use crate::services::settings::AdvancedModuleSettings;
+use crate::tr;
+
+/// What an optimizer manages that game mode manages too
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    /// Core parking / unparking (power plan processor settings)
+    CoreParking,
+    /// Process priorities, MMCSS and background demotion
+    Priorities,
+    /// Standby list / working set cleaning
+    MemoryCleaning,
+}
+
+impl Overlap {
+    pub fn describe(&self) -> String {
+        match self {
+            Overlap::CoreParking => tr!("core parking"),
+            Overlap::Priorities => tr!("process priorities"),
+            Overlap::MemoryCleaning => tr!("memory / standby list cleaning"),
+        }
+    }
+
+    /// Switch off the modules doing the same thing; false if none of them was on
+    /// Memory cleaning is part of every session, so there is no module to hand over
+    fn disable(&self, modules: &mut AdvancedModuleSettings) -> bool {
+        let switches: Vec<&mut bool> = match self {
+            Overlap::CoreParking => vec![&mut modules.disable_core_parking],
+            Overlap::Priorities => vec![&mut modules.mmcss_priority_boost, &mut modules.process_idle_demotion],
+            Overlap::MemoryCleaning => Vec::new(),
+        };
+        let mut changed = false;
+        for enabled in switches {
+            changed |= std::mem::replace(enabled, false);
+        }
+        changed
+    }
+
+    /// Any module doing the same thing is on
+    fn is_enabled(&self, modules: &AdvancedModuleSettings) -> bool {
+        match self {
+            Overlap::CoreParking => modules.disable_core_parking,
+            Overlap::Priorities => modules.mmcss_priority_boost || modules.process_idle_demotion,
+            Overlap::MemoryCleaning => false,
+        }
+    }
+}
+
+/// (product, process names without .exe, what it overlaps with)
+const KNOWN: &[(&str, &[&str], &[Overlap])] = &[
+    ("Razer Cortex", &["RazerCortex", "CortexLauncherService"], &[Overlap::Priorities, Overlap::MemoryCleaning]),
+    ("Process Lasso", &["ProcessLasso", "ProcessGovernor"], &[Overlap::Priorities, Overlap::CoreParking]),
+    ("Intelligent Standby List Cleaner", &["Intelligent standby list cleaner ISLC"], &[Overlap::MemoryCleaning]),
+    ("ParkControl", &["ParkControl"], &[Overlap::CoreParking]),
+    ("Wise Game Booster", &["WiseGameBooster"], &[Overlap::Priorities, Overlap::MemoryCleaning]),
+    ("Game Fire", &["GameFire"], &[Overlap::Priorities, Overlap::MemoryCleaning]),
+    ("Mem Reduct", &["memreduct"], &[Overlap::MemoryCleaning]),
+];
+
+/// A running optimizer
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub product: &'static str,
+    pub overlaps: &'static [Overlap],
+}
+
+impl Conflict {
+    /// "Process Lasso (process priorities, core parking)"
+    pub fn describe(&self) -> String {
+        let overlaps: Vec<String> = self.overlaps.iter().map(Overlap::describe).collect();
+        format!("{} ({})", self.product, overlaps.join(", "))
+    }
+
+    /// A module it fights with is switched on
+    pub fn overlaps_enabled(&self, modules: &AdvancedModuleSettings) -> bool {
+        self.overlaps.iter().any(|o| o.is_enabled(modules))
+    }
+}
+
+/// Known optimizers that are running right now
+pub fn detect() -> Vec<Conflict> {
+    KNOWN.iter()
+        .filter(|(_, processes, _)| ProcessService::find_process(processes).is_some())
+        .map(|&(product, _, overlaps)| Conflict { product, overlaps })
+        .collect()
+}
+
+/// Switch off every module that overlaps with `conflicts`; true if anything changed
+pub fn disable_overlapping(conflicts: &[Conflict], modules: &mut AdvancedModuleSettings) -> bool {
+    let mut changed = false;
+    for overlap in conflicts.iter().flat_map(|c| c.overlaps) {
+        changed |= overlap.disable(modules);
+    }
+    changed
+}
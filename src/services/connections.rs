@@ -0,0 +1,216 @@
+//! Game Connections
+//! Open TCP/UDP endpoints of the detected game from the IP helper tables, with the remote
+//! host's reverse DNS name (often names the region, e.g. "...fra1...") and its ICMP round trip
+
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::NetworkManagement::IpHelper::{
+    GetExtendedTcpTable, GetExtendedUdpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID,
+    MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, TCP_TABLE_OWNER_PID_ALL, UDP_TABLE_OWNER_PID,
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::thread;
+use crate::services::latency::LatencyMonitor;
+
+const AF_INET: u32 = 2;
+const AF_INET6: u32 = 23;
+const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// Remote hosts resolved and pinged per refresh (each ping may wait up to a second)
+const MAX_RESOLVED_HOSTS: usize = 16;
+
+/// One endpoint owned by the game
+#[derive(Debug, Clone)]
+pub struct GameConnection {
+    pub protocol: &'static str,
+    pub local_port: u16,
+    /// None for UDP (connectionless) and listening TCP sockets
+    pub remote: Option<SocketAddr>,
+    pub state: &'static str,
+    /// Reverse DNS name of the remote address
+    pub host: Option<String>,
+    /// ICMP round trip to the remote address, None if it doesn't answer (many servers don't)
+    pub rtt_ms: Option<u32>,
+}
+
+impl GameConnection {
+    /// "TCP 203.0.113.7:27015" or "UDP :50123" for the UI
+    pub fn endpoint(&self) -> String {
+        match self.remote {
+            Some(remote) => format!("{} {}", self.protocol, remote),
+            None => format!("{} :{}", self.protocol, self.local_port),
+        }
+    }
+}
+
+/// Endpoints of `pid`, established connections first, then resolved and pinged
+pub fn collect(pid: u32) -> Vec<GameConnection> {
+    let mut connections = list(pid);
+    connections.sort_by_key(|c| (c.remote.is_none(), c.state != "Established", c.remote.map(|r| r.ip())));
+    resolve(&mut connections);
+    connections
+}
+
+/// Raw endpoints of `pid` from the IPv4 and IPv6 TCP/UDP tables
+fn list(pid: u32) -> Vec<GameConnection> {
+    let mut connections = Vec::new();
+
+    for row in read_table::<MIB_TCPROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedTcpTable(buf, size, BOOL(0), AF_INET, TCP_TABLE_OWNER_PID_ALL, 0)
+    }) {
+        if row.dwOwningPid != pid {
+            continue;
+        }
+        let remote = IpAddr::V4(Ipv4Addr::from(row.dwRemoteAddr.to_ne_bytes()));
+        connections.push(tcp_connection(row.dwLocalPort, remote, row.dwRemotePort, row.dwState));
+    }
+    for row in read_table::<MIB_TCP6ROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedTcpTable(buf, size, BOOL(0), AF_INET6, TCP_TABLE_OWNER_PID_ALL, 0)
+    }) {
+        if row.dwOwningPid != pid {
+            continue;
+        }
+        let remote = IpAddr::V6(Ipv6Addr::from(row.ucRemoteAddr));
+        connections.push(tcp_connection(row.dwLocalPort, remote, row.dwRemotePort, row.dwState));
+    }
+    for row in read_table::<MIB_UDPROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedUdpTable(buf, size, BOOL(0), AF_INET, UDP_TABLE_OWNER_PID, 0)
+    }) {
+        if row.dwOwningPid == pid {
+            connections.push(udp_connection(row.dwLocalPort));
+        }
+    }
+    for row in read_table::<MIB_UDP6ROW_OWNER_PID>(|buf, size| unsafe {
+        GetExtendedUdpTable(buf, size, BOOL(0), AF_INET6, UDP_TABLE_OWNER_PID, 0)
+    }) {
+        if row.dwOwningPid == pid {
+            connections.push(udp_connection(row.dwLocalPort));
+        }
+    }
+
+    connections
+}
+
+fn tcp_connection(local_port: u32, remote: IpAddr, remote_port: u32, state: u32) -> GameConnection {
+    let listening = remote.is_unspecified();
+    GameConnection {
+        protocol: "TCP",
+        local_port: port(local_port),
+        remote: (!listening).then(|| SocketAddr::new(remote, port(remote_port))),
+        state: tcp_state(state),
+        host: None,
+        rtt_ms: None,
+    }
+}
+
+fn udp_connection(local_port: u32) -> GameConnection {
+    GameConnection {
+        protocol: "UDP",
+        local_port: port(local_port),
+        remote: None,
+        state: "Bound",
+        host: None,
+        rtt_ms: None,
+    }
+}
+
+/// Ports are stored in network byte order in the low 16 bits
+fn port(raw: u32) -> u16 {
+    u16::from_be(raw as u16)
+}
+
+fn tcp_state(state: u32) -> &'static str {
+    match state {
+        2 => "Listening",
+        3 | 4 => "Connecting",
+        5 => "Established",
+        6..=11 => "Closing",
+        _ => "Closed",
+    }
+}
+
+/// Reverse DNS and ping for public remote addresses, one thread per host
+fn resolve(connections: &mut [GameConnection]) {
+    let mut hosts: Vec<IpAddr> = Vec::new();
+    for ip in connections.iter().filter_map(|c| c.remote.map(|r| r.ip())).filter(is_public) {
+        if !hosts.contains(&ip) && hosts.len() < MAX_RESOLVED_HOSTS {
+            hosts.push(ip);
+        }
+    }
+    if hosts.is_empty() {
+        return;
+    }
+
+    let names = reverse_dns(&hosts);
+    let rtts: HashMap<IpAddr, Option<u32>> = thread::scope(|scope| {
+        let pings: Vec<_> = hosts.iter()
+            .map(|&ip| scope.spawn(move || (ip, match ip {
+                IpAddr::V4(v4) => LatencyMonitor::ping(u32::from_ne_bytes(v4.octets())),
+                IpAddr::V6(_) => None, // ICMP echo is IPv4 only here
+            })))
+            .collect();
+        pings.into_iter().filter_map(|p| p.join().ok()).collect()
+    });
+
+    for connection in connections.iter_mut() {
+        let Some(ip) = connection.remote.map(|r| r.ip()) else { continue };
+        connection.host = names.get(&ip).cloned();
+        connection.rtt_ms = rtts.get(&ip).copied().flatten();
+    }
+}
+
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !v4.is_loopback() && !v4.is_private() && !v4.is_unspecified() && !v4.is_link_local(),
+        IpAddr::V6(v6) => !v6.is_loopback() && !v6.is_unspecified() && (v6.segments()[0] & 0xffc0) != 0xfe80,
+    }
+}
+
+/// PTR names for `hosts` in a single PowerShell call
+fn reverse_dns(hosts: &[IpAddr]) -> HashMap<IpAddr, String> {
+    let list: Vec<String> = hosts.iter().map(|ip| format!("'{}'", ip)).collect();
+    let script = format!(
+        "foreach ($ip in @({})) {{ $n = (Resolve-DnsName -Name $ip -Type PTR -DnsOnly -QuickTimeout -ErrorAction SilentlyContinue | \
+         Select-Object -First 1).NameHost; \"$ip|$n\" }}",
+        list.join(",")
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    let Ok(output) = output else { return HashMap::new() };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().split_once('|'))
+        .filter(|(_, name)| !name.is_empty())
+        .filter_map(|(ip, name)| Some((ip.parse().ok()?, name.to_string())))
+        .collect()
+}
+
+/// Rows of an IP helper owner-PID table (dwNumEntries followed by the rows)
+/// The table can grow between the size query and the read, so retry a few times
+pub fn read_table<R: Copy>(query: impl Fn(Option<*mut core::ffi::c_void>, &mut u32) -> u32) -> Vec<R> {
+    let mut size: u32 = 0;
+    let _ = query(None, &mut size);
+
+    for _ in 0..3 {
+        if size == 0 {
+            return Vec::new();
+        }
+        // u32 buffer keeps the table correctly aligned
+        let mut buffer = vec![0u32; (size as usize).div_ceil(4)];
+        match query(Some(buffer.as_mut_ptr() as *mut _), &mut size) {
+            0 => unsafe {
+                let count = buffer[0] as usize;
+                let rows = buffer.as_ptr().add(1) as *const R;
+                return std::slice::from_raw_parts(rows, count).to_vec();
+            },
+            ERROR_INSUFFICIENT_BUFFER => continue,
+            _ => return Vec::new(),
+        }
+    }
+    Vec::new()
+}
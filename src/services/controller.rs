@@ -0,0 +1,68 @@
+//! Controller Trigger
+//! Detects connected game controllers (XInput pads and HID joysticks/gamepads such as
+//! DualSense) so picking up a pad can start a session or arm game detection
+
+use serde::{Deserialize, Serialize};
+use windows::Win32::UI::Input::{
+    GetRawInputDeviceInfoW, GetRawInputDeviceList, RAWINPUTDEVICELIST, RIDI_DEVICEINFO, RID_DEVICE_INFO, RIM_TYPEHID,
+};
+use windows::Win32::UI::Input::XboxController::{XInputGetState, XINPUT_STATE, XUSER_MAX_COUNT};
+
+/// HID Generic Desktop page: joystick and gamepad usages
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_JOYSTICK: u16 = 0x04;
+const HID_USAGE_GAMEPAD: u16 = 0x05;
+
+/// What connecting a controller does ("controller_trigger" in settings.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerTrigger {
+    #[default]
+    Off,
+    /// Enable game mode right away
+    Enable,
+    /// Watch for known games more often while a controller is connected (even with auto-activation off)
+    Detect,
+}
+
+/// Any XInput or HID game controller connected
+pub fn connected() -> bool {
+    xinput_connected() || hid_gamepad_connected()
+}
+
+fn xinput_connected() -> bool {
+    (0..XUSER_MAX_COUNT).any(|index| unsafe {
+        let mut state = XINPUT_STATE::default();
+        XInputGetState(index, &mut state) == 0
+    })
+}
+
+fn hid_gamepad_connected() -> bool {
+    unsafe {
+        let entry_size = std::mem::size_of::<RAWINPUTDEVICELIST>() as u32;
+        let mut count = 0u32;
+        if GetRawInputDeviceList(None, &mut count, entry_size) == u32::MAX || count == 0 {
+            return false;
+        }
+        let mut devices = vec![RAWINPUTDEVICELIST::default(); count as usize];
+        let listed = GetRawInputDeviceList(Some(devices.as_mut_ptr()), &mut count, entry_size);
+        if listed == u32::MAX {
+            return false;
+        }
+        devices.truncate(listed as usize);
+
+        devices.iter().filter(|d| d.dwType == RIM_TYPEHID).any(|device| {
+            let mut info = RID_DEVICE_INFO {
+                cbSize: std::mem::size_of::<RID_DEVICE_INFO>() as u32,
+                ..Default::default()
+            };
+            let mut size = info.cbSize;
+            let read = GetRawInputDeviceInfoW(device.hDevice, RIDI_DEVICEINFO, Some(&mut info as *mut _ as *mut _), &mut size);
+            if read == u32::MAX || read == 0 {
+                return false;
+            }
+            let hid = info.Anonymous.hid;
+            hid.usUsagePage == HID_USAGE_PAGE_GENERIC && matches!(hid.usUsage, HID_USAGE_JOYSTICK | HID_USAGE_GAMEPAD)
+        })
+    }
+}
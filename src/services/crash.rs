@@ -0,0 +1,124 @@
+//! Crash Handler
+//! A panic or an unhandled exception writes a minidump and the recent log lines to
+//! crash-<time>.dmp / .log in the settings folder. If the dying process had a session applied,
+//! it is restored right there from the journal; the watchdog stays the fallback when that fails
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::os::windows::io::AsRawHandle;
+use std::panic;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Diagnostics::Debug::{
+    MiniDumpWriteDump, SetUnhandledExceptionFilter, EXCEPTION_CONTINUE_SEARCH, EXCEPTION_POINTERS,
+    MINIDUMP_EXCEPTION_INFORMATION, MiniDumpNormal,
+};
+use windows::Win32::System::Threading::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId};
+use crate::services::{gamemode::GameModeService, journal, settings};
+use crate::log_line;
+
+/// Log lines kept in memory for the crash log
+const RECENT_LOG_LINES: usize = 200;
+
+static RECENT_LOG: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_LOG_LINES)));
+// Set by the first fatal crash; a second one (e.g. during the restore) only falls through
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+/// Keep a log line for the crash log (called by `log_line!`)
+pub fn remember(line: String) {
+    if let Ok(mut recent) = RECENT_LOG.lock() {
+        if recent.len() == RECENT_LOG_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+}
+
+/// Install the panic hook and the unhandled exception filter (once, early in main)
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // Release builds abort on panic; in debug builds only a main thread panic ends the process
+        let fatal = cfg!(panic = "abort") || thread::current().name() == Some("main");
+        handle(&format!("Panic: {}", info), None, fatal);
+        default_hook(info);
+    }));
+
+    unsafe {
+        SetUnhandledExceptionFilter(Some(exception_filter));
+    }
+}
+
+unsafe extern "system" fn exception_filter(info: *const EXCEPTION_POINTERS) -> i32 {
+    let code = info.as_ref()
+        .and_then(|pointers| pointers.ExceptionRecord.as_ref())
+        .map_or(0, |record| record.ExceptionCode.0 as u32);
+    handle(&format!("Unhandled exception 0x{:08X}", code), Some(info), true);
+    // Let Windows Error Reporting end the process as usual
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+fn handle(reason: &str, exception: Option<*const EXCEPTION_POINTERS>, fatal: bool) {
+    if CRASHING.load(Ordering::SeqCst) || (fatal && CRASHING.swap(true, Ordering::SeqCst)) {
+        return;
+    }
+    log_line!("[Crash] {}", reason);
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let base = settings::data_dir().join(format!("crash-{}", stamp));
+    write_minidump(&base.with_extension("dmp"), exception);
+    write_log(&base.with_extension("log"), reason);
+
+    if fatal {
+        emergency_restore();
+    }
+}
+
+/// Same restore as the panic hotkey, only if this process applied the session in the journal
+fn emergency_restore() {
+    if !journal::read().is_some_and(|session| session.app_pid == std::process::id()) {
+        return;
+    }
+    log_line!("[Crash] Session was applied, restoring before exit");
+    GameModeService::force_restore();
+}
+
+fn write_minidump(path: &Path, exception: Option<*const EXCEPTION_POINTERS>) {
+    let Ok(file) = File::create(path) else { return };
+    unsafe {
+        let info = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: GetCurrentThreadId(),
+            ExceptionPointers: pointers as *mut _,
+            ClientPointers: false.into(),
+        });
+        let written = MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            HANDLE(file.as_raw_handle()),
+            MiniDumpNormal,
+            info.as_ref().map(|i| i as *const _),
+            None,
+            None,
+        );
+        if let Err(e) = written {
+            log_line!("[Crash] Could not write the minidump: {}", e);
+        }
+    }
+}
+
+fn write_log(path: &Path, reason: &str) {
+    let mut content = format!("Xilly Game Mode {}\r\n{}\r\n\r\n", env!("CARGO_PKG_VERSION"), reason);
+    // The crashing thread may hold the lock
+    if let Ok(recent) = RECENT_LOG.try_lock() {
+        for line in recent.iter() {
+            content.push_str(line);
+            content.push_str("\r\n");
+        }
+    }
+    let _ = fs::write(path, content);
+}
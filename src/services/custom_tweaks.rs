@@ -0,0 +1,201 @@
+//! Custom Tweaks
+//! User-defined tweak modules read from tweaks.json (registry values, services, commands),
+//! applied alongside the built-in ones with the same save-original / restore-on-disable handling
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use crate::services::hooks::{HookEvent, HookService, ScriptHook};
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::services::windows::WindowsServiceManager;
+use crate::services::settings;
+use crate::log_line;
+
+/// tweaks.json: a list of named modules
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TweakFile {
+    #[serde(default)]
+    tweaks: Vec<CustomTweak>,
+}
+
+/// One user-defined module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CustomTweak {
+    name: String,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    registry: Vec<RegistrySet>,
+    /// Services stopped for the session and started again on disable
+    #[serde(default)]
+    stop_services: Vec<String>,
+    #[serde(default)]
+    enable_commands: Vec<ScriptHook>,
+    #[serde(default)]
+    disable_commands: Vec<ScriptHook>,
+}
+
+fn default_true() -> bool { true }
+
+/// Registry value to write for the session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RegistrySet {
+    /// "HKLM" (default) or "HKCU"
+    #[serde(default = "default_root")]
+    root: String,
+    path: String,
+    name: String,
+    value: TweakValue,
+}
+
+fn default_root() -> String { "HKLM".to_string() }
+
+/// JSON number = REG_DWORD, string = REG_SZ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum TweakValue {
+    Dword(u32),
+    String(String),
+}
+
+/// Value as it was before the session (raw type + bytes, so any type round-trips)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedValue {
+    root: String,
+    path: String,
+    name: String,
+    /// None = value did not exist and is deleted on restore
+    original: Option<(u32, Vec<u8>)>,
+}
+
+/// What the running session changed, persisted in the session journal
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AppliedState {
+    registry: Vec<SavedValue>,
+    stopped_services: Vec<String>,
+    /// Kept from enable so restore runs them even if tweaks.json changed meanwhile
+    disable_commands: Vec<ScriptHook>,
+    applied: bool,
+}
+
+static APPLIED: Lazy<Mutex<AppliedState>> = Lazy::new(|| Mutex::new(AppliedState::default()));
+
+pub struct CustomTweaksService;
+
+impl CustomTweaksService {
+    /// Apply every enabled module in tweaks.json, saving originals first
+    pub fn enable() {
+        let mut state = APPLIED.lock().unwrap();
+        if state.applied {
+            return;
+        }
+        let modules: Vec<CustomTweak> = Self::load().tweaks.into_iter().filter(|t| t.enabled).collect();
+        if modules.is_empty() {
+            return;
+        }
+
+        for module in &modules {
+            for set in &module.registry {
+                let Some(root) = Self::root(&set.root) else {
+                    log_line!("[CustomTweaks] {}: unknown root '{}', skipping {}", module.name, set.root, set.name);
+                    continue;
+                };
+                // A value listed twice keeps its first (real) original
+                if !state.registry.iter().any(|s| s.root == set.root && s.path == set.path && s.name == set.name) {
+                    state.registry.push(SavedValue {
+                        root: set.root.clone(),
+                        path: set.path.clone(),
+                        name: set.name.clone(),
+                        original: Win32.read_raw(root, &set.path, &set.name),
+                    });
+                }
+                let written = match &set.value {
+                    TweakValue::Dword(v) => Win32.write_dword(root, &set.path, &set.name, *v),
+                    TweakValue::String(s) => Win32.write_string(root, &set.path, &set.name, s),
+                };
+                if !written {
+                    log_line!("[CustomTweaks] {}: failed to set {}\\{}", module.name, set.path, set.name);
+                }
+            }
+
+            let names: Vec<&str> = module.stop_services.iter().map(String::as_str).collect();
+            state.stopped_services.extend(WindowsServiceManager::stop_services(&Win32, &names));
+
+            HookService::run_all(&module.enable_commands, HookEvent::Enable);
+            state.disable_commands.extend(module.disable_commands.iter().cloned());
+            log_line!("[CustomTweaks] Applied '{}'", module.name);
+        }
+        state.applied = true;
+    }
+
+    /// Put back everything `enable` changed
+    pub fn disable() {
+        let mut state = APPLIED.lock().unwrap();
+        if !state.applied {
+            return;
+        }
+
+        // Commands first, in reverse: they may depend on the tweaks still being in place
+        let commands: Vec<ScriptHook> = state.disable_commands.iter().rev().cloned().collect();
+        HookService::run_all(&commands, HookEvent::Disable);
+
+        for saved in state.registry.iter().rev() {
+            let Some(root) = Self::root(&saved.root) else { continue };
+            match &saved.original {
+                Some((value_type, data)) => {
+                    Win32.write_raw(root, &saved.path, &saved.name, *value_type, data);
+                }
+                None => Win32.delete_value(root, &saved.path, &saved.name),
+            }
+        }
+        WindowsServiceManager::restore_services(&Win32, &state.stopped_services);
+
+        log_line!(
+            "[CustomTweaks] Restored {} registry value(s) and {} service(s)",
+            state.registry.len(),
+            state.stopped_services.len()
+        );
+        *state = AppliedState::default();
+    }
+
+    /// Saved state while applied, for the session journal
+    pub fn journal_state() -> Option<serde_json::Value> {
+        let state = APPLIED.lock().unwrap();
+        if !state.applied {
+            return None;
+        }
+        serde_json::to_value(&*state).ok()
+    }
+
+    /// Restore from a journaled state (crash recovery in a fresh process)
+    pub fn restore_from_journal(value: &serde_json::Value) {
+        let Ok(state) = serde_json::from_value::<AppliedState>(value.clone()) else { return };
+        *APPLIED.lock().unwrap() = state;
+        Self::disable();
+    }
+
+    fn load() -> TweakFile {
+        let Ok(content) = fs::read_to_string(Self::tweaks_path()) else {
+            return TweakFile::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_line!("[CustomTweaks] tweaks.json could not be read ({}), no custom tweaks applied", e);
+            TweakFile::default()
+        })
+    }
+
+    /// tweaks.json in settings::data_dir()
+    fn tweaks_path() -> PathBuf {
+        settings::data_dir().join("tweaks.json")
+    }
+
+    fn root(name: &str) -> Option<Hive> {
+        match name.to_ascii_uppercase().as_str() {
+            "HKLM" | "HKEY_LOCAL_MACHINE" => Some(Hive::LocalMachine),
+            "HKCU" | "HKEY_CURRENT_USER" => Some(Hive::CurrentUser),
+            _ => None,
+        }
+    }
+}
@@ -1,26 +1,52 @@
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetWindowRect, GetSystemMetrics, SetForegroundWindow,
-    EnumWindows, IsWindowVisible, SM_CXSCREEN, SM_CYSCREEN,
-    GetWindowThreadProcessId,
+    GetWindowRect, SetForegroundWindow,
+    EnumWindows, IsWindowVisible,
+    GetWindowThreadProcessId, GetWindowTextW, GetWindowTextLengthW,
+    GetWindow, GetWindowLongW, GW_OWNER, GWL_EXSTYLE, WS_EX_TOOLWINDOW,
+    GetForegroundWindow, IsWindow, GetMessageW, TranslateMessage, DispatchMessageW, MSG,
+    EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
 };
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+use windows::Win32::UI::HiDpi::{SetThreadDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONULL};
 use windows::Win32::Foundation::{HWND, RECT, BOOL, LPARAM, CloseHandle};
 use windows::Win32::System::Diagnostics::ToolHelp::{
     CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32, TH32CS_SNAPPROCESS
 };
+use crate::services::process::ProcessService;
 use std::process::Command;
 use std::os::windows::process::CommandExt;
-use std::sync::atomic::{AtomicU32, AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicPtr, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use once_cell::sync::Lazy;
 
 pub struct GameDetector;
 
-// Static arrays for known games (zero allocation)
-static KNOWN_GAMES: &[&str] = &[
+/// Visible top-level window offered in the manual game picker
+#[derive(Debug, Clone)]
+pub struct WindowCandidate {
+    pub pid: u32,
+    pub hwnd: HWND,
+    pub title: String,
+    pub process_name: String,
+}
+
+// Games outside Steam/Epic/GOG libraries (own launchers), always known
+static BUILTIN_GAMES: &[&str] = &[
     "cod", "cod24-cod", "FortniteClient-Win64-Shipping", "r5apex", "cs2", 
     "valheim", "dota2", "League of Legends", "Overwatch", "Valorant-Win64-Shipping",
     "GTA5", "RDR2", "Cyberpunk2077", "Minecraft.Windows",
     "FPSAimTrainer-Win64-Shipping"
 ];
 
+// Built-in games plus executables found by the library scan at startup
+static KNOWN_GAMES: Lazy<RwLock<Vec<String>>> =
+    Lazy::new(|| RwLock::new(BUILTIN_GAMES.iter().map(|s| s.to_string()).collect()));
+
+// Last focused window that looked like a game: (pid, hwnd as isize so it can live in a static)
+static FOREGROUND_GAME: Mutex<Option<(u32, isize)>> = Mutex::new(None);
+
 static EXCLUDED_PROCESSES: &[&str] = &[
     "explorer", "SearchApp", "LockApp", "SearchHost"
 ];
@@ -29,12 +55,121 @@ static EXCLUDED_PROCESSES: &[&str] = &[
 static DESKTOP_CHASSIS: &[&str] = &["3", "4", "6", "7", "13", "35"];
 
 impl GameDetector {
-    /// Detect fullscreen game - Optimized single-pass version
+    /// Detect fullscreen game
     /// Returns Option<(pid, hwnd)>
+    /// Fullscreen means covering the whole monitor the window is on (any monitor, not just the primary)
+    /// Order: last game window the foreground hook saw, the current foreground window, then a process scan
     pub fn detect_fullscreen_game() -> Option<(u32, HWND)> {
+        unsafe {
+            // Physical pixels for both window and monitor rects, whatever the scaling of each display
+            let previous_dpi_context = SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+            
+            let result = Self::foreground_candidate()
+                .or_else(|| {
+                    let hwnd = GetForegroundWindow();
+                    Self::evaluate_window(hwnd).map(|pid| (pid, hwnd))
+                })
+                .or_else(Self::scan_processes);
+            
+            if !previous_dpi_context.0.is_null() {
+                SetThreadDpiAwarenessContext(previous_dpi_context);
+            }
+            result
+        }
+    }
+
+    /// Start listening for foreground changes (EVENT_SYSTEM_FOREGROUND)
+    /// Each newly focused window is evaluated once, so detection doesn't need to scan every process
+    pub fn start_foreground_hook() {
+        static STARTED: AtomicBool = AtomicBool::new(false);
+        if STARTED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        
+        thread::spawn(|| unsafe {
+            // The hook callback runs on this thread, evaluate rects in physical pixels
+            SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+            
+            let hook = SetWinEventHook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                None,
+                Some(Self::on_foreground),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+            );
+            if hook.is_invalid() {
+                return;
+            }
+            
+            // Out-of-context hooks are delivered through this thread's message loop
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+            let _ = UnhookWinEvent(hook);
+        });
+    }
+
+    unsafe extern "system" fn on_foreground(
+        _hook: HWINEVENTHOOK,
+        _event: u32,
+        hwnd: HWND,
+        id_object: i32,
+        _id_child: i32,
+        _event_thread: u32,
+        _event_time: u32,
+    ) {
+        // OBJID_WINDOW: the window itself, not a child object
+        if id_object != 0 || hwnd.0.is_null() {
+            return;
+        }
+        if let Some(pid) = Self::evaluate_window(hwnd) {
+            *FOREGROUND_GAME.lock().unwrap() = Some((pid, hwnd.0 as isize));
+        }
+    }
+
+    /// Last game window the hook saw, if it is still a game window of the same process
+    fn foreground_candidate() -> Option<(u32, HWND)> {
+        let (pid, raw) = (*FOREGROUND_GAME.lock().unwrap())?;
+        let hwnd = HWND(raw as *mut _);
+        
+        let still_valid = unsafe { IsWindow(hwnd).as_bool() } && Self::evaluate_window(hwnd) == Some(pid);
+        if !still_valid {
+            *FOREGROUND_GAME.lock().unwrap() = None;
+            return None;
+        }
+        Some((pid, hwnd))
+    }
+
+    /// PID of the window's process if the window looks like a game (known game or covers its monitor)
+    fn evaluate_window(hwnd: HWND) -> Option<u32> {
+        unsafe {
+            if hwnd.0.is_null() || !IsWindowVisible(hwnd).as_bool() {
+                return None;
+            }
+            
+            let mut pid: u32 = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            if pid == 0 || pid == std::process::id() {
+                return None;
+            }
+            
+            let path = ProcessService::executable_path(pid)?;
+            let name = std::path::Path::new(&path).file_stem()?.to_string_lossy().into_owned();
+            if EXCLUDED_PROCESSES.iter().any(|&e| e.eq_ignore_ascii_case(&name)) {
+                return None;
+            }
+            
+            (Self::is_known_game(&name) || Self::covers_monitor(hwnd)).then_some(pid)
+        }
+    }
+
+    /// Full process scan - fallback when the game never had focus while the hook was running
+    fn scan_processes() -> Option<(u32, HWND)> {
         let current_pid = std::process::id();
-        let screen_w = unsafe { GetSystemMetrics(SM_CXSCREEN) };
-        let screen_h = unsafe { GetSystemMetrics(SM_CYSCREEN) };
         
         unsafe {
             let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else { 
@@ -69,7 +204,7 @@ impl GameDetector {
                     }
                     
                     // Check if known game (priority)
-                    let is_known_game = KNOWN_GAMES.iter().any(|&g| g.eq_ignore_ascii_case(name));
+                    let is_known_game = Self::is_known_game(name);
                     
                     // Get main window for this process
                     if let Some(hwnd) = Self::get_main_window(pid) {
@@ -79,17 +214,10 @@ impl GameDetector {
                             break 'outer;
                         }
                         
-                        // Check if fullscreen
-                        let mut rect = RECT::default();
-                        if GetWindowRect(hwnd, &mut rect).is_ok() {
-                            let width = rect.right - rect.left;
-                            let height = rect.bottom - rect.top;
-                            
-                            // C# uses >= for fullscreen detection
-                            if width >= screen_w && height >= screen_h {
-                                result = Some((pid, hwnd));
-                                break 'outer;
-                            }
+                        // Check if fullscreen or borderless on its monitor
+                        if Self::covers_monitor(hwnd) {
+                            result = Some((pid, hwnd));
+                            break 'outer;
                         }
                     }
 
@@ -102,6 +230,29 @@ impl GameDetector {
         }
     }
 
+    /// True if the window covers the full area of the monitor it is on
+    /// Exclusive fullscreen and borderless windowed both match; C# used >= against the primary screen
+    fn covers_monitor(hwnd: HWND) -> bool {
+        unsafe {
+            let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL);
+            if monitor.is_invalid() {
+                return false;
+            }
+            
+            let mut info = MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                ..Default::default()
+            };
+            let mut rect = RECT::default();
+            if !GetMonitorInfoW(monitor, &mut info).as_bool() || GetWindowRect(hwnd, &mut rect).is_err() {
+                return false;
+            }
+            
+            let m = info.rcMonitor;
+            rect.left <= m.left && rect.top <= m.top && rect.right >= m.right && rect.bottom >= m.bottom
+        }
+    }
+
     /// Get main window for a process - Optimized
     fn get_main_window(pid: u32) -> Option<HWND> {
         static TARGET_PID: AtomicU32 = AtomicU32::new(0);
@@ -132,6 +283,84 @@ impl GameDetector {
         }
     }
 
+    /// Process that took over from an exited game: a child it spawned (launcher -> game),
+    /// the same executable started again (anti-cheat relaunch), or a newly detected game
+    pub fn find_successor(exited_pid: u32, exited_start: Option<u64>, exe_name: Option<&str>) -> Option<u32> {
+        if let Some(child) = ProcessService::child_processes(exited_pid, exited_start).into_iter().next() {
+            return Some(child);
+        }
+        if let Some(pid) = exe_name.and_then(|name| ProcessService::find_process(&[name])) {
+            return Some(pid);
+        }
+        Self::detect_fullscreen_game().map(|(pid, _)| pid)
+    }
+
+    /// Add executables found by the library scan to the built-in known games
+    pub fn set_library_games(executables: Vec<String>) {
+        let mut known: Vec<String> = BUILTIN_GAMES.iter().map(|s| s.to_string()).collect();
+        known.extend(executables);
+        *KNOWN_GAMES.write().unwrap() = known;
+    }
+
+    fn is_known_game(name: &str) -> bool {
+        KNOWN_GAMES.read().unwrap().iter().any(|g| g.eq_ignore_ascii_case(name))
+    }
+
+    /// PID of a running known game (built-in or from the library scan)
+    pub fn find_known_game() -> Option<u32> {
+        let known = KNOWN_GAMES.read().unwrap().clone();
+        let names: Vec<&str> = known.iter().map(String::as_str).collect();
+        ProcessService::find_process(&names)
+    }
+
+    /// Visible, titled top-level windows of other processes (manual game selection)
+    pub fn list_windows() -> Vec<WindowCandidate> {
+        unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let windows = &mut *(lparam.0 as *mut Vec<(u32, HWND, String)>);
+            
+            // Skip hidden, owned (dialogs) and tool windows
+            if !IsWindowVisible(hwnd).as_bool()
+                || GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.0.is_null())
+                || (GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0) != 0
+            {
+                return BOOL(1);
+            }
+            
+            let len = GetWindowTextLengthW(hwnd);
+            if len == 0 {
+                return BOOL(1);
+            }
+            let mut buf = vec![0u16; len as usize + 1];
+            let copied = GetWindowTextW(hwnd, &mut buf);
+            
+            let mut pid: u32 = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            windows.push((pid, hwnd, String::from_utf16_lossy(&buf[..copied as usize])));
+            BOOL(1)
+        }
+        
+        let mut windows: Vec<(u32, HWND, String)> = Vec::new();
+        unsafe {
+            let _ = EnumWindows(Some(callback), LPARAM(&mut windows as *mut _ as isize));
+        }
+        
+        let current_pid = std::process::id();
+        windows.into_iter()
+            .filter(|(pid, _, _)| *pid != current_pid && *pid != 0)
+            .filter_map(|(pid, hwnd, title)| {
+                let path = ProcessService::executable_path(pid)?;
+                let process_name = std::path::Path::new(&path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if EXCLUDED_PROCESSES.iter().any(|&e| e.eq_ignore_ascii_case(&process_name)) {
+                    return None;
+                }
+                Some(WindowCandidate { pid, hwnd, title, process_name })
+            })
+            .collect()
+    }
+
     /// Focus window
     #[inline]
     pub fn focus_window(hwnd: HWND) {
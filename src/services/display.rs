@@ -0,0 +1,492 @@
+//! Display Service
+//! Per-display queries (DXGI outputs, monitor mapping) for display-related tweaks,
+//! HDR (advanced color) state via the display configuration API, Night Light / color filters,
+//! and color calibration (gamma ramp / ICC profile) preservation around them
+
+use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1, IDXGIOutput2};
+use windows::Win32::Graphics::Gdi::{
+    CreateDCW, DeleteDC, MonitorFromWindow, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST,
+    EnumDisplayDevicesW, EnumDisplaySettingsW, DISPLAY_DEVICEW, DISPLAY_DEVICE_ACTIVE, DEVMODEW, ENUM_CURRENT_SETTINGS,
+};
+use windows::Win32::UI::ColorSystem::{
+    GetDeviceGammaRamp, GetICMProfileW, SetDeviceGammaRamp, SetICMProfileW, WcsSetDefaultColorProfile,
+    CPST_NONE, CPT_ICC, WCS_PROFILE_MANAGEMENT_SCOPE_CURRENT_USER,
+};
+use windows::Win32::Devices::Display::{
+    DisplayConfigGetDeviceInfo, DisplayConfigSetDeviceInfo, GetDisplayConfigBufferSizes, QueryDisplayConfig,
+    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    DISPLAYCONFIG_DEVICE_INFO_SET_ADVANCED_COLOR_STATE, DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO,
+    DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE, DISPLAYCONFIG_PATH_INFO,
+    DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE, QDC_ONLY_ACTIVE_PATHS, SetDisplayConfig,
+    SDC_ALLOW_CHANGES, SDC_APPLY, SDC_USE_DATABASE_CURRENT, SDC_USE_SUPPLIED_DISPLAY_CONFIG,
+};
+use windows::Win32::Foundation::{HWND, LPARAM, LUID, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SendMessageTimeoutW, EDD_GET_DEVICE_INTERFACE_NAME, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+};
+use windows::core::{w, Interface, HSTRING, PCWSTR, PWSTR};
+use crate::services::notify;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::{log_line, tr};
+
+// Night Light state blob in the CloudStore (written by Settings > Display > Night light)
+const NIGHT_LIGHT_STATE_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\CloudStore\Store\DefaultAccount\Current\default$windows.data.bluelightreduction.bluelightreductionstate\windows.data.bluelightreduction.bluelightreductionstate";
+// Settings > Accessibility > Color filters
+const COLOR_FILTERING_KEY: &str = r"Software\Microsoft\ColorFiltering";
+
+/// Gamma ramp layout expected by Get/SetDeviceGammaRamp: 256 entries each for R, G, B
+type GammaRamp = [u16; 3 * 256];
+
+/// A display output as seen by DXGI
+#[derive(Debug, Clone)]
+pub struct DisplayOutput {
+    /// GDI device name, e.g. \\.\DISPLAY1
+    pub device_name: String,
+    pub monitor: HMONITOR,
+    /// Output exposes hardware overlay planes (MPO)
+    pub supports_overlays: bool,
+}
+
+/// Monitor attached to an active display, with its current mode
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MonitorMode {
+    /// Driver-reported name, e.g. "Generic PnP Monitor"
+    pub name: String,
+    /// PnP hardware ID (manufacturer + product code), e.g. "DEL40F1"
+    pub hardware_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub refresh_hz: u32,
+}
+
+/// Display target (monitor) as addressed by the display configuration API, with its HDR state
+#[derive(Debug, Clone, Copy)]
+pub struct HdrTarget {
+    adapter_id: LUID,
+    target_id: u32,
+    /// Monitor and link can do HDR (advanced color)
+    pub supported: bool,
+    /// "Use HDR" is currently on
+    pub enabled: bool,
+}
+
+/// Active display topology saved before secondary displays were switched off
+pub struct DisplayTopology {
+    paths: Vec<DISPLAYCONFIG_PATH_INFO>,
+    modes: Vec<DISPLAYCONFIG_MODE_INFO>,
+}
+
+/// Calibration state of one display (gamma ramp loaded from the ICC VCGT, active profile)
+struct OutputColor {
+    device_name: String,
+    /// Device interface name of the monitor, which WCS profile associations are keyed by
+    monitor_id: Option<String>,
+    ramp: Box<GammaRamp>,
+    profile: Option<String>,
+}
+
+/// Color calibration of every display, taken before a display tweak
+pub struct ColorSnapshot {
+    outputs: Vec<OutputColor>,
+}
+
+pub struct DisplayService;
+
+impl DisplayService {
+    /// Enumerate every output on every adapter
+    pub fn enumerate_outputs() -> Vec<DisplayOutput> {
+        let mut outputs = Vec::new();
+
+        unsafe {
+            let factory: Result<IDXGIFactory1, _> = CreateDXGIFactory1();
+            let Ok(factory) = factory else { return outputs };
+
+            let mut adapter_index = 0;
+            while let Ok(adapter) = factory.EnumAdapters1(adapter_index) {
+                let mut output_index = 0;
+                while let Ok(output) = adapter.EnumOutputs(output_index) {
+                    if let Ok(desc) = output.GetDesc() {
+                        // IDXGIOutput2 (Windows 8.1+) reports overlay support per output
+                        let supports_overlays = output
+                            .cast::<IDXGIOutput2>()
+                            .map(|o2| o2.SupportsOverlays().as_bool())
+                            .unwrap_or(false);
+
+                        outputs.push(DisplayOutput {
+                            device_name: String::from_utf16_lossy(&desc.DeviceName)
+                                .trim_matches('\0')
+                                .to_string(),
+                            monitor: desc.Monitor,
+                            supports_overlays,
+                        });
+                    }
+                    output_index += 1;
+                }
+                adapter_index += 1;
+            }
+        }
+
+        outputs
+    }
+
+    /// Output that shows (most of) the given window
+    pub fn output_for_window(hwnd: HWND) -> Option<DisplayOutput> {
+        let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+        Self::enumerate_outputs().into_iter().find(|o| o.monitor == monitor)
+    }
+
+    /// Monitors of all active displays with resolution and refresh rate
+    pub fn monitor_modes() -> Vec<MonitorMode> {
+        let mut monitors = Vec::new();
+
+        unsafe {
+            let mut index = 0;
+            loop {
+                let mut display = DISPLAY_DEVICEW { cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32, ..Default::default() };
+                if !EnumDisplayDevicesW(PCWSTR::null(), index, &mut display, 0).as_bool() {
+                    break;
+                }
+                index += 1;
+                if !display.StateFlags.contains(DISPLAY_DEVICE_ACTIVE) {
+                    continue;
+                }
+
+                let device = PCWSTR(display.DeviceName.as_ptr());
+                let mut mode = DEVMODEW { dmSize: std::mem::size_of::<DEVMODEW>() as u16, ..Default::default() };
+                if !EnumDisplaySettingsW(device, ENUM_CURRENT_SETTINGS, &mut mode).as_bool() {
+                    continue;
+                }
+
+                // Second level: the monitor on this display; DeviceID is MONITOR\<hardware id>\{...}
+                let mut monitor = DISPLAY_DEVICEW { cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32, ..Default::default() };
+                let (name, hardware_id) = if EnumDisplayDevicesW(device, 0, &mut monitor, 0).as_bool() {
+                    let id = String::from_utf16_lossy(&monitor.DeviceID).trim_matches('\0').to_string();
+                    (
+                        String::from_utf16_lossy(&monitor.DeviceString).trim_matches('\0').to_string(),
+                        id.split('\\').nth(1).unwrap_or_default().to_string(),
+                    )
+                } else {
+                    ("Unknown monitor".to_string(), String::new())
+                };
+
+                monitors.push(MonitorMode {
+                    name,
+                    hardware_id,
+                    width: mode.dmPelsWidth,
+                    height: mode.dmPelsHeight,
+                    refresh_hz: mode.dmDisplayFrequency,
+                });
+            }
+        }
+
+        monitors
+    }
+
+    /// HDR state of the primary display (the one fullscreen games open on by default)
+    pub fn primary_hdr_target() -> Option<HdrTarget> {
+        let (paths, modes) = Self::query_active_paths()?;
+        let primary = paths.iter().find(|path| Self::is_primary_path(path, &modes))?;
+        Self::hdr_state(primary.targetInfo.adapterId, primary.targetInfo.id)
+    }
+
+    /// Switch off every display except the primary one (not saved to the display database)
+    /// Returns the previous topology, or None if there was nothing to switch off
+    pub fn disable_secondary_displays() -> Option<DisplayTopology> {
+        let (paths, modes) = Self::query_active_paths()?;
+        let primary: Vec<DISPLAYCONFIG_PATH_INFO> = paths.iter()
+            .filter(|path| Self::is_primary_path(path, &modes))
+            .copied()
+            .collect();
+        // Cloned displays share the primary source, so they stay on too
+        if primary.is_empty() || primary.len() == paths.len() {
+            return None;
+        }
+
+        let result = unsafe {
+            SetDisplayConfig(Some(&primary), Some(&modes), SDC_APPLY | SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_ALLOW_CHANGES)
+        };
+        if result != 0 {
+            log_line!("[Display] Failed to switch off secondary displays (error {})", result);
+            return None;
+        }
+        log_line!("[Display] Switched off {} secondary display(s)", paths.len() - primary.len());
+        Some(DisplayTopology { paths, modes })
+    }
+
+    /// Re-apply a saved topology; without one, fall back to the topology in the display database
+    /// (what Windows would restore on the next boot, since the reduced one was never saved)
+    pub fn restore_topology(topology: Option<&DisplayTopology>) -> bool {
+        let result = unsafe {
+            match topology {
+                Some(saved) => SetDisplayConfig(
+                    Some(&saved.paths),
+                    Some(&saved.modes),
+                    SDC_APPLY | SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_ALLOW_CHANGES,
+                ),
+                None => SetDisplayConfig(None, None, SDC_APPLY | SDC_USE_DATABASE_CURRENT),
+            }
+        };
+        if result != 0 {
+            log_line!("[Display] Failed to restore the display topology (error {})", result);
+            notify::attention(&tr!("Displays not restored"), &tr!("Secondary displays could not be switched back on. Use Win+P to extend the desktop again."));
+        }
+        result == 0
+    }
+
+    /// The primary display's desktop starts at (0, 0)
+    fn is_primary_path(path: &DISPLAYCONFIG_PATH_INFO, modes: &[DISPLAYCONFIG_MODE_INFO]) -> bool {
+        let index = unsafe { path.sourceInfo.Anonymous.modeInfoIdx } as usize;
+        modes.get(index).is_some_and(|mode| {
+            let position = unsafe { mode.Anonymous.sourceMode.position };
+            mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE && position.x == 0 && position.y == 0
+        })
+    }
+
+    /// Turn "Use HDR" on or off for a target; false if the driver rejected it
+    pub fn set_hdr(target: &HdrTarget, enabled: bool) -> bool {
+        let mut state = DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE {
+            header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type: DISPLAYCONFIG_DEVICE_INFO_SET_ADVANCED_COLOR_STATE,
+                size: std::mem::size_of::<DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE>() as u32,
+                adapterId: target.adapter_id,
+                id: target.target_id,
+            },
+            ..Default::default()
+        };
+        // Bit 0: enableAdvancedColor
+        state.Anonymous.value = enabled as u32;
+        unsafe { DisplayConfigSetDeviceInfo(&state.header) == 0 }
+    }
+
+    fn hdr_state(adapter_id: LUID, target_id: u32) -> Option<HdrTarget> {
+        let mut info = DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO {
+            header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type: DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+                size: std::mem::size_of::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>() as u32,
+                adapterId: adapter_id,
+                id: target_id,
+            },
+            ..Default::default()
+        };
+        if unsafe { DisplayConfigGetDeviceInfo(&mut info.header) } != 0 {
+            return None;
+        }
+        // Bit 0: advancedColorSupported, bit 1: advancedColorEnabled
+        let bits = unsafe { info.Anonymous.value };
+        Some(HdrTarget {
+            adapter_id,
+            target_id,
+            supported: bits & 0x1 != 0,
+            enabled: bits & 0x2 != 0,
+        })
+    }
+
+    /// Active display paths and their mode table (QueryDisplayConfig)
+    fn query_active_paths() -> Option<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+        unsafe {
+            let mut path_count = 0u32;
+            let mut mode_count = 0u32;
+            if GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count).is_err() {
+                return None;
+            }
+            let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+            let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+            if QueryDisplayConfig(
+                QDC_ONLY_ACTIVE_PATHS,
+                &mut path_count,
+                paths.as_mut_ptr(),
+                &mut mode_count,
+                modes.as_mut_ptr(),
+                None,
+            ).is_err() {
+                return None;
+            }
+            paths.truncate(path_count as usize);
+            modes.truncate(mode_count as usize);
+            Some((paths, modes))
+        }
+    }
+
+    // =========================================================================
+    // NIGHT LIGHT / COLOR FILTERS
+    // =========================================================================
+
+    /// True if Night Light is currently on (None if it was never configured)
+    pub fn night_light_enabled() -> Option<bool> {
+        let data = Win32.read_binary(Hive::CurrentUser, NIGHT_LIGHT_STATE_KEY, "Data")?;
+        // Byte 18 is the state marker: 0x15 = on, 0x13 = off
+        data.get(18).map(|marker| *marker == 0x15)
+    }
+
+    /// Switch Night Light on or off by rewriting the state blob the Settings app uses
+    pub fn set_night_light(enabled: bool) -> bool {
+        let Some(mut data) = Win32.read_binary(Hive::CurrentUser, NIGHT_LIGHT_STATE_KEY, "Data") else {
+            return false;
+        };
+        if data.len() < 25 {
+            return false; // Unknown layout
+        }
+        if (data[18] == 0x15) == enabled {
+            return true;
+        }
+
+        // "On" carries an extra 0x10 0x00 field after the marker
+        if enabled {
+            data[18] = 0x15;
+            data.splice(23..23, [0x10, 0x00]);
+        } else {
+            data[18] = 0x13;
+            data.drain(23..25);
+        }
+        // Bump the change timestamp (bytes 10-14) so the shell picks the new state up
+        if let Some(byte) = data[10..15].iter_mut().find(|b| **b != 0xFF) {
+            *byte += 1;
+        }
+        Win32.write_binary(Hive::CurrentUser, NIGHT_LIGHT_STATE_KEY, "Data", &data)
+    }
+
+    /// True if a color filter (grayscale, inverted, color blindness) is active
+    pub fn color_filter_active() -> bool {
+        Win32.read_binary(Hive::CurrentUser, COLOR_FILTERING_KEY, "Active")
+            .is_some_and(|value| value.first().is_some_and(|b| *b != 0))
+    }
+
+    /// Turn color filters on or off and tell the shell to re-read the setting
+    pub fn set_color_filter(active: bool) -> bool {
+        let written = Win32.write_dword(Hive::CurrentUser, COLOR_FILTERING_KEY, "Active", active as u32);
+        if written {
+            let area = HSTRING::from("ColorFiltering");
+            unsafe {
+                let _ = SendMessageTimeoutW(
+                    HWND_BROADCAST,
+                    WM_SETTINGCHANGE,
+                    WPARAM(0),
+                    LPARAM(area.as_ptr() as isize),
+                    SMTO_ABORTIFHUNG,
+                    1000,
+                    None,
+                );
+            }
+        }
+        written
+    }
+
+    /// Run a display tweak (MPO, HDR, refresh rate...) without losing color calibration
+    /// Mode changes can reset gamma ramps that calibration loaders applied from the ICC profile
+    pub fn preserve_color<F: FnOnce()>(tweak: F) {
+        let snapshot = Self::snapshot_color();
+        tweak();
+        Self::restore_color(&snapshot);
+    }
+
+    /// Capture gamma ramp and ICC profile of every display
+    pub fn snapshot_color() -> ColorSnapshot {
+        let outputs = Self::enumerate_outputs()
+            .into_iter()
+            .filter_map(|output| {
+                Self::with_display_dc(&output.device_name, |hdc| unsafe {
+                    let mut ramp: Box<GammaRamp> = Box::new([0; 3 * 256]);
+                    if !GetDeviceGammaRamp(hdc, ramp.as_mut_ptr() as *mut _).as_bool() {
+                        return None;
+                    }
+
+                    Some(OutputColor {
+                        device_name: output.device_name.clone(),
+                        monitor_id: Self::monitor_interface(&output.device_name),
+                        ramp,
+                        profile: Self::icm_profile(hdc),
+                    })
+                })
+                .flatten()
+            })
+            .collect();
+
+        ColorSnapshot { outputs }
+    }
+
+    /// Re-apply ICC profiles and gamma ramps that changed since the snapshot
+    pub fn restore_color(snapshot: &ColorSnapshot) {
+        for saved in &snapshot.outputs {
+            Self::with_display_dc(&saved.device_name, |hdc| unsafe {
+                if let Some(profile) = &saved.profile {
+                    if Self::icm_profile(hdc).as_ref() != Some(profile) {
+                        Self::reapply_profile(saved, profile, hdc);
+                    }
+                }
+
+                let mut current: GammaRamp = [0; 3 * 256];
+                let unchanged = GetDeviceGammaRamp(hdc, current.as_mut_ptr() as *mut _).as_bool()
+                    && current == *saved.ramp;
+                if unchanged {
+                    return;
+                }
+
+                if SetDeviceGammaRamp(hdc, saved.ramp.as_ptr() as *const _).as_bool() {
+                    log_line!(
+                        "[Display] Restored gamma ramp on {} (profile: {})",
+                        saved.device_name,
+                        saved.profile.as_deref().unwrap_or("none")
+                    );
+                }
+            });
+        }
+    }
+
+    /// Make `profile` the monitor's default ICC profile again (per-user WCS association)
+    /// and select it on `hdc`
+    unsafe fn reapply_profile(saved: &OutputColor, profile: &str, hdc: HDC) {
+        // Associations name the profile as installed in the color directory, not by path
+        let file_name = std::path::Path::new(profile)
+            .file_name()
+            .map_or_else(|| profile.to_string(), |name| name.to_string_lossy().into_owned());
+        let associated = saved.monitor_id.as_deref().is_some_and(|monitor| {
+            WcsSetDefaultColorProfile(
+                WCS_PROFILE_MANAGEMENT_SCOPE_CURRENT_USER,
+                &HSTRING::from(monitor),
+                CPT_ICC,
+                CPST_NONE,
+                0,
+                &HSTRING::from(file_name.as_str()),
+            )
+            .as_bool()
+        });
+        let selected = SetICMProfileW(hdc, &HSTRING::from(profile)).as_bool();
+        log_line!(
+            "[Display] Re-applied ICC profile {} on {} (associated: {}, selected: {})",
+            file_name, saved.device_name, associated, selected
+        );
+    }
+
+    /// Full path of the ICC profile currently in use on a display DC
+    fn icm_profile(hdc: HDC) -> Option<String> {
+        let mut buf = [0u16; 260];
+        let mut len = buf.len() as u32;
+        unsafe { GetICMProfileW(hdc, &mut len, PWSTR(buf.as_mut_ptr())) }
+            .as_bool()
+            .then(|| String::from_utf16_lossy(&buf[..(len as usize).min(buf.len())]).trim_matches('\0').to_string())
+    }
+
+    /// Device interface name (\\?\DISPLAY#...) of the monitor on a display device
+    fn monitor_interface(device_name: &str) -> Option<String> {
+        let device = HSTRING::from(device_name);
+        let mut monitor = DISPLAY_DEVICEW { cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32, ..Default::default() };
+        unsafe { EnumDisplayDevicesW(&device, 0, &mut monitor, EDD_GET_DEVICE_INTERFACE_NAME) }
+            .as_bool()
+            .then(|| String::from_utf16_lossy(&monitor.DeviceID).trim_matches('\0').to_string())
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Open a DC for a display device (\\.\DISPLAYn), run `f`, then release it
+    fn with_display_dc<T>(device_name: &str, f: impl FnOnce(HDC) -> T) -> Option<T> {
+        unsafe {
+            let device = HSTRING::from(device_name);
+            let hdc = CreateDCW(w!("DISPLAY"), PCWSTR(device.as_ptr()), PCWSTR::null(), None);
+            if hdc.is_invalid() {
+                return None;
+            }
+            let result = f(hdc);
+            let _ = DeleteDC(hdc);
+            Some(result)
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! DPC / ISR Monitor
+//! Traces deferred procedure calls and interrupt service routines through the NT Kernel Logger
+//! while game mode is active and reports the drivers with the longest ones, so stutter caused
+//! by a driver (network, audio, storage, GPU) can be told apart from stutter caused by settings
+
+use windows::core::{GUID, PWSTR};
+use windows::Win32::Foundation::{ERROR_ALREADY_EXISTS, ERROR_SUCCESS};
+use windows::Win32::System::Diagnostics::Etw::{
+    CloseTrace, ControlTraceW, OpenTraceW, ProcessTrace, StartTraceW, CONTROLTRACE_HANDLE,
+    EVENT_RECORD, EVENT_TRACE_CONTROL_STOP, EVENT_TRACE_FLAG_DPC, EVENT_TRACE_FLAG_INTERRUPT,
+    EVENT_TRACE_LOGFILEW, EVENT_TRACE_PROPERTIES, EVENT_TRACE_REAL_TIME_MODE, KERNEL_LOGGER_NAMEW,
+    PROCESS_TRACE_MODE_EVENT_RECORD, PROCESS_TRACE_MODE_REAL_TIME,
+    SystemTraceControlGuid, WNODE_FLAG_TRACED_GUID,
+};
+use windows::Win32::System::ProcessStatus::{EnumDeviceDrivers, GetDeviceDriverBaseNameW};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use crate::log_line;
+use crate::services::session;
+
+/// Event class of the kernel's DPC / ISR events
+const PERF_INFO_GUID: GUID = GUID::from_u128(0xce1dbfb4_137e_4da6_87b0_3f59aa102cbc);
+const OPCODE_THREADED_DPC: u8 = 66;
+const OPCODE_ISR: u8 = 67;
+const OPCODE_DPC: u8 = 68;
+const OPCODE_TIMER_DPC: u8 = 69;
+/// OpenTraceW failure value
+const INVALID_PROCESSTRACE_HANDLE: u64 = u64::MAX;
+/// Timestamps in QueryPerformanceCounter ticks (WNODE_HEADER.ClientContext)
+const CLOCK_QPC: u32 = 1;
+/// Drivers listed in the session report
+const REPORT_DRIVERS: usize = 5;
+/// DPC / ISR duration counted as long enough to cost a frame its pacing
+pub const LONG_EXECUTION_US: u64 = 1000;
+
+/// Worst DPC / ISR times of one driver
+#[derive(Debug, Clone, Default)]
+pub struct DriverExecution {
+    pub driver: String,
+    pub dpc_count: u64,
+    pub max_dpc_us: u64,
+    pub isr_count: u64,
+    pub max_isr_us: u64,
+    /// DPCs and ISRs longer than LONG_EXECUTION_US
+    pub long_count: u64,
+}
+
+/// Summary recorded at the end of a session
+#[derive(Debug, Clone, Default)]
+pub struct DpcReport {
+    /// Session the report belongs to (see services::session)
+    pub session_id: Option<String>,
+    pub dpc_count: u64,
+    pub isr_count: u64,
+    /// Worst offenders first (longest single DPC or ISR)
+    pub drivers: Vec<DriverExecution>,
+    /// Events the kernel dropped because the trace buffers were full
+    pub events_lost: u32,
+}
+
+impl DpcReport {
+    /// Longest DPC or ISR of the session and the driver it belongs to
+    pub fn worst(&self) -> Option<(&str, u64)> {
+        self.drivers.first().map(|d| (d.driver.as_str(), d.max_dpc_us.max(d.max_isr_us)))
+    }
+}
+
+impl std::fmt::Display for DpcReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(id) = &self.session_id {
+            write!(f, "session {} - ", id)?;
+        }
+        write!(f, "{} DPCs, {} ISRs", self.dpc_count, self.isr_count)?;
+        if self.events_lost > 0 {
+            write!(f, " ({} events lost)", self.events_lost)?;
+        }
+        for d in &self.drivers {
+            write!(
+                f,
+                "; {}: max DPC {} us, max ISR {} us, {} over {} us",
+                d.driver, d.max_dpc_us, d.max_isr_us, d.long_count, LONG_EXECUTION_US
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Per routine address; mapped to drivers once at the end of the session
+#[derive(Default, Clone, Copy)]
+struct RoutineStats {
+    dpc_count: u64,
+    max_dpc_ticks: u64,
+    isr_count: u64,
+    max_isr_ticks: u64,
+    long_count: u64,
+}
+
+#[derive(Default)]
+struct DpcStats {
+    routines: HashMap<u64, RoutineStats>,
+    /// QPC ticks per LONG_EXECUTION_US (0 until the trace is open)
+    long_ticks: u64,
+}
+
+impl DpcStats {
+    fn record(&mut self, opcode: u8, routine: u64, ticks: u64) {
+        let stats = self.routines.entry(routine).or_default();
+        if opcode == OPCODE_ISR {
+            stats.isr_count += 1;
+            stats.max_isr_ticks = stats.max_isr_ticks.max(ticks);
+        } else {
+            stats.dpc_count += 1;
+            stats.max_dpc_ticks = stats.max_dpc_ticks.max(ticks);
+        }
+        if self.long_ticks > 0 && ticks >= self.long_ticks {
+            stats.long_count += 1;
+        }
+    }
+}
+
+pub struct DpcMonitor {
+    running: Arc<AtomicBool>,
+    stats: Arc<Mutex<DpcStats>>,
+    handle: Mutex<Option<JoinHandle<i64>>>,
+}
+
+impl DpcMonitor {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(Mutex::new(DpcStats::default())),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Start the kernel trace in the background
+    /// The NT Kernel Logger is a single system-wide session; if another tool (LatencyMon, xperf,
+    /// WPR) holds it the monitor is skipped rather than taking it over
+    pub fn start(&self) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return; // Already running
+        }
+        *self.stats.lock().unwrap() = DpcStats::default();
+
+        let mut properties = Self::properties();
+        let mut control = CONTROLTRACE_HANDLE::default();
+        let status = unsafe { StartTraceW(&mut control, KERNEL_LOGGER_NAMEW, properties.as_mut_ptr().cast()) };
+        if status != ERROR_SUCCESS {
+            if status == ERROR_ALREADY_EXISTS {
+                log_line!("[DPC] Kernel logger is in use by another tool, monitor not started");
+            } else {
+                log_line!("[DPC] Could not start the kernel trace ({}), monitor not started", status.0);
+            }
+            self.running.store(false, Ordering::SeqCst);
+            return;
+        }
+
+        let running = self.running.clone();
+        let stats = self.stats.clone();
+        log_line!("[DPC] Tracing DPC / ISR execution times");
+
+        // Returns the QPC frequency once the trace is stopped
+        let handle = thread::spawn(move || unsafe {
+            let mut logger_name: Vec<u16> = "NT Kernel Logger".encode_utf16().chain([0]).collect();
+            let mut logfile = EVENT_TRACE_LOGFILEW {
+                LoggerName: PWSTR(logger_name.as_mut_ptr()),
+                Context: Arc::as_ptr(&stats) as *mut _,
+                ..Default::default()
+            };
+            logfile.Anonymous1.ProcessTraceMode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+            logfile.Anonymous2.EventRecordCallback = Some(Self::on_event);
+
+            let trace = OpenTraceW(&mut logfile);
+            if trace.Value == INVALID_PROCESSTRACE_HANDLE {
+                log_line!("[DPC] Could not open the kernel trace");
+                Self::stop_session();
+                running.store(false, Ordering::SeqCst);
+                return 0;
+            }
+            let frequency = logfile.LogfileHeader.PerfFreq;
+            stats.lock().unwrap().long_ticks = frequency as u64 * LONG_EXECUTION_US / 1_000_000;
+
+            // Blocks until stop() ends the kernel session
+            let _ = ProcessTrace(&[trace], None, None);
+            let _ = CloseTrace(trace);
+            frequency
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Stop the trace and store the session report
+    pub fn stop(&self) -> Option<DpcReport> {
+        if !self.running.swap(false, Ordering::SeqCst) {
+            // The trace thread gave up on its own (session already stopped)
+            if let Some(handle) = self.handle.lock().unwrap().take() {
+                let _ = handle.join();
+            }
+            return None;
+        }
+
+        let events_lost = Self::stop_session();
+        let frequency = self.handle.lock().unwrap().take()
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or(0);
+
+        let routines = std::mem::take(&mut self.stats.lock().unwrap().routines);
+        let report = Self::build_report(&routines, frequency, events_lost);
+        log_line!("[DPC] Session: {}", report);
+        Some(report)
+    }
+
+    /// EVENT_TRACE_PROPERTIES followed by room for the logger name, in a u64 buffer for alignment
+    fn properties() -> Vec<u64> {
+        let header = std::mem::size_of::<EVENT_TRACE_PROPERTIES>();
+        let size = header + 1024;
+        let mut buffer = vec![0u64; size.div_ceil(8)];
+        let properties = unsafe { &mut *buffer.as_mut_ptr().cast::<EVENT_TRACE_PROPERTIES>() };
+        properties.Wnode.BufferSize = size as u32;
+        properties.Wnode.Guid = SystemTraceControlGuid;
+        properties.Wnode.ClientContext = CLOCK_QPC;
+        properties.Wnode.Flags = WNODE_FLAG_TRACED_GUID;
+        properties.LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
+        properties.EnableFlags = EVENT_TRACE_FLAG_DPC | EVENT_TRACE_FLAG_INTERRUPT;
+        properties.LoggerNameOffset = header as u32;
+        buffer
+    }
+
+    /// Stop the kernel session (ends ProcessTrace); returns the events it dropped
+    fn stop_session() -> u32 {
+        let mut properties = Self::properties();
+        let status = unsafe {
+            ControlTraceW(
+                CONTROLTRACE_HANDLE::default(),
+                KERNEL_LOGGER_NAMEW,
+                properties.as_mut_ptr().cast(),
+                EVENT_TRACE_CONTROL_STOP,
+            )
+        };
+        if status != ERROR_SUCCESS {
+            log_line!("[DPC] Could not stop the kernel trace ({})", status.0);
+            return 0;
+        }
+        unsafe { (*properties.as_ptr().cast::<EVENT_TRACE_PROPERTIES>()).EventsLost }
+    }
+
+    unsafe extern "system" fn on_event(record: *mut EVENT_RECORD) {
+        let record = &*record;
+        let header = &record.EventHeader;
+        let opcode = header.EventDescriptor.Opcode;
+        if header.ProviderId != PERF_INFO_GUID
+            || !matches!(opcode, OPCODE_THREADED_DPC | OPCODE_ISR | OPCODE_DPC | OPCODE_TIMER_DPC)
+            || record.UserData.is_null()
+            || (record.UserDataLength as usize) < 16
+            || record.UserContext.is_null()
+        {
+            return;
+        }
+
+        // Payload starts with InitialTime (QPC ticks) and the routine address
+        let data = record.UserData.cast::<u64>();
+        let initial_time = data.read_unaligned();
+        let routine = data.add(1).read_unaligned();
+        let ticks = (header.TimeStamp as u64).saturating_sub(initial_time);
+
+        let stats = &*record.UserContext.cast::<Mutex<DpcStats>>();
+        if let Ok(mut stats) = stats.lock() {
+            stats.record(opcode, routine, ticks);
+        }
+    }
+
+    /// Fold the routine addresses into their drivers, worst first
+    fn build_report(routines: &HashMap<u64, RoutineStats>, frequency: i64, events_lost: u32) -> DpcReport {
+        let to_us = |ticks: u64| if frequency > 0 { ticks * 1_000_000 / frequency as u64 } else { 0 };
+        let drivers = Self::loaded_drivers();
+
+        let mut by_driver: HashMap<String, DriverExecution> = HashMap::new();
+        let mut report = DpcReport { session_id: session::current(), events_lost, ..Default::default() };
+        for (&routine, stats) in routines {
+            report.dpc_count += stats.dpc_count;
+            report.isr_count += stats.isr_count;
+
+            // Routine belongs to the driver with the highest load address below it
+            let name = drivers.iter()
+                .filter(|(base, _)| *base <= routine)
+                .max_by_key(|(base, _)| *base)
+                .map_or_else(|| format!("{:#x}", routine), |(_, name)| name.clone());
+            let entry = by_driver.entry(name.clone()).or_insert_with(|| DriverExecution { driver: name, ..Default::default() });
+            entry.dpc_count += stats.dpc_count;
+            entry.isr_count += stats.isr_count;
+            entry.max_dpc_us = entry.max_dpc_us.max(to_us(stats.max_dpc_ticks));
+            entry.max_isr_us = entry.max_isr_us.max(to_us(stats.max_isr_ticks));
+            entry.long_count += stats.long_count;
+        }
+
+        report.drivers = by_driver.into_values().collect();
+        report.drivers.sort_by_key(|d| std::cmp::Reverse(d.max_dpc_us.max(d.max_isr_us)));
+        report.drivers.truncate(REPORT_DRIVERS);
+        report
+    }
+
+    /// (load address, file name) of every loaded kernel driver
+    fn loaded_drivers() -> Vec<(u64, String)> {
+        unsafe {
+            let mut needed = 0u32;
+            let _ = EnumDeviceDrivers(std::ptr::null_mut(), 0, &mut needed);
+            if needed == 0 {
+                return Vec::new();
+            }
+            let mut bases = vec![std::ptr::null_mut(); needed as usize / std::mem::size_of::<usize>()];
+            if EnumDeviceDrivers(bases.as_mut_ptr(), needed, &mut needed).is_err() {
+                return Vec::new();
+            }
+
+            bases.iter()
+                .filter(|base| !base.is_null())
+                .filter_map(|&base| {
+                    let mut name = [0u16; 260];
+                    let len = GetDeviceDriverBaseNameW(base, &mut name) as usize;
+                    (len > 0).then(|| (base as u64, String::from_utf16_lossy(&name[..len])))
+                })
+                .collect()
+        }
+    }
+}
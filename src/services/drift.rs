@@ -0,0 +1,178 @@
+//! Drift Detection
+//! The idle state of every value a session can change is recorded in baseline.json before
+//! each session; on startup, values that still hold what a session writes while the
+//! baseline says otherwise (crash, older version, interrupted restore) are offered for repair
+
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::services::advanced_modules::AdvancedModulesService;
+use crate::services::gamemode::GameModeService;
+use crate::services::migration::{ManagedValue, MigrationService};
+use crate::services::settings;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+/// Start type written by the service tweaks (SERVICE_DISABLED)
+const DISABLED_START_TYPE: u32 = 4;
+
+/// HKLM DWORD as it was while idle (None = value absent)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BaselineValue {
+    path: String,
+    name: String,
+    data: Option<u32>,
+}
+
+/// Service start type as it was while idle (None = service absent)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BaselineService {
+    name: String,
+    start: Option<u32>,
+}
+
+/// Expected idle state, persisted next to settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IdleBaseline {
+    #[serde(default)]
+    registry: Vec<BaselineValue>,
+    #[serde(default)]
+    services: Vec<BaselineService>,
+    #[serde(default)]
+    autotuning_disabled: bool,
+}
+
+/// A session change still in place outside a session
+#[derive(Debug, Clone)]
+pub enum Drift {
+    Registry { value: ManagedValue, baseline: Option<u32> },
+    Service { name: String, baseline: u32 },
+    Autotuning,
+}
+
+impl Drift {
+    pub fn describe(&self) -> String {
+        match self {
+            Drift::Registry { value, .. } => format!("HKLM\\{}\\{} = {}", value.path, value.name, value.data),
+            Drift::Service { name, .. } => format!("Service {} still disabled", name),
+            Drift::Autotuning => "TCP autotuning still disabled".to_string(),
+        }
+    }
+
+    /// Put the baseline value back
+    pub fn repair(&self) {
+        match self {
+            Drift::Registry { value, baseline } => match baseline {
+                Some(data) => { Win32.write_dword(Hive::LocalMachine, &value.path, &value.name, *data); }
+                None => Win32.delete_value(Hive::LocalMachine, &value.path, &value.name),
+            },
+            Drift::Service { name, baseline } => {
+                let path = format!(r"SYSTEM\CurrentControlSet\Services\{}", name);
+                Win32.write_dword(Hive::LocalMachine, &path, "Start", *baseline);
+            }
+            Drift::Autotuning => AdvancedModulesService::set_bufferbloat_disabled(),
+        }
+        log_line!("[Drift] Repaired {}", self.describe());
+    }
+}
+
+pub struct DriftService;
+
+impl DriftService {
+    /// Record the current state as the expected idle state
+    /// Call only while no session is applied (before a session starts, after startup repair)
+    pub fn record_baseline() {
+        let manifest = MigrationService::current_manifest();
+        let baseline = IdleBaseline {
+            registry: manifest.registry.iter()
+                .map(|v| BaselineValue {
+                    path: v.path.clone(),
+                    name: v.name.clone(),
+                    data: Win32.read_dword(Hive::LocalMachine, &v.path, &v.name),
+                })
+                .collect(),
+            services: manifest.services.iter()
+                .map(|name| BaselineService { name: name.clone(), start: Self::service_start_type(name) })
+                .collect(),
+            autotuning_disabled: AdvancedModulesService::get_bufferbloat_status(),
+        };
+        Self::save(&baseline);
+    }
+
+    /// Re-read the values the permanent toggles (MPO, Lower Bufferbloat) own, so a deliberate
+    /// change isn't reported as drift; safe to call during a session
+    pub fn note_permanent_change() {
+        let Some(mut baseline) = Self::load() else {
+            return;
+        };
+        for value in GameModeService::managed_values() {
+            let data = Win32.read_dword(Hive::LocalMachine, &value.path, &value.name);
+            match baseline.registry.iter_mut().find(|b| b.path == value.path && b.name == value.name) {
+                Some(entry) => entry.data = data,
+                None => baseline.registry.push(BaselineValue { path: value.path, name: value.name, data }),
+            }
+        }
+        baseline.autotuning_disabled = AdvancedModulesService::get_bufferbloat_status();
+        Self::save(&baseline);
+    }
+
+    /// Session values still in place that the baseline says should be something else
+    /// Values changed to anything other than what a session writes are the user's and left alone
+    pub fn detect() -> Vec<Drift> {
+        let Some(baseline) = Self::load() else {
+            return Vec::new();
+        };
+        let manifest = MigrationService::current_manifest();
+        let mut drift = Vec::new();
+
+        for value in manifest.registry {
+            let Some(entry) = baseline.registry.iter().find(|b| b.path == value.path && b.name == value.name) else {
+                continue;
+            };
+            if entry.data != Some(value.data) && Win32.read_dword(Hive::LocalMachine, &value.path, &value.name) == Some(value.data) {
+                drift.push(Drift::Registry { baseline: entry.data, value });
+            }
+        }
+
+        for name in manifest.services {
+            let Some(entry) = baseline.services.iter().find(|b| b.name.eq_ignore_ascii_case(&name)) else {
+                continue;
+            };
+            let Some(start) = entry.start.filter(|s| *s != DISABLED_START_TYPE) else {
+                continue;
+            };
+            if Self::service_start_type(&name) == Some(DISABLED_START_TYPE) {
+                drift.push(Drift::Service { name, baseline: start });
+            }
+        }
+
+        if !baseline.autotuning_disabled && AdvancedModulesService::get_bufferbloat_status() {
+            drift.push(Drift::Autotuning);
+        }
+
+        if !drift.is_empty() {
+            log_line!("[Drift] {} session change(s) still applied outside a session", drift.len());
+        }
+        drift
+    }
+
+    fn load() -> Option<IdleBaseline> {
+        let content = fs::read_to_string(Self::baseline_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn save(baseline: &IdleBaseline) {
+        if let Ok(content) = serde_json::to_string_pretty(baseline) {
+            let _ = fs::write(Self::baseline_path(), content);
+        }
+    }
+
+    /// baseline.json in settings::data_dir() (folder is created by SettingsService)
+    fn baseline_path() -> PathBuf {
+        settings::data_dir().join("baseline.json")
+    }
+
+    fn service_start_type(name: &str) -> Option<u32> {
+        Win32.read_dword(Hive::LocalMachine, &format!(r"SYSTEM\CurrentControlSet\Services\{}", name), "Start")
+    }
+}
@@ -0,0 +1,59 @@
+//! Front-end Integration
+//! Games started from Playnite or GOG Galaxy run as children of the front-end, so they are
+//! picked up without any setup: the child is the session's game and its profile is looked up
+//! by executable. Front-end scripts can also drive sessions through xillygamemode:// links.
+
+use std::path::Path;
+use crate::services::process::ProcessService;
+use crate::services::profiles::{self, WorkloadProfile};
+
+/// Front-end processes (without .exe) and their display names
+const FRONTENDS: &[(&str, &str)] = &[
+    ("Playnite.DesktopApp", "Playnite"),
+    ("Playnite.FullscreenApp", "Playnite"),
+    ("GalaxyClient", "GOG Galaxy"),
+];
+
+/// Children of a front-end that are never the game (browser views, helpers, store clients)
+const NON_GAME_CHILDREN: &[&str] = &[
+    "cefsharp.browsersubprocess", "toolbox", "playnite.desktopapp", "playnite.fullscreenapp",
+    "galaxyclient helper", "galaxyclientservice", "galaxycommunication", "gog galaxy notifications renderer",
+    "conhost", "cmd", "powershell", "pwsh", "explorer", "werfault",
+    "steam", "epicgameslauncher", "eadesktop", "ubisoftconnect", "upc", "battle.net",
+];
+
+/// A game a front-end has started
+#[derive(Debug, Clone)]
+pub struct FrontendGame {
+    pub pid: u32,
+    /// Executable name without .exe
+    pub exe: String,
+    pub frontend: &'static str,
+}
+
+pub struct FrontendService;
+
+impl FrontendService {
+    /// First running game started by a supported front-end
+    pub fn launched_game() -> Option<FrontendGame> {
+        FRONTENDS.iter().find_map(|&(process, frontend)| {
+            let parent = ProcessService::find_process(&[process])?;
+            let parent_start = ProcessService::process_start_time(parent);
+            ProcessService::child_processes(parent, parent_start)
+                .into_iter()
+                .find_map(|pid| {
+                    let path = ProcessService::executable_path(pid)?;
+                    let exe = Path::new(&path).file_stem()?.to_string_lossy().into_owned();
+                    let helper = NON_GAME_CHILDREN.iter().any(|h| h.eq_ignore_ascii_case(&exe));
+                    (!helper).then_some(FrontendGame { pid, exe, frontend })
+                })
+        })
+    }
+
+    /// Profile for a front-end game: the one listing its exe as a trigger, otherwise Gaming
+    pub fn profile_for(profiles: &[WorkloadProfile], exe: &str) -> String {
+        profiles.iter()
+            .find(|p| p.trigger_processes.iter().any(|t| t.trim_end_matches(".exe").eq_ignore_ascii_case(exe)))
+            .map_or(profiles::GAMING_PROFILE.to_string(), |p| p.name.clone())
+    }
+}
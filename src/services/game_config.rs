@@ -0,0 +1,89 @@
+//! Per-Game Configuration
+//! Per-title flags written against the detected game's executable path:
+//! DirectX GPU preference and the fullscreen optimizations compatibility flag.
+//! These are per-exe preferences read at process start, so they stay in place
+//! (like Settings > Display > Graphics) and take effect from the next launch.
+//! The SMT flag is the exception: an affinity on the running game, for the session only.
+
+use crate::services::affinity;
+use crate::services::process::ProcessService;
+use crate::services::profiles::GameFlags;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+// Settings > Display > Graphics: "GpuPreference=2;" = High performance
+const GPU_PREFERENCES_KEY: &str = r"Software\Microsoft\DirectX\UserGpuPreferences";
+const HIGH_PERFORMANCE_GPU: &str = "GpuPreference=2;";
+
+// exe Properties > Compatibility > "Disable fullscreen optimizations"
+// (the per-app flag GameConfigStore's FSE behavior honors)
+const COMPAT_LAYERS_KEY: &str = r"Software\Microsoft\Windows NT\CurrentVersion\AppCompatFlags\Layers";
+const DISABLE_FSO_LAYER: &str = "DISABLEDXMAXIMIZEDWINDOWEDMODE";
+
+pub struct GameConfigService;
+
+impl GameConfigService {
+    /// Apply the flags configured for the game running as `game_pid`, if any
+    pub fn apply_for_pid(all_flags: &[GameFlags], game_pid: u32) {
+        if all_flags.is_empty() {
+            return;
+        }
+        let Some(exe) = ProcessService::executable_path(game_pid) else {
+            return;
+        };
+        if let Some(flags) = all_flags.iter().find(|f| f.matches(&exe)) {
+            Self::apply(flags, &exe);
+            if flags.one_thread_per_core {
+                affinity::restrict_to_physical_cores(game_pid);
+            }
+        }
+    }
+
+    /// Write (or clear) both flags for `exe_path` so the registry matches `flags`
+    pub fn apply(flags: &GameFlags, exe_path: &str) {
+        let changed_gpu = Self::sync_gpu_preference(exe_path, flags.high_performance_gpu);
+        let changed_fso = Self::sync_fso_layer(exe_path, flags.disable_fullscreen_optimizations);
+        if changed_gpu || changed_fso {
+            log_line!("[GameConfig] Updated per-game flags for {} (applies on next launch)", exe_path);
+        }
+    }
+
+    /// Returns true if the value was changed
+    fn sync_gpu_preference(exe_path: &str, high_performance: bool) -> bool {
+        let current = Win32.read_string(Hive::CurrentUser, GPU_PREFERENCES_KEY, exe_path);
+        match (high_performance, current.as_deref()) {
+            (true, Some(HIGH_PERFORMANCE_GPU)) => false,
+            (true, _) => Win32.write_string(Hive::CurrentUser, GPU_PREFERENCES_KEY, exe_path, HIGH_PERFORMANCE_GPU),
+            // Only clear a preference this flag would have written; a user-picked power saving one stays
+            (false, Some(HIGH_PERFORMANCE_GPU)) => {
+                Win32.delete_value(Hive::CurrentUser, GPU_PREFERENCES_KEY, exe_path);
+                true
+            }
+            (false, _) => false,
+        }
+    }
+
+    /// Add/remove the FSO layer, keeping any other compatibility layers on the exe
+    fn sync_fso_layer(exe_path: &str, disable_fso: bool) -> bool {
+        let current = Win32.read_string(Hive::CurrentUser, COMPAT_LAYERS_KEY, exe_path).unwrap_or_default();
+        let mut layers: Vec<&str> = current.split_whitespace().filter(|l| *l != "~").collect();
+        let has_layer = layers.iter().any(|l| l.eq_ignore_ascii_case(DISABLE_FSO_LAYER));
+        if has_layer == disable_fso {
+            return false;
+        }
+
+        if disable_fso {
+            layers.push(DISABLE_FSO_LAYER);
+        } else {
+            layers.retain(|l| !l.eq_ignore_ascii_case(DISABLE_FSO_LAYER));
+        }
+
+        if layers.is_empty() {
+            Win32.delete_value(Hive::CurrentUser, COMPAT_LAYERS_KEY, exe_path);
+            true
+        } else {
+            // "~ " marks per-user layers set from the Compatibility tab
+            Win32.write_string(Hive::CurrentUser, COMPAT_LAYERS_KEY, exe_path, &format!("~ {}", layers.join(" ")))
+        }
+    }
+}
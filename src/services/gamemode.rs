@@ -1,29 +1,74 @@
 use crate::services::{
-    registry::RegistryService,
+    registry::{RegistryService, DEFAULT_PRIORITY_SEPARATION},
     power::PowerService,
+    journal::{self, SessionJournal},
     detector::GameDetector,
     windows::WindowsServiceManager,
     memory::MemoryService,
     network::NetworkService,
-    process::ProcessService,
+    process::{ProcessService, SuspendedProcess, TerminatedProcess},
     options::GameModeOptions,
+    display::{ColorSnapshot, DisplayService},
+    migration::ManagedValue,
+    modified::ModifiedItem,
+    hooks::{HookEvent, HookService},
+    capabilities::{Capabilities, BUILDS_MPO_TEST_MODE},
+    system::{Hive, SystemBackend, Win32},
+    tasks,
 };
 use windows::Win32::Foundation::HWND;
-use windows::Win32::System::Registry::*;
-use windows::core::PCWSTR;
-use std::sync::Mutex;
-use std::thread::{self, JoinHandle};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::{log_line, tr};
+
+/// Where the service is in a session. Enable only runs from Idle and disable only from Active,
+/// so a second enable can't overwrite the stored originals and a stray disable can't restore twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameModeState {
+    Idle,
+    Enabling,
+    Active,
+    Disabling,
+}
+
+/// Resets a state still at Enabling to Idle when dropped
+struct EnablingGuard(Arc<Mutex<GameModeState>>);
+
+impl Drop for EnablingGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if *state == GameModeState::Enabling {
+            *state = GameModeState::Idle;
+        }
+    }
+}
 
 /// GameModeService - 1:1 port of GameModeService.cs
 /// Optimized for minimal resource usage
 pub struct GameModeService {
+    // Registry, service and process access (Win32, or an in-memory fake)
+    system: Arc<dyn SystemBackend>,
+    // Shared so a force restore can reset it while a hung enable/disable holds the service
+    state: Arc<Mutex<GameModeState>>,
     power: PowerService,
     registry: RegistryService,
-    suspended_shell_ux_pids: Mutex<Vec<u32>>,
+    suspended_shell_ux: Mutex<Vec<SuspendedProcess>>,
     // 1:1 with C#: Track stopped services for proper restore
     stopped_services: Mutex<Vec<String>>,
     // 1:1 with C#: Track if network isolation was enabled so we always disable on exit
     network_isolated: Mutex<bool>,
+    // File Explorer folders open before explorer was killed
+    explorer_windows: Mutex<Vec<String>>,
+    // Power plan / laptop boost was changed this session
+    power_applied: Mutex<bool>,
+    // Processes terminated this session (for restore/report)
+    terminated_processes: Mutex<Vec<TerminatedProcess>>,
+    // Firewall block rules were added for browsers/launchers this session
+    firewall_blocked: Mutex<bool>,
+    // Session-scoped MPO: original OverlayTestMode (outer Some = we changed it this session)
+    original_overlay_test_mode: Mutex<Option<Option<u32>>>,
+    // Color calibration taken before session display tweaks, re-applied on disable
+    color_snapshot: Mutex<Option<ColorSnapshot>>,
 }
 
 // ============================================================================
@@ -64,19 +109,67 @@ static PERIPHERALS: &[&str] = &[
 
 impl GameModeService {
     pub fn new() -> Self {
+        Self::build(Arc::new(Win32), PowerService::new())
+    }
+
+    /// Service working against `system` instead of the live machine
+    #[cfg(test)]
+    pub fn with_system(system: Arc<dyn SystemBackend>) -> Self {
+        Self::build(system.clone(), PowerService::with_system(system))
+    }
+
+    fn build(system: Arc<dyn SystemBackend>, power: PowerService) -> Self {
         Self {
-            power: PowerService::new(),
-            registry: RegistryService::new(),
-            suspended_shell_ux_pids: Mutex::new(Vec::with_capacity(8)),
+            registry: RegistryService::with_system(system.clone()),
+            system,
+            state: Arc::new(Mutex::new(GameModeState::Idle)),
+            power,
+            suspended_shell_ux: Mutex::new(Vec::with_capacity(8)),
             stopped_services: Mutex::new(Vec::with_capacity(16)),
             network_isolated: Mutex::new(false),
+            explorer_windows: Mutex::new(Vec::new()),
+            power_applied: Mutex::new(false),
+            terminated_processes: Mutex::new(Vec::with_capacity(32)),
+            firewall_blocked: Mutex::new(false),
+            original_overlay_test_mode: Mutex::new(None),
+            color_snapshot: Mutex::new(None),
+        }
+    }
+
+    /// Move from `from` to `to`; refused (and logged) if the session is elsewhere
+    fn transition(&self, from: GameModeState, to: GameModeState) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if *state != from {
+            log_line!("[GameMode] Ignoring {:?} -> {:?} while {:?}", from, to, *state);
+            return false;
         }
+        *state = to;
+        true
+    }
+
+    /// No session applied or in progress
+    pub fn is_idle(&self) -> bool {
+        *self.state.lock().unwrap() == GameModeState::Idle
+    }
+
+    /// Session state, reachable without locking the service (panic hotkey)
+    pub fn state_handle(&self) -> Arc<Mutex<GameModeState>> {
+        self.state.clone()
     }
 
     /// Enable game mode - Optimized parallel version
-    pub fn enable_game_mode(&mut self, options: &GameModeOptions) {
+    /// False if refused (a session is already running) or force-restored while enabling
+    pub fn enable_game_mode(&mut self, options: &GameModeOptions) -> bool {
+        if !self.transition(GameModeState::Idle, GameModeState::Enabling) {
+            return false;
+        }
+        // Back to Idle if this returns early or unwinds before reaching Active
+        let _enabling = EnablingGuard(self.state.clone());
+        
         // Step 1: Detect fullscreen game (for focus later) - run early
-        let detected_game = if options.suspend_explorer {
+        let detected_game = if options.detect_game
+            && (options.suspend_explorer || options.mpo_game_display_only || options.flush_memory)
+        {
             GameDetector::detect_fullscreen_game()
         } else {
             None
@@ -84,20 +177,36 @@ impl GameModeService {
         
         // Step 2-4: Registry and power (fast, do first on main thread)
         self.registry.unlock_power_settings();
-        self.registry.apply_tweaks();
+        self.registry.apply_tweaks(options.priority_separation.unwrap_or(DEFAULT_PRIORITY_SEPARATION));
         
-        let is_desktop = GameDetector::is_desktop();
-        if is_desktop {
-            self.power.set_high_performance();
-        } else {
-            self.power.optimize_laptop_boost();
+        if options.apply_power_plan {
+            if GameDetector::is_desktop() {
+                self.power.set_high_performance();
+            } else {
+                self.power.optimize_laptop_boost();
+            }
+            if let Ok(mut guard) = self.power_applied.lock() {
+                *guard = true;
+            }
+        }
+
+        // Step 4b: Session-scoped MPO - only when the game's display actually uses overlay planes
+        if options.mpo_game_display_only {
+            if let Some((_pid, hwnd)) = detected_game {
+                self.disable_mpo_for_game_display(hwnd);
+            }
         }
 
         // Step 5: Explorer handling (if enabled)
+        let mut terminated = Vec::with_capacity(32);
         if options.suspend_explorer {
-            ProcessService::kill_processes(START_MENU_REPLACEMENTS);
+            terminated.extend(self.system.kill(START_MENU_REPLACEMENTS));
+            // Remember open File Explorer folders so they come back with the shell
+            if let Ok(mut guard) = self.explorer_windows.lock() {
+                *guard = ProcessService::open_explorer_windows();
+            }
             self.registry.disable_auto_restart_shell();
-            ProcessService::kill_process("explorer");
+            terminated.extend(self.system.kill(&["explorer"]));
             
             if let Some((_pid, hwnd)) = detected_game {
                 GameDetector::focus_window(hwnd);
@@ -105,111 +214,187 @@ impl GameModeService {
         }
 
         // Capture options for threads
-        let suspend_browsers = options.suspend_browsers;
-        let suspend_launchers = options.suspend_launchers;
+        // Non-game profiles may keep background apps open entirely
+        let kill_apps = options.kill_background_apps;
+        let firewall_block = options.firewall_block_background && kill_apps;
+        let suspend_browsers = options.suspend_browsers && kill_apps && !firewall_block;
+        let suspend_launchers = options.suspend_launchers && kill_apps && !firewall_block;
         let isolate_network = options.isolate_network;
 
         // Parallel execution - minimize thread count
-        let mut handles: Vec<JoinHandle<Vec<String>>> = Vec::with_capacity(3);
+        // Steps run on their own threads while the process work below runs on this one
+        let system = &*self.system;
+        let mut steps: Vec<tasks::Step<Vec<String>>> = Vec::with_capacity(3);
         
-        // Thread 1: Services (heavy operation) - returns stopped services list
+        // Step 1: Services (heavy operation) - returns stopped services list
         // 1:1 with C#: Track which services were actually stopped
-        handles.push(thread::spawn(|| {
-            WindowsServiceManager::stop_optimization_services()
-        }));
+        if options.stop_services {
+            steps.push(("stop-services", Box::new(move || {
+                WindowsServiceManager::stop_optimization_services(system)
+            })));
+        }
         
-        // Thread 2: Memory flush (returns empty vec, just for consistent join)
-        handles.push(thread::spawn(|| {
-            MemoryService::flush_memory();
-            Vec::new()
-        }));
+        // Step 2: Memory flush (returns empty vec, just for consistent join)
+        // The game is excluded so it doesn't hard-fault its own pages back in right after launch
+        if options.flush_memory {
+            let game_pid = options.workload_pid.or(detected_game.map(|(pid, _)| pid));
+            steps.push(("flush-memory", Box::new(move || {
+                MemoryService::flush_memory(game_pid, &options.flush_exclusions);
+                Vec::new()
+            })));
+        }
         
-        // Thread 3: Network (only if needed)
+        // Step 3: Network (only if needed)
         if isolate_network {
-            handles.push(thread::spawn(|| {
-                NetworkService::toggle_isolation(true);
+            steps.push(("isolate-network", Box::new(move || {
+                NetworkService::isolate(&options.isolation_exceptions);
                 Vec::new()
-            }));
+            })));
             // 1:1 with C#: Track that we enabled network isolation
             if let Ok(mut guard) = self.network_isolated.lock() {
                 *guard = true;
             }
         }
 
-        // Main thread: Process operations (most critical for responsiveness)
-        // Suspend Shell UX first
-        let shell_pids = ProcessService::suspend_processes(SHELL_UX);
-        
-        // Build kill list efficiently (no allocation if sizes known)
-        let kill_count = START_MENU_REPLACEMENTS.len() 
-            + BLOATWARE.len() 
-            + PERIPHERALS.len()
-            + if suspend_browsers { BROWSERS.len() } else { 0 }
-            + if suspend_launchers { LAUNCHERS.len() } else { 0 };
-        
-        let mut all_to_kill: Vec<&str> = Vec::with_capacity(kill_count);
-        all_to_kill.extend_from_slice(START_MENU_REPLACEMENTS);
-        if suspend_browsers {
-            all_to_kill.extend_from_slice(BROWSERS);
-        }
-        all_to_kill.extend_from_slice(BLOATWARE);
-        all_to_kill.extend_from_slice(PERIPHERALS);
-        if suspend_launchers {
-            all_to_kill.extend_from_slice(LAUNCHERS);
-        }
-        
-        ProcessService::kill_processes(&all_to_kill);
-        
-        // Store suspended PIDs
-        if let Ok(mut guard) = self.suspended_shell_ux_pids.lock() {
-            *guard = shell_pids;
-        }
+        let (stopped, ()) = tasks::join_all(steps, || {
+            // Firewall mode: keep browsers/launchers running but cut their outbound traffic
+            if firewall_block && (options.suspend_browsers || options.suspend_launchers) {
+                let mut targets: Vec<&str> = Vec::with_capacity(BROWSERS.len() + LAUNCHERS.len());
+                if options.suspend_browsers {
+                    targets.extend_from_slice(BROWSERS);
+                }
+                if options.suspend_launchers {
+                    targets.extend_from_slice(LAUNCHERS);
+                }
+                let paths = ProcessService::executable_paths(&targets);
+                NetworkService::block_programs(&paths);
+                if let Ok(mut guard) = self.firewall_blocked.lock() {
+                    *guard = true;
+                }
+            }
+
+            // Main thread: Process operations (most critical for responsiveness)
+            // Suspend Shell UX first
+            let shell_suspended = self.system.suspend(SHELL_UX);
+
+            // Build kill list efficiently (no allocation if sizes known)
+            let kill_count = START_MENU_REPLACEMENTS.len() 
+                + BLOATWARE.len() 
+                + PERIPHERALS.len()
+                + if suspend_browsers { BROWSERS.len() } else { 0 }
+                + if suspend_launchers { LAUNCHERS.len() } else { 0 };
+
+            let mut all_to_kill: Vec<&str> = Vec::with_capacity(kill_count);
+            all_to_kill.extend_from_slice(START_MENU_REPLACEMENTS);
+            if suspend_browsers {
+                all_to_kill.extend_from_slice(BROWSERS);
+            }
+            if kill_apps {
+                all_to_kill.extend_from_slice(BLOATWARE);
+            }
+
+            // Launchers and peripheral suites leave helper children behind, optionally kill whole trees
+            let mut tree_targets: Vec<&str> = Vec::with_capacity(PERIPHERALS.len() + LAUNCHERS.len());
+            if kill_apps {
+                tree_targets.extend_from_slice(PERIPHERALS);
+            }
+            if suspend_launchers {
+                tree_targets.extend_from_slice(LAUNCHERS);
+            }
+            if options.kill_process_trees {
+                terminated.extend(self.system.kill_trees(&tree_targets));
+            } else {
+                all_to_kill.extend_from_slice(&tree_targets);
+            }
+
+            terminated.extend(self.system.kill(&all_to_kill));
+            for process in &terminated {
+                log_line!(
+                    "[GameMode] Terminated {} (PID {}) {}",
+                    process.name, process.pid, process.path.as_deref().unwrap_or("")
+                );
+            }
+            if let Ok(mut guard) = self.terminated_processes.lock() {
+                *guard = terminated;
+            }
+
+            // Store suspended processes (PID + start time + name)
+            if let Ok(mut guard) = self.suspended_shell_ux.lock() {
+                *guard = shell_suspended;
+            }
+        });
         
-        // Wait for background threads and collect stopped services
-        for handle in handles {
-            if let Ok(result) = handle.join() {
-                if !result.is_empty() {
-                    if let Ok(mut guard) = self.stopped_services.lock() {
-                        guard.extend(result);
-                    }
+        // Collect stopped services from the finished steps
+        for result in stopped {
+            if !result.is_empty() {
+                if let Ok(mut guard) = self.stopped_services.lock() {
+                    guard.extend(result);
                 }
             }
         }
+        
+        // User hooks last, so they see the system as the session left it
+        HookService::run_all(&options.on_enable_hooks, HookEvent::Enable);
+        if !self.transition(GameModeState::Enabling, GameModeState::Active) {
+            // Force-restored meanwhile: undo what was applied after the restore ran
+            log_line!("[GameMode] Session was force-restored while enabling, reverting");
+            *self.state.lock().unwrap() = GameModeState::Active;
+            self.disable_game_mode(options);
+            return false;
+        }
+        true
     }
 
     /// Disable game mode - Optimized parallel version
     /// 1:1 with C# DisableGameModeAsync
     pub fn disable_game_mode(&self, options: &GameModeOptions) {
-        let mut handles: Vec<JoinHandle<()>> = Vec::with_capacity(4);
+        if !self.transition(GameModeState::Active, GameModeState::Disabling) {
+            return;
+        }
+        
+        // Steps run on their own threads while the registry and power work runs on this one
+        let system = &*self.system;
+        let mut steps: Vec<tasks::Step<()>> = Vec::with_capacity(5);
         
-        // Thread 1: Restore explorer (if needed)
+        // Step 1: Restore explorer (if needed)
         // 1:1 with C#: RestartExplorer() checks if explorer is running first
         if options.suspend_explorer {
-            handles.push(thread::spawn(|| {
+            let folders = self.explorer_windows.lock()
+                .map(|mut g| std::mem::take(&mut *g))
+                .unwrap_or_default();
+            steps.push(("restore-explorer", Box::new(move || {
                 ProcessService::restart_explorer();
-            }));
+                if !folders.is_empty() {
+                    // Let the shell finish starting before opening windows
+                    thread::sleep(std::time::Duration::from_secs(2));
+                    ProcessService::reopen_explorer_windows(&folders);
+                }
+            })));
         }
         
-        // Thread 2: Restore services - 1:1 with C#: Only restore services we actually stopped
+        // Step 2: Restore services - 1:1 with C#: Only restore services we actually stopped
         let services_to_restore = self.stopped_services.lock()
             .map(|g| g.clone())
             .unwrap_or_default();
         
-        handles.push(thread::spawn(move || {
-            WindowsServiceManager::restore_services(&services_to_restore);
-        }));
+        steps.push(("restore-services", Box::new(move || {
+            WindowsServiceManager::restore_services(system, &services_to_restore);
+        })));
         
-        // Thread 3: Resume Shell UX processes
-        let pids = self.suspended_shell_ux_pids.lock()
+        // Step 3: Resume Shell UX processes
+        let suspended = self.suspended_shell_ux.lock()
             .map(|g| g.clone())
             .unwrap_or_default();
         
-        handles.push(thread::spawn(move || {
-            ProcessService::resume_processes_by_pid(&pids);
-            ProcessService::resume_processes(SHELL_UX);
-        }));
+        steps.push(("resume-shell-ux", Box::new(move || {
+            // PIDs may have been recycled during a long session - only verified ones are resumed
+            let unverified = system.resume(&suspended);
+            if unverified > 0 || suspended.is_empty() {
+                system.resume_by_name(SHELL_UX);
+            }
+        })));
         
-        // Thread 4: Network - 1:1 with C#: Always disable if it was enabled
+        // Step 4: Network - 1:1 with C#: Always disable if it was enabled
         // C# code: await _networkService.ToggleNetworkIsolationAsync(false);
         // The C# always calls this in DisableGameModeAsync
         let was_isolated = self.network_isolated.lock()
@@ -217,37 +402,250 @@ impl GameModeService {
             .unwrap_or(false);
         
         if was_isolated {
-            handles.push(thread::spawn(|| {
-                NetworkService::toggle_isolation(false);
-            }));
+            steps.push(("end-isolation", Box::new(NetworkService::end_isolation)));
         }
         
-        // Main thread: Registry operations (fast)
-        self.registry.revert_tweaks();
-        self.registry.enable_auto_restart_shell();
+        // Step 5: Remove firewall block rules
+        let was_blocked = self.firewall_blocked.lock()
+            .map(|g| *g)
+            .unwrap_or(false);
         
-        // Power revert
-        if GameDetector::is_desktop() {
-            self.power.revert_power_plan();
-        } else {
-            self.power.revert_laptop_boost();
+        if was_blocked {
+            steps.push(("unblock-programs", Box::new(NetworkService::unblock_programs)));
+        }
+        
+        tasks::join_all(steps, || {
+            // Main thread: Registry operations (fast)
+            self.restore_session_mpo();
+            self.registry.revert_tweaks();
+            self.registry.enable_auto_restart_shell();
+
+            // Power revert (only if this session changed it)
+            let power_applied = self.power_applied.lock()
+                .map(|mut g| std::mem::take(&mut *g))
+                .unwrap_or(false);
+            if power_applied {
+                if GameDetector::is_desktop() {
+                    self.power.revert_power_plan();
+                } else {
+                    self.power.revert_laptop_boost();
+                }
+            }
+
+            // Clear state
+            if let Ok(mut guard) = self.suspended_shell_ux.lock() {
+                guard.clear();
+            }
+            if let Ok(mut guard) = self.stopped_services.lock() {
+                guard.clear();
+            }
+            if let Ok(mut guard) = self.network_isolated.lock() {
+                *guard = false;
+            }
+            if let Ok(mut guard) = self.firewall_blocked.lock() {
+                *guard = false;
+            }
+            if let Ok(mut guard) = self.terminated_processes.lock() {
+                guard.clear();
+            }
+        });
+        
+        HookService::run_all(&options.on_disable_hooks, HookEvent::Disable);
+        self.transition(GameModeState::Disabling, GameModeState::Idle);
+    }
+
+    /// Restore targets of the applied session, for the persisted journal
+    pub fn journal(&self, options: &GameModeOptions) -> SessionJournal {
+        let power_applied = self.power_applied.lock().map(|g| *g).unwrap_or(false);
+        SessionJournal {
+            app_pid: std::process::id(),
+            explorer_killed: options.suspend_explorer,
+            explorer_windows: self.explorer_windows.lock().map(|g| g.clone()).unwrap_or_default(),
+            stopped_services: self.stopped_services.lock().map(|g| g.clone()).unwrap_or_default(),
+            shell_ux_suspended: self.suspended_shell_ux.lock().map(|g| !g.is_empty()).unwrap_or(false),
+            network_isolated: self.network_isolated.lock().map(|g| *g).unwrap_or(false),
+            firewall_blocked: self.firewall_blocked.lock().map(|g| *g).unwrap_or(false),
+            power: power_applied.then(|| self.power.originals()),
+            registry: self.registry.originals(),
+            original_overlay_test_mode: self.original_overlay_test_mode.lock().map(|g| *g).unwrap_or(None),
+            ..Default::default()
+        }
+    }
+
+    /// Undo a session from its journal alone (the process that applied it is gone)
+    /// Suspended shell processes are resumed by name since their PIDs were never persisted
+    pub fn restore_from_journal(system: &Arc<dyn SystemBackend>, journal: &SessionJournal) {
+        if journal.explorer_killed {
+            ProcessService::restart_explorer();
+            if !journal.explorer_windows.is_empty() {
+                thread::sleep(std::time::Duration::from_secs(2));
+                ProcessService::reopen_explorer_windows(&journal.explorer_windows);
+            }
+        }
+        WindowsServiceManager::restore_services(&**system, &journal.stopped_services);
+        if journal.shell_ux_suspended {
+            system.resume_by_name(SHELL_UX);
+        }
+        if journal.network_isolated {
+            NetworkService::end_isolation();
+        }
+        if journal.firewall_blocked {
+            NetworkService::unblock_programs();
+        }
+        
+        if let Some(original) = journal.original_overlay_test_mode {
+            let dwm_path = r"SOFTWARE\Microsoft\Windows\Dwm";
+            match original {
+                Some(value) => { system.write_dword(Hive::LocalMachine, dwm_path, "OverlayTestMode", value); }
+                None => system.delete_value(Hive::LocalMachine, dwm_path, "OverlayTestMode"),
+            }
+        }
+        
+        let registry = RegistryService::from_originals(system.clone(), &journal.registry);
+        registry.revert_tweaks();
+        registry.enable_auto_restart_shell();
+        
+        if let Some(originals) = &journal.power {
+            let power = PowerService::from_originals(system.clone(), originals);
+            if GameDetector::is_desktop() {
+                power.revert_power_plan();
+            } else {
+                power.revert_laptop_boost();
+            }
         }
+    }
+
+    /// Panic restore: replay the persisted journal, then bring back what can be found
+    /// without it (explorer, shell UX by name), for when in-memory state can't be trusted
+    pub fn force_restore() {
+        match journal::read() {
+            Some(session) => journal::recover(&session),
+            None => log_line!("[Panic] No session journal, restoring shell only"),
+        }
+        // Both are no-ops if already running / not suspended
+        ProcessService::restart_explorer();
+        ProcessService::resume_processes(SHELL_UX);
+    }
+
+    /// Drop the in-memory session after a force restore so nothing is reverted twice
+    pub fn forget_session(&self) {
+        if let Ok(mut g) = self.state.lock() { *g = GameModeState::Idle; }
+        if let Ok(mut g) = self.suspended_shell_ux.lock() { g.clear(); }
+        if let Ok(mut g) = self.stopped_services.lock() { g.clear(); }
+        if let Ok(mut g) = self.network_isolated.lock() { *g = false; }
+        if let Ok(mut g) = self.explorer_windows.lock() { g.clear(); }
+        if let Ok(mut g) = self.power_applied.lock() { *g = false; }
+        if let Ok(mut g) = self.terminated_processes.lock() { g.clear(); }
+        if let Ok(mut g) = self.firewall_blocked.lock() { *g = false; }
+        if let Ok(mut g) = self.original_overlay_test_mode.lock() { *g = None; }
+        if let Ok(mut g) = self.color_snapshot.lock() { *g = None; }
+    }
+
+    /// Session changes that are still in place, for the modified-state page
+    pub fn modified_items(&self) -> Vec<ModifiedItem> {
+        let mut items = Vec::new();
         
-        // Clear state
-        if let Ok(mut guard) = self.suspended_shell_ux_pids.lock() {
-            guard.clear();
+        let stopped = self.stopped_services.lock().map(|g| g.clone()).unwrap_or_default();
+        if !stopped.is_empty() {
+            items.push(ModifiedItem::session("gm.services", tr!("{} services stopped ({})", stopped.len(), stopped.join(", "))));
+        }
+        let suspended = self.suspended_shell_ux.lock().map(|g| g.len()).unwrap_or(0);
+        if suspended > 0 {
+            items.push(ModifiedItem::session("gm.shell_ux", tr!("{} shell UX processes suspended", suspended)));
+        }
+        if self.network_isolated.lock().map(|g| *g).unwrap_or(false) {
+            items.push(ModifiedItem::session("gm.isolation", tr!("Network isolation enabled")));
         }
-        if let Ok(mut guard) = self.stopped_services.lock() {
-            guard.clear();
+        if self.firewall_blocked.lock().map(|g| *g).unwrap_or(false) {
+            items.push(ModifiedItem::session("gm.firewall", tr!("Browsers/launchers blocked in the firewall")));
         }
-        if let Ok(mut guard) = self.network_isolated.lock() {
-            *guard = false;
+        if self.original_overlay_test_mode.lock().map(|g| g.is_some()).unwrap_or(false) {
+            items.push(ModifiedItem::session("gm.mpo", tr!("MPO disabled for this session")));
+        }
+        if self.power_applied.lock().map(|g| *g).unwrap_or(false) {
+            let label = if GameDetector::is_desktop() { tr!("High performance power plan") } else { tr!("Laptop performance boost") };
+            items.push(ModifiedItem::session("gm.power", label));
+        }
+        let terminated = self.terminated_processes.lock().map(|g| g.len()).unwrap_or(0);
+        if terminated > 0 {
+            items.push(ModifiedItem::session("gm.terminated", tr!("{} background apps closed", terminated)).fixed());
         }
         
-        // Wait for all threads
-        for handle in handles {
-            let _ = handle.join();
+        items
+    }
+
+    /// Revert one session change early; everything else stays until disable
+    pub fn revert_item(&self, id: &str) -> bool {
+        match id {
+            "gm.services" => {
+                let services = self.stopped_services.lock()
+                    .map(|mut g| std::mem::take(&mut *g))
+                    .unwrap_or_default();
+                WindowsServiceManager::restore_services(&*self.system, &services);
+            }
+            "gm.shell_ux" => {
+                let suspended = self.suspended_shell_ux.lock()
+                    .map(|mut g| std::mem::take(&mut *g))
+                    .unwrap_or_default();
+                if self.system.resume(&suspended) > 0 {
+                    self.system.resume_by_name(SHELL_UX);
+                }
+            }
+            "gm.isolation" => {
+                NetworkService::end_isolation();
+                if let Ok(mut guard) = self.network_isolated.lock() {
+                    *guard = false;
+                }
+            }
+            "gm.firewall" => {
+                NetworkService::unblock_programs();
+                if let Ok(mut guard) = self.firewall_blocked.lock() {
+                    *guard = false;
+                }
+            }
+            "gm.mpo" => self.restore_session_mpo(),
+            "gm.power" => {
+                let applied = self.power_applied.lock()
+                    .map(|mut g| std::mem::take(&mut *g))
+                    .unwrap_or(false);
+                if applied {
+                    if GameDetector::is_desktop() {
+                        self.power.revert_power_plan();
+                    } else {
+                        self.power.revert_laptop_boost();
+                    }
+                }
+            }
+            _ => return false,
         }
+        log_line!("[GameMode] Reverted {} before session end", id);
+        true
+    }
+
+    /// Processes terminated by the current session
+    pub fn terminated_processes(&self) -> Vec<TerminatedProcess> {
+        self.terminated_processes.lock()
+            .map(|g| g.clone())
+            .unwrap_or_default()
+    }
+
+    /// Services stopped by the current session
+    pub fn stopped_services(&self) -> Vec<String> {
+        self.stopped_services.lock()
+            .map(|g| g.clone())
+            .unwrap_or_default()
+    }
+
+    /// Shell UX processes suspended by the current session
+    pub fn suspended_processes(&self) -> Vec<SuspendedProcess> {
+        self.suspended_shell_ux.lock()
+            .map(|g| g.clone())
+            .unwrap_or_default()
+    }
+
+    /// True if the current session changed the power plan / laptop boost
+    pub fn power_applied(&self) -> bool {
+        self.power_applied.lock().map(|g| *g).unwrap_or(false)
     }
 
     #[inline]
@@ -255,99 +653,213 @@ impl GameModeService {
         GameDetector::detect_fullscreen_game()
     }
     
+    /// DWORD values this service writes outside a session, for the migration manifest
+    pub fn managed_values() -> Vec<ManagedValue> {
+        vec![ManagedValue {
+            path: r"SOFTWARE\Microsoft\Windows\Dwm".to_string(),
+            name: "OverlayTestMode".to_string(),
+            data: 5,
+        }]
+    }
+
     /// Enable MPO (delete OverlayTestMode) and set OverlayMinFPS=0
     pub fn set_mpo_enabled() {
         let dwm_path = r"SOFTWARE\Microsoft\Windows\Dwm";
-        Self::delete_registry_value(dwm_path, "OverlayTestMode");
-        Self::set_registry_dword(dwm_path, "OverlayMinFPS", 0);
-        println!("[GameMode] MPO enabled + OverlayMinFPS=0");
+        Win32.delete_value(Hive::LocalMachine, dwm_path, "OverlayTestMode");
+        Win32.write_dword(Hive::LocalMachine, dwm_path, "OverlayMinFPS", 0);
+        log_line!("[GameMode] MPO enabled + OverlayMinFPS=0");
     }
     
-    /// Disable MPO (OverlayTestMode=5)
+    /// Disable MPO (OverlayTestMode=5); skipped on builds that ignore the value
     pub fn set_mpo_disabled() {
+        Self::write_mpo_disabled(&Win32);
+    }
+
+    fn write_mpo_disabled(system: &dyn SystemBackend) {
+        if let Some(reason) = Capabilities::get().mpo() {
+            log_line!("[GameMode] MPO left alone: {}", reason);
+            return;
+        }
         let dwm_path = r"SOFTWARE\Microsoft\Windows\Dwm";
-        Self::set_registry_dword(dwm_path, "OverlayTestMode", 5);
-        println!("[GameMode] MPO disabled");
+        system.write_dword(Hive::LocalMachine, dwm_path, "OverlayTestMode", 5);
+        log_line!("[GameMode] MPO disabled");
     }
     
-    #[allow(dead_code)]
-    fn get_registry_dword(path: &str, value_name: &str) -> Option<u32> {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(path_wide.as_ptr()), 0, KEY_READ, &mut hkey).is_err() {
-                return None;
-            }
-            
-            let mut data: u32 = 0;
-            let mut data_size = std::mem::size_of::<u32>() as u32;
-            let mut value_type = REG_DWORD;
-            
-            let result = RegQueryValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                None,
-                Some(&mut value_type),
-                Some(std::ptr::addr_of_mut!(data) as *mut u8),
-                Some(&mut data_size),
-            );
-            
-            let _ = RegCloseKey(hkey);
-            
-            if result.is_ok() {
-                Some(data)
-            } else {
-                None
-            }
+    /// Disable MPO for the session if the game's display exposes overlay planes
+    /// Windows only has a global switch, so displays without MPO are left untouched by skipping it
+    fn disable_mpo_for_game_display(&self, hwnd: HWND) {
+        if !Capabilities::get().supports(BUILDS_MPO_TEST_MODE) {
+            return;
         }
+        let Some(output) = DisplayService::output_for_window(hwnd) else {
+            return;
+        };
+        if !output.supports_overlays {
+            log_line!("[GameMode] {} has no overlay planes, MPO left alone", output.device_name);
+            return;
+        }
+        
+        let dwm_path = r"SOFTWARE\Microsoft\Windows\Dwm";
+        let original = self.system.read_dword(Hive::LocalMachine, dwm_path, "OverlayTestMode");
+        if original == Some(5) {
+            return; // Already disabled
+        }
+        
+        if let Ok(mut guard) = self.original_overlay_test_mode.lock() {
+            *guard = Some(original);
+        }
+        if let Ok(mut guard) = self.color_snapshot.lock() {
+            *guard = Some(DisplayService::snapshot_color());
+        }
+        Self::write_mpo_disabled(&*self.system);
+        log_line!("[GameMode] MPO disabled for game display {}", output.device_name);
     }
     
-    fn set_registry_dword(path: &str, value_name: &str, data: u32) {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegCreateKeyExW(
-                HKEY_LOCAL_MACHINE,
-                PCWSTR(path_wide.as_ptr()),
-                0,
-                None,
-                REG_OPTION_NON_VOLATILE,
-                KEY_WRITE,
-                None,
-                &mut hkey,
-                None,
-            ).is_err() {
-                return;
+    /// Restore OverlayTestMode changed by `disable_mpo_for_game_display`
+    fn restore_session_mpo(&self) {
+        let original = self.original_overlay_test_mode.lock()
+            .ok()
+            .and_then(|mut g| g.take());
+        
+        if let Some(original) = original {
+            let dwm_path = r"SOFTWARE\Microsoft\Windows\Dwm";
+            match original {
+                Some(value) => { self.system.write_dword(Hive::LocalMachine, dwm_path, "OverlayTestMode", value); }
+                None => self.system.delete_value(Hive::LocalMachine, dwm_path, "OverlayTestMode"),
             }
-            
-            let _ = RegSetValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                0,
-                REG_DWORD,
-                Some(&data.to_le_bytes()),
-            );
-            
-            let _ = RegCloseKey(hkey);
+            log_line!("[GameMode] Session MPO restored");
+        }
+        
+        let snapshot = self.color_snapshot.lock()
+            .ok()
+            .and_then(|mut g| g.take());
+        if let Some(snapshot) = snapshot {
+            DisplayService::restore_color(&snapshot);
         }
     }
     
-    fn delete_registry_value(path: &str, value_name: &str) {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(path_wide.as_ptr()), 0, KEY_WRITE, &mut hkey).is_err() {
-                return;
-            }
-            
-            let _ = RegDeleteValueW(hkey, PCWSTR(value_wide.as_ptr()));
-            let _ = RegCloseKey(hkey);
+    /// True if MPO is currently disabled system-wide (OverlayTestMode=5)
+    pub fn is_mpo_disabled() -> bool {
+        Win32.read_dword(Hive::LocalMachine, r"SOFTWARE\Microsoft\Windows\Dwm", "OverlayTestMode") == Some(5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::system::fake::{FakeSystem, Value};
+    use crate::services::system::{PowerBackend, RegistryBackend, ServiceControl};
+    use windows::core::GUID;
+
+    const BALANCED: GUID = GUID::from_u128(0x381b4222_f694_41f0_9685_ff5bb260df2e);
+    const PROCESSOR: GUID = GUID::from_u128(0x54533251_82be_4824_96c1_47b60b740d00);
+    const BOOST_MODE: GUID = GUID::from_u128(0xbe337238_0d82_4146_a960_4f3749d470c7);
+    const MIN_PROCESSOR: GUID = GUID::from_u128(0x893dee8e_2bef_41e0_89c6_b55d0929964c);
+    const PRIORITY_CONTROL: &str = r"SYSTEM\CurrentControlSet\Control\PriorityControl";
+    const GAMES_TASK: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games";
+
+    /// A machine on the Balanced plan with a few optimization services running
+    fn machine() -> Arc<FakeSystem> {
+        let fake = FakeSystem::new();
+        fake.add_scheme(BALANCED, true);
+        fake.write_value_indices(&BALANCED, &PROCESSOR, &BOOST_MODE, 1, 1);
+        fake.write_value_indices(&BALANCED, &PROCESSOR, &MIN_PROCESSOR, 5, 5);
+        fake.set(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation", Value::Dword(2));
+        fake.set(Hive::CurrentUser, r"Software\Microsoft\GameBar", "AutoGameModeEnabled", Value::Dword(0));
+        fake.set(Hive::LocalMachine, GAMES_TASK, "Priority", Value::Dword(2));
+        fake.set(Hive::LocalMachine, r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon", "AutoRestartShell", Value::Dword(1));
+        fake.add_service("SysMain", true);
+        fake.add_service("WSearch", true);
+        fake.add_service("Spooler", false);
+        fake.add_process("OneDrive", 4100);
+        fake.add_process("SearchHost", 4200);
+        Arc::new(fake)
+    }
+
+    fn options() -> GameModeOptions {
+        GameModeOptions {
+            stop_services: true,
+            apply_power_plan: true,
+            kill_background_apps: true,
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn enable_then_disable_restores_the_machine() {
+        let fake = machine();
+        let mut service = GameModeService::with_system(fake.clone());
+
+        assert!(service.enable_game_mode(&options()));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation"), Some(DEFAULT_PRIORITY_SEPARATION));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, GAMES_TASK, "Priority"), Some(6));
+        assert_eq!(fake.is_running("SysMain"), Some(false));
+        assert_eq!(fake.is_running("WSearch"), Some(false));
+        assert!(fake.suspended.lock().unwrap().contains(&4200));
+        assert_eq!(service.terminated_processes().len(), 1);
+
+        service.disable_game_mode(&options());
+        assert_eq!(fake.read_dword(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation"), Some(2));
+        assert_eq!(fake.read_dword(Hive::CurrentUser, r"Software\Microsoft\GameBar", "AutoGameModeEnabled"), Some(0));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, GAMES_TASK, "Priority"), Some(2));
+        assert_eq!(fake.is_running("SysMain"), Some(true));
+        assert_eq!(fake.is_running("WSearch"), Some(true));
+        // Not running before the session, so not started by the restore
+        assert_eq!(fake.is_running("Spooler"), Some(false));
+        assert!(fake.suspended.lock().unwrap().is_empty());
+        assert_eq!(fake.active_scheme(), Some(BALANCED));
+        assert_eq!(fake.read_value_indices(&BALANCED, &PROCESSOR, &BOOST_MODE), Some((1, 1)));
+        assert_eq!(fake.read_value_indices(&BALANCED, &PROCESSOR, &MIN_PROCESSOR).map(|(ac, _)| ac), Some(5));
+    }
+
+    #[test]
+    fn second_enable_keeps_the_first_originals() {
+        let fake = machine();
+        let mut service = GameModeService::with_system(fake.clone());
+
+        assert!(service.enable_game_mode(&options()));
+        assert!(!service.enable_game_mode(&options()));
+        service.disable_game_mode(&options());
+        assert_eq!(fake.read_dword(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation"), Some(2));
+        assert_eq!(fake.is_running("SysMain"), Some(true));
+
+        // Nothing left to restore
+        fake.set(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation", Value::Dword(9));
+        service.disable_game_mode(&options());
+        assert_eq!(fake.read_dword(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation"), Some(9));
+    }
+
+    #[test]
+    fn journal_restores_without_the_session() {
+        let fake = machine();
+        let mut service = GameModeService::with_system(fake.clone());
+        service.enable_game_mode(&options());
+        let journal = service.journal(&options());
+
+        let system: Arc<dyn SystemBackend> = fake.clone();
+        GameModeService::restore_from_journal(&system, &journal);
+        assert_eq!(fake.read_dword(Hive::LocalMachine, PRIORITY_CONTROL, "Win32PrioritySeparation"), Some(2));
+        assert_eq!(fake.is_running("SysMain"), Some(true));
+        assert_eq!(fake.active_scheme(), Some(BALANCED));
+    }
+
+    #[test]
+    fn enabling_guard_only_resets_an_unfinished_enable() {
+        let state = Arc::new(Mutex::new(GameModeState::Enabling));
+        drop(EnablingGuard(state.clone()));
+        assert_eq!(*state.lock().unwrap(), GameModeState::Idle);
+
+        *state.lock().unwrap() = GameModeState::Active;
+        drop(EnablingGuard(state.clone()));
+        assert_eq!(*state.lock().unwrap(), GameModeState::Active);
+    }
+
+    #[test]
+    fn force_reset_session_can_enable_again() {
+        let fake = machine();
+        let mut service = GameModeService::with_system(fake.clone());
+        assert!(service.enable_game_mode(&options()));
+        *service.state_handle().lock().unwrap() = GameModeState::Idle;
+        assert!(service.enable_game_mode(&options()));
+    }
 }
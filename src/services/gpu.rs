@@ -0,0 +1,103 @@
+//! GPU Service
+//! DXGI adapter enumeration and target GPU selection for multi-GPU systems
+//! GPU-related modules resolve their adapter through here instead of assuming a single GPU
+
+use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE};
+use windows::Win32::Foundation::LUID;
+use crate::log_line;
+
+// PCI vendor IDs
+const VENDOR_NVIDIA: u32 = 0x10DE;
+const VENDOR_AMD: u32 = 0x1002;
+const VENDOR_INTEL: u32 = 0x8086;
+
+/// Integrated GPUs report little or no dedicated VRAM (shared system memory instead)
+const DISCRETE_VRAM_THRESHOLD: usize = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct GpuAdapter {
+    pub name: String,
+    pub vendor_id: u32,
+    pub dedicated_vram: usize,
+    pub is_software: bool,
+    /// Identifies the adapter to kernel-mode (D3DKMT) queries
+    pub luid: LUID,
+}
+
+impl GpuAdapter {
+    pub fn vendor(&self) -> &'static str {
+        match self.vendor_id {
+            VENDOR_NVIDIA => "NVIDIA",
+            VENDOR_AMD => "AMD",
+            VENDOR_INTEL => "Intel",
+            _ => "Unknown",
+        }
+    }
+
+    #[inline]
+    pub fn is_discrete(&self) -> bool {
+        !self.is_software && self.dedicated_vram >= DISCRETE_VRAM_THRESHOLD
+    }
+
+    #[inline]
+    pub fn vram_gb(&self) -> f64 {
+        self.dedicated_vram as f64 / 1073741824.0
+    }
+}
+
+pub struct GpuService;
+
+impl GpuService {
+    /// Enumerate all DXGI adapters (including the Basic Render Driver)
+    pub fn enumerate_adapters() -> Vec<GpuAdapter> {
+        let mut adapters = Vec::new();
+
+        unsafe {
+            let factory: Result<IDXGIFactory1, _> = CreateDXGIFactory1();
+            let Ok(factory) = factory else { return adapters };
+
+            let mut i = 0;
+            while let Ok(adapter) = factory.EnumAdapters1(i) {
+                if let Ok(desc) = adapter.GetDesc1() {
+                    let name = String::from_utf16_lossy(&desc.Description)
+                        .trim_matches('\0')
+                        .to_string();
+
+                    adapters.push(GpuAdapter {
+                        name,
+                        vendor_id: desc.VendorId,
+                        dedicated_vram: desc.DedicatedVideoMemory,
+                        is_software: desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0,
+                        luid: desc.AdapterLuid,
+                    });
+                }
+                i += 1;
+            }
+        }
+
+        adapters
+    }
+
+    /// Hardware adapters only, in DXGI order
+    pub fn hardware_adapters() -> Vec<GpuAdapter> {
+        Self::enumerate_adapters()
+            .into_iter()
+            .filter(|a| !a.is_software && a.name != "Microsoft Basic Render Driver")
+            .collect()
+    }
+
+    /// Resolve the GPU that GPU modules should target
+    /// `preferred` is the adapter name chosen in settings; empty means auto (most dedicated VRAM)
+    pub fn target_adapter(preferred: &str) -> Option<GpuAdapter> {
+        let adapters = Self::hardware_adapters();
+
+        if !preferred.is_empty() {
+            if let Some(adapter) = adapters.iter().find(|a| a.name == preferred) {
+                return Some(adapter.clone());
+            }
+            log_line!("[GPU] Preferred adapter '{}' not found, falling back to auto", preferred);
+        }
+
+        adapters.into_iter().max_by_key(|a| a.dedicated_vram)
+    }
+}
@@ -0,0 +1,116 @@
+//! Script Hooks
+//! User commands run when game mode enables or disables (fan profiles, RGB scenes, VPN toggles),
+//! for tweaks there is no built-in module for
+
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::log_line;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// Longest a `wait` hook may hold up enable/disable
+const WAIT_LIMIT: Duration = Duration::from_secs(30);
+
+/// One entry of "on_enable_hooks" / "on_disable_hooks" in settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptHook {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// .bat / .cmd (cmd.exe), .ps1 (PowerShell) or any executable
+    pub path: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Finish before the next step runs (up to 30s), e.g. a VPN that must be up before the game starts
+    #[serde(default)]
+    pub wait: bool,
+}
+
+fn default_true() -> bool { true }
+
+/// When a hook runs, passed to it as GAMEMODE_EVENT
+#[derive(Debug, Clone, Copy)]
+pub enum HookEvent {
+    Enable,
+    Disable,
+}
+
+impl HookEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            HookEvent::Enable => "enable",
+            HookEvent::Disable => "disable",
+        }
+    }
+}
+
+pub struct HookService;
+
+impl HookService {
+    /// Run every enabled hook in order; failures are logged and never stop the session
+    pub fn run_all(hooks: &[ScriptHook], event: HookEvent) {
+        for hook in hooks.iter().filter(|h| h.enabled && !h.path.trim().is_empty()) {
+            Self::run(hook, event);
+        }
+    }
+
+    fn run(hook: &ScriptHook, event: HookEvent) {
+        let mut child = match Self::command(hook).env("GAMEMODE_EVENT", event.as_str()).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                log_line!("[Hooks] Could not start {}: {}", hook.path, e);
+                return;
+            }
+        };
+        log_line!("[Hooks] Started {} ({})", hook.path, event.as_str());
+        if !hook.wait {
+            return;
+        }
+
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    if !status.success() {
+                        log_line!("[Hooks] {} exited with {}", hook.path, status);
+                    }
+                    return;
+                }
+                Ok(None) if started.elapsed() < WAIT_LIMIT => thread::sleep(Duration::from_millis(200)),
+                Ok(None) => {
+                    log_line!("[Hooks] {} still running after {}s, continuing without it", hook.path, WAIT_LIMIT.as_secs());
+                    return;
+                }
+                Err(e) => {
+                    log_line!("[Hooks] Lost track of {}: {}", hook.path, e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Scripts go through their interpreter, anything else is started directly
+    fn command(hook: &ScriptHook) -> Command {
+        let extension = Path::new(&hook.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let mut command = match extension.as_str() {
+            "bat" | "cmd" => {
+                let mut c = Command::new("cmd");
+                c.arg("/C").arg(&hook.path);
+                c
+            }
+            "ps1" => {
+                let mut c = Command::new("powershell");
+                c.args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"]).arg(&hook.path);
+                c
+            }
+            _ => Command::new(&hook.path),
+        };
+        command.args(&hook.args).creation_flags(CREATE_NO_WINDOW);
+        command
+    }
+}
@@ -0,0 +1,105 @@
+//! Global Hotkeys
+//! System-wide hotkeys and the Windows key block, each on a dedicated message-loop thread
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, UnregisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, VK_LWIN, VK_RWIN,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetForegroundWindow, GetMessageW, GetWindowThreadProcessId, PostThreadMessageW,
+    SetWindowsHookExW, UnhookWindowsHookEx, HC_ACTION, KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WM_HOTKEY, WM_QUIT,
+};
+use crate::log_line;
+
+const PANIC_HOTKEY_ID: i32 = 1;
+
+/// Ctrl+Alt+Shift+R, shown in the log and README
+pub const PANIC_HOTKEY_LABEL: &str = "Ctrl+Alt+Shift+R";
+
+/// Register the emergency restore hotkey; `on_press` runs on the hotkey thread
+pub fn start_panic_hotkey(on_press: impl Fn() + Send + 'static) {
+    thread::spawn(move || unsafe {
+        // Thread-owned hotkey: WM_HOTKEY is posted to this thread's queue
+        if RegisterHotKey(None, PANIC_HOTKEY_ID, MOD_CONTROL | MOD_ALT | MOD_SHIFT | MOD_NOREPEAT, 'R' as u32).is_err() {
+            log_line!("[Hotkey] {} is taken by another application", PANIC_HOTKEY_LABEL);
+            return;
+        }
+        log_line!("[Hotkey] Emergency restore registered on {}", PANIC_HOTKEY_LABEL);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            if msg.message == WM_HOTKEY && msg.wParam.0 as i32 == PANIC_HOTKEY_ID {
+                on_press();
+            }
+        }
+        let _ = UnregisterHotKey(None, PANIC_HOTKEY_ID);
+    });
+}
+
+// Game whose foreground window swallows the Windows key (0 = not blocking)
+static WIN_KEY_GAME_PID: AtomicU32 = AtomicU32::new(0);
+// Thread running the low-level keyboard hook (0 = not running)
+static WIN_KEY_HOOK_THREAD: AtomicU32 = AtomicU32::new(0);
+
+/// Block the Windows keys while `game_pid` owns the foreground window
+pub fn block_windows_key(game_pid: u32) {
+    // Hook already installed: just retarget it
+    if WIN_KEY_GAME_PID.swap(game_pid, Ordering::SeqCst) != 0 {
+        return;
+    }
+
+    thread::spawn(|| unsafe {
+        // Low-level hooks are called on the installing thread, so it needs a message loop
+        let hook = match SetWindowsHookExW(WH_KEYBOARD_LL, Some(windows_key_hook), None, 0) {
+            Ok(hook) => hook,
+            Err(e) => {
+                log_line!("[Hotkey] Failed to install the Windows key hook: {}", e);
+                WIN_KEY_GAME_PID.store(0, Ordering::SeqCst);
+                return;
+            }
+        };
+        WIN_KEY_HOOK_THREAD.store(GetCurrentThreadId(), Ordering::SeqCst);
+        if WIN_KEY_GAME_PID.load(Ordering::SeqCst) == 0 {
+            // Unblocked before the hook was up
+            WIN_KEY_HOOK_THREAD.store(0, Ordering::SeqCst);
+            let _ = UnhookWindowsHookEx(hook);
+            return;
+        }
+        log_line!("[Hotkey] Windows key blocked while the game is focused");
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {}
+        let _ = UnhookWindowsHookEx(hook);
+        log_line!("[Hotkey] Windows key unblocked");
+    });
+}
+
+/// Remove the Windows key block
+pub fn unblock_windows_key() {
+    WIN_KEY_GAME_PID.store(0, Ordering::SeqCst);
+    let thread_id = WIN_KEY_HOOK_THREAD.swap(0, Ordering::SeqCst);
+    if thread_id != 0 {
+        unsafe {
+            let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+unsafe extern "system" fn windows_key_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let key = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let is_win_key = key.vkCode == VK_LWIN.0 as u32 || key.vkCode == VK_RWIN.0 as u32;
+        let game_pid = WIN_KEY_GAME_PID.load(Ordering::SeqCst);
+        if is_win_key && game_pid != 0 {
+            let mut foreground_pid = 0u32;
+            GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut foreground_pid));
+            if foreground_pid == game_pid {
+                return LRESULT(1);
+            }
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
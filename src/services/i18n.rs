@@ -0,0 +1,144 @@
+//! Translations
+//! One gettext catalog per language (translations/<lang>/LC_MESSAGES/gamemode.po) serves both the
+//! Slint UI (@tr, bundled at build time) and Rust-side texts (tray menu, MessageBoxes, toasts) via `tr!`.
+//! English is the source language and the fallback for anything not translated.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use once_cell::sync::Lazy;
+use windows::Win32::Globalization::GetUserDefaultLocaleName;
+use crate::log_line;
+
+/// Bundled catalogs besides English: (language code, .po file)
+const CATALOGS: &[(&str, &str)] = &[
+    ("de", include_str!("../../translations/de/LC_MESSAGES/gamemode.po")),
+];
+
+// msgid -> msgstr of the selected language (empty for English)
+static CATALOG: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+// Bundled language code picked by init(), None for English
+static LANGUAGE: Lazy<RwLock<Option<&'static str>>> = Lazy::new(|| RwLock::new(None));
+
+/// Load Rust-side texts for `language` ("" = Windows display language, English if not bundled)
+/// Runs before any window exists so the startup dialogs are translated too
+pub fn init(language: &str) {
+    let code = if language.trim().is_empty() { system_language() } else { language.trim().to_ascii_lowercase() };
+    let catalog = CATALOGS.iter().find(|(c, _)| *c == code);
+    if catalog.is_none() && code != "en" {
+        log_line!("[i18n] No '{}' translation bundled, using English", code);
+    }
+
+    *CATALOG.write().unwrap() = catalog.map(|(_, po)| parse_po(po)).unwrap_or_default();
+    *LANGUAGE.write().unwrap() = catalog.map(|(c, _)| *c);
+}
+
+/// Switch the UI to the language picked by init() (Slint only accepts this once a window exists)
+pub fn select_ui_language() {
+    if let Some(code) = *LANGUAGE.read().unwrap() {
+        if let Err(e) = slint::select_bundled_translation(code) {
+            log_line!("[i18n] UI translation '{}' not available: {:?}", code, e);
+        }
+    }
+}
+
+/// Translated text for an English source string (the string itself if untranslated)
+pub fn translate(msgid: &str) -> String {
+    CATALOG.read().unwrap()
+        .get(msgid)
+        .cloned()
+        .unwrap_or_else(|| msgid.to_string())
+}
+
+/// Fill `{}` placeholders in order, the same syntax @tr uses in the .slint files
+pub fn fill(template: String, args: &[String]) -> String {
+    let mut parts = template.split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for part in parts {
+        out.push_str(args.next().map_or("", String::as_str));
+        out.push_str(part);
+    }
+    out
+}
+
+/// "de-DE" -> "de"
+fn system_language() -> String {
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    if len <= 1 {
+        return "en".to_string();
+    }
+    let locale = String::from_utf16_lossy(&buffer[..len as usize - 1]);
+    locale.split('-').next().unwrap_or("en").to_ascii_lowercase()
+}
+
+/// msgid/msgstr pairs of a .po file; msgctxt is ignored (Rust texts carry none, UI texts are looked up by Slint)
+fn parse_po(po: &str) -> HashMap<String, String> {
+    enum Field { None, Id, Str }
+    let mut entries = HashMap::new();
+    let mut field = Field::None;
+    let (mut id, mut text) = (String::new(), String::new());
+
+    // Untranslated entries (empty msgstr) and the header (empty msgid) are left out
+    let mut finish = |id: &mut String, text: &mut String| {
+        let (id, text) = (std::mem::take(id), std::mem::take(text));
+        if !id.is_empty() && !text.is_empty() {
+            entries.insert(id, text);
+        }
+    };
+
+    for line in po.lines().map(str::trim) {
+        if line.starts_with("msgctxt") || line.starts_with("msgid ") {
+            finish(&mut id, &mut text);
+        }
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            field = Field::Id;
+            id.push_str(&unquote(rest));
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            field = Field::Str;
+            text.push_str(&unquote(rest));
+        } else if line.starts_with('"') {
+            match field {
+                Field::Id => id.push_str(&unquote(line)),
+                Field::Str => text.push_str(&unquote(line)),
+                Field::None => {}
+            }
+        } else if line.is_empty() || line.starts_with("msgctxt") {
+            field = Field::None;
+        }
+    }
+    finish(&mut id, &mut text);
+    entries
+}
+
+fn unquote(quoted: &str) -> String {
+    let quoted = quoted.trim();
+    let inner = quoted.strip_prefix('"').and_then(|q| q.strip_suffix('"')).unwrap_or(quoted);
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Translated text, with `{}` placeholders filled from the arguments:
+/// `tr!("Game Mode enabled")`, `tr!("Game detected: {}", name)`
+#[macro_export]
+macro_rules! tr {
+    ($msgid:literal) => {
+        $crate::services::i18n::translate($msgid)
+    };
+    ($msgid:literal, $($arg:expr),+ $(,)?) => {
+        $crate::services::i18n::fill($crate::services::i18n::translate($msgid), &[$($arg.to_string()),+])
+    };
+}
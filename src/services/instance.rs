@@ -0,0 +1,108 @@
+//! Single Instance
+//! Only one app instance runs; later ones (a `--launch` wrapper, a xillygamemode:// link, a second start) hand their
+//! command to it over a named pipe instead of applying a second session
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::windows::io::FromRawHandle;
+use std::thread;
+use serde::{Deserialize, Serialize};
+use windows::core::{w, HSTRING};
+use windows::Win32::Foundation::{GetLastError, ERROR_ALREADY_EXISTS, ERROR_PIPE_CONNECTED};
+use windows::Win32::Storage::FileSystem::PIPE_ACCESS_INBOUND;
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::System::Threading::CreateMutexW;
+use crate::log_line;
+
+const PIPE_NAME: &str = r"\\.\pipe\XillyGameMode";
+/// Largest command accepted from a client
+const MAX_MESSAGE: u64 = 64 * 1024;
+
+/// Command sent from a later instance to the running one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum InstanceCommand {
+    /// Bring the window to front (the app was started again)
+    Show,
+    /// A `--launch` wrapper started this game: run the session for it
+    Launch { pid: u32 },
+    /// xillygamemode://enable[?profile=...]: start a session, switching profile first
+    Enable { profile: Option<String> },
+    Disable,
+    Toggle,
+}
+
+/// Claim the single-instance mutex; false if another instance already holds it
+pub fn acquire() -> bool {
+    unsafe {
+        // Held (never closed) until the process exits
+        match CreateMutexW(None, true, w!("Local\\XillyGameMode.Instance")) {
+            Ok(_) => GetLastError() != ERROR_ALREADY_EXISTS,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Hand a command to the running instance; false if none is listening
+pub fn send(command: &InstanceCommand) -> bool {
+    let Ok(message) = serde_json::to_vec(command) else { return false };
+    match OpenOptions::new().write(true).open(PIPE_NAME) {
+        Ok(mut pipe) => pipe.write_all(&message).is_ok(),
+        Err(e) => {
+            log_line!("[Instance] Running instance not reachable: {}", e);
+            false
+        }
+    }
+}
+
+/// Serve commands from later instances on a background thread
+pub fn listen(on_command: impl Fn(InstanceCommand) + Send + 'static) {
+    thread::spawn(move || loop {
+        let Some(mut pipe) = accept() else {
+            thread::sleep(std::time::Duration::from_secs(1));
+            continue;
+        };
+        // One command per connection; the client closing the pipe ends it
+        let mut message = String::new();
+        if (&mut pipe).take(MAX_MESSAGE).read_to_string(&mut message).is_err() {
+            continue;
+        }
+        match serde_json::from_str::<InstanceCommand>(&message) {
+            Ok(command) => {
+                log_line!("[Instance] Received {:?}", command);
+                on_command(command);
+            }
+            Err(e) => log_line!("[Instance] Ignored malformed command: {}", e),
+        }
+    });
+}
+
+/// Wait for the next client on a fresh pipe instance
+fn accept() -> Option<File> {
+    unsafe {
+        let pipe = CreateNamedPipeW(
+            &HSTRING::from(PIPE_NAME),
+            PIPE_ACCESS_INBOUND,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            0,
+            MAX_MESSAGE as u32,
+            0,
+            None,
+        );
+        if pipe.is_invalid() {
+            log_line!("[Instance] Could not create the command pipe");
+            return None;
+        }
+        // Owns the handle from here on, closing it on drop
+        let file = File::from_raw_handle(pipe.0);
+        match ConnectNamedPipe(pipe, None) {
+            Ok(()) => Some(file),
+            // Client connected between create and connect
+            Err(e) if e.code() == ERROR_PIPE_CONNECTED.to_hresult() => Some(file),
+            Err(_) => None,
+        }
+    }
+}
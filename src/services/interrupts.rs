@@ -0,0 +1,231 @@
+//! Device Interrupts (expert)
+//! MSI (message signaled interrupts) and interrupt affinity for the GPU and the adapter carrying
+//! the default route. Both are read by the PCI driver stack at boot from the device's Enum key,
+//! so they are permanent and only take effect after a reboot; the original values of every
+//! device touched are kept in interrupts.json (data folder) for the revert.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use windows::core::{GUID, PCWSTR};
+use windows::Win32::Devices::DeviceAndDriverInstallation::{
+    SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInfo, SetupDiGetClassDevsW, SetupDiGetDeviceInstanceIdW,
+    SetupDiGetDeviceRegistryPropertyW, DIGCF_PRESENT, GUID_DEVCLASS_DISPLAY, GUID_DEVCLASS_NET, HDEVINFO,
+    SETUP_DI_REGISTRY_PROPERTY, SPDRP_DEVICEDESC, SPDRP_FRIENDLYNAME, SP_DEVINFO_DATA,
+};
+use windows::Win32::Foundation::HWND;
+use crate::services::capabilities::Capabilities;
+use crate::services::gpu::GpuService;
+use crate::services::network::NetworkService;
+use crate::services::settings;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+const INTERRUPT_MANAGEMENT: &str = r"Device Parameters\Interrupt Management";
+const MSI_KEY: &str = "MessageSignaledInterruptProperties";
+const AFFINITY_KEY: &str = "Affinity Policy";
+
+/// IrqPolicySpecifiedProcessors: deliver to the processors in AssignmentSetOverride
+const IRQ_POLICY_SPECIFIED_PROCESSORS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Gpu,
+    Network,
+}
+
+impl DeviceKind {
+    /// Physical core the device's interrupts are pinned to: off core 0, which takes most
+    /// system interrupts, and on different cores so the GPU and NIC don't share one
+    fn affinity_core(self) -> usize {
+        match self {
+            DeviceKind::Gpu => 1,
+            DeviceKind::Network => 2,
+        }
+    }
+}
+
+/// A PCI device the module targets
+#[derive(Debug, Clone)]
+pub struct InterruptDevice {
+    pub kind: DeviceKind,
+    pub name: String,
+    /// PnP instance ID, e.g. "PCI\VEN_10DE&DEV_2684&..."
+    pub instance_id: String,
+}
+
+/// Values under the device's Interrupt Management key before the module wrote them (None = absent)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeviceBackup {
+    name: String,
+    msi_supported: Option<u32>,
+    device_policy: Option<u32>,
+    assignment_set_override: Option<Vec<u8>>,
+}
+
+/// The GPU modules target (`target_gpu`, "" = auto) and the adapter carrying the default route
+pub fn targets(target_gpu: &str) -> Vec<InterruptDevice> {
+    let mut devices = Vec::new();
+
+    if let Some(gpu) = GpuService::target_adapter(target_gpu) {
+        match find_device(&GUID_DEVCLASS_DISPLAY, &gpu.name) {
+            Some((instance_id, name)) => devices.push(InterruptDevice { kind: DeviceKind::Gpu, name, instance_id }),
+            None => log_line!("[Interrupts] No PCI device found for GPU '{}'", gpu.name),
+        }
+    }
+    if let Some(adapter) = NetworkService::active_adapter() {
+        match find_device(&GUID_DEVCLASS_NET, &adapter.description) {
+            Some((instance_id, name)) => devices.push(InterruptDevice { kind: DeviceKind::Network, name, instance_id }),
+            None => log_line!("[Interrupts] No PCI device found for adapter '{}'", adapter.description),
+        }
+    }
+    devices
+}
+
+/// Settings written by this module are in place (possibly waiting for a reboot)
+pub fn is_applied() -> bool {
+    !load_backups().is_empty()
+}
+
+/// Enable MSI (and optionally pin interrupts) on `devices` (from `targets`)
+pub fn apply(devices: &[InterruptDevice], set_affinity: bool) -> Result<(), String> {
+    let cores = Capabilities::core_masks();
+    let mut backups = load_backups();
+    for device in devices {
+        let msi_path = key_path(&device.instance_id, MSI_KEY);
+        let affinity_path = key_path(&device.instance_id, AFFINITY_KEY);
+
+        // Keep the first backup: re-applying must not record our own values as the originals
+        backups.entry(device.instance_id.clone()).or_insert_with(|| DeviceBackup {
+            name: device.name.clone(),
+            msi_supported: Win32.read_dword(Hive::LocalMachine, &msi_path, "MSISupported"),
+            device_policy: Win32.read_dword(Hive::LocalMachine, &affinity_path, "DevicePolicy"),
+            assignment_set_override: Win32.read_binary(Hive::LocalMachine, &affinity_path, "AssignmentSetOverride"),
+        });
+        save_backups(&backups);
+
+        if !Win32.write_dword(Hive::LocalMachine, &msi_path, "MSISupported", 1) {
+            return Err(format!("Could not write the MSI setting for {}", device.name));
+        }
+        log_line!("[Interrupts] MSI enabled for {} ({})", device.name, device.instance_id);
+
+        // Needs a core other than 0 for each device; skipped on small CPUs
+        let mask = cores.get(device.kind.affinity_core()).map(|mask| mask & mask.wrapping_neg());
+        match (set_affinity, mask) {
+            (true, Some(mask)) => {
+                Win32.write_dword(Hive::LocalMachine, &affinity_path, "DevicePolicy", IRQ_POLICY_SPECIFIED_PROCESSORS);
+                Win32.write_binary(Hive::LocalMachine, &affinity_path, "AssignmentSetOverride", &mask.to_le_bytes());
+                log_line!("[Interrupts] {} interrupts pinned to mask {:#x}", device.name, mask);
+            }
+            (true, None) => log_line!("[Interrupts] Too few cores to pin {} interrupts", device.name),
+            (false, _) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Write back the values from before `apply`; true if anything was restored
+pub fn restore() -> bool {
+    let backups = load_backups();
+    if backups.is_empty() {
+        return false;
+    }
+
+    for (instance_id, backup) in &backups {
+        let msi_path = key_path(instance_id, MSI_KEY);
+        let affinity_path = key_path(instance_id, AFFINITY_KEY);
+        match backup.msi_supported {
+            Some(value) => { Win32.write_dword(Hive::LocalMachine, &msi_path, "MSISupported", value); }
+            None => Win32.delete_value(Hive::LocalMachine, &msi_path, "MSISupported"),
+        }
+        match backup.device_policy {
+            Some(value) => { Win32.write_dword(Hive::LocalMachine, &affinity_path, "DevicePolicy", value); }
+            None => Win32.delete_value(Hive::LocalMachine, &affinity_path, "DevicePolicy"),
+        }
+        match &backup.assignment_set_override {
+            Some(value) => { Win32.write_binary(Hive::LocalMachine, &affinity_path, "AssignmentSetOverride", value); }
+            None => Win32.delete_value(Hive::LocalMachine, &affinity_path, "AssignmentSetOverride"),
+        }
+        log_line!("[Interrupts] Restored interrupt settings for {}", backup.name);
+    }
+    let _ = fs::remove_file(backup_path());
+    true
+}
+
+/// interrupts.json in settings::data_dir(): instance ID -> original values
+fn backup_path() -> PathBuf {
+    settings::data_dir().join("interrupts.json")
+}
+
+fn load_backups() -> HashMap<String, DeviceBackup> {
+    fs::read_to_string(backup_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_backups(backups: &HashMap<String, DeviceBackup>) {
+    if let Ok(json) = serde_json::to_string_pretty(backups) {
+        if let Err(e) = fs::write(backup_path(), json) {
+            log_line!("[Interrupts] Could not save the backup: {}", e);
+        }
+    }
+}
+
+fn key_path(instance_id: &str, key: &str) -> String {
+    format!(r"SYSTEM\CurrentControlSet\Enum\{}\{}\{}", instance_id, INTERRUPT_MANAGEMENT, key)
+}
+
+// =========================================================================
+// DEVICE ENUMERATION (SetupAPI)
+// =========================================================================
+
+/// (instance ID, name) of the present PCI device in `class` whose friendly name or
+/// description matches `name`
+fn find_device(class: &GUID, name: &str) -> Option<(String, String)> {
+    let wanted = name.trim();
+    if wanted.is_empty() {
+        return None;
+    }
+
+    unsafe {
+        let set = SetupDiGetClassDevsW(Some(class), PCWSTR::null(), HWND::default(), DIGCF_PRESENT).ok()?;
+        let mut found = None;
+        let mut index = 0;
+        loop {
+            let mut info = SP_DEVINFO_DATA {
+                cbSize: std::mem::size_of::<SP_DEVINFO_DATA>() as u32,
+                ..Default::default()
+            };
+            if SetupDiEnumDeviceInfo(set, index, &mut info).is_err() {
+                break;
+            }
+            index += 1;
+
+            let names = [SPDRP_FRIENDLYNAME, SPDRP_DEVICEDESC].map(|property| device_property(set, &info, property));
+            let Some(matched) = names.iter().flatten().find(|n| n.eq_ignore_ascii_case(wanted)) else {
+                continue;
+            };
+            let mut buffer = [0u16; 512];
+            if SetupDiGetDeviceInstanceIdW(set, &info, Some(&mut buffer), None).is_err() {
+                continue;
+            }
+            let instance_id = String::from_utf16_lossy(&buffer[..buffer.iter().position(|&c| c == 0).unwrap_or(0)]);
+            // MSI and affinity policy only exist for PCI devices (not USB adapters)
+            if instance_id.to_ascii_uppercase().starts_with(r"PCI\") {
+                found = Some((instance_id, matched.clone()));
+                break;
+            }
+        }
+        let _ = SetupDiDestroyDeviceInfoList(set);
+        found
+    }
+}
+
+unsafe fn device_property(set: HDEVINFO, info: &SP_DEVINFO_DATA, property: SETUP_DI_REGISTRY_PROPERTY) -> Option<String> {
+    let mut buffer = [0u8; 1024];
+    SetupDiGetDeviceRegistryPropertyW(set, info, property, None, Some(&mut buffer), None).ok()?;
+    let wide: Vec<u16> = buffer.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).take_while(|&c| c != 0).collect();
+    Some(String::from_utf16_lossy(&wide).trim().to_string())
+}
@@ -0,0 +1,109 @@
+//! Session Journal
+//! Restore targets of the running session persisted to session.json, so a session can
+//! still be reverted when the app that applied it is gone (watchdog, crash recovery)
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::services::advanced_modules::{AdvancedModulesService, ModuleOriginals};
+use crate::services::custom_tweaks::CustomTweaksService;
+use crate::services::gamemode::GameModeService;
+use crate::services::network::{DnsOriginals, NetworkService};
+use crate::services::power::{PowerOriginals, PowerService};
+use crate::services::registry::RegistryOriginals;
+use crate::services::revi_tweaks::ReviTweaksService;
+use crate::services::settings::{self, AdvancedModuleSettings};
+use crate::services::system::{SystemBackend, Win32};
+use crate::log_line;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionJournal {
+    /// Process that applied the session
+    pub app_pid: u32,
+    #[serde(default)]
+    pub explorer_killed: bool,
+    #[serde(default)]
+    pub explorer_windows: Vec<String>,
+    #[serde(default)]
+    pub stopped_services: Vec<String>,
+    #[serde(default)]
+    pub shell_ux_suspended: bool,
+    #[serde(default)]
+    pub network_isolated: bool,
+    #[serde(default)]
+    pub firewall_blocked: bool,
+    /// Set when the session changed the power plan / laptop boost
+    #[serde(default)]
+    pub power: Option<PowerOriginals>,
+    #[serde(default)]
+    pub registry: RegistryOriginals,
+    /// Session MPO: original OverlayTestMode (outer Some = changed this session)
+    #[serde(default)]
+    pub original_overlay_test_mode: Option<Option<u32>>,
+    /// ReviTweaks original state (opaque, owned by ReviTweaksService)
+    #[serde(default)]
+    pub revi_state: Option<serde_json::Value>,
+    /// Custom tweaks state (opaque, owned by CustomTweaksService)
+    #[serde(default)]
+    pub custom_tweaks_state: Option<serde_json::Value>,
+    /// DNS configuration replaced by the DNS override module
+    #[serde(default)]
+    pub dns_originals: Option<DnsOriginals>,
+    /// Advanced modules applied by the session
+    #[serde(default)]
+    pub advanced_modules: Option<AdvancedModuleSettings>,
+    /// Their original values (opaque, owned by AdvancedModulesService)
+    #[serde(default)]
+    pub module_originals: Option<ModuleOriginals>,
+}
+
+/// session.json in settings::data_dir()
+fn journal_path() -> PathBuf {
+    settings::data_dir().join("session.json")
+}
+
+/// Persist the journal of the running session
+pub fn write(journal: &SessionJournal) {
+    if let Ok(content) = serde_json::to_string_pretty(journal) {
+        let _ = fs::write(journal_path(), content);
+    }
+}
+
+/// Journal of a session that has not been cleanly disabled
+pub fn read() -> Option<SessionJournal> {
+    let content = fs::read_to_string(journal_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Forget the journal after a clean disable
+pub fn clear() {
+    let _ = fs::remove_file(journal_path());
+}
+
+/// Revert everything the journal records, without any in-memory state, then clear it
+pub fn recover(journal: &SessionJournal) {
+    log_line!("[Journal] Recovering session of PID {}", journal.app_pid);
+
+    let system: Arc<dyn SystemBackend> = Arc::new(Win32);
+    GameModeService::restore_from_journal(&system, journal);
+    if journal.power.is_some() {
+        PowerService::clear_marker();
+    }
+    if let Some(state) = &journal.revi_state {
+        ReviTweaksService::restore_from_journal(state);
+    }
+    if let Some(state) = &journal.custom_tweaks_state {
+        CustomTweaksService::restore_from_journal(state);
+    }
+    if let Some(originals) = &journal.dns_originals {
+        NetworkService::restore_dns(originals);
+    }
+    if let Some(modules) = &journal.advanced_modules {
+        let originals = journal.module_originals.clone().unwrap_or_default();
+        AdvancedModulesService::from_originals(system.clone(), &originals).disable(modules);
+    }
+
+    clear();
+    log_line!("[Journal] Recovery complete");
+}
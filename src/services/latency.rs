@@ -0,0 +1,279 @@
+//! Latency Monitor
+//! Pings the game server (or a configurable endpoint) while game mode is active
+//! and records latency, jitter and packet loss for the session
+
+use windows::Win32::NetworkManagement::IpHelper::{
+    IcmpCreateFile, IcmpSendEcho, IcmpCloseHandle, GetExtendedTcpTable,
+    ICMP_ECHO_REPLY, MIB_TCPTABLE_OWNER_PID, MIB_TCPROW_OWNER_PID, TCP_TABLE_OWNER_PID_CONNECTIONS,
+};
+use windows::Win32::Foundation::BOOL;
+use std::net::{Ipv4Addr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::log_line;
+use crate::services::session;
+
+const AF_INET: u32 = 2;
+const MIB_TCP_STATE_ESTAB: u32 = 5;
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const PING_TIMEOUT_MS: u32 = 1000;
+/// Fallback when no endpoint is configured and the game has no remote TCP connection
+const DEFAULT_ENDPOINT: &str = "1.1.1.1";
+
+/// Live reading passed to the UI callback after every ping
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySample {
+    /// Round trip time, None if the ping was lost
+    pub rtt_ms: Option<u32>,
+    pub jitter_ms: f64,
+    pub loss_percent: f64,
+    /// RTT exceeded the configured spike threshold
+    pub is_spike: bool,
+}
+
+/// Summary recorded at the end of a session
+#[derive(Debug, Clone, Default)]
+pub struct LatencyReport {
+    /// Session the report belongs to (see services::session)
+    pub session_id: Option<String>,
+    pub endpoint: String,
+    pub sent: u32,
+    pub lost: u32,
+    pub min_ms: u32,
+    pub avg_ms: f64,
+    pub max_ms: u32,
+    pub jitter_ms: f64,
+    pub spikes: u32,
+}
+
+impl LatencyReport {
+    pub fn loss_percent(&self) -> f64 {
+        if self.sent > 0 { self.lost as f64 * 100.0 / self.sent as f64 } else { 0.0 }
+    }
+}
+
+impl std::fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let loss = self.loss_percent();
+        if let Some(id) = &self.session_id {
+            write!(f, "session {} - ", id)?;
+        }
+        write!(
+            f,
+            "{}: {} pings, min/avg/max {}/{:.1}/{} ms, jitter {:.1} ms, loss {:.1}%, {} spikes",
+            self.endpoint, self.sent, self.min_ms, self.avg_ms, self.max_ms, self.jitter_ms, loss, self.spikes
+        )
+    }
+}
+
+/// Running statistics (no sample history kept, constant memory for long sessions)
+#[derive(Default)]
+struct LatencyStats {
+    report: LatencyReport,
+    total_ms: u64,
+    received: u32,
+    last_rtt: Option<u32>,
+    jitter_total: f64,
+    jitter_count: u32,
+}
+
+impl LatencyStats {
+    fn record(&mut self, rtt: Option<u32>, spike_threshold_ms: u32) -> LatencySample {
+        self.report.sent += 1;
+
+        let mut is_spike = false;
+        match rtt {
+            Some(ms) => {
+                if self.received == 0 || ms < self.report.min_ms {
+                    self.report.min_ms = ms;
+                }
+                self.report.max_ms = self.report.max_ms.max(ms);
+                self.received += 1;
+                self.total_ms += ms as u64;
+                self.report.avg_ms = self.total_ms as f64 / self.received as f64;
+
+                // Jitter = mean absolute difference between consecutive replies
+                if let Some(last) = self.last_rtt {
+                    self.jitter_total += (ms as f64 - last as f64).abs();
+                    self.jitter_count += 1;
+                    self.report.jitter_ms = self.jitter_total / self.jitter_count as f64;
+                }
+                self.last_rtt = Some(ms);
+
+                if spike_threshold_ms > 0 && ms >= spike_threshold_ms {
+                    self.report.spikes += 1;
+                    is_spike = true;
+                }
+            }
+            None => self.report.lost += 1,
+        }
+
+        LatencySample {
+            rtt_ms: rtt,
+            jitter_ms: self.report.jitter_ms,
+            loss_percent: self.report.lost as f64 * 100.0 / self.report.sent as f64,
+            is_spike,
+        }
+    }
+}
+
+pub struct LatencyMonitor {
+    running: Arc<AtomicBool>,
+    stats: Arc<Mutex<LatencyStats>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl LatencyMonitor {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(Mutex::new(LatencyStats::default())),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Start pinging in the background
+    /// `endpoint` empty = game server from the game's TCP connections, else DEFAULT_ENDPOINT
+    pub fn start<F>(&self, endpoint: &str, game_pid: u32, spike_threshold_ms: u32, on_sample: F)
+    where
+        F: Fn(LatencySample) + Send + 'static,
+    {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return; // Already running
+        }
+
+        let Some(target) = Self::resolve_target(endpoint, game_pid) else {
+            log_line!("[Latency] Could not resolve endpoint '{}', monitor not started", endpoint);
+            self.running.store(false, Ordering::SeqCst);
+            return;
+        };
+
+        *self.stats.lock().unwrap() = LatencyStats {
+            report: LatencyReport {
+                session_id: session::current(),
+                endpoint: target.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let running = self.running.clone();
+        let stats = self.stats.clone();
+        log_line!("[Latency] Monitoring {}", target);
+
+        let handle = thread::spawn(move || {
+            let address = u32::from_ne_bytes(target.octets());
+
+            while running.load(Ordering::SeqCst) {
+                let rtt = Self::ping(address);
+                let sample = stats.lock().unwrap().record(rtt, spike_threshold_ms);
+                on_sample(sample);
+                thread::sleep(PING_INTERVAL);
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Stop pinging and return the session report
+    pub fn stop(&self) -> Option<LatencyReport> {
+        if !self.running.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        let report = self.stats.lock().unwrap().report.clone();
+        log_line!("[Latency] Session: {}", report);
+        Some(report)
+    }
+
+    fn resolve_target(endpoint: &str, game_pid: u32) -> Option<Ipv4Addr> {
+        if !endpoint.is_empty() {
+            return Self::resolve_host(endpoint);
+        }
+        if game_pid != 0 {
+            if let Some(server) = Self::game_server_address(game_pid) {
+                return Some(server);
+            }
+        }
+        Self::resolve_host(DEFAULT_ENDPOINT)
+    }
+
+    fn resolve_host(host: &str) -> Option<Ipv4Addr> {
+        if let Ok(ip) = host.parse::<Ipv4Addr>() {
+            return Some(ip);
+        }
+        (host, 0).to_socket_addrs().ok()?.find_map(|addr| match addr.ip() {
+            std::net::IpAddr::V4(v4) => Some(v4),
+            _ => None,
+        })
+    }
+
+    /// Remote address of the game's first established, non-local TCP connection
+    fn game_server_address(game_pid: u32) -> Option<Ipv4Addr> {
+        unsafe {
+            let mut size: u32 = 0;
+            let _ = GetExtendedTcpTable(None, &mut size, BOOL(0), AF_INET, TCP_TABLE_OWNER_PID_CONNECTIONS, 0);
+            if size == 0 {
+                return None;
+            }
+
+            // u32 buffer keeps the table correctly aligned
+            let mut buffer = vec![0u32; (size as usize).div_ceil(4)];
+            if GetExtendedTcpTable(
+                Some(buffer.as_mut_ptr() as *mut _),
+                &mut size,
+                BOOL(0),
+                AF_INET,
+                TCP_TABLE_OWNER_PID_CONNECTIONS,
+                0,
+            ) != 0 {
+                return None;
+            }
+
+            let table = &*(buffer.as_ptr() as *const MIB_TCPTABLE_OWNER_PID);
+            let rows = std::slice::from_raw_parts(
+                table.table.as_ptr() as *const MIB_TCPROW_OWNER_PID,
+                table.dwNumEntries as usize,
+            );
+
+            rows.iter()
+                .filter(|r| r.dwOwningPid == game_pid && r.dwState == MIB_TCP_STATE_ESTAB)
+                .map(|r| Ipv4Addr::from(r.dwRemoteAddr.to_ne_bytes()))
+                .find(|ip| !ip.is_loopback() && !ip.is_private() && !ip.is_unspecified())
+        }
+    }
+
+    /// Single ICMP echo, returns RTT in ms or None on loss/timeout
+    pub fn ping(address: u32) -> Option<u32> {
+        unsafe {
+            let handle = IcmpCreateFile().ok()?;
+            let payload = [0x58u8; 32];
+            let mut reply = vec![0u8; std::mem::size_of::<ICMP_ECHO_REPLY>() + payload.len() + 8];
+
+            let replies = IcmpSendEcho(
+                handle,
+                address,
+                payload.as_ptr() as *const _,
+                payload.len() as u16,
+                None,
+                reply.as_mut_ptr() as *mut _,
+                reply.len() as u32,
+                PING_TIMEOUT_MS,
+            );
+            let _ = IcmpCloseHandle(handle);
+
+            if replies == 0 {
+                return None;
+            }
+            let echo: ICMP_ECHO_REPLY = std::ptr::read_unaligned(reply.as_ptr() as *const _);
+            // IP_SUCCESS = 0
+            if echo.Status == 0 { Some(echo.RoundTripTime) } else { None }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Game Library Scanner
+//! Builds the known-game executable list from installed Steam, Epic and GOG games
+//! (Steam libraryfolders.vdf/appmanifests, Epic .item manifests, GOG registry entries)
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+/// Executables shipped next to games that are never the game itself
+const NON_GAME_EXES: &[&str] = &[
+    "unins", "setup", "redist", "vcredist", "dxsetup", "dotnet",
+    "crashhandler", "crashreport", "crashpad", "easyanticheat", "beservice",
+    "ue4prereq", "ueprereq", "cefprocess", "installer", "updater",
+];
+
+/// How deep to look for executables below a game's install folder
+const MAX_EXE_DEPTH: usize = 3;
+
+/// An installed game found in a store library
+#[derive(Debug, Clone)]
+pub struct LibraryGame {
+    pub name: String,
+    /// Executable names without .exe
+    pub executables: Vec<String>,
+    pub source: &'static str,
+}
+
+pub struct LibraryService;
+
+impl LibraryService {
+    /// Scan every supported store; missing stores are skipped
+    pub fn scan() -> Vec<LibraryGame> {
+        let mut games = Self::scan_steam();
+        games.extend(Self::scan_epic());
+        games.extend(Self::scan_gog());
+        games.retain(|g| !g.executables.is_empty());
+
+        for game in &games {
+            log_line!("[Library] {} ({}): {}", game.name, game.source, game.executables.join(", "));
+        }
+        log_line!("[Library] Found {} installed games", games.len());
+        games
+    }
+
+    /// Flattened, de-duplicated executable names of all games
+    pub fn executables(games: &[LibraryGame]) -> Vec<String> {
+        let mut names: Vec<String> = games.iter().flat_map(|g| g.executables.iter().cloned()).collect();
+        names.sort_by_key(|n| n.to_ascii_lowercase());
+        names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        names
+    }
+
+    // =========================================================================
+    // STEAM
+    // libraryfolders.vdf lists library roots, appmanifest_*.acf one game each
+    // =========================================================================
+
+    fn scan_steam() -> Vec<LibraryGame> {
+        let steam_path = Win32.read_string(Hive::CurrentUser, r"Software\Valve\Steam", "SteamPath")
+            .or_else(|| Win32.read_string(Hive::LocalMachine, r"SOFTWARE\WOW6432Node\Valve\Steam", "InstallPath"));
+        let Some(steam_path) = steam_path else { return Vec::new() };
+        let steam_path = PathBuf::from(steam_path);
+
+        let mut libraries = vec![steam_path.clone()];
+        if let Ok(content) = fs::read_to_string(steam_path.join("steamapps").join("libraryfolders.vdf")) {
+            libraries.extend(Self::vdf_values(&content, "path").into_iter().map(PathBuf::from));
+        }
+        libraries.dedup();
+
+        let mut games = Vec::new();
+        for library in libraries {
+            let steamapps = library.join("steamapps");
+            let Ok(entries) = fs::read_dir(&steamapps) else { continue };
+
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                if !file_name.starts_with("appmanifest_") || !file_name.ends_with(".acf") {
+                    continue;
+                }
+                let Ok(content) = fs::read_to_string(entry.path()) else { continue };
+                let (Some(name), Some(install_dir)) = (
+                    Self::vdf_values(&content, "name").into_iter().next(),
+                    Self::vdf_values(&content, "installdir").into_iter().next(),
+                ) else {
+                    continue;
+                };
+
+                let executables = Self::find_executables(&steamapps.join("common").join(install_dir));
+                games.push(LibraryGame { name, executables, source: "Steam" });
+            }
+        }
+        games
+    }
+
+    /// All values of a key in Valve KeyValues text ("key"  "value" pairs)
+    fn vdf_values(content: &str, key: &str) -> Vec<String> {
+        let quoted_key = format!("\"{}\"", key);
+        content
+            .lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix(quoted_key.as_str())?;
+                let value = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+                Some(value.replace("\\\\", "\\"))
+            })
+            .collect()
+    }
+
+    // =========================================================================
+    // EPIC GAMES
+    // %ProgramData%\Epic\EpicGamesLauncher\Data\Manifests\*.item (JSON)
+    // =========================================================================
+
+    fn scan_epic() -> Vec<LibraryGame> {
+        let program_data = std::env::var("ProgramData").unwrap_or_else(|_| r"C:\ProgramData".to_string());
+        let manifests = Path::new(&program_data).join(r"Epic\EpicGamesLauncher\Data\Manifests");
+        let Ok(entries) = fs::read_dir(manifests) else { return Vec::new() };
+
+        entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("item")))
+            .filter_map(|e| {
+                let content = fs::read_to_string(e.path()).ok()?;
+                let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+                let name = manifest["DisplayName"].as_str()?.to_string();
+                let launch = manifest["LaunchExecutable"].as_str().unwrap_or_default();
+
+                let executables = match Self::exe_stem(Path::new(launch)) {
+                    Some(stem) => vec![stem],
+                    // No launch executable recorded, fall back to the install folder
+                    None => Self::find_executables(Path::new(manifest["InstallLocation"].as_str()?)),
+                };
+                Some(LibraryGame { name, executables, source: "Epic" })
+            })
+            .collect()
+    }
+
+    // =========================================================================
+    // GOG GALAXY
+    // HKLM\SOFTWARE\WOW6432Node\GOG.com\Games\<id>: gameName, exe, path
+    // =========================================================================
+
+    fn scan_gog() -> Vec<LibraryGame> {
+        let root = r"SOFTWARE\WOW6432Node\GOG.com\Games";
+        Win32.subkeys(Hive::LocalMachine, root)
+            .into_iter()
+            .filter_map(|id| {
+                let key = format!(r"{}\{}", root, id);
+                let name = Win32.read_string(Hive::LocalMachine, &key, "gameName")?;
+                let executables = match Win32.read_string(Hive::LocalMachine, &key, "exe")
+                    .and_then(|exe| Self::exe_stem(Path::new(&exe)))
+                {
+                    Some(stem) => vec![stem],
+                    None => Self::find_executables(Path::new(&Win32.read_string(Hive::LocalMachine, &key, "path")?)),
+                };
+                Some(LibraryGame { name, executables, source: "GOG" })
+            })
+            .collect()
+    }
+
+    // =========================================================================
+    // HELPERS
+    // =========================================================================
+
+    /// Game executables below an install folder, skipping installers/crash reporters
+    fn find_executables(dir: &Path) -> Vec<String> {
+        let mut found = Vec::new();
+        let mut pending = vec![(dir.to_path_buf(), 0usize)];
+
+        while let Some((current, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&current) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if depth < MAX_EXE_DEPTH {
+                        pending.push((path, depth + 1));
+                    }
+                } else if let Some(stem) = Self::exe_stem(&path) {
+                    found.push(stem);
+                }
+            }
+        }
+        found
+    }
+
+    /// File stem of an .exe path, None for other files and known non-game executables
+    fn exe_stem(path: &Path) -> Option<String> {
+        if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exe")) {
+            return None;
+        }
+        let stem = path.file_stem()?.to_string_lossy().into_owned();
+        let lower = stem.to_ascii_lowercase();
+        if NON_GAME_EXES.iter().any(|n| lower.contains(n)) {
+            return None;
+        }
+        Some(stem)
+    }
+}
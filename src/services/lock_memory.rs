@@ -0,0 +1,159 @@
+//! Lock Pages in Memory
+//! Large pages only work for accounts holding SeLockMemoryPrivilege; the LargePageMinimum /
+//! LargeSystemCache values don't grant it. The right is added to the current user through LSA
+//! policy (part of the token from the next sign-in) and only removed again if this app added it,
+//! which is recorded with the user's SID in the data folder.
+
+use std::fs;
+use std::path::PathBuf;
+use windows::core::{HRESULT, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, BOOLEAN, HANDLE, HLOCAL, LocalFree, NTSTATUS};
+use windows::Win32::Security::Authentication::Identity::{
+    LsaAddAccountRights, LsaClose, LsaEnumerateAccountRights, LsaFreeMemory, LsaNtStatusToWinError, LsaOpenPolicy,
+    LsaRemoveAccountRights, LSA_HANDLE, LSA_OBJECT_ATTRIBUTES, LSA_UNICODE_STRING, POLICY_CREATE_ACCOUNT,
+    POLICY_LOOKUP_NAMES,
+};
+use windows::Win32::Security::Authorization::ConvertSidToStringSidW;
+use windows::Win32::Security::{GetTokenInformation, TokenUser, PSID, TOKEN_QUERY, TOKEN_USER};
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use crate::services::settings;
+use crate::log_line;
+
+const PRIVILEGE: &str = "SeLockMemoryPrivilege";
+
+/// The current user holds the right (it may only be in the token after the next sign-in)
+pub fn is_held() -> bool {
+    with_user(|policy, sid| unsafe { has_right(policy, sid) }).unwrap_or(false)
+}
+
+/// Added by this app for the current user (and not removed since)
+pub fn granted_by_app() -> bool {
+    let Some(marker) = fs::read_to_string(marker_path()).ok() else { return false };
+    current_sid_string().is_some_and(|sid| sid == marker.trim())
+}
+
+/// Add the right to the current user; Ok(false) if the user already had it
+pub fn grant() -> Result<bool, String> {
+    let sid_string = current_sid_string().ok_or("Could not read the current user")?;
+    let added = with_user(|policy, sid| unsafe {
+        if has_right(policy, sid) {
+            return Ok(false);
+        }
+        let mut name: Vec<u16> = PRIVILEGE.encode_utf16().collect();
+        let right = unicode_string(&mut name);
+        check(LsaAddAccountRights(policy, sid, &[right])).map(|_| true)
+    }).ok_or("Could not open the local security policy")??;
+
+    if added {
+        let _ = fs::write(marker_path(), &sid_string);
+        log_line!("[LockMemory] {} granted to {}", PRIVILEGE, sid_string);
+    }
+    Ok(added)
+}
+
+/// Remove the right again if this app added it; true if it was removed
+pub fn revoke_if_granted() -> bool {
+    if !granted_by_app() {
+        return false;
+    }
+    let removed = with_user(|policy, sid| unsafe {
+        let mut name: Vec<u16> = PRIVILEGE.encode_utf16().collect();
+        let right = unicode_string(&mut name);
+        check(LsaRemoveAccountRights(policy, sid, BOOLEAN::from(false), Some(&[right])))
+    });
+
+    match removed {
+        Some(Ok(())) => {
+            let _ = fs::remove_file(marker_path());
+            log_line!("[LockMemory] {} removed", PRIVILEGE);
+            true
+        }
+        Some(Err(e)) => {
+            log_line!("[LockMemory] Could not remove {}: {}", PRIVILEGE, e);
+            false
+        }
+        None => false,
+    }
+}
+
+/// lock_memory.txt in settings::data_dir(): SID of the user the right was added to
+fn marker_path() -> PathBuf {
+    settings::data_dir().join("lock_memory.txt")
+}
+
+/// Run `f` with an LSA policy handle and the current user's SID
+fn with_user<T>(f: impl FnOnce(LSA_HANDLE, PSID) -> T) -> Option<T> {
+    let user = token_user()?;
+    unsafe {
+        let sid = (*user.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+        let attributes = LSA_OBJECT_ATTRIBUTES {
+            Length: std::mem::size_of::<LSA_OBJECT_ATTRIBUTES>() as u32,
+            ..Default::default()
+        };
+        let mut policy = LSA_HANDLE::default();
+        let access = (POLICY_CREATE_ACCOUNT | POLICY_LOOKUP_NAMES) as u32;
+        if let Err(e) = check(LsaOpenPolicy(None, &attributes, access, &mut policy)) {
+            log_line!("[LockMemory] LsaOpenPolicy failed: {}", e);
+            return None;
+        }
+        let result = f(policy, sid);
+        let _ = LsaClose(policy);
+        Some(result)
+    }
+}
+
+unsafe fn has_right(policy: LSA_HANDLE, sid: PSID) -> bool {
+    let mut rights: *mut LSA_UNICODE_STRING = std::ptr::null_mut();
+    let mut count = 0u32;
+    // Fails with "file not found" when the account has no rights at all
+    if LsaEnumerateAccountRights(policy, sid, &mut rights, &mut count).is_err() || rights.is_null() {
+        return false;
+    }
+    let held = std::slice::from_raw_parts(rights, count as usize).iter().any(|right| {
+        let len = right.Length as usize / 2;
+        let name = String::from_utf16_lossy(std::slice::from_raw_parts(right.Buffer.0, len));
+        name.eq_ignore_ascii_case(PRIVILEGE)
+    });
+    let _ = LsaFreeMemory(Some(rights as *const _));
+    held
+}
+
+/// TOKEN_USER of this process (the signed-in user, elevated); u64 buffer for the struct's alignment
+fn token_user() -> Option<Vec<u64>> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).ok()?;
+        let mut len = 0u32;
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut len);
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let result = GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr().cast()), len, &mut len);
+        let _ = CloseHandle(token);
+        result.ok().map(|_| buffer)
+    }
+}
+
+/// "S-1-5-21-..." of the current user
+fn current_sid_string() -> Option<String> {
+    let user = token_user()?;
+    unsafe {
+        let sid = (*user.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+        let mut string = PWSTR::null();
+        ConvertSidToStringSidW(sid, &mut string).ok()?;
+        let value = string.to_string().ok();
+        let _ = LocalFree(HLOCAL(string.0.cast()));
+        value
+    }
+}
+
+fn unicode_string(buffer: &mut [u16]) -> LSA_UNICODE_STRING {
+    let bytes = (buffer.len() * 2) as u16;
+    LSA_UNICODE_STRING { Length: bytes, MaximumLength: bytes, Buffer: PWSTR(buffer.as_mut_ptr()) }
+}
+
+fn check(status: NTSTATUS) -> Result<(), String> {
+    if status.is_ok() {
+        return Ok(());
+    }
+    let code = unsafe { LsaNtStatusToWinError(status) };
+    Err(HRESULT::from_win32(code).message())
+}
@@ -1,18 +1,56 @@
 use windows::Win32::System::ProcessStatus::EmptyWorkingSet;
 use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_QUOTA};
-use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use windows::Win32::Security::{
+    AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED,
+    SE_PROF_SINGLE_PROCESS_NAME, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
+};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, LUID};
 use windows::Win32::System::Diagnostics::ToolHelp::{
     CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32, TH32CS_SNAPPROCESS
 };
+use crate::log_line;
+use crate::services::process::ProcessService;
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtSetSystemInformation(class: u32, information: *const core::ffi::c_void, length: u32) -> i32;
+}
+
+/// SYSTEM_INFORMATION_CLASS::SystemMemoryListInformation
+const SYSTEM_MEMORY_LIST_INFORMATION: u32 = 80;
+/// SYSTEM_MEMORY_LIST_COMMAND::MemoryPurgeStandbyList
+const MEMORY_PURGE_STANDBY_LIST: u32 = 4;
+
+/// Never trimmed: their pages are needed again right away, and a hard fault there is felt
+/// (compositor stutter, audio crackle, anti-cheat heartbeats timing out)
+const FLUSH_EXCLUDED: &[&str] = &[
+    "System", "Registry", "MemCompression", "smss", "csrss", "wininit", "winlogon", "lsass", "services",
+    "dwm", "audiodg",
+    // Anti-cheat services and their user-mode clients
+    "EasyAntiCheat", "EasyAntiCheat_EOS", "BEService", "vgc", "vgtray", "FACEITService", "FACEIT",
+    "EAAntiCheat.GameService", "GameGuard", "nProtect", "PnkBstrA", "PnkBstrB", "mhyprot2",
+];
 
 pub struct MemoryService;
 
 impl MemoryService {
     /// 1:1 FlushMemoryAsync - Optimized version
-    /// Empties working set of all processes except self
+    /// Empties the working set of every process except self, `game_pid` with its children,
+    /// FLUSH_EXCLUDED and `extra_excluded` (names without .exe)
     #[inline]
-    pub fn flush_memory() {
+    pub fn flush_memory(game_pid: Option<u32>, extra_excluded: &[String]) {
         let self_pid = std::process::id();
+        let mut excluded_pids = vec![self_pid];
+        if let Some(game) = game_pid {
+            excluded_pids.push(game);
+            excluded_pids.extend(ProcessService::child_processes(game, ProcessService::process_start_time(game)));
+        }
+        let is_excluded_name = |name: &str| {
+            FLUSH_EXCLUDED.iter().any(|n| n.eq_ignore_ascii_case(name))
+                || extra_excluded.iter().any(|n| n.eq_ignore_ascii_case(name))
+        };
+        let mut skipped = 0;
         
         unsafe {
             let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else { return };
@@ -27,8 +65,12 @@ impl MemoryService {
                 loop {
                     let pid = entry.th32ProcessID;
                     
-                    // Skip self (1:1 with C#: process.Id != currentProcess.Id)
-                    if pid != self_pid {
+                    let name = Self::extract_process_name(&entry.szExeFile);
+                    
+                    // Skip self (1:1 with C#: process.Id != currentProcess.Id), the game and critical processes
+                    if excluded_pids.contains(&pid) || is_excluded_name(name) {
+                        skipped += 1;
+                    } else {
                         // C# checks process.Handle != IntPtr.Zero
                         // OpenProcess returns error if we can't access
                         if let Ok(handle) = OpenProcess(
@@ -48,5 +90,50 @@ impl MemoryService {
             
             let _ = CloseHandle(snapshot);
         }
+        log_line!("[Memory] Working sets flushed ({} processes skipped)", skipped);
+    }
+
+    /// Purge the standby list (cached file pages no process owns) - what ISLC / RAMMap do
+    /// Needs SeProfileSingleProcessPrivilege, which elevated admins hold but have disabled
+    pub fn purge_standby_list() -> bool {
+        Self::enable_privilege();
+        let command = MEMORY_PURGE_STANDBY_LIST;
+        let status = unsafe {
+            NtSetSystemInformation(
+                SYSTEM_MEMORY_LIST_INFORMATION,
+                &command as *const u32 as *const _,
+                std::mem::size_of::<u32>() as u32,
+            )
+        };
+        if status != 0 {
+            log_line!("[Memory] Standby list purge failed (NTSTATUS 0x{:08X})", status as u32);
+        }
+        status == 0
+    }
+
+    fn enable_privilege() {
+        unsafe {
+            let mut luid = LUID::default();
+            if LookupPrivilegeValueW(None, SE_PROF_SINGLE_PROCESS_NAME, &mut luid).is_err() {
+                return;
+            }
+            let mut token = HANDLE::default();
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES, &mut token).is_err() {
+                return;
+            }
+            let privileges = TOKEN_PRIVILEGES {
+                PrivilegeCount: 1,
+                Privileges: [LUID_AND_ATTRIBUTES { Luid: luid, Attributes: SE_PRIVILEGE_ENABLED }],
+            };
+            let _ = AdjustTokenPrivileges(token, false, Some(&privileges), 0, None, None);
+            let _ = CloseHandle(token);
+        }
+    }
+
+    fn extract_process_name(sz_exe_file: &[i8; 260]) -> &str {
+        let len = sz_exe_file.iter().position(|&c| c == 0).unwrap_or(260);
+        let bytes = unsafe { std::slice::from_raw_parts(sz_exe_file.as_ptr() as *const u8, len) };
+        let name = std::str::from_utf8(bytes).unwrap_or("");
+        ProcessService::strip_exe(name)
     }
 }
@@ -0,0 +1,156 @@
+//! Version Migration
+//! Every version records what it manages in managed.json; after an upgrade, values
+//! and services an older version managed but this one no longer does are offered
+//! for revert instead of being orphaned
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::services::gamemode::GameModeService;
+use crate::services::revi_tweaks::ReviTweaksService;
+use crate::services::settings;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+const SERVICES_KEY: &str = r"SYSTEM\CurrentControlSet\Services";
+/// Start type used when reverting a leftover whose original was never recorded (SERVICE_DEMAND_START)
+const REVERT_START_TYPE: u32 = 3;
+/// Start type written by the service tweaks (SERVICE_DISABLED)
+const DISABLED_START_TYPE: u32 = 4;
+
+/// HKLM DWORD value and the data this app writes to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManagedValue {
+    pub path: String,
+    pub name: String,
+    pub data: u32,
+}
+
+/// What one app version manages, persisted next to settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManagedManifest {
+    pub app_version: String,
+    #[serde(default)]
+    pub registry: Vec<ManagedValue>,
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// Start type each managed service had before the app disabled it, kept across versions
+    #[serde(default)]
+    pub service_start_types: BTreeMap<String, u32>,
+}
+
+/// Change left on the system by an older version that this version no longer manages
+#[derive(Debug, Clone)]
+pub enum Leftover {
+    Registry(ManagedValue),
+    /// Disabled service and the start type to put back
+    Service { name: String, start_type: u32 },
+}
+
+impl Leftover {
+    pub fn describe(&self) -> String {
+        match self {
+            Leftover::Registry(v) => format!("HKLM\\{}\\{} = {}", v.path, v.name, v.data),
+            Leftover::Service { name, .. } => format!("Service {} (Disabled)", name),
+        }
+    }
+
+    /// Back to the original: delete the value / give the service its recorded start type
+    pub fn revert(&self) {
+        match self {
+            Leftover::Registry(v) => {
+                Win32.delete_value(Hive::LocalMachine, &v.path, &v.name);
+                log_line!("[Migration] Reverted {}", self.describe());
+            }
+            Leftover::Service { name, start_type } => {
+                Win32.write_dword(Hive::LocalMachine, &service_key(name), "Start", *start_type);
+                log_line!("[Migration] Set service {} back to start type {}", name, start_type);
+            }
+        }
+    }
+}
+
+/// Upgrade from an older version with changes still applied
+pub struct PendingMigration {
+    pub previous_version: String,
+    pub leftovers: Vec<Leftover>,
+}
+
+pub struct MigrationService;
+
+impl MigrationService {
+    /// Manifest of the running version
+    pub fn current_manifest() -> ManagedManifest {
+        let mut registry = ReviTweaksService::managed_values();
+        registry.extend(GameModeService::managed_values());
+
+        ManagedManifest {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            registry,
+            services: ReviTweaksService::managed_services(),
+            service_start_types: BTreeMap::new(),
+        }
+    }
+
+    /// Leftovers from the previously recorded version, if it differs from this one
+    /// Only items that are still in the state the old version wrote are reported
+    pub fn pending() -> Option<PendingMigration> {
+        let previous = Self::load_manifest()?;
+        let current = Self::current_manifest();
+        if previous.app_version == current.app_version {
+            return None;
+        }
+
+        let mut leftovers = Vec::new();
+
+        for value in &previous.registry {
+            let still_managed = current.registry.iter().any(|v| v.path == value.path && v.name == value.name);
+            if !still_managed && Win32.read_dword(Hive::LocalMachine, &value.path, &value.name) == Some(value.data) {
+                leftovers.push(Leftover::Registry(value.clone()));
+            }
+        }
+
+        for service in &previous.services {
+            let still_managed = current.services.iter().any(|s| s.eq_ignore_ascii_case(service));
+            if !still_managed && Win32.read_dword(Hive::LocalMachine, &service_key(service), "Start") == Some(DISABLED_START_TYPE) {
+                let start_type = previous.service_start_types.get(service).copied().unwrap_or(REVERT_START_TYPE);
+                leftovers.push(Leftover::Service { name: service.clone(), start_type });
+            }
+        }
+
+        Some(PendingMigration { previous_version: previous.app_version, leftovers })
+    }
+
+    /// Persist the manifest of this version (after any migration was handled)
+    /// A service's start type is recorded while it is not disabled, otherwise the earlier record is kept
+    pub fn record_current() {
+        let mut manifest = Self::current_manifest();
+        let mut start_types = Self::load_manifest().map(|m| m.service_start_types).unwrap_or_default();
+        for service in &manifest.services {
+            let start = Win32.read_dword(Hive::LocalMachine, &service_key(service), "Start");
+            if let Some(start) = start.filter(|&start| start != DISABLED_START_TYPE) {
+                start_types.insert(service.clone(), start);
+            }
+        }
+        manifest.service_start_types = start_types;
+
+        if let Ok(content) = serde_json::to_string_pretty(&manifest) {
+            let _ = fs::write(Self::manifest_path(), content);
+        }
+    }
+
+    fn load_manifest() -> Option<ManagedManifest> {
+        let content = fs::read_to_string(Self::manifest_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// managed.json in settings::data_dir() (folder is created by SettingsService)
+    fn manifest_path() -> PathBuf {
+        settings::data_dir().join("managed.json")
+    }
+}
+
+fn service_key(name: &str) -> String {
+    format!(r"{}\{}", SERVICES_KEY, name)
+}
@@ -12,3 +12,51 @@ pub mod update;
 pub mod gamemode;
 pub mod revi_tweaks;
 pub mod advanced_modules;
+pub mod gpu;
+pub mod latency;
+pub mod display;
+pub mod session;
+pub mod profiles;
+pub mod migration;
+pub mod modified;
+pub mod library;
+pub mod schedule;
+pub mod journal;
+pub mod hotkey;
+pub mod clipboard;
+pub mod specs;
+pub mod capabilities;
+pub mod recommend;
+pub mod audio;
+pub mod game_config;
+pub mod status;
+pub mod drift;
+pub mod preset;
+pub mod tray;
+pub mod notify;
+pub mod obs;
+pub mod hooks;
+pub mod custom_tweaks;
+pub mod instance;
+pub mod protocol;
+pub mod frontend;
+pub mod controller;
+pub mod i18n;
+pub mod theme;
+pub mod startup;
+pub mod shutdown;
+pub mod lock_memory;
+pub mod interrupts;
+pub mod bcdedit;
+pub mod affinity;
+pub mod dpc;
+pub mod thermal;
+pub mod conflicts;
+pub mod vbs;
+pub mod write_audit;
+pub mod system;
+pub mod tasks;
+pub mod crash;
+pub mod connections;
+pub mod bandwidth;
+pub mod trim;
@@ -0,0 +1,110 @@
+//! Modified State
+//! Everything the app currently has in a non-original state (session or permanent),
+//! gathered from each service, with a per-item revert
+
+use crate::services::advanced_modules::AdvancedModulesService;
+use crate::services::gamemode::GameModeService;
+use crate::services::bcdedit;
+use crate::services::interrupts;
+use crate::services::lock_memory;
+use crate::services::revi_tweaks::ReviTweaksService;
+use crate::services::vbs;
+use crate::tr;
+
+/// One system setting the app has changed
+#[derive(Debug, Clone)]
+pub struct ModifiedItem {
+    /// Stable ID used to revert the item ("gm.*", "adv.*", "revi", "perm.*")
+    pub id: String,
+    pub label: String,
+    /// Restored automatically when game mode is disabled
+    pub session: bool,
+    /// Can be reverted on its own (false for e.g. closed apps or reboot-pending values)
+    pub revertable: bool,
+}
+
+impl ModifiedItem {
+    pub fn session(id: &str, label: impl Into<String>) -> Self {
+        Self { id: id.to_string(), label: label.into(), session: true, revertable: true }
+    }
+
+    pub fn permanent(id: &str, label: impl Into<String>) -> Self {
+        Self { id: id.to_string(), label: label.into(), session: false, revertable: true }
+    }
+
+    /// Shown for completeness but not revertable from the list
+    pub fn fixed(mut self) -> Self {
+        self.revertable = false;
+        self
+    }
+}
+
+/// Every change currently in place, session items first
+pub fn collect(gamemode: &GameModeService, advanced: &AdvancedModulesService) -> Vec<ModifiedItem> {
+    let mut items = gamemode.modified_items();
+
+    if ReviTweaksService::is_applied() {
+        items.push(ModifiedItem::session("revi", tr!("ReviOS tweaks (services, telemetry, VBS, mitigations)")));
+    }
+
+    items.extend(advanced.modified_items());
+
+    // Permanent toggles, unless the session already accounts for the same value
+    let session_id = |id: &str| match id {
+        "perm.mpo" => Some("gm.mpo"),
+        "perm.autotuning" => Some("adv.lower_bufferbloat"),
+        _ => None,
+    };
+    let permanent: Vec<ModifiedItem> = permanent(advanced)
+        .into_iter()
+        .filter(|item| !session_id(&item.id).is_some_and(|id| items.iter().any(|i| i.id == id)))
+        .collect();
+    items.extend(permanent);
+
+    items
+}
+
+/// Changes that stay in place after the session ends (and after exit)
+pub fn permanent(advanced: &AdvancedModulesService) -> Vec<ModifiedItem> {
+    let mut items = Vec::new();
+    if GameModeService::is_mpo_disabled() {
+        items.push(ModifiedItem::permanent("perm.mpo", tr!("MPO disabled (OverlayTestMode=5)")));
+    }
+    if AdvancedModulesService::get_bufferbloat_status() {
+        items.push(ModifiedItem::permanent("perm.autotuning", tr!("TCP autotuning disabled (Lower Bufferbloat)")));
+    }
+    if lock_memory::granted_by_app() {
+        items.push(ModifiedItem::permanent("perm.lock_memory", tr!("Lock Pages in Memory granted to this account")));
+    }
+    if interrupts::is_applied() {
+        items.push(ModifiedItem::permanent("perm.interrupts", tr!("MSI mode / interrupt affinity (GPU, network adapter)")));
+    }
+    if bcdedit::is_applied() {
+        items.push(ModifiedItem::permanent("perm.bcdedit", tr!("Platform timer boot settings (bcdedit)")));
+    }
+    if vbs::is_disabled_by_app() {
+        items.push(ModifiedItem::permanent("perm.vbs", tr!("Core isolation / Memory Integrity turned off")));
+    }
+    for item in advanced.pending_reboot_items() {
+        items.push(ModifiedItem::permanent("perm.reboot", tr!("{} (applies after reboot)", item)).fixed());
+    }
+
+    items
+}
+
+/// Revert a single item by ID; false if the ID is unknown or no longer applied
+pub fn revert(id: &str, gamemode: &GameModeService, advanced: &AdvancedModulesService) -> bool {
+    match id {
+        "perm.mpo" => GameModeService::set_mpo_enabled(),
+        "perm.autotuning" => AdvancedModulesService::set_bufferbloat_disabled(),
+        "perm.lock_memory" => return lock_memory::revoke_if_granted(),
+        "perm.interrupts" => return interrupts::restore(),
+        "perm.bcdedit" => return bcdedit::restore(),
+        "perm.vbs" => return vbs::restore(),
+        "revi" => ReviTweaksService::disable(),
+        _ if id.starts_with("adv.") => return advanced.revert_module(id),
+        _ if id.starts_with("gm.") => return gamemode.revert_item(id),
+        _ => return false,
+    }
+    true
+}
@@ -1,115 +1,512 @@
-use windows::core::{PCWSTR, HSTRING, PWSTR};
-use windows::Win32::System::Registry::{
-    RegOpenKeyExW, RegSetValueExW, RegCloseKey, RegDeleteValueW, RegEnumKeyExW,
-    RegCreateKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_WRITE, KEY_READ, REG_DWORD,
-    REG_OPTION_NON_VOLATILE, REG_CREATE_KEY_DISPOSITION,
-};
-use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::os::windows::process::CommandExt;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+// Group Policy DNS client settings (LLMNR) and the per-interface NetBIOS options
+const DNS_CLIENT_POLICY: &str = r"SOFTWARE\Policies\Microsoft\Windows NT\DNSClient";
+const NETBT_INTERFACES: &str = r"SYSTEM\CurrentControlSet\Services\NetBT\Parameters\Interfaces";
+
+/// Shared name for every temporary block rule, so one delete removes them all (also after a crash)
+const FIREWALL_RULE_NAME: &str = "XillyGameMode Background Block";
+
+/// NIC advanced properties tuned for gaming: (registry keyword, tuned value)
+/// Standardized NDIS keywords so the same list works across Intel/Realtek/Killer drivers
+const ADAPTER_TWEAKS: &[(&str, &str)] = &[
+    ("*InterruptModeration", "0"),  // Deliver interrupts per packet instead of batching
+    ("*RSS", "1"),                  // Spread receive processing across cores
+    ("*EEE", "0"),                  // Energy Efficient Ethernet
+    ("EnableGreenEthernet", "0"),   // Vendor power saving (Realtek/Intel)
+    ("*PMARPOffload", "0"),         // ARP offload (power management)
+    ("*PMNSOffload", "0"),          // NS offload (power management)
+    ("*SelectiveSuspend", "0"),     // USB NIC selective suspend
+];
+
+/// Original adapter property values captured before tuning
+#[derive(Default)]
+struct AdapterTuningState {
+    adapter_name: Option<String>,
+    /// (keyword, original value) for every property we actually changed
+    original_values: Vec<(String, String)>,
+}
+
+/// The adapter carrying the default route
+#[derive(Debug, Clone)]
+pub struct ActiveAdapter {
+    pub name: String,
+    pub description: String,
+    pub is_wireless: bool,
+}
+
+impl ActiveAdapter {
+    /// Short label for the UI, e.g. "Ethernet (Intel I225-V) - Wired"
+    pub fn label(&self) -> String {
+        let kind = if self.is_wireless { "Wi-Fi" } else { "Wired" };
+        if self.description.is_empty() {
+            format!("{} - {}", self.name, kind)
+        } else {
+            format!("{} ({}) - {}", self.name, self.description, kind)
+        }
+    }
+}
+
+/// Autotuning levels as named by Get-NetTCPSetting, lowercased (same keywords as netsh)
+const AUTOTUNING_LEVELS: &[&str] = &["disabled", "highlyrestricted", "restricted", "normal", "experimental"];
+
+/// Global TCP settings the network modules change
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TcpGlobals {
+    /// Receive window autotuning level ("normal", "disabled", ...)
+    pub autotuning: Option<String>,
+    pub ecn: Option<bool>,
+    pub rsc: Option<bool>,
+}
+
+/// What network isolation leaves alone, per workload profile (everything is isolated by default)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IsolationExceptions {
+    /// Keep LLMNR/mDNS name resolution (Sunshine/Moonlight pairing, casting, smart home bridges)
+    #[serde(default)]
+    pub keep_multicast: bool,
+
+    /// Adapters (name or description, partial match) that keep NetBIOS, e.g. the LAN with a NAS
+    #[serde(default)]
+    pub netbios_adapters: Vec<String>,
+}
+
+/// DNS servers configured on the gaming adapter before a session override (empty = from DHCP)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DnsOriginals {
+    pub adapter: String,
+    #[serde(default)]
+    pub ipv4: Vec<String>,
+    #[serde(default)]
+    pub ipv6: Vec<String>,
+}
+
+static ADAPTER_STATE: Lazy<Mutex<AdapterTuningState>> = Lazy::new(|| Mutex::new(AdapterTuningState::default()));
+static DNS_STATE: Lazy<Mutex<Option<DnsOriginals>>> = Lazy::new(|| Mutex::new(None));
 
 pub struct NetworkService;
 
 impl NetworkService {
-    #[inline]
-    pub fn toggle_isolation(enable: bool) {
-        if enable {
-            Self::disable_multicast();
-            Self::disable_netbios();
+    /// Turn off multicast name resolution and NetBIOS, except what `exceptions` keeps
+    pub fn isolate(exceptions: &IsolationExceptions) {
+        if exceptions.keep_multicast {
+            log_line!("[Network] Isolation keeps LLMNR/mDNS");
         } else {
-            Self::enable_multicast();
-            Self::enable_netbios();
+            Self::disable_multicast();
         }
+
+        let kept = Self::adapter_guids(&exceptions.netbios_adapters);
+        if !kept.is_empty() {
+            log_line!("[Network] Isolation keeps NetBIOS on {} adapter(s)", kept.len());
+        }
+        Self::set_netbios_option(2, &kept); // 2 = Disable
+    }
+
+    /// Undo `isolate`; resets every protocol, excepted ones were never changed
+    #[inline]
+    pub fn end_isolation() {
+        Self::enable_multicast();
+        Self::enable_netbios();
     }
 
     /// C# uses Registry.LocalMachine.CreateSubKey() which creates if not exists
     fn disable_multicast() {
-        unsafe {
-            let mut key_handle = HKEY::default();
-            let subkey = HSTRING::from("SOFTWARE\\Policies\\Microsoft\\Windows NT\\DNSClient");
-            let mut disposition = REG_CREATE_KEY_DISPOSITION::default();
-            
-            // CreateSubKey in C# creates the key if it doesn't exist
-            if RegCreateKeyExW(
-                HKEY_LOCAL_MACHINE,
-                PCWSTR(subkey.as_ptr()),
-                0,
-                None,
-                REG_OPTION_NON_VOLATILE,
-                KEY_WRITE,
-                None,
-                &mut key_handle,
-                Some(&mut disposition),
-            ).is_ok() {
-                let value_name = HSTRING::from("EnableMulticast");
-                let data = 0u32;
-                let data_bytes = std::slice::from_raw_parts(&data as *const _ as *const u8, size_of::<u32>());
-                let _ = RegSetValueExW(key_handle, PCWSTR(value_name.as_ptr()), 0, REG_DWORD, Some(data_bytes));
-                let _ = RegCloseKey(key_handle);
-            }
-        }
+        Win32.write_dword(Hive::LocalMachine, DNS_CLIENT_POLICY, "EnableMulticast", 0);
     }
 
     fn enable_multicast() {
-        unsafe {
-            let mut key_handle = HKEY::default();
-            let subkey = HSTRING::from("SOFTWARE\\Policies\\Microsoft\\Windows NT\\DNSClient");
-            
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(subkey.as_ptr()), 0, KEY_WRITE, &mut key_handle).is_ok() {
-                let value_name = HSTRING::from("EnableMulticast");
-                let _ = RegDeleteValueW(key_handle, PCWSTR(value_name.as_ptr()));
-                let _ = RegCloseKey(key_handle);
+        Win32.delete_value(Hive::LocalMachine, DNS_CLIENT_POLICY, "EnableMulticast");
+    }
+
+    fn enable_netbios() {
+        Self::set_netbios_option(0, &[]); // 0 = Default (enable)
+    }
+
+    /// Interface GUIDs ("{...}") of adapters whose name or description contains one of `patterns`
+    fn adapter_guids(patterns: &[String]) -> Vec<String> {
+        if patterns.is_empty() {
+            return Vec::new();
+        }
+        let Some(output) = Self::run_powershell(
+            "Get-NetAdapter | ForEach-Object { \"$($_.InterfaceGuid)|$($_.Name)|$($_.InterfaceDescription)\" }"
+        ) else {
+            return Vec::new();
+        };
+
+        output.lines()
+            .filter_map(|line| {
+                let mut parts = line.trim().splitn(3, '|');
+                let guid = parts.next()?.to_string();
+                let label = parts.collect::<Vec<_>>().join(" ").to_lowercase();
+                patterns.iter()
+                    .any(|p| !p.trim().is_empty() && label.contains(&p.trim().to_lowercase()))
+                    .then_some(guid)
+            })
+            .collect()
+    }
+
+    /// Optimized: Single pass through all NetBT interfaces (Tcpip_{GUID}), skipping `skip_guids`
+    fn set_netbios_option(value: u32, skip_guids: &[String]) {
+        for interface in Win32.subkeys(Hive::LocalMachine, NETBT_INTERFACES) {
+            if skip_guids.iter().any(|guid| interface.to_lowercase().ends_with(&guid.to_lowercase())) {
+                continue;
             }
+            let path = format!(r"{}\{}", NETBT_INTERFACES, interface);
+            Win32.write_dword(Hive::LocalMachine, &path, "NetbiosOptions", value);
         }
     }
 
-    fn disable_netbios() {
-        Self::set_netbios_option(2); // 2 = Disable
+    // =========================================================================
+    // FIREWALL BLOCKING
+    // Outbound-block rules for background apps instead of killing them
+    // =========================================================================
+
+    /// Add an outbound-block rule for each executable path
+    pub fn block_programs(paths: &[String]) {
+        // Clear leftovers from an interrupted session first
+        Self::unblock_programs();
+
+        for path in paths {
+            let program = format!("program={}", path);
+            let _ = Command::new("netsh")
+                .args(["advfirewall", "firewall", "add", "rule"])
+                .arg(format!("name={}", FIREWALL_RULE_NAME))
+                .args(["dir=out", "action=block", "enable=yes", "profile=any"])
+                .arg(program)
+                .creation_flags(CREATE_NO_WINDOW)
+                .output();
+        }
+
+        if !paths.is_empty() {
+            log_line!("[Network] Blocked outbound traffic for {} programs", paths.len());
+        }
     }
 
-    fn enable_netbios() {
-        Self::set_netbios_option(0); // 0 = Default (enable)
-    }
-
-    /// Optimized: Single pass through all NetBT interfaces
-    fn set_netbios_option(value: u32) {
-        unsafe {
-            let mut root_key = HKEY::default();
-            let subkey = HSTRING::from("SYSTEM\\CurrentControlSet\\Services\\NetBT\\Parameters\\Interfaces");
-            
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(subkey.as_ptr()), 0, KEY_READ, &mut root_key).is_ok() {
-                let value_name = HSTRING::from("NetbiosOptions");
-                let data_bytes = std::slice::from_raw_parts(&value as *const _ as *const u8, size_of::<u32>());
-                
-                let mut index = 0u32;
-                let mut name_buf = [0u16; 256];
-                
-                loop {
-                    let mut name_len = 256u32;
-                    
-                    if RegEnumKeyExW(
-                        root_key, 
-                        index, 
-                        PWSTR(name_buf.as_mut_ptr()), 
-                        &mut name_len, 
-                        None, 
-                        PWSTR::null(), 
-                        None,
-                        None
-                    ).is_err() {
-                        break;
-                    }
-                    
-                    // Open subkey directly using the enumerated name
-                    let mut sub_key = HKEY::default();
-                    if RegOpenKeyExW(root_key, PWSTR(name_buf.as_mut_ptr()), 0, KEY_WRITE, &mut sub_key).is_ok() {
-                        let _ = RegSetValueExW(sub_key, PCWSTR(value_name.as_ptr()), 0, REG_DWORD, Some(data_bytes));
-                        let _ = RegCloseKey(sub_key);
-                    }
-                    
-                    index += 1;
-                }
-                
-                let _ = RegCloseKey(root_key);
+    /// Remove every rule added by `block_programs`
+    pub fn unblock_programs() {
+        let _ = Command::new("netsh")
+            .args(["advfirewall", "firewall", "delete", "rule"])
+            .arg(format!("name={}", FIREWALL_RULE_NAME))
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+    }
+
+    // =========================================================================
+    // TCP GLOBALS
+    // Read through the NetTCPIP cmdlets, whose enum names are the same in every display language
+    // (netsh's "show global" text is localized); written with netsh, whose keywords are not
+    // =========================================================================
+
+    /// Current autotuning level, ECN and RSC (fields are None if they could not be read)
+    pub fn tcp_globals() -> TcpGlobals {
+        let output = Self::run_powershell(
+            "$t = Get-NetTCPSetting -SettingName Internet -ErrorAction SilentlyContinue; \
+             $o = Get-NetOffloadGlobalSetting -ErrorAction SilentlyContinue; \
+             \"$($t.AutoTuningLevelLocal)|$($t.EcnCapability)|$($o.ReceiveSegmentCoalescing)\""
+        ).unwrap_or_default();
+        Self::parse_tcp_globals(output.trim())
+    }
+
+    /// Parse "AutoTuningLevel|EcnCapability|ReceiveSegmentCoalescing" produced by `tcp_globals`
+    fn parse_tcp_globals(line: &str) -> TcpGlobals {
+        let mut parts = line.splitn(3, '|').map(|p| p.trim().to_lowercase());
+        let autotuning = parts.next().filter(|level| AUTOTUNING_LEVELS.contains(&level.as_str()));
+        let switch = |value: Option<String>| match value.as_deref() {
+            Some("enabled") => Some(true),
+            Some("disabled") => Some(false),
+            _ => None,
+        };
+        TcpGlobals {
+            autotuning,
+            ecn: switch(parts.next()),
+            rsc: switch(parts.next()),
+        }
+    }
+
+    /// Set the receive window autotuning level ("disabled", "normal", ...)
+    pub fn set_autotuning(level: &str) {
+        if !AUTOTUNING_LEVELS.contains(&level) {
+            log_line!("[Network] Unknown autotuning level '{}', not applied", level);
+            return;
+        }
+        Self::set_tcp_global("autotuninglevel", level);
+    }
+
+    /// Explicit Congestion Notification
+    pub fn set_ecn(enabled: bool) {
+        Self::set_tcp_global("ecncapability", if enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Receive Segment Coalescing (batches received segments, trading latency for CPU)
+    pub fn set_rsc(enabled: bool) {
+        Self::set_tcp_global("rsc", if enabled { "enabled" } else { "disabled" });
+    }
+
+    fn set_tcp_global(keyword: &str, value: &str) {
+        let _ = Command::new("netsh")
+            .args(["int", "tcp", "set", "global"])
+            .arg(format!("{}={}", keyword, value))
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+    }
+
+    // =========================================================================
+    // NIC ADAPTER TUNING
+    // Interrupt moderation, RSS and power-saving offloads on the gaming NIC
+    // =========================================================================
+
+    /// Apply per-adapter tweaks to the active NIC, capturing original values first
+    pub fn apply_adapter_tuning() {
+        let mut state = ADAPTER_STATE.lock().unwrap();
+        if state.adapter_name.is_some() {
+            return; // Already applied
+        }
+
+        // Only the adapter carrying the default route is tuned
+        let Some(adapter) = Self::active_adapter().map(|a| a.name) else {
+            log_line!("[Network] No active adapter found, skipping NIC tuning");
+            return;
+        };
+
+        let current = Self::read_adapter_properties(&adapter);
+        let mut originals = Vec::with_capacity(ADAPTER_TWEAKS.len());
+        let mut script = String::new();
+
+        for &(keyword, tuned) in ADAPTER_TWEAKS {
+            // Only touch properties the driver actually exposes
+            let Some((_, original)) = current.iter().find(|(k, _)| k.eq_ignore_ascii_case(keyword)) else {
+                continue;
+            };
+            if original == tuned {
+                continue;
             }
+            script.push_str(&Self::set_property_command(&adapter, keyword, tuned));
+            originals.push((keyword.to_string(), original.clone()));
         }
+
+        if !originals.is_empty() {
+            // Single restart after all properties are written
+            script.push_str(&format!("Restart-NetAdapter -Name '{}' -Confirm:$false;", Self::ps_quote(&adapter)));
+            Self::run_powershell(&script);
+        }
+
+        log_line!("[Network] Tuned {} properties on adapter '{}'", originals.len(), adapter);
+        state.adapter_name = Some(adapter);
+        state.original_values = originals;
+    }
+
+    /// Restore the adapter properties changed by `apply_adapter_tuning`
+    pub fn restore_adapter_tuning() {
+        let mut state = ADAPTER_STATE.lock().unwrap();
+        let Some(adapter) = state.adapter_name.take() else {
+            return; // Nothing to restore
+        };
+        let originals = std::mem::take(&mut state.original_values);
+
+        if !originals.is_empty() {
+            let mut script = String::new();
+            for (keyword, value) in &originals {
+                script.push_str(&Self::set_property_command(&adapter, keyword, value));
+            }
+            script.push_str(&format!("Restart-NetAdapter -Name '{}' -Confirm:$false;", Self::ps_quote(&adapter)));
+            Self::run_powershell(&script);
+        }
+
+        log_line!("[Network] Restored {} properties on adapter '{}'", originals.len(), adapter);
+    }
+
+    // =========================================================================
+    // DNS
+    // Resolver cache flush and a per-session override of the gaming adapter's DNS servers
+    // =========================================================================
+
+    /// Clear the DNS resolver cache
+    pub fn flush_dns_cache() {
+        let _ = Command::new("ipconfig")
+            .arg("/flushdns")
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+        log_line!("[Network] DNS resolver cache flushed");
+    }
+
+    /// Point the active adapter at `servers`, capturing its current DNS configuration first
+    pub fn apply_dns_override(servers: &[String]) {
+        let mut state = DNS_STATE.lock().unwrap();
+        if state.is_some() {
+            return; // Already applied
+        }
+
+        let servers: Vec<&str> = servers.iter()
+            .map(|s| s.trim())
+            .filter(|s| s.parse::<std::net::IpAddr>().is_ok())
+            .collect();
+        if servers.is_empty() {
+            log_line!("[Network] No valid DNS servers configured, skipping DNS override");
+            return;
+        }
+        let Some(adapter) = Self::active_adapter().map(|a| a.name) else {
+            log_line!("[Network] No active adapter found, skipping DNS override");
+            return;
+        };
+        // Without the original configuration there is nothing to go back to
+        let Some(originals) = Self::read_dns_servers(&adapter) else {
+            log_line!("[Network] Could not read DNS configuration of '{}', skipping DNS override", adapter);
+            return;
+        };
+
+        Self::set_dns_servers(&adapter, &servers);
+        Self::flush_dns_cache();
+        log_line!("[Network] DNS on '{}' set to {} (was {:?} / {:?})",
+            adapter, servers.join(", "), originals.ipv4, originals.ipv6);
+        *state = Some(originals);
+    }
+
+    /// Restore the DNS configuration changed by `apply_dns_override`
+    pub fn restore_dns_override() {
+        let Some(originals) = DNS_STATE.lock().unwrap().take() else {
+            return; // Nothing to restore
+        };
+        Self::restore_dns(&originals);
+    }
+
+    /// Originals of the running override, for the session journal
+    pub fn dns_originals() -> Option<DnsOriginals> {
+        DNS_STATE.lock().unwrap().clone()
+    }
+
+    /// Put the adapter back to `originals` (also used by journal recovery)
+    pub fn restore_dns(originals: &DnsOriginals) {
+        let adapter = Self::ps_quote(&originals.adapter);
+        // Back to DHCP first, then re-apply whatever was static
+        let mut script = format!("Set-DnsClientServerAddress -InterfaceAlias '{}' -ResetServerAddresses;", adapter);
+        let static_servers: Vec<&str> = originals.ipv4.iter().chain(&originals.ipv6).map(String::as_str).collect();
+        if !static_servers.is_empty() {
+            script.push_str(&Self::dns_servers_command(&originals.adapter, &static_servers));
+        }
+        Self::run_powershell(&script);
+        Self::flush_dns_cache();
+        log_line!("[Network] DNS on '{}' restored ({})", originals.adapter,
+            if static_servers.is_empty() { "automatic".to_string() } else { static_servers.join(", ") });
+    }
+
+    /// Statically configured DNS servers per family, read from the Tcpip interface keys
+    /// (Get-DnsClientServerAddress can't tell DHCP-provided servers from static ones)
+    fn read_dns_servers(adapter: &str) -> Option<DnsOriginals> {
+        let script = format!(
+            "$a = Get-NetAdapter -Name '{}' -ErrorAction Stop; \
+             $v4 = (Get-ItemProperty \"HKLM:\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\$($a.InterfaceGuid)\" -ErrorAction SilentlyContinue).NameServer; \
+             $v6 = (Get-ItemProperty \"HKLM:\\SYSTEM\\CurrentControlSet\\Services\\Tcpip6\\Parameters\\Interfaces\\$($a.InterfaceGuid)\" -ErrorAction SilentlyContinue).NameServer; \
+             \"ok|$v4|$v6\"",
+            Self::ps_quote(adapter)
+        );
+        let output = Self::run_powershell(&script)?;
+        let mut parts = output.trim().splitn(3, '|');
+        if parts.next()? != "ok" {
+            return None;
+        }
+        // NameServer is comma or space separated depending on who wrote it
+        let split = |value: Option<&str>| -> Vec<String> {
+            value.unwrap_or("")
+                .split([',', ' '])
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        Some(DnsOriginals {
+            adapter: adapter.to_string(),
+            ipv4: split(parts.next()),
+            ipv6: split(parts.next()),
+        })
+    }
+
+    fn set_dns_servers(adapter: &str, servers: &[&str]) {
+        Self::run_powershell(&Self::dns_servers_command(adapter, servers));
+    }
+
+    fn dns_servers_command(adapter: &str, servers: &[&str]) -> String {
+        let list: Vec<String> = servers.iter().map(|s| format!("'{}'", Self::ps_quote(s))).collect();
+        format!(
+            "Set-DnsClientServerAddress -InterfaceAlias '{}' -ServerAddresses ({});",
+            Self::ps_quote(adapter), list.join(",")
+        )
+    }
+
+    /// Adapter that carries the default route (lowest route + interface metric)
+    /// This is the adapter game traffic actually leaves through, not just the first one that is up
+    pub fn active_adapter() -> Option<ActiveAdapter> {
+        let output = Self::run_powershell(
+            "$r = Get-NetRoute -DestinationPrefix '0.0.0.0/0','::/0' -ErrorAction SilentlyContinue | \
+             Sort-Object { $_.RouteMetric + $_.InterfaceMetric } | Select-Object -First 1; \
+             if ($r) { $a = Get-NetAdapter -InterfaceIndex $r.ifIndex; \
+             \"$($a.ifIndex)|$($a.NdisPhysicalMedium)|$($a.Name)|$($a.InterfaceDescription)\" }"
+        )?;
+        Self::parse_active_adapter(output.trim())
+    }
+
+    /// Parse "ifIndex|NdisPhysicalMedium|Name|Description" produced by `active_adapter`
+    fn parse_active_adapter(line: &str) -> Option<ActiveAdapter> {
+        let mut parts = line.splitn(4, '|');
+        // ifIndex must parse, otherwise the route lookup produced no adapter
+        parts.next()?.trim().parse::<u32>().ok()?;
+        let medium: u32 = parts.next()?.trim().parse().unwrap_or(0);
+        let name = parts.next()?.trim().to_string();
+        let description = parts.next().unwrap_or("").trim().to_string();
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(ActiveAdapter {
+            name,
+            description,
+            // NdisPhysicalMediumNative802_11 = 9 (numeric so it is locale-independent)
+            is_wireless: medium == 9,
+        })
+    }
+
+    /// Read all advanced properties as (keyword, value) pairs
+    /// Keywords and raw registry values are not localized, unlike DisplayName/DisplayValue
+    fn read_adapter_properties(adapter: &str) -> Vec<(String, String)> {
+        let script = format!(
+            "Get-NetAdapterAdvancedProperty -Name '{}' -AllProperties | ForEach-Object {{ \"$($_.RegistryKeyword)=$($_.RegistryValue -join ',')\" }}",
+            Self::ps_quote(adapter)
+        );
+        Self::run_powershell(&script)
+            .map(|out| {
+                out.lines()
+                    .filter_map(|line| line.trim().split_once('='))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn set_property_command(adapter: &str, keyword: &str, value: &str) -> String {
+        format!(
+            "Set-NetAdapterAdvancedProperty -Name '{}' -RegistryKeyword '{}' -RegistryValue '{}' -NoRestart;",
+            Self::ps_quote(adapter), keyword, Self::ps_quote(value)
+        )
+    }
+
+    /// Escape a value for use inside a single-quoted PowerShell string
+    fn ps_quote(value: &str) -> String {
+        value.replace('\'', "''")
+    }
+
+    fn run_powershell(script: &str) -> Option<String> {
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
     }
 }
@@ -0,0 +1,116 @@
+//! Toast Notifications
+//! Non-blocking Windows toasts for session events (game detected, system restored) and
+//! restore failures that need the user's attention; questions still use MessageBoxW
+
+use std::sync::{Mutex, Once};
+use once_cell::sync::Lazy;
+use windows::core::HSTRING;
+use windows::core::{IInspectable, Interface};
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::Foundation::TypedEventHandler;
+use windows::UI::Notifications::{ToastActivatedEventArgs, ToastNotification, ToastNotificationManager};
+use windows::Win32::UI::Shell::SetCurrentProcessExplicitAppUserModelID;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+/// App identity toasts are shown under (also lists the app in Settings > Notifications)
+const APP_ID: &str = "XillyGameMode";
+const APP_DISPLAY_NAME: &str = "Xilly Game Mode";
+
+/// Toasts with buttons kept alive so their Activated handler still fires (newest last)
+const MAX_ACTION_TOASTS: usize = 8;
+
+static REGISTER: Once = Once::new();
+static ACTION_TOASTS: Lazy<Mutex<Vec<ToastNotification>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Informational toast
+pub fn info(title: &str, body: &str) {
+    show(title, body, false);
+}
+
+/// Toast for something that went wrong and needs a look (e.g. a setting that failed to restore)
+pub fn attention(title: &str, body: &str) {
+    show(title, body, true);
+}
+
+/// Toast with buttons as (label, argument); `on_action` gets the argument of the clicked button
+/// Runs on a WinRT thread, and only while this process is alive (the toast is not persistent)
+pub fn actions<F>(title: &str, body: &str, buttons: &[(&str, &str)], on_action: F)
+where
+    F: Fn(&str) + Send + 'static,
+{
+    REGISTER.call_once(register_app_id);
+    if let Err(e) = try_show_actions(title, body, buttons, on_action) {
+        log_line!("[Notify] Toast failed ({}): {} - {}", e.code(), title, body);
+    }
+}
+
+fn try_show_actions<F>(title: &str, body: &str, buttons: &[(&str, &str)], on_action: F) -> windows::core::Result<()>
+where
+    F: Fn(&str) + Send + 'static,
+{
+    let actions: String = buttons.iter()
+        .map(|(label, argument)| format!(r#"<action content="{}" arguments="{}"/>"#, escape(label), escape(argument)))
+        .collect();
+    let xml = format!(
+        r#"<toast><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text></binding></visual><actions>{}</actions></toast>"#,
+        escape(title),
+        escape(body),
+        actions,
+    );
+
+    let document = XmlDocument::new()?;
+    document.LoadXml(&HSTRING::from(xml))?;
+    let toast = ToastNotification::CreateToastNotification(&document)?;
+    toast.Activated(&TypedEventHandler::<ToastNotification, IInspectable>::new(move |_, args| {
+        if let Some(args) = args.as_ref().and_then(|a| a.cast::<ToastActivatedEventArgs>().ok()) {
+            on_action(&args.Arguments()?.to_string());
+        }
+        Ok(())
+    }))?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)?;
+
+    let mut alive = ACTION_TOASTS.lock().unwrap();
+    if alive.len() == MAX_ACTION_TOASTS {
+        alive.remove(0);
+    }
+    alive.push(toast);
+    Ok(())
+}
+
+fn show(title: &str, body: &str, urgent: bool) {
+    REGISTER.call_once(register_app_id);
+    if let Err(e) = try_show(title, body, urgent) {
+        log_line!("[Notify] Toast failed ({}): {} - {}", e.code(), title, body);
+    }
+}
+
+fn try_show(title: &str, body: &str, urgent: bool) -> windows::core::Result<()> {
+    // "reminder" keeps the toast on screen until dismissed instead of timing out
+    let scenario = if urgent { r#" scenario="reminder""# } else { "" };
+    let xml = format!(
+        r#"<toast{}><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text></binding></visual>{}</toast>"#,
+        scenario,
+        escape(title),
+        escape(body),
+        if urgent { r#"<actions><action content="Dismiss" arguments="dismiss" activationType="system"/></actions>"# } else { "" },
+    );
+
+    let document = XmlDocument::new()?;
+    document.LoadXml(&HSTRING::from(xml))?;
+    let toast = ToastNotification::CreateToastNotification(&document)?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)
+}
+
+/// Unpackaged apps need their AppUserModelID registered under HKCU for toasts to appear
+fn register_app_id() {
+    unsafe {
+        let _ = SetCurrentProcessExplicitAppUserModelID(&HSTRING::from(APP_ID));
+    }
+    let subkey = format!(r"Software\Classes\AppUserModelId\{}", APP_ID);
+    Win32.write_string(Hive::CurrentUser, &subkey, "DisplayName", APP_DISPLAY_NAME);
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,238 @@
+//! OBS Integration
+//! Starts the replay buffer or a recording through obs-websocket (v5, built into OBS 28+)
+//! when game mode activates and stops it again on disable. While enabled, OBS processes
+//! are protected from every kill list so a stream or recording is never cut.
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tungstenite::{Message, WebSocket};
+use crate::log_line;
+
+/// OBS and its helper processes (browser sources, muxer, encoder probes)
+pub const OBS_PROCESSES: &[&str] = &[
+    "obs64", "obs32", "obs", "obs-browser-page", "obs-ffmpeg-mux", "obs-nvenc-test", "obs-amf-test", "obs-qsv-test",
+];
+
+// obs-websocket 5.x opcodes
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+const RPC_VERSION: u32 = 1;
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// What to start in OBS when game mode activates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsCapture {
+    ReplayBuffer,
+    Recording,
+}
+
+/// "obs" in settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Tools > WebSocket Server Settings in OBS (default 4455)
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Empty when authentication is off in OBS
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_capture")]
+    pub capture: ObsCapture,
+}
+
+fn default_host() -> String { "127.0.0.1".to_string() }
+fn default_port() -> u16 { 4455 }
+fn default_capture() -> ObsCapture { ObsCapture::ReplayBuffer }
+
+impl Default for ObsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_host(),
+            port: default_port(),
+            password: String::new(),
+            capture: default_capture(),
+        }
+    }
+}
+
+impl ObsCapture {
+    /// (status, start, stop) request types
+    fn requests(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ObsCapture::ReplayBuffer => ("GetReplayBufferStatus", "StartReplayBuffer", "StopReplayBuffer"),
+            ObsCapture::Recording => ("GetRecordStatus", "StartRecord", "StopRecord"),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ObsCapture::ReplayBuffer => "replay buffer",
+            ObsCapture::Recording => "recording",
+        }
+    }
+}
+
+// Output started by us this session, with the connection it was started on;
+// one that was already running is left alone on disable
+static STARTED: Lazy<Mutex<Option<ObsSettings>>> = Lazy::new(|| Mutex::new(None));
+
+pub struct ObsService;
+
+impl ObsService {
+    /// Processes never to be killed or suspended (empty while the integration is off)
+    pub fn protected_processes(settings: &ObsSettings) -> &'static [&'static str] {
+        if settings.enabled { OBS_PROCESSES } else { &[] }
+    }
+
+    /// Start the configured output unless it is already running
+    pub fn start(settings: &ObsSettings) {
+        if !settings.enabled {
+            return;
+        }
+        let capture = settings.capture;
+        let (status, start, _) = capture.requests();
+        let result = ObsConnection::open(settings).and_then(|mut obs| {
+            if Self::output_active(&obs.request(status)?) {
+                log_line!("[OBS] {} already running, leaving it as is", capture.label());
+                return Ok(false);
+            }
+            obs.request(start)?;
+            Ok(true)
+        });
+
+        match result {
+            Ok(true) => {
+                log_line!("[OBS] Started {}", capture.label());
+                *STARTED.lock().unwrap() = Some(settings.clone());
+            }
+            Ok(false) => {}
+            Err(e) => log_line!("[OBS] Could not start {}: {}", capture.label(), e),
+        }
+    }
+
+    /// Stop the output started by `start`, if any
+    pub fn stop() {
+        let Some(settings) = STARTED.lock().unwrap().take() else {
+            return;
+        };
+        let capture = settings.capture;
+        let (_, _, stop) = capture.requests();
+        match ObsConnection::open(&settings).and_then(|mut obs| obs.request(stop)) {
+            Ok(_) => log_line!("[OBS] Stopped {}", capture.label()),
+            Err(e) => log_line!("[OBS] Could not stop {}: {}", capture.label(), e),
+        }
+    }
+
+    fn output_active(status: &Value) -> bool {
+        status.get("outputActive").and_then(Value::as_bool).unwrap_or(false)
+    }
+}
+
+/// Short-lived, identified obs-websocket connection
+struct ObsConnection {
+    socket: WebSocket<TcpStream>,
+    next_id: u32,
+}
+
+impl ObsConnection {
+    fn open(settings: &ObsSettings) -> Result<Self, String> {
+        let address = (settings.host.as_str(), settings.port)
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("could not resolve {}", settings.host))?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)
+            .map_err(|e| format!("OBS not reachable on {} ({})", address, e))?;
+        let _ = stream.set_read_timeout(Some(TIMEOUT));
+        let _ = stream.set_write_timeout(Some(TIMEOUT));
+
+        let url = format!("ws://{}:{}", settings.host, settings.port);
+        let (socket, _) = tungstenite::client(url.as_str(), stream).map_err(|e| e.to_string())?;
+        let mut connection = Self { socket, next_id: 0 };
+
+        let hello = connection.receive(OP_HELLO)?;
+        let mut identify = json!({ "rpcVersion": RPC_VERSION, "eventSubscriptions": 0 });
+        if let Some(auth) = hello.get("authentication") {
+            let salt = auth.get("salt").and_then(Value::as_str).unwrap_or_default();
+            let challenge = auth.get("challenge").and_then(Value::as_str).unwrap_or_default();
+            identify["authentication"] = json!(Self::auth_string(&settings.password, salt, challenge));
+        }
+        connection.send(OP_IDENTIFY, identify)?;
+        connection.receive(OP_IDENTIFIED)?;
+        Ok(connection)
+    }
+
+    /// base64(sha256(base64(sha256(password + salt)) + challenge))
+    fn auth_string(password: &str, salt: &str, challenge: &str) -> String {
+        let secret = BASE64.encode(Sha256::digest(format!("{}{}", password, salt)));
+        BASE64.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+    }
+
+    /// Send a request and wait for its response; returns responseData (Null if none)
+    fn request(&mut self, request_type: &str) -> Result<Value, String> {
+        self.next_id += 1;
+        let id = self.next_id.to_string();
+        self.send(OP_REQUEST, json!({ "requestType": request_type, "requestId": id }))?;
+
+        loop {
+            let response = self.receive(OP_REQUEST_RESPONSE)?;
+            if response.get("requestId").and_then(Value::as_str) != Some(id.as_str()) {
+                continue;
+            }
+            let status = &response["requestStatus"];
+            if status.get("result").and_then(Value::as_bool) != Some(true) {
+                let comment = status.get("comment").and_then(Value::as_str).unwrap_or("no details");
+                return Err(format!("{} failed: {}", request_type, comment));
+            }
+            return Ok(response.get("responseData").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn send(&mut self, op: u64, data: Value) -> Result<(), String> {
+        self.socket
+            .send(Message::text(json!({ "op": op, "d": data }).to_string()))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Next message with opcode `op` (events and other traffic are skipped)
+    fn receive(&mut self, op: u64) -> Result<Value, String> {
+        loop {
+            match self.socket.read().map_err(|e| e.to_string())? {
+                Message::Text(text) => {
+                    let Ok(mut message) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    if message.get("op").and_then(Value::as_u64) == Some(op) {
+                        return Ok(message["d"].take());
+                    }
+                }
+                // Wrong password closes with 4009 "Authentication failed."
+                Message::Close(frame) => {
+                    return Err(frame.map_or("connection closed".to_string(), |f| f.reason.to_string()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for ObsConnection {
+    fn drop(&mut self) {
+        let _ = self.socket.close(None);
+        let _ = self.socket.flush();
+    }
+}
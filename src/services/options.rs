@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use crate::services::hooks::ScriptHook;
+use crate::services::network::IsolationExceptions;
 
 /// GameModeOptions - 1:1 Port of GameModeOptions.cs
 /// Options passed to enable/disable game mode
@@ -27,20 +29,89 @@ pub struct GameModeOptions {
     #[serde(rename = "SuspendLaunchers")]
     pub suspend_launchers: bool,
     
+    /// Firewall-block browsers/launchers instead of killing them
+    #[serde(default)]
+    pub firewall_block_background: bool,
+    
     /// Whether to enable network isolation (C#: IsolateNetwork)
     #[serde(rename = "IsolateNetwork")]
     pub isolate_network: bool,
+    
+    /// What isolation keeps, from the active profile
+    #[serde(default)]
+    pub isolation_exceptions: IsolationExceptions,
+    
+    /// Look for a fullscreen game to focus/monitor (false for non-game profiles)
+    #[serde(default)]
+    pub detect_game: bool,
+    
+    /// Stop the optimization service set
+    #[serde(default)]
+    pub stop_services: bool,
+    
+    /// Close bloatware, peripheral suites, browsers and launchers
+    #[serde(default)]
+    pub kill_background_apps: bool,
+    
+    /// Apply the high performance plan / laptop boost
+    #[serde(default)]
+    pub apply_power_plan: bool,
+    
+    /// Kill launchers/peripheral apps together with their child processes
+    #[serde(default)]
+    pub kill_process_trees: bool,
+    
+    /// Flush background working sets at start (the game and critical processes are excluded)
+    #[serde(default)]
+    pub flush_memory: bool,
+    #[serde(default)]
+    pub flush_exclusions: Vec<String>,
+    /// Workload already known when the session starts (--launch PID, running trigger process),
+    /// spared by the flush like a detected game
+    #[serde(skip)]
+    pub workload_pid: Option<u32>,
+    
+    /// Disable MPO only for the session, and only if the game's display uses overlay planes
+    #[serde(default)]
+    pub mpo_game_display_only: bool,
+    
+    /// Win32PrioritySeparation chosen by the active profile (None = registry::DEFAULT_PRIORITY_SEPARATION)
+    #[serde(default)]
+    pub priority_separation: Option<u32>,
+    
+    /// User hooks run at the end of enable / disable
+    #[serde(default)]
+    pub on_enable_hooks: Vec<ScriptHook>,
+    #[serde(default)]
+    pub on_disable_hooks: Vec<ScriptHook>,
 }
 
 impl GameModeOptions {
-    /// Create GameModeOptions from AppSettings
-    #[allow(dead_code)]
+    /// Create GameModeOptions from AppSettings, narrowed by the session's workload profile
     pub fn from_settings(settings: &crate::services::settings::AppSettings) -> Self {
+        let profile = crate::services::profiles::find(&settings.profiles, &settings.session_profile());
+        let detect_game = profile.map_or(true, |p| p.detect_fullscreen);
+        
         Self {
-            suspend_explorer: settings.suspend_explorer,
+            // Explorer/MPO handling is tied to a fullscreen game
+            suspend_explorer: settings.suspend_explorer && detect_game,
             suspend_browsers: settings.suspend_browsers,
             suspend_launchers: settings.suspend_launchers,
+            firewall_block_background: settings.firewall_block_background,
             isolate_network: settings.isolate_network,
+            isolation_exceptions: profile.map(|p| p.isolation_exceptions.clone()).unwrap_or_default(),
+            mpo_game_display_only: settings.disable_mpo && settings.mpo_game_display_only && detect_game,
+            detect_game,
+            stop_services: profile.map_or(true, |p| p.stop_services),
+            kill_background_apps: profile.map_or(true, |p| p.kill_background_apps),
+            apply_power_plan: profile.map_or(true, |p| p.apply_power_plan),
+            kill_process_trees: settings.advanced_modules.kill_process_trees,
+            flush_memory: settings.advanced_modules.flush_working_sets,
+            flush_exclusions: settings.advanced_modules.flush_exclusions.clone(),
+            workload_pid: None,
+            priority_separation: profile.and_then(|p| p.priority_separation),
+            on_enable_hooks: settings.on_enable_hooks.clone(),
+            on_disable_hooks: settings.on_disable_hooks.clone(),
         }
     }
 }
@@ -1,11 +1,19 @@
 use windows::Win32::System::Power::{
     PowerSetActiveScheme, PowerGetActiveScheme, PowerWriteACValueIndex, PowerReadACValueIndex,
+    PowerWriteDCValueIndex, PowerReadDCValueIndex, GetSystemPowerStatus, SYSTEM_POWER_STATUS,
 };
 use windows::Win32::Foundation::{LocalFree, HLOCAL};
 use windows::core::GUID;
 use std::ptr;
+use std::sync::Arc;
+use std::fs;
+use std::path::PathBuf;
 use std::process::Command;
 use std::os::windows::process::CommandExt;
+use serde::{Deserialize, Serialize};
+use crate::services::settings;
+use crate::services::system::{SystemBackend, Win32};
+use crate::log_line;
 
 // ============================================================================
 // GUIDs from PowerService.cs
@@ -19,7 +27,7 @@ const GUID_ULTIMATE_PERFORMANCE: GUID = GUID::from_u128(0xe9a42b02_d5df_448d_aa0
 
 // 54533251-82be-4824-96c1-47b60b740d00 (Processor Subgroup)
 // C#: private static Guid PROCESSOR_SUBGROUP = new Guid("54533251-82be-4824-96c1-47b60b740d00");
-const GUID_PROCESSOR_SUBGROUP: GUID = GUID::from_u128(0x54533251_82be_4824_96c1_47b60b740d00);
+pub const PROCESSOR_SUBGROUP: GUID = GUID::from_u128(0x54533251_82be_4824_96c1_47b60b740d00);
 
 // be337238-0d82-4146-a960-4f3749d470c7 (Perf Boost Mode)
 // C#: private static Guid PERF_BOOST_MODE = new Guid("be337238-0d82-4146-a960-4f3749d470c7");
@@ -29,9 +37,45 @@ const GUID_PROCESSOR_PERF_BOOST_MODE: GUID = GUID::from_u128(0xbe337238_0d82_414
 // C#: private static Guid MIN_PROCESSOR_STATE = new Guid("893dee8e-2bef-41e0-89c6-b55d0929964c");
 const GUID_PROCESSOR_THROTTLE_MINIMUM: GUID = GUID::from_u128(0x893dee8e_2bef_41e0_89c6_b55d0929964c);
 
+/// Original power state, persisted in the session journal (GUIDs as 32 hex digits)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PowerOriginals {
+    pub scheme: Option<String>,
+    pub boost_mode: Option<u32>,
+    pub min_processor: Option<u32>,
+    pub laptop_scheme: Option<String>,
+    #[serde(default)]
+    pub dc_boost_mode: Option<u32>,
+}
+
+/// Power originals of a session in progress, kept in the settings folder so a crash or
+/// power loss can't leave the user on Ultimate Performance / aggressive boost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerMarker {
+    pub app_pid: u32,
+    pub originals: PowerOriginals,
+}
+
+/// power_restore.json in settings::data_dir()
+fn marker_path() -> PathBuf {
+    settings::data_dir().join("power_restore.json")
+}
+
+pub fn guid_to_hex(guid: &GUID) -> String {
+    format!("{:032x}", guid.to_u128())
+}
+
+pub fn guid_from_hex(hex: &str) -> Option<GUID> {
+    u128::from_str_radix(hex, 16).ok().map(GUID::from_u128)
+}
+
 /// PowerService - 1:1 port of PowerService.cs
 /// Handles power plan switching for both desktop and laptop scenarios
 pub struct PowerService {
+    // Power plan access (Win32, or an in-memory fake)
+    system: Arc<dyn SystemBackend>,
+    // power_restore.json is only kept for the live machine
+    persist_marker: bool,
     // Original power scheme GUID to restore later (1:1 with C# _originalScheme)
     original_scheme: Option<GUID>,
     // For laptop: original boost mode value (1:1 with C# _originalBoostMode)
@@ -40,27 +84,31 @@ pub struct PowerService {
     original_min_processor: Option<u32>,
     // For laptop: the active scheme when we modified it
     laptop_active_scheme: Option<GUID>,
+    // For laptop: original on-battery (DC) boost mode
+    original_dc_boost_mode: Option<u32>,
 }
 
 impl PowerService {
     pub fn new() -> Self {
+        Self {
+            persist_marker: true,
+            ..Self::with_system(Arc::new(Win32))
+        }
+    }
+
+    /// Service working against `system` instead of the live machine (no restore marker)
+    pub fn with_system(system: Arc<dyn SystemBackend>) -> Self {
         // Get and store current active scheme at startup
-        let original_scheme = unsafe {
-            let mut scheme_ptr = ptr::null_mut();
-            if PowerGetActiveScheme(None, &mut scheme_ptr).is_ok() && !scheme_ptr.is_null() {
-                let scheme = *scheme_ptr;
-                let _ = LocalFree(HLOCAL(scheme_ptr as *mut _));
-                Some(scheme)
-            } else {
-                None
-            }
-        };
+        let original_scheme = system.active_scheme();
 
         Self {
+            system,
+            persist_marker: false,
             original_scheme,
             original_boost_mode: None,
             original_min_processor: None,
             laptop_active_scheme: None,
+            original_dc_boost_mode: None,
         }
     }
 
@@ -68,152 +116,259 @@ impl PowerService {
     /// Used for DESKTOP systems
     /// Logic: Try Ultimate Performance, if not found duplicate High Performance, else use High Performance
     pub fn set_high_performance(&mut self) {
-        unsafe {
-            // Store original scheme for revert
-            let mut scheme_ptr = ptr::null_mut();
-            if PowerGetActiveScheme(None, &mut scheme_ptr).is_ok() && !scheme_ptr.is_null() {
-                self.original_scheme = Some(*scheme_ptr);
-                let _ = LocalFree(HLOCAL(scheme_ptr as *mut _));
-            }
+        // Store original scheme for revert
+        if let Some(scheme) = self.system.active_scheme() {
+            self.original_scheme = Some(scheme);
+        }
+        self.save_marker();
 
-            // Check if Ultimate Performance exists using powercfg
-            // C#: this.PowerPlanExists(GUID_ULTIMATE_PERFORMANCE)
-            let ultimate_exists = self.power_plan_exists(&GUID_ULTIMATE_PERFORMANCE);
-            
-            if ultimate_exists {
-                // Activate Ultimate Performance
-                if PowerSetActiveScheme(None, Some(&GUID_ULTIMATE_PERFORMANCE)).is_err() {
-                    // Fall back to High Performance
-                    let _ = PowerSetActiveScheme(None, Some(&GUID_HIGH_PERFORMANCE));
-                }
-            } else {
-                // C#: Try to duplicate the scheme to create it
-                // this.DuplicatePowerScheme(GUID_ULTIMATE_PERFORMANCE);
-                self.duplicate_power_scheme(&GUID_ULTIMATE_PERFORMANCE);
-                
-                // Check again
-                let ultimate_exists_now = self.power_plan_exists(&GUID_ULTIMATE_PERFORMANCE);
-                
-                if ultimate_exists_now {
-                    if PowerSetActiveScheme(None, Some(&GUID_ULTIMATE_PERFORMANCE)).is_err() {
-                        let _ = PowerSetActiveScheme(None, Some(&GUID_HIGH_PERFORMANCE));
-                    }
-                } else {
-                    // Fall back to High Performance
-                    let _ = PowerSetActiveScheme(None, Some(&GUID_HIGH_PERFORMANCE));
-                }
-            }
+        // C#: this.PowerPlanExists(GUID_ULTIMATE_PERFORMANCE), else DuplicatePowerScheme and check again
+        if !self.system.scheme_exists(&GUID_ULTIMATE_PERFORMANCE) {
+            self.system.duplicate_scheme(&GUID_ULTIMATE_PERFORMANCE);
+        }
+        let ultimate_active = self.system.scheme_exists(&GUID_ULTIMATE_PERFORMANCE)
+            && self.system.set_active_scheme(&GUID_ULTIMATE_PERFORMANCE);
+
+        if !ultimate_active {
+            // Fall back to High Performance
+            self.system.set_active_scheme(&GUID_HIGH_PERFORMANCE);
         }
     }
 
-    /// 1:1 port of OptimizeLaptopBoost() from PowerService.cs
-    /// Used for LAPTOP systems
-    /// Modifies current scheme's processor boost mode and min processor state
-    pub fn optimize_laptop_boost(&mut self) {
+    /// GUID of the currently active power scheme
+    pub fn active_scheme() -> Option<GUID> {
         unsafe {
-            // Get current active scheme
             let mut scheme_ptr = ptr::null_mut();
             if PowerGetActiveScheme(None, &mut scheme_ptr).is_err() || scheme_ptr.is_null() {
-                return;
+                return None;
             }
-            let active_scheme = *scheme_ptr;
-            self.laptop_active_scheme = Some(active_scheme);
+            let scheme = *scheme_ptr;
             let _ = LocalFree(HLOCAL(scheme_ptr as *mut _));
+            Some(scheme)
+        }
+    }
 
-            // Read and store original boost mode value
-            // C#: PowerReadACValueIndex(IntPtr.Zero, ref scheme, ref PROCESSOR_SUBGROUP, ref PERF_BOOST_MODE, out originalBoost);
-            let mut current_boost: u32 = 0;
-            if PowerReadACValueIndex(
+    /// AC value index of a processor (sub_processor) setting on a scheme
+    pub fn read_processor_ac_index(scheme: &GUID, setting: &GUID) -> Option<u32> {
+        let mut value: u32 = 0;
+        unsafe {
+            PowerReadACValueIndex(
                 None,
-                Some(&active_scheme as *const GUID),
-                Some(&GUID_PROCESSOR_SUBGROUP),
-                Some(&GUID_PROCESSOR_PERF_BOOST_MODE),
-                &mut current_boost
-            ).is_ok() {
-                self.original_boost_mode = Some(current_boost);
-            }
+                Some(scheme as *const GUID),
+                Some(&PROCESSOR_SUBGROUP),
+                Some(setting as *const GUID),
+                &mut value
+            ).is_ok().then_some(value)
+        }
+    }
 
-            // Set boost mode to 4 (Aggressive)
-            // C#: PowerWriteACValueIndex(IntPtr.Zero, ref scheme, ref PROCESSOR_SUBGROUP, ref PERF_BOOST_MODE, 4);
-            let _ = PowerWriteACValueIndex(
+    /// Write the AC value index of a processor setting; false on failure (e.g. setting hidden/unsupported)
+    pub fn write_processor_ac_index(scheme: &GUID, setting: &GUID, value: u32) -> bool {
+        unsafe {
+            PowerWriteACValueIndex(
                 None,
-                &active_scheme,
-                Some(&GUID_PROCESSOR_SUBGROUP),
-                Some(&GUID_PROCESSOR_PERF_BOOST_MODE),
-                4 // Aggressive
-            );
+                scheme,
+                Some(&PROCESSOR_SUBGROUP),
+                Some(setting as *const GUID),
+                value
+            ).is_ok()
+        }
+    }
 
-            // Read and store original min processor state
-            let mut current_min: u32 = 0;
-            if PowerReadACValueIndex(
+    /// (AC, DC) value indices of any subgroup setting on a scheme
+    pub fn read_value_indices(scheme: &GUID, subgroup: &GUID, setting: &GUID) -> Option<(u32, u32)> {
+        let mut ac: u32 = 0;
+        let mut dc: u32 = 0;
+        unsafe {
+            let ac_ok = PowerReadACValueIndex(
                 None,
-                Some(&active_scheme as *const GUID),
-                Some(&GUID_PROCESSOR_SUBGROUP),
-                Some(&GUID_PROCESSOR_THROTTLE_MINIMUM),
-                &mut current_min
-            ).is_ok() {
-                self.original_min_processor = Some(current_min);
-            }
+                Some(scheme as *const GUID),
+                Some(subgroup as *const GUID),
+                Some(setting as *const GUID),
+                &mut ac
+            ).is_ok();
+            let dc_ok = PowerReadDCValueIndex(
+                None,
+                Some(scheme as *const GUID),
+                Some(subgroup as *const GUID),
+                Some(setting as *const GUID),
+                &mut dc
+            ).is_ok();
+            (ac_ok && dc_ok).then_some((ac, dc))
+        }
+    }
 
-            // Set min processor state to 100%
-            // C#: PowerWriteACValueIndex(IntPtr.Zero, ref scheme, ref PROCESSOR_SUBGROUP, ref MIN_PROCESSOR_STATE, 100);
-            let _ = PowerWriteACValueIndex(
+    /// Write the AC and DC value indices of a subgroup setting; false if either write fails
+    pub fn write_value_indices(scheme: &GUID, subgroup: &GUID, setting: &GUID, ac: u32, dc: u32) -> bool {
+        unsafe {
+            let ac_ok = PowerWriteACValueIndex(
                 None,
-                &active_scheme,
-                Some(&GUID_PROCESSOR_SUBGROUP),
-                Some(&GUID_PROCESSOR_THROTTLE_MINIMUM),
-                100
-            );
+                scheme,
+                Some(subgroup as *const GUID),
+                Some(setting as *const GUID),
+                ac
+            ).is_ok();
+            let dc_ok = PowerWriteDCValueIndex(
+                None,
+                scheme,
+                Some(subgroup as *const GUID),
+                Some(setting as *const GUID),
+                dc
+            ).is_ok();
+            ac_ok && dc_ok
+        }
+    }
+
+    /// Activate a scheme (also re-applies its indices if it is already active)
+    pub fn set_active_scheme(scheme: &GUID) -> bool {
+        unsafe { PowerSetActiveScheme(None, Some(scheme as *const GUID)).is_ok() }
+    }
+
+    /// True for the High / Ultimate Performance schemes a desktop session switches to
+    pub fn is_performance_scheme(scheme: &GUID) -> bool {
+        *scheme == GUID_HIGH_PERFORMANCE || *scheme == GUID_ULTIMATE_PERFORMANCE
+    }
+
+    /// True if the system is running on battery (AC line offline)
+    pub fn on_battery() -> bool {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status).is_ok() && status.ACLineStatus == 0 }
+    }
+
+    /// 1:1 port of OptimizeLaptopBoost() from PowerService.cs
+    /// Used for LAPTOP systems
+    /// Modifies current scheme's processor boost mode and min processor state
+    /// Plugged in (AC): aggressive boost + 100% min state. On battery (DC): aggressive boost only,
+    /// so unplugging mid-session falls back to the battery-friendly variant automatically
+    pub fn optimize_laptop_boost(&mut self) {
+        // Get current active scheme
+        let Some(active_scheme) = self.system.active_scheme() else {
+            return;
+        };
+        self.laptop_active_scheme = Some(active_scheme);
+
+        // Read and store the originals (C#: PowerReadACValueIndex ... PERF_BOOST_MODE / MIN_PROCESSOR_STATE)
+        self.original_boost_mode = self.system.read_processor_ac_index(&active_scheme, &GUID_PROCESSOR_PERF_BOOST_MODE);
+        self.original_min_processor = self.system.read_processor_ac_index(&active_scheme, &GUID_PROCESSOR_THROTTLE_MINIMUM);
+        self.original_dc_boost_mode = self.system
+            .read_value_indices(&active_scheme, &PROCESSOR_SUBGROUP, &GUID_PROCESSOR_PERF_BOOST_MODE)
+            .map(|(_, dc)| dc);
+
+        // Boost mode 4 (Aggressive) on AC and DC, min processor state 100% on AC only:
+        // the battery-friendly variant leaves the DC min processor state alone
+        self.system.write_value_indices(&active_scheme, &PROCESSOR_SUBGROUP, &GUID_PROCESSOR_PERF_BOOST_MODE, 4, 4);
+        self.system.write_processor_ac_index(&active_scheme, &GUID_PROCESSOR_THROTTLE_MINIMUM, 100);
+
+        // Re-apply scheme to take effect
+        // C#: PowerSetActiveScheme(IntPtr.Zero, ref scheme);
+        self.system.set_active_scheme(&active_scheme);
+        self.save_marker();
+
+        if Self::on_battery() {
+            log_line!("[Power] On battery: boost raised, min processor state kept (applies at 100% once plugged in)");
+        }
+    }
+
+    /// Originals captured so far, for the session journal
+    pub fn originals(&self) -> PowerOriginals {
+        PowerOriginals {
+            scheme: self.original_scheme.as_ref().map(guid_to_hex),
+            boost_mode: self.original_boost_mode,
+            min_processor: self.original_min_processor,
+            laptop_scheme: self.laptop_active_scheme.as_ref().map(guid_to_hex),
+            dc_boost_mode: self.original_dc_boost_mode,
+        }
+    }
 
-            // Re-apply scheme to take effect
-            // C#: PowerSetActiveScheme(IntPtr.Zero, ref scheme);
-            let _ = PowerSetActiveScheme(None, Some(&active_scheme));
+    /// Service that reverts to journaled originals (crash recovery)
+    pub fn from_originals(system: Arc<dyn SystemBackend>, originals: &PowerOriginals) -> Self {
+        Self {
+            system,
+            persist_marker: false,
+            original_scheme: originals.scheme.as_deref().and_then(guid_from_hex),
+            original_boost_mode: originals.boost_mode,
+            original_min_processor: originals.min_processor,
+            laptop_active_scheme: originals.laptop_scheme.as_deref().and_then(guid_from_hex),
+            original_dc_boost_mode: originals.dc_boost_mode,
         }
     }
 
     /// 1:1 port of RevertPowerPlan() from PowerService.cs
     /// Used for DESKTOP systems to restore original power plan
     pub fn revert_power_plan(&self) {
-        unsafe {
-            if let Some(original) = self.original_scheme {
-                let _ = PowerSetActiveScheme(None, Some(&original));
-            }
+        if let Some(original) = self.original_scheme {
+            self.system.set_active_scheme(&original);
+        }
+        if self.persist_marker {
+            Self::clear_marker();
         }
     }
 
     /// 1:1 port of RevertLaptopBoost() from PowerService.cs
     /// Used for LAPTOP systems to restore original boost mode and min processor state
     pub fn revert_laptop_boost(&self) {
-        unsafe {
-            if let Some(scheme) = self.laptop_active_scheme {
-                // Restore original boost mode
-                if let Some(original_boost) = self.original_boost_mode {
-                    let _ = PowerWriteACValueIndex(
-                        None,
-                        &scheme,
-                        Some(&GUID_PROCESSOR_SUBGROUP),
-                        Some(&GUID_PROCESSOR_PERF_BOOST_MODE),
-                        original_boost
-                    );
+        if let Some(scheme) = self.laptop_active_scheme {
+            // Restore original boost mode (AC and DC)
+            match (self.original_boost_mode, self.original_dc_boost_mode) {
+                (Some(ac), Some(dc)) => {
+                    self.system.write_value_indices(&scheme, &PROCESSOR_SUBGROUP, &GUID_PROCESSOR_PERF_BOOST_MODE, ac, dc);
                 }
-
-                // Restore original min processor state
-                if let Some(original_min) = self.original_min_processor {
-                    let _ = PowerWriteACValueIndex(
-                        None,
-                        &scheme,
-                        Some(&GUID_PROCESSOR_SUBGROUP),
-                        Some(&GUID_PROCESSOR_THROTTLE_MINIMUM),
-                        original_min
-                    );
+                (Some(ac), None) => {
+                    self.system.write_processor_ac_index(&scheme, &GUID_PROCESSOR_PERF_BOOST_MODE, ac);
+                }
+                (None, Some(dc)) => {
+                    let ac = self.system.read_processor_ac_index(&scheme, &GUID_PROCESSOR_PERF_BOOST_MODE).unwrap_or(dc);
+                    self.system.write_value_indices(&scheme, &PROCESSOR_SUBGROUP, &GUID_PROCESSOR_PERF_BOOST_MODE, ac, dc);
                 }
+                (None, None) => {}
+            }
 
-                // Re-apply to take effect
-                let _ = PowerSetActiveScheme(None, Some(&scheme));
+            // Restore original min processor state
+            if let Some(original_min) = self.original_min_processor {
+                self.system.write_processor_ac_index(&scheme, &GUID_PROCESSOR_THROTTLE_MINIMUM, original_min);
             }
+
+            // Re-apply to take effect
+            self.system.set_active_scheme(&scheme);
+        }
+        if self.persist_marker {
+            Self::clear_marker();
+        }
+    }
+
+    fn save_marker(&self) {
+        if !self.persist_marker {
+            return;
+        }
+        let marker = PowerMarker { app_pid: std::process::id(), originals: self.originals() };
+        if let Ok(content) = serde_json::to_string_pretty(&marker) {
+            let _ = fs::write(marker_path(), content);
         }
     }
 
+    /// Remove power_restore.json (the session's power changes are reverted)
+    pub fn clear_marker() {
+        let _ = fs::remove_file(marker_path());
+    }
+
+    /// Marker left by a session that never reverted its power changes
+    pub fn stale_marker() -> Option<PowerMarker> {
+        let content = fs::read_to_string(marker_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Put the scheme / laptop boost values from a stale marker back, then remove it
+    pub fn restore_marker(marker: &PowerMarker) {
+        let power = Self::from_originals(Arc::new(Win32), &marker.originals);
+        if power.laptop_active_scheme.is_some() {
+            power.revert_laptop_boost();
+        } else {
+            power.revert_power_plan();
+        }
+        Self::clear_marker();
+        log_line!("[Power] Restored power settings left by PID {}", marker.app_pid);
+    }
+
     /// Generic revert that calls the appropriate method based on system type
     /// (Kept for backwards compatibility)
     #[allow(dead_code)]
@@ -223,9 +378,24 @@ impl PowerService {
         self.revert_power_plan();
     }
 
+    /// Name of the active power plan, from `powercfg /getactivescheme`
+    /// Output: "Power Scheme GUID: <guid>  (Balanced)"
+    pub fn active_scheme_name() -> Option<String> {
+        let output = Command::new("powercfg")
+            .args(["/getactivescheme"])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output()
+            .ok()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let start = stdout.find('(')?;
+        let end = stdout.rfind(')')?;
+        (end > start).then(|| stdout[start + 1..end].trim().to_string())
+    }
+
     /// 1:1 port of PowerPlanExists() from PowerService.cs
     /// Checks if a power plan GUID exists using powercfg /list
-    fn power_plan_exists(&self, guid: &GUID) -> bool {
+    pub fn power_plan_exists(guid: &GUID) -> bool {
         let output = Command::new("powercfg")
             .args(["/list"])
             .creation_flags(0x08000000) // CREATE_NO_WINDOW
@@ -245,7 +415,7 @@ impl PowerService {
 
     /// 1:1 port of DuplicatePowerScheme() from PowerService.cs
     /// Duplicates a power scheme using powercfg -duplicatescheme
-    fn duplicate_power_scheme(&self, guid: &GUID) {
+    pub fn duplicate_power_scheme(guid: &GUID) {
         let guid_str = format!("{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
             guid.data1, guid.data2, guid.data3,
             guid.data4[0], guid.data4[1],
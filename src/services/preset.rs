@@ -0,0 +1,238 @@
+//! Settings Presets
+//! The full AppSettings (advanced modules, profiles, process lists, game flags) in a single
+//! JSON file with a version header, to share tuned configurations or move them to another PC.
+//! Curated community presets are listed in the project repo with a SHA-256 per file. The hash
+//! only proves the download matches the list (integrity), it is not a signature.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use windows::Win32::UI::Controls::Dialogs::{
+    GetOpenFileNameW, GetSaveFileNameW, OPENFILENAMEW, OFN_FILEMUSTEXIST, OFN_NOCHANGEDIR,
+    OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST,
+};
+use windows::core::{PCWSTR, PWSTR};
+use crate::services::settings::{AppSettings, SettingsService};
+use crate::services::update::UpdateService;
+use crate::log_line;
+
+/// Preset file format; bump when the header or layout changes incompatibly
+pub const PRESET_FORMAT: u32 = 1;
+
+/// Curated preset list in the project repo
+const COMMUNITY_INDEX_URL: &str = "https://raw.githubusercontent.com/xillyservices-code/GameMode/main/presets/index.json";
+
+/// Name / pattern pairs for the file dialogs, double-NUL terminated
+const FILTER: &str = "Game Mode preset (*.json)\0*.json\0All files\0*.*\0\0";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Preset {
+    /// Format of this file (PRESET_FORMAT when written)
+    format: u32,
+    /// App version that exported it, informational
+    #[serde(default)]
+    app_version: String,
+    /// AppSettings, read through the settings migrations so older presets still import
+    settings: serde_json::Value,
+}
+
+/// Entry of the community preset index
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommunityPreset {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub description: String,
+    /// Preset file, in the same format as "Export Preset"
+    pub url: String,
+    /// Hex SHA-256 of the file at `url` (integrity check against the index, not a signature)
+    pub sha256: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommunityIndex {
+    presets: Vec<CommunityPreset>,
+}
+
+pub struct PresetService;
+
+impl PresetService {
+    /// Write `settings` as a preset file
+    pub fn export(settings: &AppSettings, path: &Path) -> Result<(), String> {
+        // Presets get shared: the OBS WebSocket password never leaves this PC
+        let mut settings = settings.clone();
+        settings.obs.password.clear();
+        let preset = Preset {
+            format: PRESET_FORMAT,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            settings: serde_json::to_value(&settings).map_err(|e| e.to_string())?,
+        };
+        let content = serde_json::to_string_pretty(&preset).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        log_line!("[Preset] Exported settings to {}", path.display());
+        Ok(())
+    }
+
+    /// Settings from a preset file; `current` supplies the machine-specific values a preset doesn't carry
+    pub fn import(path: &Path, current: &AppSettings) -> Result<AppSettings, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let settings = Self::parse(&content, current)?;
+        log_line!("[Preset] Imported settings from {}", path.display());
+        Ok(settings)
+    }
+
+    /// Presets listed in the project repo
+    pub fn community_index() -> Result<Vec<CommunityPreset>, String> {
+        let index: CommunityIndex = UpdateService::agent()
+            .get(COMMUNITY_INDEX_URL)
+            .call()
+            .map_err(|e| format!("Could not load the preset list: {}", e))?
+            .into_json()
+            .map_err(|e| format!("Preset list is not valid: {}", e))?;
+        Ok(index.presets)
+    }
+
+    /// Download a community preset, check it against the index hash and read it like an import
+    /// (which drops its hooks, see `parse`);
+    /// the user's own profiles are kept, the preset's are added (or replace same-named ones)
+    pub fn fetch_community(preset: &CommunityPreset, current: &AppSettings) -> Result<AppSettings, String> {
+        let bytes = UpdateService::download_file(&preset.url).map_err(|e| format!("Download failed: {}", e))?;
+        let hash: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+        if !hash.eq_ignore_ascii_case(preset.sha256.trim()) {
+            log_line!("[Preset] Hash mismatch for community preset '{}' (got {})", preset.name, hash);
+            return Err("The downloaded file does not match the published SHA-256, it was not imported.".to_string());
+        }
+        let content = String::from_utf8(bytes).map_err(|_| "Not a valid preset file".to_string())?;
+        let mut settings = Self::parse(&content, current)?;
+
+        let mut profiles = current.profiles.clone();
+        for profile in settings.profiles {
+            match profiles.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&profile.name)) {
+                Some(existing) => *existing = profile,
+                None => profiles.push(profile),
+            }
+        }
+        settings.profiles = profiles;
+        log_line!("[Preset] Community preset '{}' matches its published SHA-256", preset.name);
+        Ok(settings)
+    }
+
+    /// Dry run: every setting `imported` would change, as "path: old -> new"
+    pub fn changes(current: &AppSettings, imported: &AppSettings) -> Vec<String> {
+        let (mut before, mut after) = (BTreeMap::new(), BTreeMap::new());
+        Self::flatten("", &serde_json::to_value(current).unwrap_or_default(), &mut before);
+        Self::flatten("", &serde_json::to_value(imported).unwrap_or_default(), &mut after);
+
+        let mut changes = Vec::new();
+        for (path, new) in &after {
+            match before.get(path) {
+                Some(old) if old == new => {}
+                Some(old) => changes.push(format!("{}: {} -> {}", path, old, new)),
+                None => changes.push(format!("{}: (new) {}", path, new)),
+            }
+        }
+        for path in before.keys().filter(|p| !after.contains_key(*p)) {
+            changes.push(format!("{}: removed", path));
+        }
+        changes
+    }
+
+    /// Leaf values by dotted path; lists of named entries (profiles) are keyed by name
+    fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+        let join = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    Self::flatten(&join(key), child, out);
+                }
+            }
+            Value::Array(items) if !items.is_empty() && items.iter().all(|i| i.get("name").is_some_and(Value::is_string)) => {
+                for item in items {
+                    let name = item["name"].as_str().unwrap_or_default();
+                    Self::flatten(&format!("{}[{}]", prefix, name), item, out);
+                }
+            }
+            _ => {
+                out.insert(prefix.to_string(), value.to_string());
+            }
+        }
+    }
+
+    /// Preset file content to settings (format check, settings migrations, local values kept)
+    fn parse(content: &str, current: &AppSettings) -> Result<AppSettings, String> {
+        let preset: Preset = serde_json::from_str(content)
+            .map_err(|e| format!("Not a valid preset file: {}", e))?;
+        if preset.format > PRESET_FORMAT {
+            return Err(format!(
+                "This preset was made by a newer version ({}). Update the app to import it.",
+                if preset.app_version.is_empty() { "unknown" } else { preset.app_version.as_str() }
+            ));
+        }
+
+        let (mut settings, salvaged) = SettingsService::from_value(preset.settings);
+        if salvaged {
+            log_line!("[Preset] Some preset fields could not be read and were left at their defaults");
+        }
+        // Startup registration belongs to this PC, not the shared configuration
+        settings.run_on_startup = current.run_on_startup;
+        settings.startup_method = current.startup_method;
+        // So is the Lock Pages in Memory grant, which only the Advanced Modules switch adds (after asking)
+        settings.advanced_modules.lock_memory_privilege = current.advanced_modules.lock_memory_privilege;
+        // Hooks run arbitrary programs: only ones the user added on this PC are ever run
+        let dropped = settings.on_enable_hooks.len() + settings.on_disable_hooks.len();
+        settings.on_enable_hooks = current.on_enable_hooks.clone();
+        settings.on_disable_hooks = current.on_disable_hooks.clone();
+        if dropped > 0 {
+            log_line!("[Preset] Ignored {} hook(s) from the preset, kept the local ones", dropped);
+        }
+        // The OBS connection points at this PC's OBS, with its password
+        settings.obs = current.obs.clone();
+        log_line!("[Preset] Read preset made by v{}", preset.app_version);
+        Ok(settings)
+    }
+
+    /// Ask where to save a preset; None if cancelled
+    pub fn pick_export_path() -> Option<PathBuf> {
+        Self::file_dialog(true)
+    }
+
+    /// Ask for a preset to import; None if cancelled
+    pub fn pick_import_path() -> Option<PathBuf> {
+        Self::file_dialog(false)
+    }
+
+    fn file_dialog(save: bool) -> Option<PathBuf> {
+        let filter: Vec<u16> = FILTER.encode_utf16().collect();
+        let default_ext: Vec<u16> = "json\0".encode_utf16().collect();
+        let mut file = [0u16; 1024];
+        if save {
+            for (slot, c) in file.iter_mut().zip("XillyGameMode-Preset.json".encode_utf16()) {
+                *slot = c;
+            }
+        }
+
+        let mut ofn = OPENFILENAMEW {
+            lStructSize: std::mem::size_of::<OPENFILENAMEW>() as u32,
+            lpstrFilter: PCWSTR(filter.as_ptr()),
+            lpstrFile: PWSTR(file.as_mut_ptr()),
+            nMaxFile: file.len() as u32,
+            lpstrDefExt: PCWSTR(default_ext.as_ptr()),
+            Flags: OFN_PATHMUSTEXIST | OFN_NOCHANGEDIR
+                | if save { OFN_OVERWRITEPROMPT } else { OFN_FILEMUSTEXIST },
+            ..Default::default()
+        };
+
+        let picked = unsafe {
+            if save { GetSaveFileNameW(&mut ofn) } else { GetOpenFileNameW(&mut ofn) }
+        };
+        if !picked.as_bool() {
+            return None;
+        }
+        let len = file.iter().position(|&c| c == 0).unwrap_or(file.len());
+        Some(PathBuf::from(String::from_utf16_lossy(&file[..len])))
+    }
+}
@@ -1,10 +1,15 @@
-use windows::Win32::System::Threading::{OpenProcess, PROCESS_SUSPEND_RESUME};
-use windows::Win32::Foundation::{HANDLE, CloseHandle};
+use windows::Win32::System::Threading::{
+    GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, TerminateProcess, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SUSPEND_RESUME, PROCESS_TERMINATE,
+};
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{HANDLE, CloseHandle, FILETIME};
 use windows::Win32::System::Diagnostics::ToolHelp::{
     CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32, TH32CS_SNAPPROCESS
 };
 use std::process::Command;
-use std::os::windows::process::CommandExt;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
 
 #[link(name = "ntdll")]
 extern "system" {
@@ -12,20 +17,63 @@ extern "system" {
     fn NtResumeProcess(process_handle: HANDLE) -> i32;
 }
 
+/// A process terminated by `ProcessService::kill_processes`
+#[derive(Debug, Clone)]
+pub struct TerminatedProcess {
+    pub pid: u32,
+    /// Name without .exe
+    pub name: String,
+    pub path: Option<String>,
+}
+
+/// A process suspended by `ProcessService`, identified beyond its PID
+/// PIDs are recycled, so start time + name are checked before resuming
+#[derive(Debug, Clone)]
+pub struct SuspendedProcess {
+    pub pid: u32,
+    /// Creation time (FILETIME), None if it could not be read
+    pub start_time: Option<u64>,
+    /// Name without .exe
+    pub name: String,
+}
+
+// Names (without .exe) no kill may touch, e.g. OBS while the integration is on
+static PROTECTED: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
 pub struct ProcessService;
 
 impl ProcessService {
+    /// Replace the list of processes that kills skip (matched by name, with or without .exe)
+    /// `name` without a trailing ".exe", in any case
+    pub fn strip_exe(name: &str) -> &str {
+        match name.len().checked_sub(4) {
+            Some(split) if name.is_char_boundary(split) && name[split..].eq_ignore_ascii_case(".exe") => &name[..split],
+            _ => name,
+        }
+    }
+
+    pub fn set_protected(names: &[&str]) {
+        *PROTECTED.lock().unwrap() = names.iter()
+            .map(|n| n.strip_suffix(".exe").unwrap_or(n).to_string())
+            .collect();
+    }
+
+    /// True if `name` (without .exe) is on the protected list
+    pub fn is_protected(name: &str) -> bool {
+        PROTECTED.lock().unwrap().iter().any(|p| p.eq_ignore_ascii_case(name))
+    }
+
     /// Suspend processes by name - Optimized single-pass version
-    /// Returns PIDs of suspended processes
+    /// Returns the suspended processes (PID + start time + name)
     #[inline]
-    pub fn suspend_processes(target_names: &[&str]) -> Vec<u32> {
-        let mut suspended_pids = Vec::with_capacity(target_names.len());
+    pub fn suspend_processes(target_names: &[&str]) -> Vec<SuspendedProcess> {
+        let mut suspended = Vec::with_capacity(target_names.len());
         
         unsafe {
             let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else { 
-                return suspended_pids; 
+                return suspended; 
             };
-            if snapshot.is_invalid() { return suspended_pids; }
+            if snapshot.is_invalid() { return suspended; }
 
             let mut entry = PROCESSENTRY32 {
                 dwSize: std::mem::size_of::<PROCESSENTRY32>() as u32,
@@ -39,9 +87,14 @@ impl ProcessService {
                     
                     // Check if this process should be suspended (case-insensitive)
                     if target_names.iter().any(|&t| t.eq_ignore_ascii_case(name)) {
-                        if let Ok(handle) = OpenProcess(PROCESS_SUSPEND_RESUME, false, entry.th32ProcessID) {
+                        let pid = entry.th32ProcessID;
+                        if let Ok(handle) = OpenProcess(PROCESS_SUSPEND_RESUME, false, pid) {
                             NtSuspendProcess(handle);
-                            suspended_pids.push(entry.th32ProcessID);
+                            suspended.push(SuspendedProcess {
+                                pid,
+                                start_time: Self::process_start_time(pid),
+                                name: name.to_string(),
+                            });
                             let _ = CloseHandle(handle);
                         }
                     }
@@ -51,7 +104,7 @@ impl ProcessService {
             }
             let _ = CloseHandle(snapshot);
         }
-        suspended_pids
+        suspended
     }
 
     /// Resume processes by name - Optimized single-pass version
@@ -84,74 +137,268 @@ impl ProcessService {
         }
     }
 
-    /// Resume processes by PID list
-    #[inline]
-    pub fn resume_processes_by_pid(pids: &[u32]) {
+    /// Resume previously suspended processes, skipping any PID that now belongs to another process
+    /// Returns how many records could not be verified (callers fall back to name-based resume)
+    pub fn resume_suspended(processes: &[SuspendedProcess]) -> usize {
+        if processes.is_empty() { return 0; }
+        
+        let running = Self::snapshot_processes();
+        let mut unverified = 0;
+        
         unsafe {
-            for &pid in pids {
-                if let Ok(handle) = OpenProcess(PROCESS_SUSPEND_RESUME, false, pid) {
+            for process in processes {
+                let same_name = running.iter()
+                    .any(|(pid, _, name)| *pid == process.pid && name.eq_ignore_ascii_case(&process.name));
+                let same_start = process.start_time.is_some()
+                    && Self::process_start_time(process.pid) == process.start_time;
+                
+                if !(same_name && same_start) {
+                    unverified += 1;
+                    continue;
+                }
+                
+                if let Ok(handle) = OpenProcess(PROCESS_SUSPEND_RESUME, false, process.pid) {
                     NtResumeProcess(handle);
                     let _ = CloseHandle(handle);
                 }
             }
         }
+        unverified
     }
 
-    /// Kill processes - FAST batch version using single taskkill command
-    /// C# calls taskkill for each process individually twice, but batching is faster
-    #[inline]
-    pub fn kill_processes(target_names: &[&str]) {
-        if target_names.is_empty() { return; }
-        
-        // Build taskkill arguments: /F /IM proc1.exe /IM proc2.exe ...
-        // Capacity: "/F" + ("/IM" + "name.exe") * count
-        let mut args = Vec::with_capacity(1 + target_names.len() * 2);
-        args.push("/F");
+    /// PID of the first running process matching any of the names
+    pub fn find_process(target_names: &[&str]) -> Option<u32> {
+        let current_pid = std::process::id();
+        Self::snapshot_processes()
+            .into_iter()
+            .find(|(pid, _, name)| *pid != current_pid && target_names.iter().any(|&t| t.eq_ignore_ascii_case(name)))
+            .map(|(pid, _, _)| pid)
+    }
+
+    /// Full executable paths of running processes by name (deduplicated)
+    /// Used where a rule must target the binary rather than a PID (e.g. firewall rules)
+    pub fn executable_paths(target_names: &[&str]) -> Vec<String> {
+        let mut paths: Vec<String> = Vec::with_capacity(target_names.len());
         
-        for name in target_names {
-            args.push("/IM");
-            // taskkill needs .exe extension
-            if name.to_lowercase().ends_with(".exe") {
-                args.push(name);
-            } else {
-                // We need to allocate here, but only once per unique name
-                // For static slices, this is acceptable
-                let exe_name = Box::leak(format!("{}.exe", name).into_boxed_str());
-                args.push(exe_name);
+        unsafe {
+            let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else { 
+                return paths; 
+            };
+            if snapshot.is_invalid() { return paths; }
+
+            let mut entry = PROCESSENTRY32 {
+                dwSize: std::mem::size_of::<PROCESSENTRY32>() as u32,
+                ..Default::default()
+            };
+
+            if Process32First(snapshot, &mut entry).is_ok() {
+                loop {
+                    let name = Self::extract_process_name(&entry.szExeFile);
+                    
+                    if target_names.iter().any(|&t| t.eq_ignore_ascii_case(name)) {
+                        if let Some(path) = Self::executable_path(entry.th32ProcessID) {
+                            if !paths.iter().any(|p| p.eq_ignore_ascii_case(&path)) {
+                                paths.push(path);
+                            }
+                        }
+                    }
+
+                    if Process32Next(snapshot, &mut entry).is_err() { break; }
+                }
             }
+            let _ = CloseHandle(snapshot);
+        }
+        paths
+    }
+
+    /// Full executable path of a running process
+    pub fn executable_path(pid: u32) -> Option<String> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buf = [0u16; 1024];
+            let mut len = buf.len() as u32;
+            let result = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+            let _ = CloseHandle(handle);
+            result.ok().map(|_| String::from_utf16_lossy(&buf[..len as usize]))
         }
+    }
+
+    /// Executable file name of a running process (e.g. "game.exe")
+    pub fn executable_name(pid: u32) -> Option<String> {
+        let path = Self::executable_path(pid)?;
+        std::path::Path::new(&path).file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+
+    /// Kill processes by name - native TerminateProcess over a toolhelp snapshot
+    /// Two passes like the old double taskkill, catching children respawned during the first
+    /// Returns what was actually terminated so callers can restore/report it
+    pub fn kill_processes(target_names: &[&str]) -> Vec<TerminatedProcess> {
+        let mut terminated = Vec::with_capacity(target_names.len());
+        if target_names.is_empty() { return terminated; }
         
-        // Fire twice for reliability (matching C# behavior)
-        let _ = Command::new("taskkill")
-            .args(&args)
-            .creation_flags(0x08000000)
-            .spawn();
+        for _ in 0..2 {
+            Self::terminate_matching(target_names, &mut terminated);
+        }
+        terminated
+    }
+
+    fn terminate_matching(target_names: &[&str], terminated: &mut Vec<TerminatedProcess>) {
+        let current_pid = std::process::id();
         
-        let _ = Command::new("taskkill")
-            .args(&args)
-            .creation_flags(0x08000000)
-            .spawn();
+        for (pid, _ppid, name) in Self::snapshot_processes() {
+            // Targets may be given with or without .exe
+            let matches = target_names.iter().any(|&t| Self::strip_exe(t).eq_ignore_ascii_case(&name));
+            
+            if matches && pid != current_pid && !terminated.iter().any(|p| p.pid == pid) && !Self::is_protected(&name) {
+                if let Some(process) = Self::terminate_pid(pid, name) {
+                    terminated.push(process);
+                }
+            }
+        }
     }
 
-    /// Kill a single process
-    #[inline]
-    pub fn kill_process(name: &str) {
-        let exe_name = if name.to_lowercase().ends_with(".exe") {
-            name.to_string()
-        } else {
-            format!("{}.exe", name)
-        };
+    /// Kill one process by PID (this app and protected processes are skipped)
+    pub fn kill_pid(pid: u32) -> Option<TerminatedProcess> {
+        let name = Self::executable_name(pid)?;
+        let name = Self::strip_exe(&name).to_string();
+        if pid == std::process::id() || Self::is_protected(&name) {
+            return None;
+        }
+        Self::terminate_pid(pid, name)
+    }
+
+    /// Suspend one process by PID; resume it with `resume_suspended`
+    pub fn suspend_pid(pid: u32) -> Option<SuspendedProcess> {
+        let name = Self::executable_name(pid)?;
+        let name = Self::strip_exe(&name).to_string();
+        if pid == std::process::id() || Self::is_protected(&name) {
+            return None;
+        }
+        unsafe {
+            let handle = OpenProcess(PROCESS_SUSPEND_RESUME, false, pid).ok()?;
+            NtSuspendProcess(handle);
+            let _ = CloseHandle(handle);
+        }
+        Some(SuspendedProcess { pid, start_time: Self::process_start_time(pid), name })
+    }
+
+    fn terminate_pid(pid: u32, name: String) -> Option<TerminatedProcess> {
+        // Path must be read before the process is gone
+        let path = Self::executable_path(pid);
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, false, pid).ok()?;
+            let result = TerminateProcess(handle, 1);
+            let _ = CloseHandle(handle);
+            result.ok().map(|_| TerminatedProcess { pid, name, path })
+        }
+    }
+
+    // =========================================================================
+    // PROCESS TREES
+    // Launchers spawn helper children (Agent.exe, updaters) that survive a parent kill
+    // =========================================================================
+
+    /// Kill processes matching `target_names` together with all their descendants
+    pub fn kill_process_trees(target_names: &[&str]) -> Vec<TerminatedProcess> {
+        // Parents come first so they cannot respawn children we already killed
+        Self::process_trees(target_names)
+            .into_iter()
+            .filter_map(|(pid, name)| Self::terminate_pid(pid, name))
+            .collect()
+    }
+
+    /// Matched processes plus all descendants as (pid, name), breadth-first from the roots
+    fn process_trees(target_names: &[&str]) -> Vec<(u32, String)> {
+        let current_pid = std::process::id();
+        let all = Self::snapshot_processes();
         
-        // Fire twice for reliability
-        let _ = Command::new("taskkill")
-            .args(["/F", "/IM", &exe_name])
-            .creation_flags(0x08000000)
-            .spawn();
+        let mut tree: Vec<(u32, String)> = all.iter()
+            .filter(|(pid, _, name)| {
+                *pid != current_pid
+                    && target_names.iter().any(|&t| Self::strip_exe(t).eq_ignore_ascii_case(name))
+                    && !Self::is_protected(name)
+            })
+            .map(|(pid, _, name)| (*pid, name.clone()))
+            .collect();
         
-        let _ = Command::new("taskkill")
-            .args(["/F", "/IM", &exe_name])
-            .creation_flags(0x08000000)
-            .spawn();
+        let mut i = 0;
+        while i < tree.len() {
+            let parent = tree[i].0;
+            let parent_start = Self::process_start_time(parent);
+            for (pid, ppid, name) in &all {
+                // A protected child (OBS started from a launcher) keeps its own subtree too
+                if *ppid != parent || *pid == current_pid || tree.iter().any(|(p, _)| p == pid) || Self::is_protected(name) {
+                    continue;
+                }
+                // Parent PIDs are not cleared when a parent exits - a child started before
+                // its "parent" belongs to an earlier process that reused the PID
+                let started_after = match (parent_start, Self::process_start_time(*pid)) {
+                    (Some(parent_time), Some(child_time)) => child_time >= parent_time,
+                    _ => false,
+                };
+                if started_after {
+                    tree.push((*pid, name.clone()));
+                }
+            }
+            i += 1;
+        }
+        tree
+    }
+
+    /// (pid, parent pid, name without .exe) for every running process
+    fn snapshot_processes() -> Vec<(u32, u32, String)> {
+        let mut processes = Vec::with_capacity(256);
+        
+        unsafe {
+            let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else { 
+                return processes; 
+            };
+            if snapshot.is_invalid() { return processes; }
+
+            let mut entry = PROCESSENTRY32 {
+                dwSize: std::mem::size_of::<PROCESSENTRY32>() as u32,
+                ..Default::default()
+            };
+
+            if Process32First(snapshot, &mut entry).is_ok() {
+                loop {
+                    let name = Self::extract_process_name(&entry.szExeFile);
+                    processes.push((entry.th32ProcessID, entry.th32ParentProcessID, name.to_string()));
+                    
+                    if Process32Next(snapshot, &mut entry).is_err() { break; }
+                }
+            }
+            let _ = CloseHandle(snapshot);
+        }
+        processes
+    }
+
+    /// Running processes started by `pid`
+    /// With the parent's start time, "children" older than the parent (PID reuse) are skipped
+    pub fn child_processes(pid: u32, parent_start: Option<u64>) -> Vec<u32> {
+        Self::snapshot_processes()
+            .into_iter()
+            .filter(|(child, ppid, _)| *ppid == pid && *child != pid)
+            .filter(|(child, _, _)| match (parent_start, Self::process_start_time(*child)) {
+                (Some(parent), Some(child)) => child >= parent,
+                _ => true,
+            })
+            .map(|(child, _, _)| child)
+            .collect()
+    }
+
+    /// Process creation time (FILETIME as u64), used to tell a process apart from a PID reuse
+    pub fn process_start_time(pid: u32) -> Option<u64> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut creation = FILETIME::default();
+            let mut exit = FILETIME::default();
+            let mut kernel = FILETIME::default();
+            let mut user = FILETIME::default();
+            let result = GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+            let _ = CloseHandle(handle);
+            result.ok().map(|_| ((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64)
+        }
     }
 
     /// Restart explorer.exe - 1:1 with C# RestartExplorer()
@@ -191,6 +438,77 @@ impl ProcessService {
         }
     }
 
+    /// Folders shown in open File Explorer windows (via IShellWindows)
+    /// Captured before explorer is killed so `reopen_explorer_windows` can restore them
+    pub fn open_explorer_windows() -> Vec<String> {
+        use windows::Win32::System::Com::{
+            CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+        };
+        use windows::Win32::UI::Shell::{IShellWindows, IWebBrowser2, ShellWindows};
+        use windows::core::{Interface, VARIANT};
+        
+        let mut folders = Vec::new();
+        unsafe {
+            let initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+            
+            if let Ok(windows) = CoCreateInstance::<_, IShellWindows>(&ShellWindows, None, CLSCTX_ALL) {
+                let count = windows.Count().unwrap_or(0);
+                for i in 0..count {
+                    let Ok(dispatch) = windows.Item(&VARIANT::from(i)) else { continue };
+                    let Ok(browser) = dispatch.cast::<IWebBrowser2>() else { continue };
+                    let Ok(url) = browser.LocationURL() else { continue };
+                    // Virtual folders (This PC, Home) have no file:// location
+                    if let Some(path) = Self::file_url_to_path(&url.to_string()) {
+                        if !folders.contains(&path) {
+                            folders.push(path);
+                        }
+                    }
+                }
+            }
+            
+            if initialized {
+                CoUninitialize();
+            }
+        }
+        folders
+    }
+    
+    /// Reopen folders captured by `open_explorer_windows`
+    pub fn reopen_explorer_windows(folders: &[String]) {
+        for folder in folders {
+            let _ = Command::new("explorer.exe").arg(folder).spawn();
+        }
+    }
+    
+    /// file:///C:/Some%20Dir -> C:\Some Dir (network: file://server/share -> \\server\share)
+    fn file_url_to_path(url: &str) -> Option<String> {
+        let rest = url.strip_prefix("file:")?;
+        let raw = match rest.strip_prefix("///") {
+            Some(local) => local.to_string(),
+            None => format!("//{}", rest.trim_start_matches('/')),
+        };
+        
+        // Percent-decode (%20 etc.)
+        let bytes = raw.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                if let Ok(b) = u8::from_str_radix(hex, 16) {
+                    decoded.push(b);
+                    i += 3;
+                    continue;
+                }
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+        
+        let path = String::from_utf8_lossy(&decoded).replace('/', "\\");
+        (!path.is_empty()).then_some(path)
+    }
+
     /// Extract process name from PROCESSENTRY32 szExeFile efficiently
     /// Returns name without .exe extension
     #[inline]
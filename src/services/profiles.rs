@@ -0,0 +1,157 @@
+//! Workload Profiles
+//! Gaming is one profile among others: Rendering/Compilation reuse the same engine
+//! (and the same restore path) with different service/power/demotion sets
+
+use serde::{Deserialize, Serialize};
+use crate::services::network::IsolationExceptions;
+use crate::services::process::ProcessService;
+
+pub const GAMING_PROFILE: &str = "Gaming";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadProfile {
+    pub name: String,
+
+    /// Process names (without .exe) that activate this profile automatically
+    /// Empty = manual activation only
+    #[serde(default)]
+    pub trigger_processes: Vec<String>,
+
+    /// Look for a fullscreen game to focus and monitor (Explorer/MPO handling depend on it)
+    #[serde(default)]
+    pub detect_fullscreen: bool,
+
+    /// Stop the optimization service set
+    #[serde(default = "default_true")]
+    pub stop_services: bool,
+
+    /// Close bloatware, peripheral suites, browsers and launchers
+    #[serde(default = "default_true")]
+    pub kill_background_apps: bool,
+
+    /// Switch to the high performance plan / laptop boost
+    #[serde(default = "default_true")]
+    pub apply_power_plan: bool,
+
+    /// Override the Process Idle Demotion module (None = use the advanced setting)
+    #[serde(default)]
+    pub process_idle_demotion: Option<bool>,
+
+    /// Win32PrioritySeparation for the session (None = 0x26, see registry::DEFAULT_PRIORITY_SEPARATION)
+    #[serde(default)]
+    pub priority_separation: Option<u32>,
+
+    /// Protocols/adapters network isolation leaves alone while this profile runs
+    #[serde(default)]
+    pub isolation_exceptions: IsolationExceptions,
+}
+
+fn default_true() -> bool { true }
+
+impl WorkloadProfile {
+    /// PID of a running trigger process for this profile
+    pub fn running_trigger(&self) -> Option<u32> {
+        if self.trigger_processes.is_empty() {
+            return None;
+        }
+        let names: Vec<&str> = self.trigger_processes.iter().map(String::as_str).collect();
+        ProcessService::find_process(&names)
+    }
+}
+
+/// Per-title flags written for a game's executable when it is detected
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameFlags {
+    /// Executable name ("cs2.exe") or full path
+    pub exe: String,
+
+    /// Prefer the high-performance GPU (DirectX UserGpuPreferences)
+    #[serde(default)]
+    pub high_performance_gpu: bool,
+
+    /// Disable fullscreen optimizations (compatibility layer)
+    #[serde(default)]
+    pub disable_fullscreen_optimizations: bool,
+
+    /// Run the game on one logical processor per physical core (for titles that dislike SMT)
+    #[serde(default)]
+    pub one_thread_per_core: bool,
+}
+
+impl GameFlags {
+    /// True if this entry targets `exe_path` (by full path, or by file name)
+    pub fn matches(&self, exe_path: &str) -> bool {
+        let wanted = self.exe.trim();
+        if wanted.contains('\\') {
+            return wanted.eq_ignore_ascii_case(exe_path);
+        }
+        let file_name = exe_path.rsplit('\\').next().unwrap_or(exe_path);
+        let stem = file_name.strip_suffix(".exe").or_else(|| file_name.strip_suffix(".EXE")).unwrap_or(file_name);
+        wanted.eq_ignore_ascii_case(file_name) || wanted.eq_ignore_ascii_case(stem)
+    }
+}
+
+/// Built-in profiles; Gaming matches the behavior before profiles existed
+pub fn default_profiles() -> Vec<WorkloadProfile> {
+    vec![
+        WorkloadProfile {
+            name: GAMING_PROFILE.to_string(),
+            trigger_processes: Vec::new(),
+            detect_fullscreen: true,
+            stop_services: true,
+            kill_background_apps: true,
+            apply_power_plan: true,
+            process_idle_demotion: None,
+            priority_separation: None,
+            isolation_exceptions: IsolationExceptions::default(),
+        },
+        WorkloadProfile {
+            name: "Rendering".to_string(),
+            trigger_processes: ["blender", "ffmpeg", "HandBrake", "Resolve", "AfterFX", "Cinema 4D"]
+                .iter().map(|s| s.to_string()).collect(),
+            detect_fullscreen: false,
+            stop_services: true,
+            // Keep the browser/launchers open, creators usually work alongside them
+            kill_background_apps: false,
+            apply_power_plan: true,
+            process_idle_demotion: Some(true),
+            priority_separation: None,
+            isolation_exceptions: IsolationExceptions::default(),
+        },
+        WorkloadProfile {
+            name: "Compilation".to_string(),
+            trigger_processes: ["link", "cl", "msbuild", "rustc", "clang"]
+                .iter().map(|s| s.to_string()).collect(),
+            detect_fullscreen: false,
+            // SysMain prefetching helps repeated builds, leave services running
+            stop_services: false,
+            kill_background_apps: false,
+            apply_power_plan: true,
+            process_idle_demotion: Some(true),
+            priority_separation: None,
+            isolation_exceptions: IsolationExceptions::default(),
+        },
+    ]
+}
+
+/// Profile by name, falling back to Gaming (or the first profile)
+pub fn find<'a>(profiles: &'a [WorkloadProfile], name: &str) -> Option<&'a WorkloadProfile> {
+    profiles.iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name))
+        .or_else(|| profiles.iter().find(|p| p.name == GAMING_PROFILE))
+        .or_else(|| profiles.first())
+}
+
+/// Mutable profile by name, with the same fallback as `find`
+pub fn find_mut<'a>(profiles: &'a mut [WorkloadProfile], name: &str) -> Option<&'a mut WorkloadProfile> {
+    let index = profiles.iter().position(|p| p.name.eq_ignore_ascii_case(name))
+        .or_else(|| profiles.iter().position(|p| p.name == GAMING_PROFILE))
+        .or((!profiles.is_empty()).then_some(0))?;
+    profiles.get_mut(index)
+}
+
+/// First profile whose trigger process is running, with that process' PID
+pub fn detect_triggered(profiles: &[WorkloadProfile]) -> Option<(&WorkloadProfile, u32)> {
+    profiles.iter().find_map(|p| p.running_trigger().map(|pid| (p, pid)))
+}
+
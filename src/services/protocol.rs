@@ -0,0 +1,84 @@
+//! URL Protocol
+//! Registers xillygamemode:// so browser dashboards, Playnite or Stream Deck actions can send
+//! commands like `xillygamemode://enable?profile=Valorant`; links are handed to the running
+//! instance over the single-instance pipe
+
+use crate::services::instance::InstanceCommand;
+use crate::services::settings;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::log_line;
+
+pub const SCHEME: &str = "xillygamemode";
+
+/// Point xillygamemode:// at this exe (per user, refreshed every start so a moved exe keeps working)
+pub fn register() {
+    let Ok(exe) = std::env::current_exe() else { return };
+    let base = format!(r"Software\Classes\{}", SCHEME);
+    let portable = if settings::is_portable() { " --portable" } else { "" };
+    let command = format!("\"{}\"{} \"%1\"", exe.to_string_lossy(), portable);
+    let ok = set_string(&base, "", "URL:Xilly Game Mode")
+        && set_string(&base, "URL Protocol", "")
+        && set_string(&format!(r"{}\shell\open\command", base), "", &command);
+    if !ok {
+        log_line!("[Protocol] Could not register {}://", SCHEME);
+    }
+}
+
+/// Command for a `xillygamemode://<action>[?profile=<name>]` argument, None for anything else
+///
+/// Actions: enable (optionally with a profile), disable, toggle, show
+pub fn parse(arg: &str) -> Option<InstanceCommand> {
+    let prefix = format!("{}:", SCHEME);
+    if !arg.get(..prefix.len())?.eq_ignore_ascii_case(&prefix) {
+        return None;
+    }
+    let rest = arg[prefix.len()..].trim_start_matches('/');
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    // Browsers may add a trailing slash ("enable/?profile=...")
+    let action = action.trim_end_matches('/').to_ascii_lowercase();
+    let profile = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("profile"))
+        .map(|(_, value)| decode(value))
+        .filter(|name| !name.trim().is_empty());
+
+    let command = match action.as_str() {
+        "enable" => InstanceCommand::Enable { profile },
+        "disable" => InstanceCommand::Disable,
+        "toggle" => InstanceCommand::Toggle,
+        "show" | "" => InstanceCommand::Show,
+        _ => {
+            log_line!("[Protocol] Unknown action in {}", arg);
+            return None;
+        }
+    };
+    Some(command)
+}
+
+/// Percent-decoding ("+" as space, as in form-encoded queries)
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 3;
+                    continue;
+                }
+                None => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn set_string(path: &str, name: &str, value: &str) -> bool {
+    Win32.write_string(Hive::CurrentUser, path, name, value)
+}
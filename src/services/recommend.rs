@@ -0,0 +1,98 @@
+//! Recommended Settings
+//! Hardware-based preset proposed by the first-run wizard: only modules that help on
+//! this machine, and no combinations known to hurt (e.g. NIC tuning on Wi-Fi)
+
+use crate::services::capabilities::Capabilities;
+use crate::services::detector::GameDetector;
+use crate::services::gpu::{GpuAdapter, GpuService};
+use crate::services::network::NetworkService;
+use crate::services::settings::AppSettings;
+
+/// What the wizard found and the settings it proposes
+pub struct Recommendation {
+    /// One line per detected property ("8 cores", "Wired network"...)
+    pub hardware: Vec<String>,
+    /// One line per enabled module/option, with the reason
+    pub changes: Vec<String>,
+    pub settings: AppSettings,
+}
+
+/// Build a preset on top of `base` (the defaults on first run)
+pub fn recommend(base: &AppSettings) -> Recommendation {
+    let caps = Capabilities::get();
+    let is_desktop = GameDetector::is_desktop();
+    let gpu = GpuService::target_adapter("");
+    let wireless = NetworkService::active_adapter().map(|a| a.is_wireless);
+
+    let mut hardware = Vec::new();
+    if let Some(cores) = caps.physical_cores {
+        hardware.push(format!("{} CPU cores", cores));
+    }
+    if let Some(gb) = caps.total_ram_gb {
+        hardware.push(format!("{:.0} GB RAM", gb));
+    }
+    if let Some(gpu) = &gpu {
+        hardware.push(gpu.name.clone());
+    }
+    hardware.push(if is_desktop { "Desktop" } else { "Laptop" }.to_string());
+    hardware.push(match wireless {
+        Some(true) => "Wi-Fi network",
+        Some(false) => "Wired network",
+        None => "Network type unknown",
+    }.to_string());
+
+    let mut settings = base.clone();
+    let mut changes = Vec::new();
+    let modules = &mut settings.advanced_modules;
+
+    // Safe everywhere
+    modules.mmcss_priority_boost = true;
+    modules.process_idle_demotion = true;
+    changes.push("MMCSS Priority Boost and Process Idle Demotion".to_string());
+
+    if caps.core_parking().is_none() {
+        modules.disable_core_parking = true;
+        changes.push("Disable Core Parking (enough cores to benefit)".to_string());
+    }
+
+    // Only worth it on GPUs where hardware scheduling is mature
+    if caps.hags().is_none() && gpu.as_ref().is_some_and(is_modern_gpu) {
+        modules.enable_hags = true;
+        changes.push("Hardware GPU Scheduling (supported, recent GPU)".to_string());
+    }
+
+    // Large pages trade file cache for TLB reach; only with memory to spare
+    if caps.total_ram_gb.is_some_and(|gb| gb >= 31.5) {
+        modules.enable_large_pages = true;
+        changes.push("Large Pages (32GB+ RAM)".to_string());
+    }
+
+    // NIC tuning disables Wi-Fi power saving and interrupt moderation; wired only
+    if wireless == Some(false) {
+        modules.tune_network_adapter = true;
+        changes.push("NIC Adapter Tuning (wired connection)".to_string());
+    }
+
+    // Explorer suspension hides the tray (battery, Wi-Fi, volume); it stays an explicit opt-in
+    settings.suspend_explorer = false;
+
+    Recommendation { hardware, changes, settings }
+}
+
+/// NVIDIA RTX 30+, AMD RX 6000+, Intel Arc
+fn is_modern_gpu(gpu: &GpuAdapter) -> bool {
+    let model_number = |prefix: &str| {
+        gpu.name
+            .split_once(prefix)
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .and_then(|model| model.trim_start_matches(|c: char| !c.is_ascii_digit()).get(..4))
+            .and_then(|digits| digits.parse::<u32>().ok())
+    };
+
+    match gpu.vendor() {
+        "NVIDIA" => model_number("RTX ").is_some_and(|n| n >= 3000),
+        "AMD" => model_number("RX ").is_some_and(|n| n >= 6000),
+        "Intel" => gpu.name.contains("Arc"),
+        _ => false,
+    }
+}
@@ -2,16 +2,21 @@
 //! Saves original state before applying and restores on disable
 
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
 use windows::Win32::System::Registry::*;
-use windows::Win32::System::Services::*;
-use windows::core::{PCWSTR, HSTRING};
+use crate::log_line;
+use serde::{Deserialize, Serialize};
+use crate::services::migration::ManagedValue;
+use crate::services::capabilities::{Builds, Capabilities, BUILDS_HAGS, BUILDS_POWER_THROTTLING};
+use crate::services::vbs;
+use crate::services::system::{Hive, SystemBackend, Win32};
+use crate::services::windows::WindowsServiceManager;
 
-/// Stores original values to restore later
-static ORIGINAL_STATE: Lazy<Mutex<OriginalState>> = Lazy::new(|| Mutex::new(OriginalState::default()));
+/// The service acting on the live machine, behind the static API
+static LIVE: Lazy<ReviTweaksService> = Lazy::new(|| ReviTweaksService::with_system(Arc::new(Win32)));
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 struct OriginalState {
     registry_values: HashMap<String, Option<RegistryValue>>,
     /// Stores (service_name, original_startup_type, was_running)
@@ -19,7 +24,7 @@ struct OriginalState {
     applied: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct RegistryValue {
     data: Vec<u8>,
     value_type: u32,
@@ -40,7 +45,7 @@ const SERVICES_TO_DISABLE: &[&str] = &[
     "Themes",              // Themes service
     "TabletInputService",  // Touch Keyboard
     "CDPSvc",              // Connected Devices Platform
-    "CDPUserSvc",          // Connected Devices Platform User Service
+    "CDPUserSvc",          // Connected Devices Platform User Service (per-user, CDPUserSvc_xxxxx)
     "MapsBroker",          // Maps Broker
     "lfsvc",               // Geolocation Service
     "WbioSrvc",            // Biometric Service
@@ -52,139 +57,197 @@ struct RegistryTweak {
     path: &'static str,
     value_name: &'static str,
     data: u32,
+    /// Windows builds the value is honored on (see capabilities)
+    builds: Builds,
 }
 
 const REGISTRY_TWEAKS: &[RegistryTweak] = &[
     // === Performance Tweaks ===
     // Disable VBS/HVCI for gaming performance
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\DeviceGuard", value_name: "EnableVirtualizationBasedSecurity", data: 0 },
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\DeviceGuard\Scenarios\HypervisorEnforcedCodeIntegrity", value_name: "Enabled", data: 0 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\DeviceGuard", value_name: "EnableVirtualizationBasedSecurity", data: 0, builds: Builds::ANY },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\DeviceGuard\Scenarios\HypervisorEnforcedCodeIntegrity", value_name: "Enabled", data: 0, builds: Builds::ANY },
     
     // Disable Spectre/Meltdown mitigations (performance boost)
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management", value_name: "FeatureSettingsOverride", data: 3 },
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management", value_name: "FeatureSettingsOverrideMask", data: 3 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management", value_name: "FeatureSettingsOverride", data: 3, builds: Builds::ANY },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management", value_name: "FeatureSettingsOverrideMask", data: 3, builds: Builds::ANY },
     
     // Faster shutdown
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control", value_name: "WaitToKillServiceTimeout", data: 1500 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control", value_name: "WaitToKillServiceTimeout", data: 1500, builds: Builds::ANY },
     
     // Disable automatic maintenance
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Schedule\Maintenance", value_name: "MaintenanceDisabled", data: 1 },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Schedule\Maintenance", value_name: "MaintenanceDisabled", data: 1, builds: Builds::ANY },
     
     // === Telemetry Disabled ===
-    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\DataCollection", value_name: "AllowTelemetry", data: 0 },
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\DataCollection", value_name: "AllowTelemetry", data: 0 },
+    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\DataCollection", value_name: "AllowTelemetry", data: 0, builds: Builds::ANY },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\DataCollection", value_name: "AllowTelemetry", data: 0, builds: Builds::ANY },
     
     // Disable experimentation
-    RegistryTweak { path: r"SOFTWARE\Microsoft\PolicyManager\current\device\System", value_name: "AllowExperimentation", data: 0 },
-    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\PreviewBuilds", value_name: "EnableConfigFlighting", data: 0 },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\PolicyManager\current\device\System", value_name: "AllowExperimentation", data: 0, builds: Builds::ANY },
+    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\PreviewBuilds", value_name: "EnableConfigFlighting", data: 0, builds: Builds::ANY },
     
     // === Explorer Performance ===
     // Disable folder type auto-discovery
-    RegistryTweak { path: r"SOFTWARE\Classes\Local Settings\Software\Microsoft\Windows\Shell\Bags\AllFolders\Shell", value_name: "FolderType", data: 0 }, // Will handle as string
+    RegistryTweak { path: r"SOFTWARE\Classes\Local Settings\Software\Microsoft\Windows\Shell\Bags\AllFolders\Shell", value_name: "FolderType", data: 0, builds: Builds::ANY }, // Will handle as string
     
     // Disable search indexing in explorer
-    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\Windows Search", value_name: "AllowCortana", data: 0 },
+    RegistryTweak { path: r"SOFTWARE\Policies\Microsoft\Windows\Windows Search", value_name: "AllowCortana", data: 0, builds: Builds::ANY },
     
     // === Network Optimizations ===
     // Disable Nagle's algorithm for lower latency
-    RegistryTweak { path: r"SOFTWARE\Microsoft\MSMQ\Parameters", value_name: "TCPNoDelay", data: 1 },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\MSMQ\Parameters", value_name: "TCPNoDelay", data: 1, builds: Builds::ANY },
     
     // === GPU Optimizations ===
     // Disable GPU power saving
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Power\PowerSettings\54533251-82be-4824-96c1-47b60b740d00\be337238-0d82-4146-a960-4f3749d470c7", value_name: "Attributes", data: 2 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Power\PowerSettings\54533251-82be-4824-96c1-47b60b740d00\be337238-0d82-4146-a960-4f3749d470c7", value_name: "Attributes", data: 2, builds: Builds::ANY },
     
     // Hardware accelerated GPU scheduling (if supported)
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\GraphicsDrivers", value_name: "HwSchMode", data: 2 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\GraphicsDrivers", value_name: "HwSchMode", data: 2, builds: BUILDS_HAGS },
     
     // === Multimedia/Gaming ===
     // Multimedia Class Scheduler - prioritize games
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile", value_name: "SystemResponsiveness", data: 0 },
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile", value_name: "NetworkThrottlingIndex", data: 0xFFFFFFFF },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile", value_name: "SystemResponsiveness", data: 0, builds: Builds::ANY },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile", value_name: "NetworkThrottlingIndex", data: 0xFFFFFFFF, builds: Builds::ANY },
     
     // Game priority
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "Priority", data: 6 },
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "Scheduling Category", data: 2 }, // Will handle as string
-    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "SFIO Priority", data: 3 }, // Will handle as string
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "Priority", data: 6, builds: Builds::ANY },
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "Scheduling Category", data: 2, builds: Builds::ANY }, // Will handle as string
+    RegistryTweak { path: r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games", value_name: "SFIO Priority", data: 3, builds: Builds::ANY }, // Will handle as string
     
     // === Power Tweaks ===
     // Disable power throttling
-    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Power\PowerThrottling", value_name: "PowerThrottlingOff", data: 1 },
+    RegistryTweak { path: r"SYSTEM\CurrentControlSet\Control\Power\PowerThrottling", value_name: "PowerThrottlingOff", data: 1, builds: BUILDS_POWER_THROTTLING },
 ];
 
-pub struct ReviTweaksService;
+pub struct ReviTweaksService {
+    // Registry and service access (Win32, or an in-memory fake)
+    system: Arc<dyn SystemBackend>,
+    // Original values to restore later
+    state: Mutex<OriginalState>,
+}
 
 impl ReviTweaksService {
+    /// Service working against `system`, with its own original state
+    pub fn with_system(system: Arc<dyn SystemBackend>) -> Self {
+        Self { system, state: Mutex::new(OriginalState::default()) }
+    }
+
+    /// DWORD values written by `enable`, for the migration manifest
+    pub fn managed_values() -> Vec<ManagedValue> {
+        REGISTRY_TWEAKS.iter()
+            .map(|t| ManagedValue { path: t.path.to_string(), name: t.value_name.to_string(), data: t.data })
+            .collect()
+    }
+
+    /// Services disabled by `enable`, for the migration manifest
+    pub fn managed_services() -> Vec<String> {
+        SERVICES_TO_DISABLE.iter().map(|s| s.to_string()).collect()
+    }
+
     /// Apply all ReviOS-style tweaks, saving original state first
     pub fn enable() {
-        let mut state = ORIGINAL_STATE.lock().unwrap();
+        LIVE.apply();
+    }
+
+    /// `enable` on this service's backend
+    pub fn apply(&self) {
+        let system = &*self.system;
+        let mut state = self.state.lock().unwrap();
         
         if state.applied {
             return; // Already applied
         }
         
-        println!("[ReviTweaks] Saving original state and applying tweaks...");
+        log_line!("[ReviTweaks] Saving original state and applying tweaks...");
         
         // Save and modify services - both registry AND actually stop them
-        for service_name in SERVICES_TO_DISABLE {
+        // Per-user templates expand to this sign-in's instances
+        let service_names: Vec<String> = SERVICES_TO_DISABLE.iter()
+            .flat_map(|name| WindowsServiceManager::resolve_service_names(system, name))
+            .collect();
+        for service_name in &service_names {
             // Get original startup type from registry
-            let original_startup = Self::get_service_startup_registry(service_name).unwrap_or(3);
+            let original_startup = Self::get_service_startup_registry(system, service_name).unwrap_or(3);
             
             // Check if service is currently running
-            let was_running = Self::is_service_running(service_name);
+            let was_running = system.is_running(service_name) == Some(true);
             
             // Save original state
             state.service_states.insert(service_name.to_string(), (original_startup, was_running));
             
             // Set startup type to Disabled (4) in registry
-            Self::set_service_startup_registry(service_name, 4);
+            Self::set_service_startup_registry(system, service_name, 4);
             
             // Actually STOP the service if it's running
             if was_running {
-                Self::stop_service(service_name);
+                system.stop(service_name);
             }
         }
         
         // Save and modify registry values
+        let capabilities = Capabilities::get();
+        // A UEFI lock or policy keeps core isolation on whatever these values say
+        let vbs_locked = vbs::registry_ignored();
         for tweak in REGISTRY_TWEAKS {
+            if !capabilities.supports(tweak.builds) {
+                log_line!("[ReviTweaks] Skipping {}\\{}: {}", tweak.path, tweak.value_name,
+                         capabilities.build_support(tweak.builds).unwrap_or_default());
+                continue;
+            }
+            if vbs_locked && tweak.path.contains(r"Control\DeviceGuard") {
+                log_line!("[ReviTweaks] Skipping {}: core isolation is locked", tweak.value_name);
+                continue;
+            }
             let key = format!("HKLM\\{}\\{}", tweak.path, tweak.value_name);
             
             // Save original value
-            let original = Self::get_registry_dword(tweak.path, tweak.value_name);
+            let original = system.read_dword(Hive::LocalMachine, tweak.path, tweak.value_name);
             state.registry_values.insert(key.clone(), original.map(|d| RegistryValue {
                 data: d.to_le_bytes().to_vec(),
                 value_type: REG_DWORD.0,
             }));
             
             // Apply new value
-            Self::set_registry_dword(tweak.path, tweak.value_name, tweak.data);
+            system.write_dword(Hive::LocalMachine, tweak.path, tweak.value_name, tweak.data);
         }
         
         // Apply string registry values
-        Self::apply_string_tweaks(&mut state);
+        Self::apply_string_tweaks(system, &mut state);
         
         state.applied = true;
-        println!("[ReviTweaks] Applied {} service changes and {} registry tweaks", 
+        log_line!("[ReviTweaks] Applied {} service changes and {} registry tweaks", 
                  state.service_states.len(), state.registry_values.len());
     }
     
     /// Restore all original values
     pub fn disable() {
-        let mut state = ORIGINAL_STATE.lock().unwrap();
+        LIVE.revert();
+    }
+
+    /// `disable` on this service's backend
+    pub fn revert(&self) {
+        let system = &*self.system;
+        let mut state = self.state.lock().unwrap();
         
         if !state.applied {
             return; // Nothing to restore
         }
         
-        println!("[ReviTweaks] Restoring original state...");
+        log_line!("[ReviTweaks] Restoring original state...");
         
         // Restore services - both registry AND restart if they were running
         for (service_name, (original_startup, was_running)) in &state.service_states {
+            // A per-user instance is gone after signing out (journal recovery); don't recreate its key
+            if Self::get_service_startup_registry(system, service_name).is_none() {
+                log_line!("[ReviTweaks] {} no longer exists, skipping", service_name);
+                continue;
+            }
+            
             // Restore original startup type in registry
-            Self::set_service_startup_registry(service_name, *original_startup);
+            Self::set_service_startup_registry(system, service_name, *original_startup);
             
             // Restart service if it was running before
             if *was_running {
-                Self::start_service(service_name);
+                system.start(service_name);
             }
         }
         
@@ -197,63 +260,78 @@ impl ReviTweaksService {
                 if let Some(reg_val) = original_value {
                     if reg_val.value_type == REG_DWORD.0 && reg_val.data.len() >= 4 {
                         let data = u32::from_le_bytes([reg_val.data[0], reg_val.data[1], reg_val.data[2], reg_val.data[3]]);
-                        Self::set_registry_dword(path, value_name, data);
+                        system.write_dword(Hive::LocalMachine, path, value_name, data);
                     }
                 } else {
                     // Value didn't exist before, delete it
-                    Self::delete_registry_value(path, value_name);
+                    system.delete_value(Hive::LocalMachine, path, value_name);
                 }
             }
         }
         
         // Restore string values
-        Self::restore_string_tweaks(&state);
+        Self::restore_string_tweaks(system, &state);
         
         state.service_states.clear();
         state.registry_values.clear();
         state.applied = false;
         
-        println!("[ReviTweaks] Restored original state");
+        log_line!("[ReviTweaks] Restored original state");
     }
     
+    /// Saved original state while applied, for the session journal
+    pub fn journal_state() -> Option<serde_json::Value> {
+        let state = LIVE.state.lock().unwrap();
+        if !state.applied {
+            return None;
+        }
+        serde_json::to_value(&*state).ok()
+    }
+
+    /// Restore from a journaled original state (crash recovery in a fresh process)
+    pub fn restore_from_journal(value: &serde_json::Value) {
+        let Ok(state) = serde_json::from_value::<OriginalState>(value.clone()) else { return };
+        *LIVE.state.lock().unwrap() = state;
+        LIVE.revert();
+    }
+
     /// Check if tweaks are currently applied
-    #[allow(dead_code)]
     pub fn is_applied() -> bool {
-        ORIGINAL_STATE.lock().unwrap().applied
+        LIVE.state.lock().unwrap().applied
     }
     
-    fn apply_string_tweaks(state: &mut OriginalState) {
+    fn apply_string_tweaks(system: &dyn SystemBackend, state: &mut OriginalState) {
         // FolderType = NotSpecified (string value)
         let folder_path = r"SOFTWARE\Classes\Local Settings\Software\Microsoft\Windows\Shell\Bags\AllFolders\Shell";
         let key = format!("HKLM\\{}\\FolderType_str", folder_path);
-        let original = Self::get_registry_string(folder_path, "FolderType");
+        let original = system.read_string(Hive::LocalMachine, folder_path, "FolderType");
         state.registry_values.insert(key, original.map(|s| RegistryValue {
             data: s.into_bytes(),
             value_type: REG_SZ.0,
         }));
-        Self::set_registry_string(folder_path, "FolderType", "NotSpecified");
+        system.write_string(Hive::LocalMachine, folder_path, "FolderType", "NotSpecified");
         
         // MMCSS Game scheduling
         let mmcss_path = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games";
         
         let key = format!("HKLM\\{}\\Scheduling Category_str", mmcss_path);
-        let original = Self::get_registry_string(mmcss_path, "Scheduling Category");
+        let original = system.read_string(Hive::LocalMachine, mmcss_path, "Scheduling Category");
         state.registry_values.insert(key, original.map(|s| RegistryValue {
             data: s.into_bytes(),
             value_type: REG_SZ.0,
         }));
-        Self::set_registry_string(mmcss_path, "Scheduling Category", "High");
+        system.write_string(Hive::LocalMachine, mmcss_path, "Scheduling Category", "High");
         
         let key = format!("HKLM\\{}\\SFIO Priority_str", mmcss_path);
-        let original = Self::get_registry_string(mmcss_path, "SFIO Priority");
+        let original = system.read_string(Hive::LocalMachine, mmcss_path, "SFIO Priority");
         state.registry_values.insert(key, original.map(|s| RegistryValue {
             data: s.into_bytes(),
             value_type: REG_SZ.0,
         }));
-        Self::set_registry_string(mmcss_path, "SFIO Priority", "High");
+        system.write_string(Hive::LocalMachine, mmcss_path, "SFIO Priority", "High");
     }
     
-    fn restore_string_tweaks(state: &OriginalState) {
+    fn restore_string_tweaks(system: &dyn SystemBackend, state: &OriginalState) {
         for (key, original_value) in &state.registry_values {
             if key.ends_with("_str") {
                 if let Some((path, value_name)) = key.strip_prefix("HKLM\\").and_then(|k| {
@@ -262,290 +340,108 @@ impl ReviTweaksService {
                     if let Some(reg_val) = original_value {
                         if reg_val.value_type == REG_SZ.0 {
                             let s = String::from_utf8_lossy(&reg_val.data).to_string();
-                            Self::set_registry_string(path, value_name, &s);
+                            system.write_string(Hive::LocalMachine, path, value_name, &s);
                         }
                     } else {
-                        Self::delete_registry_value(path, value_name);
+                        system.delete_value(Hive::LocalMachine, path, value_name);
                     }
                 }
             }
         }
     }
     
-    // ========== Service Control (SCM API) ==========
-    
-    /// Check if a service is currently running
-    fn is_service_running(service_name: &str) -> bool {
-        unsafe {
-            let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else {
-                return false;
-            };
-            
-            let name_w = HSTRING::from(service_name);
-            let result = if let Ok(service) = OpenServiceW(
-                scm,
-                PCWSTR(name_w.as_ptr()),
-                SERVICE_QUERY_STATUS
-            ) {
-                let mut status = SERVICE_STATUS::default();
-                let running = QueryServiceStatus(service, &mut status).is_ok() 
-                    && status.dwCurrentState == SERVICE_RUNNING;
-                let _ = CloseServiceHandle(service);
-                running
-            } else {
-                false
-            };
-            
-            let _ = CloseServiceHandle(scm);
-            result
-        }
-    }
-    
-    /// Stop a running service
-    fn stop_service(service_name: &str) -> bool {
-        unsafe {
-            let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else {
-                return false;
-            };
-            
-            let name_w = HSTRING::from(service_name);
-            let result = if let Ok(service) = OpenServiceW(
-                scm,
-                PCWSTR(name_w.as_ptr()),
-                SERVICE_STOP | SERVICE_QUERY_STATUS
-            ) {
-                let mut status = SERVICE_STATUS::default();
-                let stopped = if QueryServiceStatus(service, &mut status).is_ok() 
-                    && status.dwCurrentState == SERVICE_RUNNING 
-                {
-                    let mut new_status = SERVICE_STATUS::default();
-                    ControlService(service, SERVICE_CONTROL_STOP, &mut new_status).is_ok()
-                } else {
-                    true // Already stopped
-                };
-                let _ = CloseServiceHandle(service);
-                stopped
-            } else {
-                false
-            };
-            
-            let _ = CloseServiceHandle(scm);
-            result
-        }
-    }
-    
-    /// Start a stopped service
-    fn start_service(service_name: &str) -> bool {
-        unsafe {
-            let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else {
-                return false;
-            };
-            
-            let name_w = HSTRING::from(service_name);
-            let result = if let Ok(service) = OpenServiceW(
-                scm,
-                PCWSTR(name_w.as_ptr()),
-                SERVICE_START | SERVICE_QUERY_STATUS
-            ) {
-                let mut status = SERVICE_STATUS::default();
-                let started = if QueryServiceStatus(service, &mut status).is_ok() {
-                    // SERVICE_STOPPED = 1
-                    if status.dwCurrentState.0 == 1 {
-                        StartServiceW(service, None).is_ok()
-                    } else {
-                        true // Already running
-                    }
-                } else {
-                    false
-                };
-                let _ = CloseServiceHandle(service);
-                started
-            } else {
-                false
-            };
-            
-            let _ = CloseServiceHandle(scm);
-            result
-        }
-    }
-    
     // ========== Registry-based service startup type ==========
     
-    fn get_service_startup_registry(service_name: &str) -> Option<u32> {
+    fn get_service_startup_registry(system: &dyn SystemBackend, service_name: &str) -> Option<u32> {
         let path = format!(r"SYSTEM\CurrentControlSet\Services\{}", service_name);
-        Self::get_registry_dword(&path, "Start")
+        system.read_dword(Hive::LocalMachine, &path, "Start")
     }
     
-    fn set_service_startup_registry(service_name: &str, startup: u32) {
+    fn set_service_startup_registry(system: &dyn SystemBackend, service_name: &str, startup: u32) {
         let path = format!(r"SYSTEM\CurrentControlSet\Services\{}", service_name);
-        Self::set_registry_dword(&path, "Start", startup);
+        system.write_dword(Hive::LocalMachine, &path, "Start", startup);
     }
-    
-    fn get_registry_dword(path: &str, value_name: &str) -> Option<u32> {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(path_wide.as_ptr()), 0, KEY_READ, &mut hkey).is_err() {
-                return None;
-            }
-            
-            let mut data: u32 = 0;
-            let mut data_size = std::mem::size_of::<u32>() as u32;
-            let mut value_type = REG_DWORD;
-            
-            let result = RegQueryValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                None,
-                Some(&mut value_type),
-                Some(std::ptr::addr_of_mut!(data) as *mut u8),
-                Some(&mut data_size),
-            );
-            
-            let _ = RegCloseKey(hkey);
-            
-            if result.is_ok() {
-                Some(data)
-            } else {
-                None
-            }
-        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::system::fake::{FakeSystem, Value};
+    use crate::services::system::{RegistryBackend, ServiceControl};
+
+    const SERVICES: &str = r"SYSTEM\CurrentControlSet\Services";
+    const MMCSS: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile";
+    const GAMES_TASK: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\Games";
+    const MAINTENANCE: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Schedule\Maintenance";
+    const TELEMETRY: &str = r"SOFTWARE\Policies\Microsoft\Windows\DataCollection";
+
+    fn service(fake: &FakeSystem, name: &str, start: u32, running: bool) {
+        fake.set(Hive::LocalMachine, &format!(r"{}\{}", SERVICES, name), "Start", Value::Dword(start));
+        fake.add_service(name, running);
     }
-    
-    fn set_registry_dword(path: &str, value_name: &str, data: u32) {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegCreateKeyExW(
-                HKEY_LOCAL_MACHINE,
-                PCWSTR(path_wide.as_ptr()),
-                0,
-                None,
-                REG_OPTION_NON_VOLATILE,
-                KEY_WRITE,
-                None,
-                &mut hkey,
-                None,
-            ).is_err() {
-                return;
-            }
-            
-            let _ = RegSetValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                0,
-                REG_DWORD,
-                Some(&data.to_le_bytes()),
-            );
-            
-            let _ = RegCloseKey(hkey);
-        }
+
+    fn start_type(fake: &FakeSystem, name: &str) -> Option<u32> {
+        fake.read_dword(Hive::LocalMachine, &format!(r"{}\{}", SERVICES, name), "Start")
     }
-    
-    fn get_registry_string(path: &str, value_name: &str) -> Option<String> {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(path_wide.as_ptr()), 0, KEY_READ, &mut hkey).is_err() {
-                return None;
-            }
-            
-            let mut data_size: u32 = 0;
-            let mut value_type = REG_SZ;
-            
-            // First call to get size
-            let _ = RegQueryValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                None,
-                Some(&mut value_type),
-                None,
-                Some(&mut data_size),
-            );
-            
-            if data_size == 0 {
-                let _ = RegCloseKey(hkey);
-                return None;
-            }
-            
-            let mut buffer: Vec<u16> = vec![0; (data_size / 2) as usize];
-            
-            let result = RegQueryValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                None,
-                Some(&mut value_type),
-                Some(buffer.as_mut_ptr() as *mut u8),
-                Some(&mut data_size),
-            );
-            
-            let _ = RegCloseKey(hkey);
-            
-            if result.is_ok() {
-                // Remove null terminator
-                while buffer.last() == Some(&0) {
-                    buffer.pop();
-                }
-                Some(String::from_utf16_lossy(&buffer))
-            } else {
-                None
-            }
-        }
+
+    #[test]
+    fn apply_then_revert_restores_registry_and_services() {
+        let fake = Arc::new(FakeSystem::new());
+        service(&fake, "SysMain", 2, true);
+        service(&fake, "DiagTrack", 3, false);
+        fake.set(Hive::LocalMachine, MMCSS, "SystemResponsiveness", Value::Dword(20));
+        fake.set(Hive::LocalMachine, GAMES_TASK, "Scheduling Category", Value::String("Medium".into()));
+        // Group Policy holds the telemetry level
+        fake.set(Hive::LocalMachine, TELEMETRY, "AllowTelemetry", Value::Dword(1));
+        fake.block(Hive::LocalMachine, TELEMETRY, "AllowTelemetry");
+
+        let revi = ReviTweaksService::with_system(fake.clone());
+        revi.apply();
+        assert_eq!(start_type(&fake, "SysMain"), Some(4));
+        assert_eq!(fake.is_running("SysMain"), Some(false));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "SystemResponsiveness"), Some(0));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MAINTENANCE, "MaintenanceDisabled"), Some(1));
+        assert_eq!(fake.read_string(Hive::LocalMachine, GAMES_TASK, "Scheduling Category").as_deref(), Some("High"));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, TELEMETRY, "AllowTelemetry"), Some(1));
+
+        revi.revert();
+        assert_eq!(start_type(&fake, "SysMain"), Some(2));
+        assert_eq!(fake.is_running("SysMain"), Some(true));
+        assert_eq!(start_type(&fake, "DiagTrack"), Some(3));
+        assert_eq!(fake.is_running("DiagTrack"), Some(false));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, MMCSS, "SystemResponsiveness"), Some(20));
+        // Absent before, so removed again
+        assert_eq!(fake.get(Hive::LocalMachine, MAINTENANCE, "MaintenanceDisabled"), None);
+        assert_eq!(fake.read_string(Hive::LocalMachine, GAMES_TASK, "Scheduling Category").as_deref(), Some("Medium"));
+        assert_eq!(fake.read_dword(Hive::LocalMachine, TELEMETRY, "AllowTelemetry"), Some(1));
     }
-    
-    fn set_registry_string(path: &str, value_name: &str, data: &str) {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            let data_wide: Vec<u16> = data.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegCreateKeyExW(
-                HKEY_LOCAL_MACHINE,
-                PCWSTR(path_wide.as_ptr()),
-                0,
-                None,
-                REG_OPTION_NON_VOLATILE,
-                KEY_WRITE,
-                None,
-                &mut hkey,
-                None,
-            ).is_err() {
-                return;
-            }
-            
-            let data_bytes: Vec<u8> = data_wide.iter().flat_map(|&x| x.to_le_bytes()).collect();
-            
-            let _ = RegSetValueExW(
-                hkey,
-                PCWSTR(value_wide.as_ptr()),
-                0,
-                REG_SZ,
-                Some(&data_bytes),
-            );
-            
-            let _ = RegCloseKey(hkey);
-        }
+
+    #[test]
+    fn per_user_service_instances_are_restored() {
+        let fake = Arc::new(FakeSystem::new());
+        service(&fake, "CDPUserSvc", 2, false);
+        fake.set(Hive::LocalMachine, &format!(r"{}\CDPUserSvc", SERVICES), "Type", Value::Dword(0x60));
+        service(&fake, "CDPUserSvc_4a1b2", 2, true);
+        fake.set(Hive::LocalMachine, &format!(r"{}\CDPUserSvc_4a1b2", SERVICES), "Type", Value::Dword(0xE0));
+
+        let revi = ReviTweaksService::with_system(fake.clone());
+        revi.apply();
+        assert_eq!(start_type(&fake, "CDPUserSvc"), Some(4));
+        assert_eq!(start_type(&fake, "CDPUserSvc_4a1b2"), Some(4));
+        assert_eq!(fake.is_running("CDPUserSvc_4a1b2"), Some(false));
+
+        revi.revert();
+        assert_eq!(start_type(&fake, "CDPUserSvc"), Some(2));
+        assert_eq!(start_type(&fake, "CDPUserSvc_4a1b2"), Some(2));
+        assert_eq!(fake.is_running("CDPUserSvc_4a1b2"), Some(true));
     }
-    
-    fn delete_registry_value(path: &str, value_name: &str) {
-        unsafe {
-            let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-            let value_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
-            
-            let mut hkey = HKEY::default();
-            if RegOpenKeyExW(HKEY_LOCAL_MACHINE, PCWSTR(path_wide.as_ptr()), 0, KEY_WRITE, &mut hkey).is_err() {
-                return;
-            }
-            
-            let _ = RegDeleteValueW(hkey, PCWSTR(value_wide.as_ptr()));
-            let _ = RegCloseKey(hkey);
-        }
+
+    #[test]
+    fn revert_without_apply_changes_nothing() {
+        let fake = Arc::new(FakeSystem::new());
+        fake.set(Hive::LocalMachine, MMCSS, "SystemResponsiveness", Value::Dword(0));
+
+        ReviTweaksService::with_system(fake.clone()).revert();
+        assert_eq!(fake.values.lock().unwrap().len(), 1);
     }
 }
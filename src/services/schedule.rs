@@ -0,0 +1,62 @@
+//! Scheduled Activation
+//! Time-window rules (e.g. weekdays 19:00-23:00) that enable game mode automatically,
+//! plus the local-time helpers the scheduler thread needs
+
+use serde::{Deserialize, Serialize};
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+/// One activation window; `end` before `start` spans midnight (22:00-02:00)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Days the window starts on, 0 = Monday ... 6 = Sunday (empty = every day)
+    #[serde(default)]
+    pub days: Vec<u8>,
+
+    /// "HH:MM", local time
+    pub start: String,
+    pub end: String,
+}
+
+fn default_true() -> bool { true }
+
+impl ScheduleRule {
+    /// True if (weekday, minute of day) falls inside this rule's window
+    fn contains(&self, weekday: u8, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_minutes(&self.start), parse_minutes(&self.end)) else {
+            return false;
+        };
+        let runs_on = |day: u8| self.days.is_empty() || self.days.contains(&day);
+
+        if start <= end {
+            runs_on(weekday) && minute >= start && minute < end
+        } else {
+            // Overnight: the part after midnight belongs to the previous day's window
+            let yesterday = (weekday + 6) % 7;
+            (runs_on(weekday) && minute >= start) || (runs_on(yesterday) && minute < end)
+        }
+    }
+}
+
+/// "19:30" -> 1170
+fn parse_minutes(time: &str) -> Option<u32> {
+    let (h, m) = time.trim().split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// Current local (weekday 0 = Monday, minute of day)
+fn local_now() -> (u8, u32) {
+    let now = unsafe { GetLocalTime() };
+    // SYSTEMTIME counts Sunday as 0
+    let weekday = ((now.wDayOfWeek + 6) % 7) as u8;
+    (weekday, now.wHour as u32 * 60 + now.wMinute as u32)
+}
+
+/// True if any enabled rule covers the current local time
+pub fn is_scheduled_now(rules: &[ScheduleRule]) -> bool {
+    let (weekday, minute) = local_now();
+    rules.iter().any(|r| r.enabled && r.contains(weekday, minute))
+}
@@ -0,0 +1,78 @@
+//! Session IDs
+//! Short unique ID per game mode session, prefixed to every log line
+//! so a log covering several sessions can be filtered per session,
+//! and the profile an auto-activated session runs with
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+
+static SESSION_ID: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+static SESSION_STARTED: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+static SESSION_PROFILE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Start a new session with a fresh ID (8 hex chars, e.g. "3f9a01c2")
+pub fn begin() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    // Mix in the PID so two instances started in the same tick still differ
+    let id = format!("{:08x}", (nanos as u32) ^ std::process::id().rotate_left(16));
+    *SESSION_ID.lock().unwrap() = Some(id);
+    *SESSION_STARTED.lock().unwrap() = Some(Instant::now());
+}
+
+/// End the current session; later log lines are untagged
+pub fn end() {
+    *SESSION_ID.lock().unwrap() = None;
+    *SESSION_STARTED.lock().unwrap() = None;
+    *SESSION_PROFILE.lock().unwrap() = None;
+}
+
+/// Run the next session with `profile` instead of the saved active profile (auto-activation);
+/// cleared by `end`, so the user's own pick is never overwritten.
+/// Ignored while a session is running, which keeps the profile it started with
+pub fn set_profile(profile: &str) {
+    let id = SESSION_ID.lock().unwrap();
+    if id.is_none() {
+        *SESSION_PROFILE.lock().unwrap() = Some(profile.to_string());
+    }
+}
+
+/// Drop a profile set for a session that never began (enable skipped)
+pub fn clear_profile() {
+    let id = SESSION_ID.lock().unwrap();
+    if id.is_none() {
+        *SESSION_PROFILE.lock().unwrap() = None;
+    }
+}
+
+/// Profile set for the running session with `set_profile`, if any
+pub fn profile() -> Option<String> {
+    SESSION_PROFILE.lock().unwrap().clone()
+}
+
+/// ID of the running session, if any
+pub fn current() -> Option<String> {
+    SESSION_ID.lock().unwrap().clone()
+}
+
+/// How long the running session has been active
+pub fn elapsed() -> Option<Duration> {
+    SESSION_STARTED.lock().unwrap().map(|started| started.elapsed())
+}
+
+/// println! that prefixes the active session ID: "[3f9a01c2] [Tag] message"
+/// The line is also kept for the crash log
+#[macro_export]
+macro_rules! log_line {
+    ($($arg:tt)*) => {{
+        let line = match $crate::services::session::current() {
+            Some(id) => format!("[{}] {}", id, format_args!($($arg)*)),
+            None => format!($($arg)*),
+        };
+        println!("{}", line);
+        $crate::services::crash::remember(line);
+    }};
+}
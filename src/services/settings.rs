@@ -1,12 +1,42 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::Write;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use crate::services::profiles::{self, GameFlags, WorkloadProfile};
+use crate::services::schedule::ScheduleRule;
+use crate::services::session;
+use crate::services::obs::ObsSettings;
+use crate::services::hooks::ScriptHook;
+use crate::services::update::UpdateChannel;
+use crate::services::controller::ControllerTrigger;
+use crate::services::theme::ThemeMode;
+use crate::services::startup::StartupMethod;
+use crate::services::registry::DEFAULT_PRIORITY_SEPARATION;
+use crate::log_line;
+
+/// Schema version written to settings.json; bump it and add a step to `MIGRATIONS`
+/// whenever a field is renamed or restructured
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` turns a version `n` document into version `n + 1`
+const MIGRATIONS: &[fn(&mut serde_json::Value)] = &[
+    // 0 -> 1: files from before versioning; same layout, only the version is added
+    |_| {},
+];
 
 /// AppSettings - 1:1 port of AppSettings.cs
 /// Note: C# has SuspendExplorer (default false), SuspendBrowsers (default true), SuspendLaunchers (default true)
 /// IsolateNetwork is in GameModeOptions but we store it in settings for persistence
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppSettings {
+    /// settings.json schema version (0 = written before versioning)
+    #[serde(default)]
+    pub version: u32,
+    
     /// Whether to kill explorer.exe during game mode (default: false)
     /// C#: public bool SuspendExplorer { get; set; }
     #[serde(default)]
@@ -22,6 +52,11 @@ pub struct AppSettings {
     #[serde(default = "default_true")]
     pub suspend_launchers: bool,
     
+    /// Block browsers/launchers with temporary firewall rules instead of killing them
+    /// Keeps their sessions alive while still freeing bandwidth during game mode
+    #[serde(default)]
+    pub firewall_block_background: bool,
+    
     /// Whether to enable network isolation (DNS multicast disable, NetBIOS disable)
     /// C#: This is passed via GameModeOptions.IsolateNetwork
     #[serde(default)]
@@ -38,11 +73,95 @@ pub struct AppSettings {
     #[serde(default)]
     pub disable_mpo: bool,
     
+    /// Scope "Disable MPO" to game mode sessions on the game's display
+    /// When true: OverlayTestMode is only written while a game runs on a display with overlay planes
+    #[serde(default)]
+    pub mpo_game_display_only: bool,
+    
     /// Whether to run on Windows startup
     /// Note: This was not in C# AppSettings but is useful for the app
     #[serde(default)]
     pub run_on_startup: bool,
     
+    /// How run_on_startup starts the app: logon task without UAC prompt ("task") or Run key ("run_key")
+    #[serde(default)]
+    pub startup_method: StartupMethod,
+    
+    /// Workload profiles (Gaming, Rendering, Compilation, user-defined)
+    #[serde(default = "profiles::default_profiles")]
+    pub profiles: Vec<WorkloadProfile>,
+    
+    /// Profile used for manual activation
+    #[serde(default = "default_profile_name")]
+    pub active_profile: String,
+    
+    /// Activate a profile automatically when one of its trigger processes starts
+    #[serde(default)]
+    pub auto_activate_profiles: bool,
+    
+    /// Start a session for games launched from Playnite / GOG Galaxy
+    #[serde(default = "default_true")]
+    pub frontend_integration: bool,
+    
+    /// Connecting a game controller enables game mode ("enable") or arms game detection ("detect")
+    #[serde(default)]
+    pub controller_trigger: ControllerTrigger,
+    
+    /// Time windows that enable game mode automatically (and disable it when they end)
+    #[serde(default)]
+    pub schedule: Vec<ScheduleRule>,
+    
+    /// Auto-revert any session after this many hours (0 = no limit)
+    #[serde(default)]
+    pub max_session_hours: u32,
+    
+    /// Seconds to wait for a relaunched game / launcher handoff after the monitored PID exits
+    /// 0 = restore immediately
+    #[serde(default = "default_relaunch_grace_secs")]
+    pub relaunch_grace_secs: u32,
+    
+    /// Specs report format for Copy/Save Specs: "text", "markdown" or "json"
+    #[serde(default = "default_specs_format")]
+    pub specs_format: String,
+    
+    /// Per-title GPU preference / fullscreen optimization flags, matched by exe name or path
+    #[serde(default)]
+    pub game_flags: Vec<GameFlags>,
+    
+    /// OBS replay buffer / recording control over obs-websocket
+    #[serde(default)]
+    pub obs: ObsSettings,
+    
+    /// User scripts/programs run after game mode is applied / after it is restored
+    #[serde(default)]
+    pub on_enable_hooks: Vec<ScriptHook>,
+    #[serde(default)]
+    pub on_disable_hooks: Vec<ScriptHook>,
+    
+    /// "stable" (default) or "beta" to also be offered pre-releases
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// Background update check interval in hours (0 = off); found updates are only shown, never downloaded
+    #[serde(default)]
+    pub update_check_hours: u32,
+    
+    /// UI language code ("de", "en", ...); empty follows the Windows display language
+    #[serde(default)]
+    pub language: String,
+    /// "system" (default, follows Windows), "dark" or "light"
+    #[serde(default)]
+    pub theme: ThemeMode,
+    /// Accent color as "#RRGGBB"; empty uses the Windows accent color
+    #[serde(default)]
+    pub accent_color: String,
+    /// Tighter layout for small screens
+    #[serde(default)]
+    pub compact_window: bool,
+    
+    /// Conflicting optimizers already warned about at startup (see services::conflicts)
+    #[serde(default)]
+    pub acknowledged_conflicts: Vec<String>,
+    
     /// Advanced module settings for 1% lows optimization
     #[serde(default)]
     pub advanced_modules: AdvancedModuleSettings,
@@ -57,11 +176,46 @@ pub struct AdvancedModuleSettings {
     #[serde(default)]
     pub disable_core_parking: bool,
     
+    /// Core parking module values written to the active plan's processor settings (AC, percent)
+    /// CPMINCORES / CPMAXCORES: share of cores kept unparked (100 = parking off)
+    #[serde(default = "default_percent_full")]
+    pub parking_min_cores: u32,
+    #[serde(default = "default_percent_full")]
+    pub parking_max_cores: u32,
+    
+    /// PERFINCTHRESHOLD / PERFDECTHRESHOLD: busy % that raises / lowers the performance state
+    /// None = leave the plan's value alone
+    #[serde(default)]
+    pub perf_increase_threshold: Option<u32>,
+    #[serde(default)]
+    pub perf_decrease_threshold: Option<u32>,
+    
+    /// PROCTHROTTLEMIN: frequency floor as minimum processor state % (None = unchanged)
+    #[serde(default)]
+    pub min_processor_state: Option<u32>,
+    
     /// Enable large system pages for better TLB efficiency
     /// Best for: 16GB+ RAM systems
     #[serde(default)]
     pub enable_large_pages: bool,
     
+    /// Grant SeLockMemoryPrivilege to the current user while large pages are on (asked first,
+    /// removed again when turned off; see lock_memory)
+    #[serde(default)]
+    pub lock_memory_privilege: bool,
+    
+    /// Hard minimum working set for the detected game while large pages are on
+    #[serde(default)]
+    pub raise_game_working_set: bool,
+    
+    /// Minimum working set in MB for `raise_game_working_set` (settings.json only)
+    #[serde(default = "default_game_min_working_set_mb")]
+    pub game_min_working_set_mb: u32,
+    
+    /// MSI mode also pins GPU / NIC interrupts to their own cores (see interrupts; permanent, not per session)
+    #[serde(default)]
+    pub pin_device_interrupts: bool,
+    
     /// Boost MMCSS (Multimedia Class Scheduler Service) priority for game threads
     /// Reduces scheduling latency for multimedia/game threads
     #[serde(default)]
@@ -81,50 +235,439 @@ pub struct AdvancedModuleSettings {
     /// Reduces network latency spikes during gaming (default: true)
     #[serde(default = "default_true")]
     pub lower_bufferbloat: bool,
+    
+    /// Turn off TCP Explicit Congestion Notification for the session (original restored on disable)
+    #[serde(default)]
+    pub disable_ecn: bool,
+    
+    /// Turn off Receive Segment Coalescing for the session (original restored on disable)
+    #[serde(default)]
+    pub disable_rsc: bool,
+    
+    /// Flush the DNS resolver cache when a session starts
+    #[serde(default)]
+    pub flush_dns_cache: bool,
+    
+    /// Switch the gaming adapter to `dns_servers` for the session (original servers restored on disable)
+    #[serde(default)]
+    pub dns_override: bool,
+    
+    /// DNS servers used by `dns_override` (IPv4 and/or IPv6)
+    #[serde(default = "default_dns_servers")]
+    pub dns_servers: Vec<String>,
+    
+    /// Tune the active NIC: interrupt moderation off, RSS on, power-saving offloads off
+    /// Original adapter properties are captured and restored on disable
+    #[serde(default)]
+    pub tune_network_adapter: bool,
+    
+    /// Tag the detected game's traffic with `dscp_value` through a QoS policy for the session
+    /// Only helps with routers that honor DSCP; the policy is removed on disable
+    #[serde(default)]
+    pub dscp_tagging: bool,
+    
+    /// DSCP value written by the QoS policy (46 = Expedited Forwarding)
+    #[serde(default = "default_dscp_value")]
+    pub dscp_value: u32,
+    
+    /// GPU whose interrupts MSI mode / interrupt pinning tune on multi-GPU systems (DXGI adapter name);
+    /// HAGS only checks it is hardware. Empty = auto (adapter with the most dedicated VRAM)
+    #[serde(default)]
+    pub target_gpu: String,
+    
+    /// Ping the game server (or latency_target) during game mode
+    /// Records latency/jitter/loss for the session and flags spikes
+    #[serde(default)]
+    pub latency_monitor: bool,
+    
+    /// Endpoint pinged by the latency monitor (host or IPv4)
+    /// Empty = game server from the game's TCP connections, else 1.1.1.1
+    #[serde(default)]
+    pub latency_target: String,
+    
+    /// RTT in ms counted as a latency spike (0 = no spike alerts)
+    #[serde(default = "default_latency_spike_ms")]
+    pub latency_spike_ms: u32,
+    
+    /// Watch per-process TCP traffic during game mode and alert (with Suspend / Kill)
+    /// when a background process stays above `bandwidth_alert_kbps`
+    #[serde(default)]
+    pub bandwidth_watch: bool,
+    
+    /// Background traffic in KB/s that triggers a bandwidth alert
+    #[serde(default = "default_bandwidth_alert_kbps")]
+    pub bandwidth_alert_kbps: u32,
+    
+    /// Trace DPC/ISR execution times during game mode
+    /// Reports the drivers with the longest ones when the session ends
+    #[serde(default)]
+    pub dpc_monitor: bool,
+    
+    /// Sample CPU temperature and clock limits during game mode
+    /// Warns when the CPU stays thermal- or power-throttled
+    #[serde(default)]
+    pub thermal_monitor: bool,
+    
+    /// Demotion category: telemetry (compattelrunner)
+    #[serde(default = "default_true")]
+    pub demote_telemetry: bool,
+    
+    /// Demotion category: cloud sync (OneDrive, Dropbox, Google Drive, SettingSyncHost)
+    #[serde(default = "default_true")]
+    pub demote_sync: bool,
+    
+    /// Demotion category: search indexer
+    #[serde(default = "default_true")]
+    pub demote_search: bool,
+    
+    /// Demotion category: security (Defender, SecurityHealthService, SgrmBroker)
+    /// Off by default - demoting the AV engine is not wanted by most users
+    #[serde(default)]
+    pub demote_security: bool,
+    
+    /// Demotion category: generic background hosts (WmiPrvSE, spoolsv, dllhost, RuntimeBroker...)
+    #[serde(default = "default_true")]
+    pub demote_background: bool,
+    
+    /// Extra process names (without .exe) demoted in addition to the categories
+    #[serde(default)]
+    pub demote_custom: Vec<String>,
+    
+    /// Pause Windows Update and throttle Delivery Optimization during game mode
+    /// Uses the official pause keys (expire after a day) instead of only stopping wuauserv/dosvc
+    #[serde(default)]
+    pub pause_windows_update: bool,
+    
+    /// Add a temporary Defender process exclusion for the detected game exe
+    /// Stops real-time scans of the game's file I/O, which idle demotion of MsMpEng does not
+    #[serde(default)]
+    pub defender_game_exclusion: bool,
+    
+    /// Cooperate with Windows Game Mode during sessions
+    /// Turns on Windows Game Mode and registers the detected game in GameConfigStore
+    #[serde(default)]
+    pub windows_game_mode_coop: bool,
+    
+    /// Also kill child processes of closed launchers/peripheral apps (Agent.exe, updaters)
+    #[serde(default = "default_true")]
+    pub kill_process_trees: bool,
+    
+    /// Empty the working sets of background processes when the session starts
+    /// The game, its children and critical processes (csrss, dwm, audiodg, anti-cheat) are never trimmed
+    #[serde(default = "default_true")]
+    pub flush_working_sets: bool,
+    
+    /// Extra process names (without .exe) the working set flush leaves alone
+    #[serde(default)]
+    pub flush_exclusions: Vec<String>,
+    
+    /// Re-trim background working sets and purge the standby list every `periodic_trim_minutes`
+    /// during game mode (for long sessions on 8-16 GB machines); the game is never trimmed
+    #[serde(default)]
+    pub periodic_trim: bool,
+    
+    /// Minutes between periodic trims
+    #[serde(default = "default_periodic_trim_minutes")]
+    pub periodic_trim_minutes: u32,
+    
+    /// Disable USB selective suspend in the active power plan (AC + DC)
+    /// Avoids input latency spikes from mice/keyboards/DACs waking up
+    #[serde(default)]
+    pub disable_usb_suspend: bool,
+    
+    /// Turn off PCIe Link State Power Management in the active power plan (AC + DC)
+    /// Avoids GPU/NVMe link renegotiation stutter
+    #[serde(default)]
+    pub disable_pcie_aspm: bool,
+    
+    /// Turn off Enhance pointer precision (mouse acceleration) for the session
+    #[serde(default)]
+    pub disable_mouse_acceleration: bool,
+    
+    /// Raise the mouclass MouseDataQueueSize buffer (takes effect after a reboot)
+    #[serde(default)]
+    pub raise_mouse_queue_size: bool,
+    
+    /// Turn off the Sticky Keys / Filter Keys shortcut prompts (Shift x5, hold right Shift) for the session
+    #[serde(default)]
+    pub suppress_accessibility_hotkeys: bool,
+    
+    /// Swallow the Windows key while the detected game is in the foreground
+    #[serde(default)]
+    pub block_windows_key: bool,
+    
+    /// Disable audio enhancements (APO effects) on the default playback device
+    #[serde(default)]
+    pub disable_audio_enhancements: bool,
+    
+    /// Set communications ducking to "Do nothing" so voice chat doesn't lower game audio
+    #[serde(default)]
+    pub disable_comms_ducking: bool,
+    
+    /// Turn on Windows HDR on the primary display for the session (restored on disable)
+    #[serde(default)]
+    pub enable_hdr: bool,
+    
+    /// Turn on Auto HDR for the session (Windows 11; also turns HDR on)
+    #[serde(default)]
+    pub enable_auto_hdr: bool,
+    
+    /// Turn Night Light and color filters off for the session (restored on disable)
+    #[serde(default)]
+    pub suspend_night_light: bool,
+    
+    /// Switch Windows animations, shadows and transparency to "best performance" for the session
+    #[serde(default)]
+    pub best_performance_visuals: bool,
+    
+    /// Switch off every display except the primary one for the session (topology restored on disable)
+    #[serde(default)]
+    pub disable_secondary_displays: bool,
+    
+    /// Switch off Game DVR background recording and the Game Bar presence writer for the session
+    /// (killing the processes alone doesn't last, Windows relaunches them)
+    #[serde(default)]
+    pub disable_game_dvr: bool,
 }
 
 impl Default for AdvancedModuleSettings {
     fn default() -> Self {
         Self {
             disable_core_parking: false,
+            parking_min_cores: default_percent_full(),
+            parking_max_cores: default_percent_full(),
+            perf_increase_threshold: None,
+            perf_decrease_threshold: None,
+            min_processor_state: None,
             enable_large_pages: false,
+            lock_memory_privilege: false,
+            raise_game_working_set: false,
+            game_min_working_set_mb: default_game_min_working_set_mb(),
+            pin_device_interrupts: false,
             mmcss_priority_boost: false,
             enable_hags: false,
             process_idle_demotion: false,
             lower_bufferbloat: true, // ON by default
+            disable_ecn: false,
+            disable_rsc: false,
+            flush_dns_cache: false,
+            dns_override: false,
+            dns_servers: default_dns_servers(),
+            tune_network_adapter: false,
+            dscp_tagging: false,
+            dscp_value: default_dscp_value(),
+            target_gpu: String::new(),
+            latency_monitor: false,
+            latency_target: String::new(),
+            latency_spike_ms: default_latency_spike_ms(),
+            bandwidth_watch: false,
+            bandwidth_alert_kbps: default_bandwidth_alert_kbps(),
+            dpc_monitor: false,
+            thermal_monitor: false,
+            demote_telemetry: true,
+            demote_sync: true,
+            demote_search: true,
+            demote_security: false,
+            demote_background: true,
+            demote_custom: Vec::new(),
+            pause_windows_update: false,
+            defender_game_exclusion: false,
+            windows_game_mode_coop: false,
+            kill_process_trees: true,
+            flush_working_sets: true,
+            flush_exclusions: Vec::new(),
+            periodic_trim: false,
+            periodic_trim_minutes: default_periodic_trim_minutes(),
+            disable_usb_suspend: false,
+            disable_pcie_aspm: false,
+            disable_mouse_acceleration: false,
+            raise_mouse_queue_size: false,
+            suppress_accessibility_hotkeys: false,
+            block_windows_key: false,
+            disable_audio_enhancements: false,
+            disable_comms_ducking: false,
+            enable_hdr: false,
+            enable_auto_hdr: false,
+            suspend_night_light: false,
+            best_performance_visuals: false,
+            disable_secondary_displays: false,
+            disable_game_dvr: false,
         }
     }
 }
 
 fn default_true() -> bool { true }
 
+fn default_profile_name() -> String { profiles::GAMING_PROFILE.to_string() }
+
+fn default_latency_spike_ms() -> u32 { 100 }
+fn default_bandwidth_alert_kbps() -> u32 { 1024 }
+fn default_periodic_trim_minutes() -> u32 { 15 }
+
+fn default_dscp_value() -> u32 { 46 }
+fn default_dns_servers() -> Vec<String> { vec!["1.1.1.1".to_string(), "1.0.0.1".to_string()] }
+
+fn default_percent_full() -> u32 { 100 }
+
+fn default_game_min_working_set_mb() -> u32 { 1024 }
+
+fn default_relaunch_grace_secs() -> u32 { 15 }
+
+fn default_specs_format() -> String { "text".to_string() }
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             suspend_explorer: false,
             suspend_browsers: true,
             suspend_launchers: true,
+            firewall_block_background: false,
             isolate_network: false,
             advanced_tweaks: false,
             disable_mpo: false,
+            mpo_game_display_only: false,
             run_on_startup: false,
+            startup_method: StartupMethod::default(),
+            profiles: profiles::default_profiles(),
+            active_profile: default_profile_name(),
+            auto_activate_profiles: false,
+            frontend_integration: true,
+            controller_trigger: ControllerTrigger::default(),
+            schedule: Vec::new(),
+            max_session_hours: 0,
+            relaunch_grace_secs: default_relaunch_grace_secs(),
+            specs_format: default_specs_format(),
+            game_flags: Vec::new(),
+            obs: ObsSettings::default(),
+            on_enable_hooks: Vec::new(),
+            on_disable_hooks: Vec::new(),
+            update_channel: UpdateChannel::default(),
+            update_check_hours: 0,
+            language: String::new(),
+            theme: ThemeMode::default(),
+            accent_color: String::new(),
+            compact_window: false,
+            acknowledged_conflicts: Vec::new(),
             advanced_modules: AdvancedModuleSettings::default(),
         }
     }
 }
 
+impl AppSettings {
+    /// Profile sessions run with: the one auto-activation picked for this session, else active_profile
+    pub fn session_profile(&self) -> String {
+        session::profile().unwrap_or_else(|| self.active_profile.clone())
+    }
+
+    /// Advanced modules with the session profile's overrides applied
+    pub fn effective_advanced_modules(&self) -> AdvancedModuleSettings {
+        let mut modules = self.advanced_modules.clone();
+        if let Some(demotion) = profiles::find(&self.profiles, &self.session_profile())
+            .and_then(|p| p.process_idle_demotion)
+        {
+            modules.process_idle_demotion = demotion;
+        }
+        modules
+    }
+
+    /// Win32PrioritySeparation the active profile writes for a session
+    pub fn effective_priority_separation(&self) -> u32 {
+        profiles::find(&self.profiles, &self.active_profile)
+            .and_then(|p| p.priority_separation)
+            .unwrap_or(DEFAULT_PRIORITY_SEPARATION)
+    }
+}
+
+/// Marker file beside the exe that turns on portable mode, same as --portable
+const PORTABLE_MARKER: &str = "portable.txt";
+
+// Decided once per process: --portable on the command line or portable.txt beside the exe
+static PORTABLE: Lazy<bool> = Lazy::new(|| {
+    std::env::args().skip(1).any(|a| a == "--portable")
+        || exe_dir().is_some_and(|dir| dir.join(PORTABLE_MARKER).exists())
+});
+
+/// Portable mode: settings.json and the state files (session journal, baselines, markers)
+/// live beside the exe so the app can run from a USB stick without touching %LOCALAPPDATA%
+pub fn is_portable() -> bool {
+    *PORTABLE
+}
+
+/// Folder for settings.json and the state files: beside the exe in portable mode,
+/// %LOCALAPPDATA%\XillyGameMode otherwise
+pub fn data_dir() -> PathBuf {
+    if is_portable() {
+        if let Some(dir) = exe_dir() {
+            return dir;
+        }
+    }
+    dirs::data_local_dir()
+        .unwrap_or(PathBuf::from("."))
+        .join("XillyGameMode")
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(PathBuf::from)
+}
+
+/// Quiet time before a debounced save is written; rapid toggling ends up as one write
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(750);
+
+struct PendingSave {
+    file_path: PathBuf,
+    settings: AppSettings,
+    due: Instant,
+}
+
+// Latest settings waiting for the debounce; a writer thread is running while this is Some.
+// Writes happen under this lock, so a direct save and the writer can't overtake each other
+static PENDING_SAVE: Lazy<Mutex<Option<PendingSave>>> = Lazy::new(|| Mutex::new(None));
+
+/// Write a debounced save right away (exit, update, shutdown); no-op if nothing is waiting
+pub fn flush_pending() {
+    let mut pending = PENDING_SAVE.lock().unwrap();
+    if let Some(save) = pending.take() {
+        write_atomic(&save.file_path, &save.settings);
+    }
+}
+
+/// Write through settings.json.tmp and a rename, so a crash mid-write leaves the old file intact;
+/// the replaced file is kept as settings.json.prev
+fn write_atomic(file_path: &Path, settings: &AppSettings) {
+    let Ok(content) = serde_json::to_string_pretty(settings) else {
+        return;
+    };
+    let temp = file_path.with_extension("json.tmp");
+    let written = fs::File::create(&temp)
+        .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()));
+    if let Err(e) = written {
+        log_line!("[Settings] Could not write settings.json: {}", e);
+        let _ = fs::remove_file(&temp);
+        return;
+    }
+    if file_path.exists() {
+        let _ = fs::copy(file_path, file_path.with_extension("json.prev"));
+    }
+    // Replaces the target in one step (MoveFileEx with MOVEFILE_REPLACE_EXISTING)
+    if let Err(e) = fs::rename(&temp, file_path) {
+        log_line!("[Settings] Could not replace settings.json: {}", e);
+        let _ = fs::remove_file(&temp);
+    }
+}
+
 /// SettingsService - 1:1 port of SettingsService.cs
 /// Handles loading and saving settings to JSON file in %LOCALAPPDATA%\XillyGameMode
+/// (beside the exe in portable mode, see `data_dir`)
 pub struct SettingsService {
     file_path: PathBuf,
 }
 
 impl SettingsService {
     /// 1:1 with C# constructor
-    /// Creates settings folder in %LOCALAPPDATA%\XillyGameMode if it doesn't exist
+    /// Creates the settings folder (see `data_dir`) if it doesn't exist
     pub fn new() -> Self {
-        let app_data = dirs::data_local_dir().unwrap_or(PathBuf::from("."));
-        let folder = app_data.join("XillyGameMode");
+        let folder = data_dir();
         if !folder.exists() {
             let _ = fs::create_dir_all(&folder);
         }
@@ -133,22 +676,145 @@ impl SettingsService {
         }
     }
 
+    /// False on first run (no settings saved yet)
+    pub fn exists(&self) -> bool {
+        self.file_path.exists()
+    }
+
     /// 1:1 with C# LoadSettingsAsync (synchronous version)
+    /// Older files are migrated to the current schema; fields that still fail to parse fall back
+    /// to their defaults one by one (the original is kept as settings.json.bak) instead of all at once
     pub fn load(&self) -> AppSettings {
-        if self.file_path.exists() {
-            if let Ok(content) = fs::read_to_string(&self.file_path) {
-                if let Ok(settings) = serde_json::from_str(&content) {
-                    return settings;
+        let Ok(content) = fs::read_to_string(&self.file_path) else {
+            return AppSettings::default();
+        };
+        let Ok(document) = serde_json::from_str::<serde_json::Value>(&content) else {
+            let _ = fs::copy(&self.file_path, self.file_path.with_extension("json.bak"));
+            return self.load_previous();
+        };
+
+        let previous_version = Self::document_version(&document);
+        let (settings, salvaged) = Self::from_value(document);
+        if salvaged {
+            let _ = fs::copy(&self.file_path, self.file_path.with_extension("json.bak"));
+        }
+        // A file from a newer version is read as far as possible but not overwritten
+        if previous_version > SETTINGS_VERSION {
+            log_line!("[Settings] settings.json is from a newer version ({}), not rewriting it", previous_version);
+        } else if salvaged || previous_version != SETTINGS_VERSION {
+            log_line!("[Settings] Migrated settings.json from version {} to {}", previous_version, SETTINGS_VERSION);
+            self.save(&settings);
+        }
+        settings
+    }
+
+    /// settings.json is unreadable: fall back to the copy kept by the last save, then to defaults
+    fn load_previous(&self) -> AppSettings {
+        let previous = fs::read_to_string(self.file_path.with_extension("json.prev")).ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+        let Some(document) = previous else {
+            log_line!("[Settings] settings.json is not valid JSON, using defaults");
+            return AppSettings::default();
+        };
+        log_line!("[Settings] settings.json is not valid JSON, using the previous save");
+        let (settings, _) = Self::from_value(document);
+        self.save(&settings);
+        settings
+    }
+
+    /// Settings from a settings document of any version (settings.json, presets)
+    /// The flag is true if some fields could not be read and were reset to defaults
+    pub fn from_value(mut document: serde_json::Value) -> (AppSettings, bool) {
+        let version = Self::document_version(&document);
+        for (step, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migrate(&mut document);
+            log_line!("[Settings] Applied settings migration {} -> {}", step, step + 1);
+        }
+        if let Some(fields) = document.as_object_mut() {
+            fields.insert("version".to_string(), SETTINGS_VERSION.into());
+        }
+
+        if let Ok(settings) = serde_json::from_value::<AppSettings>(document.clone()) {
+            return (settings, false);
+        }
+
+        // Keep every field that still parses, on top of the defaults
+        let mut merged = serde_json::to_value(AppSettings::default()).unwrap_or_default();
+        Self::salvage(&mut merged, &document, &[]);
+        let settings = serde_json::from_value(merged).unwrap_or_default();
+        (settings, true)
+    }
+
+    fn document_version(document: &serde_json::Value) -> u32 {
+        document.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32
+    }
+
+    /// Copy fields of `source` into `merged` (at `path`) one at a time, keeping only those
+    /// that leave `merged` deserializable; nested objects are merged field by field
+    fn salvage(merged: &mut serde_json::Value, source: &serde_json::Value, path: &[String]) {
+        let Some(fields) = source.as_object() else {
+            return;
+        };
+        for (key, value) in fields {
+            let mut field_path = path.to_vec();
+            field_path.push(key.clone());
+
+            let Some(target) = Self::pointer_mut(merged, &field_path) else {
+                continue; // Unknown field, serde ignores it anyway
+            };
+            if target.is_object() && value.is_object() {
+                Self::salvage(merged, value, &field_path);
+                continue;
+            }
+
+            let previous = std::mem::replace(target, value.clone());
+            if serde_json::from_value::<AppSettings>(merged.clone()).is_err() {
+                if let Some(target) = Self::pointer_mut(merged, &field_path) {
+                    *target = previous;
                 }
+                log_line!("[Settings] Reset unreadable field {} to its default", field_path.join("."));
             }
         }
-        AppSettings::default()
+    }
+
+    fn pointer_mut<'a>(value: &'a mut serde_json::Value, path: &[String]) -> Option<&'a mut serde_json::Value> {
+        path.iter().try_fold(value, |current, key| current.get_mut(key))
     }
 
     /// 1:1 with C# SaveSettingsAsync (synchronous version)
+    /// Supersedes a debounced save that is still waiting
     pub fn save(&self, settings: &AppSettings) {
-        if let Ok(content) = serde_json::to_string_pretty(settings) {
-            let _ = fs::write(&self.file_path, content);
+        let mut pending = PENDING_SAVE.lock().unwrap();
+        *pending = None;
+        write_atomic(&self.file_path, settings);
+    }
+
+    /// Save once no further change arrived for `SAVE_DEBOUNCE` (switches, tray toggles)
+    pub fn save_debounced(&self, settings: &AppSettings) {
+        let mut pending = PENDING_SAVE.lock().unwrap();
+        let writer_running = pending.is_some();
+        *pending = Some(PendingSave {
+            file_path: self.file_path.clone(),
+            settings: settings.clone(),
+            due: Instant::now() + SAVE_DEBOUNCE,
+        });
+        if writer_running {
+            return;
         }
+        drop(pending);
+
+        thread::spawn(|| loop {
+            // None: a direct save or a flush got there first
+            let Some(due) = PENDING_SAVE.lock().unwrap().as_ref().map(|p| p.due) else {
+                return;
+            };
+            let now = Instant::now();
+            if now < due {
+                thread::sleep(due - now);
+                continue;
+            }
+            flush_pending();
+            return;
+        });
     }
 }
@@ -0,0 +1,111 @@
+//! Shutdown / Logoff
+//! Windows ends the process at shutdown or logoff without the close path running, leaving the
+//! session's tweaks applied until the watchdog's journal recovery at the next start. A hidden
+//! window answers WM_QUERYENDSESSION / WM_ENDSESSION and restores the session before the process dies.
+//! (A console control handler would not help: GUI processes never get CTRL_SHUTDOWN_EVENT.)
+
+use std::thread;
+use once_cell::sync::OnceCell;
+use windows::core::{w, HSTRING};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy};
+use windows::Win32::System::Threading::SetProcessShutdownParameters;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, MSG, WINDOW_EX_STYLE,
+    WINDOW_STYLE, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW,
+};
+use crate::services::settings;
+use crate::{log_line, tr};
+
+/// Highest level for applications (0x100-0x3FF): notified before other apps, so the
+/// restore can still talk to services and explorer
+const SHUTDOWN_LEVEL: u32 = 0x3FF;
+
+struct ShutdownCallbacks {
+    is_active: Box<dyn Fn() -> bool + Send + Sync>,
+    on_end: Box<dyn Fn() + Send + Sync>,
+}
+
+static CALLBACKS: OnceCell<ShutdownCallbacks> = OnceCell::new();
+
+/// Run `on_end` (the full disable path) when Windows shuts down or logs off while `is_active`;
+/// shutdown waits for it to return
+pub fn watch(
+    is_active: impl Fn() -> bool + Send + Sync + 'static,
+    on_end: impl Fn() + Send + Sync + 'static,
+) {
+    if CALLBACKS.set(ShutdownCallbacks { is_active: Box::new(is_active), on_end: Box::new(on_end) }).is_err() {
+        return; // Already watching
+    }
+
+    unsafe {
+        // No SHUTDOWN_NORETRY: if the restore runs long, Windows asks the user instead of killing it
+        if let Err(e) = SetProcessShutdownParameters(SHUTDOWN_LEVEL, 0) {
+            log_line!("[Shutdown] Could not raise the shutdown priority: {}", e);
+        }
+    }
+
+    thread::spawn(|| unsafe {
+        let Ok(module) = GetModuleHandleW(None) else { return };
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(shutdown_window_proc),
+            hInstance: HINSTANCE::from(module),
+            lpszClassName: w!("XillyGameModeShutdown"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+
+        // Hidden top-level window: message-only windows don't get WM_QUERYENDSESSION
+        if let Err(e) = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("XillyGameModeShutdown"),
+            w!("Xilly Game Mode"),
+            WINDOW_STYLE::default(),
+            0, 0, 0, 0,
+            None,
+            None,
+            HINSTANCE::from(module),
+            None,
+        ) {
+            log_line!("[Shutdown] Failed to create the shutdown window: {}", e);
+            return;
+        }
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            DispatchMessageW(&msg);
+        }
+    });
+}
+
+fn is_active() -> bool {
+    CALLBACKS.get().is_some_and(|c| (c.is_active)())
+}
+
+unsafe extern "system" fn shutdown_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_QUERYENDSESSION => {
+            // Never veto; the reason is shown if Windows lists apps that are still busy
+            if is_active() {
+                let _ = ShutdownBlockReasonCreate(hwnd, &HSTRING::from(tr!("Restoring settings changed by game mode")));
+            }
+            LRESULT(1)
+        }
+        WM_ENDSESSION => {
+            // wparam is FALSE when the shutdown was cancelled
+            if wparam.0 != 0 {
+                settings::flush_pending();
+            }
+            if wparam.0 != 0 && is_active() {
+                log_line!("[Shutdown] Windows is ending the session, restoring");
+                if let Some(callbacks) = CALLBACKS.get() {
+                    (callbacks.on_end)();
+                }
+            }
+            let _ = ShutdownBlockReasonDestroy(hwnd);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
@@ -0,0 +1,360 @@
+//! System Specs
+//! Hardware / OS report for Copy Specs and Save Specs, rendered as plain text,
+//! Markdown (support threads) or JSON
+
+use std::process::Command;
+use std::os::windows::process::CommandExt;
+use serde::Serialize;
+use crate::services::display::{DisplayService, MonitorMode};
+use crate::services::gpu::GpuService;
+use crate::services::power::PowerService;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Report output format, stored as "text" / "markdown" / "json" in settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecsFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl SpecsFormat {
+    /// Unknown names fall back to plain text
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Self::Markdown,
+            "json" => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuSpec {
+    pub name: String,
+    pub vram_gb: f64,
+    pub driver_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemSpecs {
+    pub cpu: String,
+    pub gpus: Vec<GpuSpec>,
+    pub ram: String,
+    pub motherboard: String,
+    pub storage: Vec<String>,
+    pub os: String,
+    pub monitors: Vec<MonitorMode>,
+    pub power_plan: String,
+    /// Game mode state and every change currently in place
+    pub tweaks: Vec<String>,
+}
+
+impl SystemSpecs {
+    /// Query everything; `tweaks` is the tweak status supplied by the caller
+    pub fn collect(tweaks: Vec<String>) -> Self {
+        Self {
+            cpu: Self::cpu(),
+            gpus: Self::gpus(),
+            ram: Self::ram(),
+            motherboard: Self::motherboard(),
+            storage: Self::storage(),
+            os: Self::os(),
+            monitors: DisplayService::monitor_modes(),
+            power_plan: PowerService::active_scheme_name().unwrap_or_else(|| "Unknown".to_string()),
+            tweaks,
+        }
+    }
+
+    pub fn render(&self, format: SpecsFormat) -> String {
+        match format {
+            SpecsFormat::Text => self.to_text(),
+            SpecsFormat::Markdown => self.to_markdown(),
+            SpecsFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+        }
+    }
+
+    fn gpu_lines(&self) -> Vec<String> {
+        if self.gpus.is_empty() {
+            return vec!["Unknown".to_string()];
+        }
+        self.gpus
+            .iter()
+            .map(|gpu| {
+                let mut line = gpu.name.clone();
+                if gpu.vram_gb > 0.1 {
+                    line.push_str(&format!(" ({:.1} GB)", gpu.vram_gb));
+                }
+                if let Some(driver) = &gpu.driver_version {
+                    line.push_str(&format!(", driver {}", driver));
+                }
+                line
+            })
+            .collect()
+    }
+
+    fn monitor_lines(&self) -> Vec<String> {
+        if self.monitors.is_empty() {
+            return vec!["Unknown".to_string()];
+        }
+        self.monitors
+            .iter()
+            .map(|m| {
+                let name = if m.hardware_id.is_empty() { m.name.clone() } else { format!("{} [{}]", m.name, m.hardware_id) };
+                format!("{} {}x{} @ {} Hz", name, m.width, m.height, m.refresh_hz)
+            })
+            .collect()
+    }
+
+    fn to_text(&self) -> String {
+        // Continuation lines align under the value column
+        let join = |lines: &[String]| lines.join("\n          ");
+        let storage = if self.storage.is_empty() { vec!["Unknown".to_string()] } else { self.storage.clone() };
+
+        format!(
+            "System Specs:\n\
+             CPU:      {}\n\
+             GPU:      {}\n\
+             RAM:      {}\n\
+             Mobo:     {}\n\
+             Storage:  {}\n\
+             Display:  {}\n\
+             OS:       {}\n\
+             Power:    {}\n\
+             Tweaks:   {}",
+            self.cpu,
+            join(&self.gpu_lines()),
+            self.ram,
+            self.motherboard,
+            join(&storage),
+            join(&self.monitor_lines()),
+            self.os,
+            self.power_plan,
+            join(&self.tweaks),
+        )
+    }
+
+    fn to_markdown(&self) -> String {
+        let list = |lines: &[String]| lines.iter().map(|l| format!("  - {}", l)).collect::<Vec<_>>().join("\n");
+
+        format!(
+            "### System Specs\n\n\
+             | Component | Details |\n\
+             |---|---|\n\
+             | CPU | {} |\n\
+             | GPU | {} |\n\
+             | RAM | {} |\n\
+             | Motherboard | {} |\n\
+             | Storage | {} |\n\
+             | Display | {} |\n\
+             | OS | {} |\n\
+             | Power plan | {} |\n\n\
+             **Tweak status**\n{}",
+            self.cpu,
+            self.gpu_lines().join("<br>"),
+            self.ram,
+            self.motherboard,
+            self.storage.join("<br>"),
+            self.monitor_lines().join("<br>"),
+            self.os,
+            self.power_plan,
+            list(&self.tweaks),
+        )
+    }
+
+    // =========================================================================
+    // QUERIES (wmic /format:list output: one Key=Value per line)
+    // =========================================================================
+
+    fn wmic(args: &[&str]) -> Option<String> {
+        Command::new("wmic")
+            .args(args)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    }
+
+    /// CPU: Name, Cores, Threads
+    fn cpu() -> String {
+        let Some(s) = Self::wmic(&["cpu", "get", "name,NumberOfCores,NumberOfLogicalProcessors", "/format:list"]) else {
+            return "Unknown".to_string();
+        };
+        let mut name = String::new();
+        let mut cores = String::new();
+        let mut threads = String::new();
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Name=") {
+                name = v.trim().to_string();
+            } else if let Some(v) = line.strip_prefix("NumberOfCores=") {
+                cores = v.trim().to_string();
+            } else if let Some(v) = line.strip_prefix("NumberOfLogicalProcessors=") {
+                threads = v.trim().to_string();
+            }
+        }
+        if !name.is_empty() {
+            format!("{} ({} cores / {} threads)", name, cores, threads)
+        } else {
+            "Unknown".to_string()
+        }
+    }
+
+    /// GPUs from DXGI (accurate VRAM), driver versions from Win32_VideoController by name
+    fn gpus() -> Vec<GpuSpec> {
+        let drivers: Vec<(String, String)> = Self::wmic(&["path", "win32_VideoController", "get", "Name,DriverVersion", "/format:list"])
+            .map(|s| {
+                let mut pairs = Vec::new();
+                let mut driver = String::new();
+                for line in s.lines() {
+                    let line = line.trim();
+                    if let Some(v) = line.strip_prefix("DriverVersion=") {
+                        driver = v.trim().to_string();
+                    } else if let Some(v) = line.strip_prefix("Name=") {
+                        pairs.push((v.trim().to_string(), std::mem::take(&mut driver)));
+                    }
+                }
+                pairs
+            })
+            .unwrap_or_default();
+
+        GpuService::enumerate_adapters()
+            .into_iter()
+            // Basic Render Driver only when it has some VRAM
+            .filter(|a| a.name != "Microsoft Basic Render Driver" || a.vram_gb() > 0.0)
+            .map(|adapter| GpuSpec {
+                driver_version: drivers
+                    .iter()
+                    .find(|(name, driver)| name == &adapter.name && !driver.is_empty())
+                    .map(|(_, driver)| driver.clone()),
+                vram_gb: adapter.vram_gb(),
+                name: adapter.name,
+            })
+            .collect()
+    }
+
+    /// RAM: Total capacity and speed
+    fn ram() -> String {
+        let Some(s) = Self::wmic(&["memorychip", "get", "Capacity,Speed", "/format:list"]) else {
+            return "Unknown".to_string();
+        };
+        let mut total_capacity: u64 = 0;
+        let mut speed: u32 = 0;
+        let mut stick_count = 0;
+
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Capacity=") {
+                if let Ok(cap) = v.trim().parse::<u64>() {
+                    total_capacity += cap;
+                    stick_count += 1;
+                }
+            } else if let Some(v) = line.strip_prefix("Speed=") {
+                if let Ok(spd) = v.trim().parse::<u32>() {
+                    if spd > speed { speed = spd; }
+                }
+            }
+        }
+
+        let gb = total_capacity as f64 / 1073741824.0;
+        if speed > 0 {
+            format!("{:.0} GB ({} sticks @ {} MHz)", gb, stick_count, speed)
+        } else {
+            format!("{:.0} GB ({} sticks)", gb, stick_count)
+        }
+    }
+
+    /// OS: Caption + Build
+    fn os() -> String {
+        let Some(s) = Self::wmic(&["os", "get", "caption,BuildNumber,OSArchitecture", "/format:list"]) else {
+            return "Windows".to_string();
+        };
+        let mut caption = String::new();
+        let mut build = String::new();
+        let mut arch = String::new();
+
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Caption=") {
+                caption = v.trim().to_string();
+            } else if let Some(v) = line.strip_prefix("BuildNumber=") {
+                build = v.trim().to_string();
+            } else if let Some(v) = line.strip_prefix("OSArchitecture=") {
+                arch = v.trim().to_string();
+            }
+        }
+
+        format!("{} (Build {}) {}", caption, build, arch)
+    }
+
+    fn motherboard() -> String {
+        let Some(s) = Self::wmic(&["baseboard", "get", "Manufacturer,Product", "/format:list"]) else {
+            return "Unknown".to_string();
+        };
+        let mut manufacturer = String::new();
+        let mut product = String::new();
+
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Manufacturer=") {
+                manufacturer = v.trim().to_string();
+            } else if let Some(v) = line.strip_prefix("Product=") {
+                product = v.trim().to_string();
+            }
+        }
+        format!("{} {}", manufacturer, product)
+    }
+
+    /// Storage drives: "Model (N GB) SSD/HDD"
+    fn storage() -> Vec<String> {
+        let Some(s) = Self::wmic(&["diskdrive", "get", "Model,Size,MediaType", "/format:list"]) else {
+            return Vec::new();
+        };
+
+        let describe = |model: &str, size: u64, media: &str| {
+            let gb = size as f64 / 1000000000.0;
+            let type_str = if media.contains("SSD") || media.contains("Solid") {
+                "SSD"
+            } else if media.contains("Fixed") {
+                "HDD"
+            } else {
+                ""
+            };
+            format!("{} ({:.0} GB) {}", model, gb, type_str).trim().to_string()
+        };
+
+        let mut drives = Vec::new();
+        let mut current_model = String::new();
+        let mut current_size: u64 = 0;
+        let mut current_type = String::new();
+
+        for line in s.lines() {
+            let line = line.trim();
+            if let Some(v) = line.strip_prefix("Model=") {
+                if !current_model.is_empty() {
+                    drives.push(describe(&current_model, current_size, &current_type));
+                }
+                current_model = v.trim().to_string();
+                current_size = 0;
+                current_type.clear();
+            } else if let Some(v) = line.strip_prefix("Size=") {
+                current_size = v.trim().parse().unwrap_or(0);
+            } else if let Some(v) = line.strip_prefix("MediaType=") {
+                current_type = v.trim().to_string();
+            }
+        }
+        if !current_model.is_empty() {
+            drives.push(describe(&current_model, current_size, &current_type));
+        }
+        drives
+    }
+}
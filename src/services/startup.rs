@@ -0,0 +1,150 @@
+//! Startup
+//! Runs the app at logon. The exe requires administrator, so a Run-key entry makes Windows show
+//! a UAC prompt at every logon; a scheduled task with highest privileges starts it elevated without one
+
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::services::settings;
+use crate::log_line;
+
+const TASK_NAME: &str = "XillyGameMode";
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// How "run_on_startup" starts the app ("startup_method" in settings.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupMethod {
+    /// Task Scheduler task at logon with highest privileges (no UAC prompt)
+    #[default]
+    Task,
+    /// HKCU Run key (UAC prompt at every logon)
+    RunKey,
+}
+
+/// Register or remove autostart; the other method is always cleaned up so only one starts the app
+pub fn apply(enabled: bool, method: StartupMethod) {
+    let use_task = enabled && method == StartupMethod::Task;
+    let use_run_key = enabled && method == StartupMethod::RunKey;
+
+    if use_task {
+        create_task();
+    } else if task_exists() {
+        delete_task();
+    }
+    set_run_key(use_run_key);
+}
+
+/// Command-line arguments the app is started with at logon
+fn arguments() -> &'static [&'static str] {
+    if settings::is_portable() { &["--portable"] } else { &[] }
+}
+
+fn task_exists() -> bool {
+    schtasks(&["/Query", "/TN", TASK_NAME])
+}
+
+fn create_task() {
+    let Ok(exe) = std::env::current_exe() else { return };
+    // The logon trigger and principal are bound to the current user
+    let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(domain), Ok(name)) => format!(r"{}\{}", domain, name),
+        (_, Ok(name)) => name,
+        _ => return,
+    };
+
+    // XML instead of /SC ONLOGON: schtasks' defaults stop the task after 72 hours,
+    // don't start it on battery and run it at below-normal priority
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Starts Xilly Game Mode at logon</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>{user}</UserId>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>{user}</UserId>
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>HighestAvailable</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Priority>4</Priority>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+      <Arguments>{arguments}</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        user = escape_xml(&user),
+        command = escape_xml(&exe.to_string_lossy()),
+        arguments = escape_xml(&arguments().join(" ")),
+    );
+
+    // schtasks reads the definition from a UTF-16 file
+    let path = std::env::temp_dir().join("XillyGameMode-Task.xml");
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(xml.encode_utf16().flat_map(u16::to_le_bytes));
+    if let Err(e) = fs::write(&path, bytes) {
+        log_line!("[Startup] Could not write the task definition: {}", e);
+        return;
+    }
+
+    let path_arg = path.to_string_lossy().to_string();
+    if schtasks(&["/Create", "/TN", TASK_NAME, "/XML", &path_arg, "/F"]) {
+        log_line!("[Startup] Logon task registered");
+    } else {
+        log_line!("[Startup] Could not register the logon task");
+    }
+    let _ = fs::remove_file(&path);
+}
+
+fn delete_task() {
+    if schtasks(&["/Delete", "/TN", TASK_NAME, "/F"]) {
+        log_line!("[Startup] Logon task removed");
+    } else {
+        log_line!("[Startup] Could not remove the logon task");
+    }
+}
+
+fn set_run_key(enabled: bool) {
+    let Ok(auto) = auto_launch::AutoLaunchBuilder::new()
+        .set_app_name(TASK_NAME)
+        .set_app_path(&std::env::current_exe().unwrap_or_default().to_string_lossy())
+        .set_args(arguments())
+        .build()
+    else {
+        return;
+    };
+    if enabled {
+        let _ = auto.enable();
+    } else if auto.is_enabled().unwrap_or(false) {
+        let _ = auto.disable();
+    }
+}
+
+fn schtasks(args: &[&str]) -> bool {
+    Command::new("schtasks")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,140 @@
+//! System Status
+//! Live read-back of the values game mode touches, next to what the running session
+//! (or no session) should have left in place, to spot anything stuck after a crash or manual change
+
+use crate::services::advanced_modules::AdvancedModulesService;
+use crate::services::detector::GameDetector;
+use crate::services::gamemode::GameModeService;
+use crate::services::journal;
+use crate::services::power::PowerService;
+use crate::services::process::ProcessService;
+use crate::services::revi_tweaks::ReviTweaksService;
+use crate::services::windows::WindowsServiceManager;
+use crate::services::write_audit;
+
+/// Shown as the expected value of settings nothing is holding right now
+const NOT_MANAGED: &str = "Not managed";
+
+/// One live value and what it should be
+#[derive(Debug, Clone)]
+pub struct StatusRow {
+    pub label: &'static str,
+    pub current: String,
+    pub expected: String,
+    /// Current value matches what is expected (always true when not managed)
+    pub ok: bool,
+}
+
+impl StatusRow {
+    /// Row for a value the app may not be managing (`expected` None = anything goes)
+    fn compare(label: &'static str, current: String, expected: Option<String>) -> Self {
+        match expected {
+            Some(expected) => Self { label, ok: current == expected, current, expected },
+            None => Self { label, current, expected: NOT_MANAGED.to_string(), ok: true },
+        }
+    }
+}
+
+/// Read back every tracked value; `session_active` selects session or idle expectations
+pub fn collect(gamemode: &GameModeService, advanced: &AdvancedModulesService, session_active: bool) -> Vec<StatusRow> {
+    let modules = if session_active { advanced.session_modules() } else { None };
+    let revi = session_active && ReviTweaksService::is_applied();
+    let mut rows = Vec::with_capacity(8);
+
+    // Power plan
+    let plan = PowerService::active_scheme_name().unwrap_or_else(|| "Unknown".to_string());
+    rows.push(if session_active && gamemode.power_applied() {
+        if !GameDetector::is_desktop() {
+            // Laptops keep their plan and raise boost on it instead
+            StatusRow { label: "Power plan", current: plan, expected: "Current plan, boost raised".to_string(), ok: true }
+        } else {
+            let ok = PowerService::active_scheme().is_some_and(|s| PowerService::is_performance_scheme(&s));
+            StatusRow { label: "Power plan", current: plan, expected: "High / Ultimate Performance".to_string(), ok }
+        }
+    } else if !session_active && PowerService::stale_marker().is_some() {
+        StatusRow { label: "Power plan", current: plan, expected: "Plan from before the last session".to_string(), ok: false }
+    } else {
+        StatusRow::compare("Power plan", plan, None)
+    });
+
+    // TCP autotuning
+    let autotuning = if AdvancedModulesService::get_bufferbloat_status() { "Disabled" } else { "Enabled" };
+    let lower_bufferbloat = modules.as_ref().is_some_and(|m| m.lower_bufferbloat);
+    rows.push(StatusRow::compare(
+        "TCP autotuning",
+        autotuning.to_string(),
+        lower_bufferbloat.then(|| "Disabled".to_string()),
+    ));
+
+    // Hardware GPU scheduling
+    let hags = AdvancedModulesService::hags_mode().map_or("Not set".to_string(), |v| v.to_string());
+    let hags_applied = revi || modules.as_ref().is_some_and(|m| m.enable_hags);
+    rows.push(StatusRow::compare("HwSchMode", hags, hags_applied.then(|| "2".to_string())));
+
+    // MMCSS
+    let responsiveness = AdvancedModulesService::system_responsiveness().map_or("Not set".to_string(), |v| v.to_string());
+    let mmcss_applied = revi || modules.as_ref().is_some_and(|m| m.mmcss_priority_boost);
+    rows.push(StatusRow::compare("SystemResponsiveness", responsiveness, mmcss_applied.then(|| "0".to_string())));
+
+    // Services: the session's stopped list should still be stopped
+    let stopped_now: Vec<&str> = WindowsServiceManager::OPTIMIZATION_SERVICES.iter()
+        .copied()
+        .filter(|name| WindowsServiceManager::is_running(name) == Some(false))
+        .collect();
+    let stopped_by_session = gamemode.stopped_services();
+    let current = if stopped_now.is_empty() { "None".to_string() } else { stopped_now.join(", ") };
+    rows.push(if session_active && !stopped_by_session.is_empty() {
+        let restarted: Vec<&str> = stopped_by_session.iter()
+            .map(String::as_str)
+            .filter(|name| WindowsServiceManager::is_running(name) == Some(true))
+            .collect();
+        StatusRow {
+            label: "Stopped services",
+            current,
+            expected: stopped_by_session.join(", "),
+            ok: restarted.is_empty(),
+        }
+    } else {
+        StatusRow::compare("Stopped services", current, None)
+    });
+
+    // Suspended processes still alive (same PID and start time)
+    let suspended = gamemode.suspended_processes();
+    let alive: Vec<String> = suspended.iter()
+        .filter(|p| p.start_time.is_some() && ProcessService::process_start_time(p.pid) == p.start_time)
+        .map(|p| format!("{} ({})", p.name, p.pid))
+        .collect();
+    let current = if alive.is_empty() { "None".to_string() } else { alive.join(", ") };
+    rows.push(if session_active && !suspended.is_empty() {
+        // A suspended process that exited since is fine, it just drops off the list
+        StatusRow { label: "Suspended PIDs", current, expected: format!("{} held until disable", suspended.len()), ok: true }
+    } else {
+        StatusRow::compare("Suspended PIDs", current, (!session_active).then(|| "None".to_string()))
+    });
+
+    // A journal left behind by a session that never reverted
+    if !session_active {
+        if let Some(stale) = journal::read() {
+            rows.push(StatusRow {
+                label: "Session journal",
+                current: format!("Left by PID {}", stale.app_pid),
+                expected: "None".to_string(),
+                ok: false,
+            });
+        }
+    }
+
+    // Registry writes that read back differently (policy, Tamper Protection, permissions)
+    let blocked = write_audit::mismatches();
+    if !blocked.is_empty() {
+        let current: Vec<String> = blocked.iter().map(|m| m.describe()).collect();
+        rows.push(StatusRow {
+            label: "Blocked registry writes",
+            current: current.join("; "),
+            expected: "None".to_string(),
+            ok: false,
+        });
+    }
+
+    rows
+}
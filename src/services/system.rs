@@ -0,0 +1,591 @@
+//! System Backends
+//! The registry, service, process and power calls behind the save/restore logic, as traits.
+//! `Win32` talks to the live machine; `fake::FakeSystem` keeps everything in memory so the
+//! save/restore paths of GameModeService, ReviTweaksService and AdvancedModulesService can be
+//! driven without mutating a real system
+
+use windows::core::{GUID, HSTRING, PCWSTR, PWSTR};
+use windows::Win32::System::Registry::*;
+use crate::services::power::PowerService;
+use crate::services::process::{ProcessService, SuspendedProcess, TerminatedProcess};
+use crate::services::windows::WindowsServiceManager;
+use crate::services::write_audit;
+
+/// Registry root a value lives under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hive {
+    LocalMachine,
+    CurrentUser,
+}
+
+impl Hive {
+    /// Short name for logs ("HKLM", "HKCU")
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hive::LocalMachine => "HKLM",
+            Hive::CurrentUser => "HKCU",
+        }
+    }
+
+    fn root(&self) -> HKEY {
+        match self {
+            Hive::LocalMachine => HKEY_LOCAL_MACHINE,
+            Hive::CurrentUser => HKEY_CURRENT_USER,
+        }
+    }
+}
+
+pub trait RegistryBackend {
+    fn read_dword(&self, hive: Hive, path: &str, name: &str) -> Option<u32>;
+    fn read_string(&self, hive: Hive, path: &str, name: &str) -> Option<String>;
+    /// Create the key if needed and write; true if the value reads back as written
+    fn write_dword(&self, hive: Hive, path: &str, name: &str, data: u32) -> bool;
+    fn write_string(&self, hive: Hive, path: &str, name: &str, data: &str) -> bool;
+    /// Type and data of a value of any type, so it can be written back unchanged
+    fn read_raw(&self, hive: Hive, path: &str, name: &str) -> Option<(u32, Vec<u8>)>;
+    fn write_raw(&self, hive: Hive, path: &str, name: &str, value_type: u32, data: &[u8]) -> bool;
+    /// Data of a value of any type (REG_BINARY masks, etc.)
+    fn read_binary(&self, hive: Hive, path: &str, name: &str) -> Option<Vec<u8>> {
+        self.read_raw(hive, path, name).map(|(_, data)| data)
+    }
+    fn write_binary(&self, hive: Hive, path: &str, name: &str, data: &[u8]) -> bool {
+        self.write_raw(hive, path, name, REG_BINARY.0, data)
+    }
+    fn delete_value(&self, hive: Hive, path: &str, name: &str);
+    /// Delete a key without subkeys
+    fn delete_key(&self, hive: Hive, path: &str);
+    /// Names of the direct subkeys
+    fn subkeys(&self, hive: Hive, path: &str) -> Vec<String>;
+}
+
+pub trait ServiceControl {
+    /// None if the service doesn't exist or can't be queried
+    fn is_running(&self, name: &str) -> Option<bool>;
+    /// Stop a running service; false if it wasn't running or refused
+    fn stop(&self, name: &str) -> bool;
+    /// Start a stopped service; true once it is confirmed running
+    fn start(&self, name: &str) -> bool;
+}
+
+pub trait ProcessControl {
+    fn suspend(&self, names: &[&str]) -> Vec<SuspendedProcess>;
+    /// Resume what `suspend` returned; returns how many PIDs no longer matched and were skipped
+    fn resume(&self, processes: &[SuspendedProcess]) -> usize;
+    /// Resume every process with one of these names (fallback when PIDs are unknown)
+    fn resume_by_name(&self, names: &[&str]);
+    /// Terminate every process with one of these names; returns what was terminated
+    fn kill(&self, names: &[&str]) -> Vec<TerminatedProcess>;
+    /// Like `kill`, also taking down each match's child processes
+    fn kill_trees(&self, names: &[&str]) -> Vec<TerminatedProcess>;
+}
+
+pub trait PowerBackend {
+    fn scheme_exists(&self, scheme: &GUID) -> bool;
+    /// Create a scheme from its built-in template (e.g. Ultimate Performance)
+    fn duplicate_scheme(&self, scheme: &GUID);
+    fn active_scheme(&self) -> Option<GUID>;
+    fn set_active_scheme(&self, scheme: &GUID) -> bool;
+    fn read_processor_ac_index(&self, scheme: &GUID, setting: &GUID) -> Option<u32>;
+    fn write_processor_ac_index(&self, scheme: &GUID, setting: &GUID, value: u32) -> bool;
+    fn read_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID) -> Option<(u32, u32)>;
+    fn write_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID, ac: u32, dc: u32) -> bool;
+}
+
+/// Everything a service needs, held as one `Arc<dyn SystemBackend>`
+pub trait SystemBackend: RegistryBackend + ServiceControl + ProcessControl + PowerBackend + Send + Sync {}
+
+impl<T: RegistryBackend + ServiceControl + ProcessControl + PowerBackend + Send + Sync> SystemBackend for T {}
+
+/// The live machine
+pub struct Win32;
+
+impl RegistryBackend for Win32 {
+    fn read_dword(&self, hive: Hive, path: &str, name: &str) -> Option<u32> {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(hive.root(), &HSTRING::from(path), 0, KEY_READ, &mut key).is_err() {
+                return None;
+            }
+            let mut data = 0u32;
+            let mut size = std::mem::size_of::<u32>() as u32;
+            let mut value_type = REG_VALUE_TYPE::default();
+            let result = RegQueryValueExW(
+                key,
+                &HSTRING::from(name),
+                None,
+                Some(&mut value_type),
+                Some(&mut data as *mut u32 as *mut u8),
+                Some(&mut size),
+            );
+            let _ = RegCloseKey(key);
+            (result.is_ok() && value_type == REG_DWORD).then_some(data)
+        }
+    }
+
+    fn read_string(&self, hive: Hive, path: &str, name: &str) -> Option<String> {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(hive.root(), &HSTRING::from(path), 0, KEY_READ, &mut key).is_err() {
+                return None;
+            }
+            let mut buffer = [0u16; 1024];
+            let mut size = std::mem::size_of_val(&buffer) as u32;
+            let result = RegQueryValueExW(
+                key,
+                &HSTRING::from(name),
+                None,
+                None,
+                Some(buffer.as_mut_ptr() as *mut u8),
+                Some(&mut size),
+            );
+            let _ = RegCloseKey(key);
+            if result.is_err() {
+                return None;
+            }
+            let len = (size as usize / 2).min(buffer.len());
+            Some(String::from_utf16_lossy(&buffer[..len]).trim_end_matches('\0').to_string())
+        }
+    }
+
+    fn write_dword(&self, hive: Hive, path: &str, name: &str, data: u32) -> bool {
+        Self::write(hive, path, name, REG_DWORD, &data.to_le_bytes());
+        write_audit::check(hive.name(), path, name, data, self.read_dword(hive, path, name))
+    }
+
+    fn write_string(&self, hive: Hive, path: &str, name: &str, data: &str) -> bool {
+        let bytes: Vec<u8> = data.encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        Self::write(hive, path, name, REG_SZ, &bytes);
+        write_audit::check(hive.name(), path, name, data.to_string(), self.read_string(hive, path, name))
+    }
+
+    fn read_raw(&self, hive: Hive, path: &str, name: &str) -> Option<(u32, Vec<u8>)> {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(hive.root(), &HSTRING::from(path), 0, KEY_READ, &mut key).is_err() {
+                return None;
+            }
+            let name = HSTRING::from(name);
+            let mut value_type = REG_NONE;
+            let mut size = 0u32;
+            let mut result = RegQueryValueExW(key, &name, None, Some(&mut value_type), None, Some(&mut size));
+            let mut data = vec![0u8; size as usize];
+            if result.is_ok() {
+                result = RegQueryValueExW(key, &name, None, Some(&mut value_type), Some(data.as_mut_ptr()), Some(&mut size));
+            }
+            let _ = RegCloseKey(key);
+            data.truncate(size as usize);
+            result.is_ok().then_some((value_type.0, data))
+        }
+    }
+
+    fn write_raw(&self, hive: Hive, path: &str, name: &str, value_type: u32, data: &[u8]) -> bool {
+        Self::write(hive, path, name, REG_VALUE_TYPE(value_type), data);
+        write_audit::check(hive.name(), path, name, hex(data), self.read_binary(hive, path, name).map(|d| hex(&d)))
+    }
+
+    fn delete_value(&self, hive: Hive, path: &str, name: &str) {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(hive.root(), &HSTRING::from(path), 0, KEY_WRITE, &mut key).is_ok() {
+                let _ = RegDeleteValueW(key, &HSTRING::from(name));
+                let _ = RegCloseKey(key);
+            }
+        }
+    }
+
+    fn delete_key(&self, hive: Hive, path: &str) {
+        unsafe {
+            let _ = RegDeleteKeyW(hive.root(), &HSTRING::from(path));
+        }
+    }
+
+    fn subkeys(&self, hive: Hive, path: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(hive.root(), &HSTRING::from(path), 0, KEY_READ, &mut key).is_err() {
+                return names;
+            }
+            let mut buffer = [0u16; 256];
+            for index in 0.. {
+                let mut len = buffer.len() as u32;
+                if RegEnumKeyExW(key, index, PWSTR(buffer.as_mut_ptr()), &mut len, None, PWSTR::null(), None, None).is_err() {
+                    break;
+                }
+                names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+            }
+            let _ = RegCloseKey(key);
+        }
+        names
+    }
+}
+
+/// Binary data as hex for the write audit ("0f00")
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Win32 {
+    fn write(hive: Hive, path: &str, name: &str, value_type: REG_VALUE_TYPE, data: &[u8]) {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegCreateKeyExW(
+                hive.root(),
+                &HSTRING::from(path),
+                0,
+                None,
+                REG_OPTION_NON_VOLATILE,
+                KEY_WRITE,
+                None,
+                &mut key,
+                None,
+            ).is_err() {
+                return;
+            }
+            let name_w = HSTRING::from(name);
+            let _ = RegSetValueExW(key, PCWSTR(name_w.as_ptr()), 0, value_type, Some(data));
+            let _ = RegCloseKey(key);
+        }
+    }
+}
+
+impl ServiceControl for Win32 {
+    fn is_running(&self, name: &str) -> Option<bool> {
+        WindowsServiceManager::is_running(name)
+    }
+
+    fn stop(&self, name: &str) -> bool {
+        WindowsServiceManager::stop_single_service(name)
+    }
+
+    fn start(&self, name: &str) -> bool {
+        WindowsServiceManager::start_single_service(name)
+    }
+}
+
+impl ProcessControl for Win32 {
+    fn suspend(&self, names: &[&str]) -> Vec<SuspendedProcess> {
+        ProcessService::suspend_processes(names)
+    }
+
+    fn resume(&self, processes: &[SuspendedProcess]) -> usize {
+        ProcessService::resume_suspended(processes)
+    }
+
+    fn resume_by_name(&self, names: &[&str]) {
+        ProcessService::resume_processes(names)
+    }
+
+    fn kill(&self, names: &[&str]) -> Vec<TerminatedProcess> {
+        ProcessService::kill_processes(names)
+    }
+
+    fn kill_trees(&self, names: &[&str]) -> Vec<TerminatedProcess> {
+        ProcessService::kill_process_trees(names)
+    }
+}
+
+impl PowerBackend for Win32 {
+    fn scheme_exists(&self, scheme: &GUID) -> bool {
+        PowerService::power_plan_exists(scheme)
+    }
+
+    fn duplicate_scheme(&self, scheme: &GUID) {
+        PowerService::duplicate_power_scheme(scheme)
+    }
+
+    fn active_scheme(&self) -> Option<GUID> {
+        PowerService::active_scheme()
+    }
+
+    fn set_active_scheme(&self, scheme: &GUID) -> bool {
+        PowerService::set_active_scheme(scheme)
+    }
+
+    fn read_processor_ac_index(&self, scheme: &GUID, setting: &GUID) -> Option<u32> {
+        PowerService::read_processor_ac_index(scheme, setting)
+    }
+
+    fn write_processor_ac_index(&self, scheme: &GUID, setting: &GUID, value: u32) -> bool {
+        PowerService::write_processor_ac_index(scheme, setting, value)
+    }
+
+    fn read_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID) -> Option<(u32, u32)> {
+        PowerService::read_value_indices(scheme, subgroup, setting)
+    }
+
+    fn write_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID, ac: u32, dc: u32) -> bool {
+        PowerService::write_value_indices(scheme, subgroup, setting, ac, dc)
+    }
+}
+
+/// In-memory system for exercising save/restore logic off a live machine
+#[cfg(test)]
+pub mod fake {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use windows::core::GUID;
+    use crate::services::power::PROCESSOR_SUBGROUP;
+    use crate::services::process::{SuspendedProcess, TerminatedProcess};
+    use windows::Win32::System::Registry::{REG_BINARY, REG_DWORD, REG_SZ};
+    use super::{Hive, PowerBackend, ProcessControl, RegistryBackend, ServiceControl};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Value {
+        Dword(u32),
+        String(String),
+        Binary(Vec<u8>),
+    }
+
+    /// Keys are matched case-insensitively like the real registry
+    type ValueKey = (Hive, String, String);
+
+    #[derive(Default)]
+    pub struct FakeSystem {
+        pub values: Mutex<HashMap<ValueKey, Value>>,
+        /// Writes to these (hive, path, name) are silently dropped, like a policy-held value
+        pub blocked: Mutex<HashSet<ValueKey>>,
+        /// Service name -> running
+        pub services: Mutex<HashMap<String, bool>>,
+        /// Process name -> PIDs, and the PIDs currently suspended
+        pub processes: Mutex<HashMap<String, Vec<u32>>>,
+        pub suspended: Mutex<HashSet<u32>>,
+        /// Installed power schemes and the active one
+        pub schemes: Mutex<HashSet<GUID>>,
+        pub active_scheme: Mutex<Option<GUID>>,
+        /// (scheme, subgroup, setting) -> (AC, DC)
+        pub power_indices: Mutex<HashMap<(GUID, GUID, GUID), (u32, u32)>>,
+    }
+
+    fn key(hive: Hive, path: &str, name: &str) -> ValueKey {
+        (hive, path.to_ascii_lowercase(), name.to_ascii_lowercase())
+    }
+
+    impl FakeSystem {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn set(&self, hive: Hive, path: &str, name: &str, value: Value) {
+            self.values.lock().unwrap().insert(key(hive, path, name), value);
+        }
+
+        pub fn get(&self, hive: Hive, path: &str, name: &str) -> Option<Value> {
+            self.values.lock().unwrap().get(&key(hive, path, name)).cloned()
+        }
+
+        pub fn block(&self, hive: Hive, path: &str, name: &str) {
+            self.blocked.lock().unwrap().insert(key(hive, path, name));
+        }
+
+        pub fn add_service(&self, name: &str, running: bool) {
+            self.services.lock().unwrap().insert(name.to_ascii_lowercase(), running);
+        }
+
+        /// Install a power scheme, optionally making it the active one
+        pub fn add_scheme(&self, scheme: GUID, active: bool) {
+            self.schemes.lock().unwrap().insert(scheme);
+            if active {
+                *self.active_scheme.lock().unwrap() = Some(scheme);
+            }
+        }
+
+        pub fn add_process(&self, name: &str, pid: u32) {
+            self.processes.lock().unwrap().entry(name.to_ascii_lowercase()).or_default().push(pid);
+        }
+
+        fn write(&self, hive: Hive, path: &str, name: &str, value: Value) -> bool {
+            let key = key(hive, path, name);
+            if self.blocked.lock().unwrap().contains(&key) {
+                return false;
+            }
+            self.values.lock().unwrap().insert(key, value);
+            true
+        }
+    }
+
+    impl RegistryBackend for FakeSystem {
+        fn read_dword(&self, hive: Hive, path: &str, name: &str) -> Option<u32> {
+            match self.get(hive, path, name)? {
+                Value::Dword(data) => Some(data),
+                _ => None,
+            }
+        }
+
+        fn read_string(&self, hive: Hive, path: &str, name: &str) -> Option<String> {
+            match self.get(hive, path, name)? {
+                Value::String(data) => Some(data),
+                _ => None,
+            }
+        }
+
+        fn write_dword(&self, hive: Hive, path: &str, name: &str, data: u32) -> bool {
+            self.write(hive, path, name, Value::Dword(data))
+        }
+
+        fn write_string(&self, hive: Hive, path: &str, name: &str, data: &str) -> bool {
+            self.write(hive, path, name, Value::String(data.to_string()))
+        }
+
+        fn read_raw(&self, hive: Hive, path: &str, name: &str) -> Option<(u32, Vec<u8>)> {
+            Some(match self.get(hive, path, name)? {
+                Value::Dword(data) => (REG_DWORD.0, data.to_le_bytes().to_vec()),
+                Value::String(data) => {
+                    let wide = data.encode_utf16().chain(std::iter::once(0)).flat_map(|c| c.to_le_bytes()).collect();
+                    (REG_SZ.0, wide)
+                }
+                Value::Binary(data) => (REG_BINARY.0, data),
+            })
+        }
+
+        fn write_raw(&self, hive: Hive, path: &str, name: &str, value_type: u32, data: &[u8]) -> bool {
+            let value = match value_type {
+                t if t == REG_DWORD.0 && data.len() == 4 => Value::Dword(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                t if t == REG_SZ.0 => {
+                    let wide: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                    Value::String(String::from_utf16_lossy(&wide).trim_end_matches('\0').to_string())
+                }
+                _ => Value::Binary(data.to_vec()),
+            };
+            self.write(hive, path, name, value)
+        }
+
+        fn delete_value(&self, hive: Hive, path: &str, name: &str) {
+            self.values.lock().unwrap().remove(&key(hive, path, name));
+        }
+
+        fn delete_key(&self, hive: Hive, path: &str) {
+            let path = path.to_ascii_lowercase();
+            self.values.lock().unwrap().retain(|(h, p, _), _| !(*h == hive && *p == path));
+        }
+
+        fn subkeys(&self, hive: Hive, path: &str) -> Vec<String> {
+            let prefix = format!("{}\\", path.to_ascii_lowercase());
+            let mut names: Vec<String> = self.values.lock().unwrap().keys()
+                .filter(|(h, p, _)| *h == hive && p.starts_with(&prefix))
+                .filter_map(|(_, p, _)| p[prefix.len()..].split('\\').next().map(str::to_string))
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        }
+    }
+
+    impl ServiceControl for FakeSystem {
+        fn is_running(&self, name: &str) -> Option<bool> {
+            self.services.lock().unwrap().get(&name.to_ascii_lowercase()).copied()
+        }
+
+        fn stop(&self, name: &str) -> bool {
+            match self.services.lock().unwrap().get_mut(&name.to_ascii_lowercase()) {
+                Some(running) if *running => {
+                    *running = false;
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        fn start(&self, name: &str) -> bool {
+            match self.services.lock().unwrap().get_mut(&name.to_ascii_lowercase()) {
+                Some(running) => {
+                    *running = true;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    impl ProcessControl for FakeSystem {
+        fn suspend(&self, names: &[&str]) -> Vec<SuspendedProcess> {
+            let processes = self.processes.lock().unwrap();
+            let mut suspended = self.suspended.lock().unwrap();
+            let mut result = Vec::new();
+            for name in names {
+                for &pid in processes.get(&name.to_ascii_lowercase()).into_iter().flatten() {
+                    if suspended.insert(pid) {
+                        result.push(SuspendedProcess { pid, start_time: Some(pid as u64), name: name.to_string() });
+                    }
+                }
+            }
+            result
+        }
+
+        fn resume(&self, processes: &[SuspendedProcess]) -> usize {
+            let mut suspended = self.suspended.lock().unwrap();
+            processes.iter().filter(|p| !suspended.remove(&p.pid)).count()
+        }
+
+        fn resume_by_name(&self, names: &[&str]) {
+            let processes = self.processes.lock().unwrap();
+            let mut suspended = self.suspended.lock().unwrap();
+            for name in names {
+                for pid in processes.get(&name.to_ascii_lowercase()).into_iter().flatten() {
+                    suspended.remove(pid);
+                }
+            }
+        }
+
+        fn kill(&self, names: &[&str]) -> Vec<TerminatedProcess> {
+            let mut processes = self.processes.lock().unwrap();
+            let mut result = Vec::new();
+            for name in names {
+                let name = name.strip_suffix(".exe").unwrap_or(name);
+                for pid in processes.remove(&name.to_ascii_lowercase()).into_iter().flatten() {
+                    result.push(TerminatedProcess { pid, name: name.to_string(), path: None });
+                }
+            }
+            result
+        }
+
+        fn kill_trees(&self, names: &[&str]) -> Vec<TerminatedProcess> {
+            // No parent/child links in the fake
+            self.kill(names)
+        }
+    }
+
+    impl PowerBackend for FakeSystem {
+        fn scheme_exists(&self, scheme: &GUID) -> bool {
+            self.schemes.lock().unwrap().contains(scheme)
+        }
+
+        fn duplicate_scheme(&self, scheme: &GUID) {
+            self.schemes.lock().unwrap().insert(*scheme);
+        }
+
+        fn active_scheme(&self) -> Option<GUID> {
+            *self.active_scheme.lock().unwrap()
+        }
+
+        fn set_active_scheme(&self, scheme: &GUID) -> bool {
+            if !self.scheme_exists(scheme) {
+                return false;
+            }
+            *self.active_scheme.lock().unwrap() = Some(*scheme);
+            true
+        }
+
+        fn read_processor_ac_index(&self, scheme: &GUID, setting: &GUID) -> Option<u32> {
+            self.read_value_indices(scheme, &PROCESSOR_SUBGROUP, setting).map(|(ac, _)| ac)
+        }
+
+        fn write_processor_ac_index(&self, scheme: &GUID, setting: &GUID, value: u32) -> bool {
+            let mut indices = self.power_indices.lock().unwrap();
+            indices.entry((*scheme, PROCESSOR_SUBGROUP, *setting)).or_insert((value, value)).0 = value;
+            true
+        }
+
+        fn read_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID) -> Option<(u32, u32)> {
+            self.power_indices.lock().unwrap().get(&(*scheme, *subgroup, *setting)).copied()
+        }
+
+        fn write_value_indices(&self, scheme: &GUID, subgroup: &GUID, setting: &GUID, ac: u32, dc: u32) -> bool {
+            self.power_indices.lock().unwrap().insert((*scheme, *subgroup, *setting), (ac, dc));
+            true
+        }
+    }
+}
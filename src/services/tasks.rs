@@ -0,0 +1,213 @@
+//! Session Tasks
+//! Enable and disable run on one worker thread in the order they were requested, so a fast
+//! on/off/on can't interleave an enable with a disable. Jobs that haven't started yet can be
+//! cancelled, and callers can wait for the queue with a timeout (close, shutdown).
+//! Other background work (UI jobs, downloads, monitors) runs through `spawn`, and independent
+//! session steps through `join_all`, so every thread is named and cancellable.
+//!
+//! Release builds use panic = "abort": a panicking job runs the crash hook (which restores the
+//! session from the journal) and ends the process, nothing here tries to recover from it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use crate::log_line;
+
+/// Queue for session enable/disable work
+static SESSION: Lazy<TaskQueue> = Lazy::new(|| TaskQueue::new("session-tasks"));
+
+/// The session queue shared by the toggle, the game-exit monitor, the panic hotkey and close
+pub fn session() -> &'static TaskQueue {
+    &SESSION
+}
+
+/// Tokens of background jobs that are still running
+static BACKGROUND: Lazy<Mutex<Vec<CancelToken>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Run `run` on its own named thread, outside the session queue; long-running jobs
+/// (monitors, watchers) loop until their token is cancelled by `cancel_background`
+pub fn spawn(label: &'static str, run: impl FnOnce(&CancelToken) + Send + 'static) {
+    let cancel = CancelToken::default();
+    BACKGROUND.lock().unwrap().push(cancel.clone());
+
+    let token = cancel.clone();
+    let spawned = thread::Builder::new().name(label.to_string()).spawn(move || {
+        run(&token);
+        BACKGROUND.lock().unwrap().retain(|t| !Arc::ptr_eq(&t.0, &token.0));
+    });
+    if let Err(e) = spawned {
+        log_line!("[Tasks] Could not start {}: {}", label, e);
+        BACKGROUND.lock().unwrap().retain(|t| !Arc::ptr_eq(&t.0, &cancel.0));
+    }
+}
+
+/// Ask every background job to stop (exit); monitors return at their next check
+pub fn cancel_background() {
+    for token in BACKGROUND.lock().unwrap().iter() {
+        token.cancel();
+    }
+}
+
+/// A labelled step for `join_all`
+pub type Step<'a, T> = (&'static str, Box<dyn FnOnce() -> T + Send + 'a>);
+
+/// Run independent steps in parallel with `foreground` on the calling thread and wait for all
+/// of them; step results in step order. Steps may borrow from the caller since they can't
+/// outlive this call
+pub fn join_all<'a, T: Send, R>(steps: Vec<Step<'a, T>>, foreground: impl FnOnce() -> R) -> (Vec<T>, R) {
+    thread::scope(|scope| {
+        let mut handles = Vec::with_capacity(steps.len());
+        for (label, step) in steps {
+            match thread::Builder::new().name(label.to_string()).spawn_scoped(scope, step) {
+                Ok(handle) => handles.push((label, handle)),
+                Err(e) => log_line!("[Tasks] Could not start {}: {}", label, e),
+            }
+        }
+        let foreground = foreground();
+        let mut results = Vec::with_capacity(handles.len());
+        for (label, handle) in handles {
+            match handle.join() {
+                Ok(result) => results.push(result),
+                Err(_) => log_line!("[Tasks] {} panicked", label),
+            }
+        }
+        (results, foreground)
+    })
+}
+
+/// Set when a job is superseded; a running job checks it to skip work that no longer matters
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Signalled once a job has run or was skipped
+#[derive(Default)]
+struct Completion {
+    done: Mutex<bool>,
+    signal: Condvar,
+}
+
+impl Completion {
+    fn finish(&self) {
+        *self.done.lock().unwrap() = true;
+        self.signal.notify_all();
+    }
+
+    /// False if the timeout elapsed first
+    fn wait(&self, timeout: Duration) -> bool {
+        let done = self.done.lock().unwrap();
+        !self.signal.wait_timeout_while(done, timeout, |d| !*d).unwrap().1.timed_out()
+    }
+}
+
+/// Finishes a completion when dropped, even if the job's thread unwinds (debug builds)
+struct FinishOnDrop(Arc<Completion>);
+
+impl Drop for FinishOnDrop {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+struct Job {
+    label: &'static str,
+    cancel: CancelToken,
+    completion: Arc<Completion>,
+    run: Box<dyn FnOnce(&CancelToken) + Send>,
+}
+
+/// Single worker thread fed in submission order
+pub struct TaskQueue {
+    sender: Mutex<Sender<Job>>,
+    /// Tokens of jobs that are queued but not started yet
+    queued: Arc<Mutex<Vec<CancelToken>>>,
+    /// Token of the job the worker is running
+    running: Arc<Mutex<Option<CancelToken>>>,
+    /// Completion of the most recently submitted job; the queue is idle once it is done
+    last: Mutex<Option<Arc<Completion>>>,
+}
+
+impl TaskQueue {
+    fn new(name: &str) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let queued: Arc<Mutex<Vec<CancelToken>>> = Arc::new(Mutex::new(Vec::new()));
+        let running: Arc<Mutex<Option<CancelToken>>> = Arc::new(Mutex::new(None));
+        let queued_for_worker = queued.clone();
+        let running_for_worker = running.clone();
+
+        let spawned = thread::Builder::new().name(name.to_string()).spawn(move || {
+            for job in receiver {
+                queued_for_worker.lock().unwrap().retain(|t| !Arc::ptr_eq(&t.0, &job.cancel.0));
+                let _finished = FinishOnDrop(job.completion);
+                if job.cancel.is_cancelled() {
+                    log_line!("[Tasks] Skipped {} (superseded)", job.label);
+                } else {
+                    *running_for_worker.lock().unwrap() = Some(job.cancel.clone());
+                    (job.run)(&job.cancel);
+                    *running_for_worker.lock().unwrap() = None;
+                }
+            }
+        });
+        if let Err(e) = spawned {
+            log_line!("[Tasks] Could not start the {} worker: {}", name, e);
+        }
+
+        Self {
+            sender: Mutex::new(sender),
+            queued,
+            running,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Queue a job behind everything submitted before it
+    pub fn submit(&self, label: &'static str, run: impl FnOnce(&CancelToken) + Send + 'static) {
+        let cancel = CancelToken::default();
+        let completion = Arc::new(Completion::default());
+        let job = Job {
+            label,
+            cancel: cancel.clone(),
+            completion: completion.clone(),
+            run: Box::new(run),
+        };
+
+        self.queued.lock().unwrap().push(cancel);
+        *self.last.lock().unwrap() = Some(completion);
+        if let Err(mpsc::SendError(job)) = self.sender.lock().unwrap().send(job) {
+            // Worker is gone: run inline rather than dropping a restore
+            log_line!("[Tasks] Worker unavailable, running {} inline", job.label);
+            self.queued.lock().unwrap().retain(|t| !Arc::ptr_eq(&t.0, &job.cancel.0));
+            (job.run)(&job.cancel);
+            job.completion.finish();
+        }
+    }
+
+    /// Cancel every job that is still waiting; the running one (if any) only sees its token
+    /// and still finishes, so nothing is left half-applied
+    pub fn cancel_pending(&self) {
+        for token in self.queued.lock().unwrap().drain(..) {
+            token.cancel();
+        }
+        if let Some(token) = self.running.lock().unwrap().as_ref() {
+            token.cancel();
+        }
+    }
+
+    /// Wait until everything submitted so far has finished; false on timeout
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let last = self.last.lock().unwrap().clone();
+        last.map_or(true, |completion| completion.wait(timeout))
+    }
+}
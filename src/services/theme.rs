@@ -0,0 +1,85 @@
+//! Theme
+//! Follows the Windows app mode (dark/light) and accent color through UISettings;
+//! "theme" and "accent_color" in settings.json override them
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use windows::Foundation::TypedEventHandler;
+use windows::UI::ViewManagement::{UIColorType, UISettings};
+use crate::log_line;
+
+/// Default accent when neither Windows nor settings.json provide one
+pub const DEFAULT_ACCENT: (u8, u8, u8) = (0x00, 0x72, 0xFF);
+
+/// Dark or light UI ("theme" in settings.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    /// Follow the Windows app mode
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+/// Colors the UI is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedTheme {
+    pub dark: bool,
+    pub accent: (u8, u8, u8),
+}
+
+// Kept alive for ColorValuesChanged (the event stops firing once UISettings is dropped)
+static UI_SETTINGS: OnceCell<UISettings> = OnceCell::new();
+
+/// Theme for `mode` and `accent_color` ("" = Windows accent, otherwise "#RRGGBB")
+pub fn resolve(mode: ThemeMode, accent_color: &str) -> ResolvedTheme {
+    let settings = ui_settings();
+    let dark = match mode {
+        ThemeMode::Dark => true,
+        ThemeMode::Light => false,
+        ThemeMode::System => settings.map_or(true, system_dark),
+    };
+    let accent = parse_hex(accent_color)
+        .or_else(|| settings.and_then(system_accent))
+        .unwrap_or(DEFAULT_ACCENT);
+    ResolvedTheme { dark, accent }
+}
+
+/// Call `on_change` whenever Windows switches app mode or accent color (on a background thread)
+pub fn watch(on_change: impl Fn() + Send + 'static) {
+    let Some(settings) = ui_settings() else { return };
+    let handler = TypedEventHandler::new(move |_, _| {
+        on_change();
+        Ok(())
+    });
+    if let Err(e) = settings.ColorValuesChanged(&handler) {
+        log_line!("[Theme] Not following Windows color changes: {}", e);
+    }
+}
+
+fn ui_settings() -> Option<&'static UISettings> {
+    UI_SETTINGS.get_or_try_init(UISettings::new)
+        .map_err(|e| log_line!("[Theme] UISettings unavailable: {}", e))
+        .ok()
+}
+
+/// Windows has no "is dark" flag; dark app mode is the one with a dark background color
+fn system_dark(settings: &UISettings) -> bool {
+    settings.GetColorValue(UIColorType::Background)
+        .map_or(true, |c| (c.R as u32 + c.G as u32 + c.B as u32) < 3 * 128)
+}
+
+fn system_accent(settings: &UISettings) -> Option<(u8, u8, u8)> {
+    settings.GetColorValue(UIColorType::Accent).ok().map(|c| (c.R, c.G, c.B))
+}
+
+/// "#RRGGBB" or "RRGGBB"
+fn parse_hex(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
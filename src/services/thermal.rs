@@ -0,0 +1,267 @@
+//! Thermal Monitor
+//! Samples the ACPI thermal zones (WMI) and the processor clock limits (CallNtPowerInformation)
+//! while game mode is active and raises a warning when the CPU stays throttled, so a hardware
+//! bottleneck (cooling, power limits) isn't mistaken for a software one
+
+use windows::Win32::System::Power::{CallNtPowerInformation, ProcessorInformation, PROCESSOR_POWER_INFORMATION};
+use windows::Win32::System::SystemInformation::{GetSystemInfo, SYSTEM_INFO};
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::log_line;
+use crate::services::session;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Consecutive throttled samples before warning (30 s)
+const SUSTAINED_SAMPLES: u32 = 6;
+/// Clock limit below this share of the rated maximum counts as a clock drop
+const CLOCK_DROP_PERCENT: u32 = 85;
+
+/// Why the CPU is held back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleCause {
+    /// A thermal zone is in passive cooling (firmware lowering the clock to cool down)
+    Thermal,
+    /// The clock limit dropped without a thermal zone reporting it (power / current limits)
+    Clock,
+}
+
+/// Raised once per throttling episode after it lasted SUSTAINED_SAMPLES
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleWarning {
+    pub cause: ThrottleCause,
+    /// Hottest thermal zone, None if the firmware exposes none
+    pub temperature_c: Option<f64>,
+    /// Clock limit as a share of the rated maximum
+    pub clock_percent: u32,
+}
+
+/// Summary recorded at the end of a session
+#[derive(Debug, Clone, Default)]
+pub struct ThermalReport {
+    /// Session the report belongs to (see services::session)
+    pub session_id: Option<String>,
+    pub samples: u32,
+    pub throttled_samples: u32,
+    pub max_temperature_c: Option<f64>,
+    /// Lowest clock limit seen, as a share of the rated maximum
+    pub min_clock_percent: u32,
+    pub warnings: u32,
+}
+
+impl std::fmt::Display for ThermalReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(id) = &self.session_id {
+            write!(f, "session {} - ", id)?;
+        }
+        match self.max_temperature_c {
+            Some(c) => write!(f, "max {:.0} °C", c)?,
+            None => write!(f, "no thermal zones")?,
+        }
+        write!(
+            f,
+            ", lowest clock limit {}%, throttled {} of {} samples, {} warnings",
+            self.min_clock_percent, self.throttled_samples, self.samples, self.warnings
+        )
+    }
+}
+
+/// One reading of the sensors
+#[derive(Debug, Clone, Copy, Default)]
+struct ThermalSample {
+    temperature_c: Option<f64>,
+    /// Any zone below 100% passive limit or with throttle reasons set
+    zone_throttled: bool,
+    clock_percent: u32,
+}
+
+/// Running statistics and the throttling streak
+#[derive(Default)]
+struct ThermalStats {
+    report: ThermalReport,
+    streak: u32,
+    warned: bool,
+}
+
+impl ThermalStats {
+    /// Returns a warning when a throttling streak just became sustained
+    fn record(&mut self, sample: ThermalSample) -> Option<ThrottleWarning> {
+        let report = &mut self.report;
+        if report.samples == 0 || sample.clock_percent < report.min_clock_percent {
+            report.min_clock_percent = sample.clock_percent;
+        }
+        report.samples += 1;
+        if let Some(c) = sample.temperature_c {
+            report.max_temperature_c = Some(report.max_temperature_c.map_or(c, |max| max.max(c)));
+        }
+
+        let clock_drop = sample.clock_percent < CLOCK_DROP_PERCENT;
+        if !sample.zone_throttled && !clock_drop {
+            // Episode over, warn again if it comes back
+            self.streak = 0;
+            self.warned = false;
+            return None;
+        }
+        report.throttled_samples += 1;
+        self.streak += 1;
+        if self.warned || self.streak < SUSTAINED_SAMPLES {
+            return None;
+        }
+
+        self.warned = true;
+        report.warnings += 1;
+        Some(ThrottleWarning {
+            cause: if sample.zone_throttled { ThrottleCause::Thermal } else { ThrottleCause::Clock },
+            temperature_c: sample.temperature_c,
+            clock_percent: sample.clock_percent,
+        })
+    }
+}
+
+pub struct ThermalMonitor {
+    running: Arc<AtomicBool>,
+    stats: Arc<Mutex<ThermalStats>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ThermalMonitor {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(Mutex::new(ThermalStats::default())),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Start sampling in the background; `on_throttle` runs once per sustained throttling episode
+    pub fn start<F>(&self, on_throttle: F)
+    where
+        F: Fn(ThrottleWarning) + Send + 'static,
+    {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return; // Already running
+        }
+
+        *self.stats.lock().unwrap() = ThermalStats {
+            report: ThermalReport { session_id: session::current(), ..Default::default() },
+            ..Default::default()
+        };
+
+        let running = self.running.clone();
+        let stats = self.stats.clone();
+        log_line!("[Thermal] Monitoring CPU temperature and clock limits");
+
+        let handle = thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                let sample = Self::sample();
+                let warning = stats.lock().unwrap().record(sample);
+                if let Some(warning) = warning {
+                    log_line!("[Thermal] Sustained throttling: {:?}", warning);
+                    on_throttle(warning);
+                }
+
+                // Sleep in short steps so stop() doesn't wait a whole interval
+                let mut slept = Duration::ZERO;
+                while slept < SAMPLE_INTERVAL && running.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(250));
+                    slept += Duration::from_millis(250);
+                }
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Stop sampling and store the session report
+    pub fn stop(&self) -> Option<ThermalReport> {
+        if !self.running.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        let report = self.stats.lock().unwrap().report.clone();
+        log_line!("[Thermal] Session: {}", report);
+        Some(report)
+    }
+
+    fn sample() -> ThermalSample {
+        let (temperature_c, zone_throttled) = Self::thermal_zones();
+        ThermalSample {
+            temperature_c,
+            zone_throttled,
+            clock_percent: Self::clock_limit_percent(),
+        }
+    }
+
+    /// (hottest zone in °C, any zone throttling) from the thermal zone performance counters
+    /// Temperature is in Kelvin; PercentPassiveLimit < 100 and ThrottleReasons != 0 mean the
+    /// zone is holding the processors back
+    fn thermal_zones() -> (Option<f64>, bool) {
+        let Some(output) = Command::new("wmic")
+            .args([
+                "path", "Win32_PerfFormattedData_Counters_ThermalZoneInformation",
+                "get", "Temperature,PercentPassiveLimit,ThrottleReasons", "/format:list",
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()
+        else {
+            return (None, false);
+        };
+
+        let (mut hottest, mut throttled) = (None::<f64>, false);
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<u64>() else {
+                continue;
+            };
+            match key {
+                // Zones without a sensor report 0 K
+                "Temperature" if value > 0 => {
+                    let celsius = value as f64 - 273.15;
+                    hottest = Some(hottest.map_or(celsius, |max| max.max(celsius)));
+                }
+                "PercentPassiveLimit" if value < 100 => throttled = true,
+                "ThrottleReasons" if value != 0 => throttled = true,
+                _ => {}
+            }
+        }
+        (hottest, throttled)
+    }
+
+    /// Lowest per-processor clock limit as a share of the rated maximum (100 = not limited)
+    fn clock_limit_percent() -> u32 {
+        unsafe {
+            let mut info = SYSTEM_INFO::default();
+            GetSystemInfo(&mut info);
+            let count = info.dwNumberOfProcessors.max(1) as usize;
+
+            let mut processors = vec![PROCESSOR_POWER_INFORMATION::default(); count];
+            let status = CallNtPowerInformation(
+                ProcessorInformation,
+                None,
+                0,
+                Some(processors.as_mut_ptr().cast()),
+                (count * std::mem::size_of::<PROCESSOR_POWER_INFORMATION>()) as u32,
+            );
+            if status.is_err() {
+                return 100;
+            }
+
+            processors.iter()
+                .filter(|p| p.MaxMhz > 0)
+                .map(|p| (p.MhzLimit.min(p.MaxMhz) * 100 / p.MaxMhz).max(1))
+                .min()
+                .unwrap_or(100)
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! System Tray
+//! Notification-area icon with the session status in its tooltip and a menu for activation,
+//! profile selection and quick toggles; runs on its own message-loop thread
+
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use once_cell::sync::{Lazy, OnceCell};
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DispatchMessageW, GetCursorPos,
+    GetMessageW, KillTimer, LoadIconW, PostMessageW, RegisterClassW, RegisterWindowMessageW, SetForegroundWindow,
+    SetTimer, TrackPopupMenu, HICON, HMENU, IDI_APPLICATION, MENU_ITEM_FLAGS, MF_CHECKED, MF_GRAYED, MF_POPUP,
+    MF_SEPARATOR, MF_STRING, MSG, TPM_BOTTOMALIGN, TPM_RETURNCMD, TPM_RIGHTBUTTON, WINDOW_EX_STYLE, WINDOW_STYLE,
+    WM_APP, WM_CONTEXTMENU, WM_LBUTTONUP, WM_RBUTTONUP, WM_TIMER, WNDCLASSW,
+};
+use crate::{log_line, tr};
+
+/// Icon callback (clicks) and "status changed" messages sent to the tray window
+const WM_TRAY_ICON: u32 = WM_APP + 1;
+const WM_TRAY_REFRESH: u32 = WM_APP + 2;
+const TRAY_ICON_ID: u32 = 1;
+/// Tooltip duration refresh while a session runs (idle: no timer, no wakeups)
+const DURATION_TIMER_ID: usize = 1;
+const DURATION_TIMER_MS: u32 = 60_000;
+
+// Menu command IDs; profiles are PROFILE_BASE + index
+const CMD_SHOW: usize = 1;
+const CMD_TOGGLE: usize = 2;
+const CMD_SUSPEND_BROWSERS: usize = 3;
+const CMD_ISOLATE_NETWORK: usize = 4;
+const CMD_EXIT: usize = 5;
+const CMD_UPDATE: usize = 6;
+const PROFILE_BASE: usize = 100;
+
+/// What the tray shows, read fresh whenever the menu opens or the tooltip refreshes
+#[derive(Debug, Clone, Default)]
+pub struct TrayStatus {
+    pub active: bool,
+    /// Detected / picked game (exe name)
+    pub game: Option<String>,
+    pub elapsed: Option<Duration>,
+    pub profiles: Vec<String>,
+    pub active_profile: String,
+    pub suspend_browsers: bool,
+    pub isolate_network: bool,
+    /// Version of an update found by the background check
+    pub update: Option<String>,
+}
+
+/// Menu action, run on the tray thread
+#[derive(Debug, Clone)]
+pub enum TrayCommand {
+    Show,
+    ToggleGameMode,
+    SelectProfile(String),
+    ToggleSuspendBrowsers,
+    ToggleNetworkIsolation,
+    InstallUpdate,
+    Exit,
+}
+
+struct TrayCallbacks {
+    status: Box<dyn Fn() -> TrayStatus + Send + Sync>,
+    on_command: Box<dyn Fn(TrayCommand) + Send + Sync>,
+}
+
+static CALLBACKS: OnceCell<TrayCallbacks> = OnceCell::new();
+// Broadcast when explorer (re)starts, including after our own explorer suspension: icons must be re-added
+static TASKBAR_CREATED: Lazy<u32> = Lazy::new(|| unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) });
+// Tray window (0 = not created yet)
+static TRAY_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// Add the tray icon; `status` is polled for menu/tooltip content, `on_command` runs menu actions
+pub fn start(
+    status: impl Fn() -> TrayStatus + Send + Sync + 'static,
+    on_command: impl Fn(TrayCommand) + Send + Sync + 'static,
+) {
+    if CALLBACKS.set(TrayCallbacks { status: Box::new(status), on_command: Box::new(on_command) }).is_err() {
+        return; // Already running
+    }
+
+    thread::spawn(|| unsafe {
+        let Ok(module) = GetModuleHandleW(None) else { return };
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(tray_window_proc),
+            hInstance: HINSTANCE::from(module),
+            lpszClassName: w!("XillyGameModeTray"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+
+        // Hidden top-level window (not message-only, so it still gets the TaskbarCreated broadcast)
+        let hwnd = match CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("XillyGameModeTray"),
+            w!("Xilly Game Mode"),
+            WINDOW_STYLE::default(),
+            0, 0, 0, 0,
+            None,
+            None,
+            HINSTANCE::from(module),
+            None,
+        ) {
+            Ok(hwnd) => hwnd,
+            Err(e) => {
+                log_line!("[Tray] Failed to create the tray window: {}", e);
+                return;
+            }
+        };
+        TRAY_WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+        Lazy::force(&TASKBAR_CREATED);
+        add_icon(hwnd);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            DispatchMessageW(&msg);
+        }
+        let _ = Shell_NotifyIconW(NIM_DELETE, &icon_data(hwnd));
+    });
+}
+
+/// Re-read the status into the tooltip (session started/ended, game picked)
+pub fn refresh() {
+    let hwnd = TRAY_WINDOW.load(Ordering::SeqCst);
+    if hwnd != 0 {
+        unsafe {
+            let _ = PostMessageW(HWND(hwnd as *mut _), WM_TRAY_REFRESH, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Remove the icon (the process is exiting)
+pub fn remove() {
+    let hwnd = TRAY_WINDOW.swap(0, Ordering::SeqCst);
+    if hwnd != 0 {
+        unsafe {
+            let _ = Shell_NotifyIconW(NIM_DELETE, &icon_data(HWND(hwnd as *mut _)));
+        }
+    }
+}
+
+fn status() -> TrayStatus {
+    CALLBACKS.get().map(|c| (c.status)()).unwrap_or_default()
+}
+
+fn run(command: TrayCommand) {
+    if let Some(callbacks) = CALLBACKS.get() {
+        (callbacks.on_command)(command);
+    }
+}
+
+/// "Active: cs2.exe (12 min)" / "Idle"
+fn status_line(status: &TrayStatus) -> String {
+    if !status.active {
+        return tr!("Idle");
+    }
+    let minutes = status.elapsed.map_or(0, |d| d.as_secs() / 60);
+    match &status.game {
+        Some(game) => tr!("Active: {} ({} min)", game, minutes),
+        None => tr!("Active ({} min)", minutes),
+    }
+}
+
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: TRAY_ICON_ID,
+        ..Default::default()
+    }
+}
+
+unsafe fn app_icon() -> HICON {
+    // winres embeds the exe icon as resource 1
+    GetModuleHandleW(None).ok()
+        .and_then(|module| LoadIconW(HINSTANCE::from(module), PCWSTR(1 as _)).ok())
+        .or_else(|| LoadIconW(None, IDI_APPLICATION).ok())
+        .unwrap_or_default()
+}
+
+unsafe fn add_icon(hwnd: HWND) {
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP;
+    data.uCallbackMessage = WM_TRAY_ICON;
+    data.hIcon = app_icon();
+    set_tip(&mut data, &status());
+    if !Shell_NotifyIconW(NIM_ADD, &data).as_bool() {
+        log_line!("[Tray] Failed to add the tray icon");
+    }
+}
+
+unsafe fn update_tooltip(hwnd: HWND) {
+    let status = status();
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_TIP;
+    set_tip(&mut data, &status);
+    let _ = Shell_NotifyIconW(NIM_MODIFY, &data);
+
+    // Minutes only tick while a session runs
+    if status.active {
+        SetTimer(hwnd, DURATION_TIMER_ID, DURATION_TIMER_MS, None);
+    } else {
+        let _ = KillTimer(hwnd, DURATION_TIMER_ID);
+    }
+}
+
+fn set_tip(data: &mut NOTIFYICONDATAW, status: &TrayStatus) {
+    let mut tip = format!("Xilly Game Mode - {}", status_line(status));
+    if status.update.is_some() {
+        tip.push_str(&tr!(" - Update available"));
+    }
+    let len = data.szTip.len() - 1;
+    for (slot, c) in data.szTip.iter_mut().take(len).zip(tip.encode_utf16()) {
+        *slot = c;
+    }
+}
+
+unsafe fn append(menu: HMENU, flags: MENU_ITEM_FLAGS, id: usize, text: &str) {
+    let text_w = HSTRING::from(text);
+    let _ = AppendMenuW(menu, MF_STRING | flags, id, &text_w);
+}
+
+unsafe fn show_menu(hwnd: HWND) {
+    let status = status();
+    let Ok(menu) = CreatePopupMenu() else { return };
+    let checked = |on: bool| if on { MF_CHECKED } else { MF_STRING };
+
+    append(menu, MF_GRAYED, 0, &status_line(&status));
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+    append(menu, MF_STRING, CMD_TOGGLE, &if status.active { tr!("Deactivate Game Mode") } else { tr!("Activate Game Mode") });
+
+    if let Ok(profiles) = CreatePopupMenu() {
+        for (i, name) in status.profiles.iter().enumerate() {
+            append(profiles, checked(*name == status.active_profile), PROFILE_BASE + i, name);
+        }
+        append(menu, MF_POPUP, profiles.0 as usize, &tr!("Profile"));
+    }
+
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+    append(menu, checked(status.suspend_browsers), CMD_SUSPEND_BROWSERS, &tr!("Suspend Browsers"));
+    append(menu, checked(status.isolate_network), CMD_ISOLATE_NETWORK, &tr!("Network Isolation"));
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+    if let Some(version) = &status.update {
+        // Installing exits the app, so not while a session is applied
+        append(menu, if status.active { MF_GRAYED } else { MF_STRING }, CMD_UPDATE, &tr!("Update to {}", version));
+    }
+    append(menu, MF_STRING, CMD_SHOW, &tr!("Show"));
+    append(menu, MF_STRING, CMD_EXIT, &tr!("Exit"));
+
+    // Required for the menu to close when clicking elsewhere
+    let _ = SetForegroundWindow(hwnd);
+    let mut cursor = POINT::default();
+    let _ = GetCursorPos(&mut cursor);
+    let picked = TrackPopupMenu(
+        menu,
+        TPM_RETURNCMD | TPM_RIGHTBUTTON | TPM_BOTTOMALIGN,
+        cursor.x,
+        cursor.y,
+        0,
+        hwnd,
+        None,
+    ).0 as usize;
+    // Submenus are destroyed with their parent
+    let _ = DestroyMenu(menu);
+
+    let command = match picked {
+        CMD_SHOW => TrayCommand::Show,
+        CMD_TOGGLE => TrayCommand::ToggleGameMode,
+        CMD_SUSPEND_BROWSERS => TrayCommand::ToggleSuspendBrowsers,
+        CMD_ISOLATE_NETWORK => TrayCommand::ToggleNetworkIsolation,
+        CMD_UPDATE => TrayCommand::InstallUpdate,
+        CMD_EXIT => TrayCommand::Exit,
+        id if id >= PROFILE_BASE => match status.profiles.get(id - PROFILE_BASE) {
+            Some(name) => TrayCommand::SelectProfile(name.clone()),
+            None => return,
+        },
+        _ => return, // Dismissed
+    };
+    run(command);
+}
+
+unsafe extern "system" fn tray_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_TRAY_ICON => {
+            match lparam.0 as u32 {
+                WM_LBUTTONUP => run(TrayCommand::Show),
+                WM_RBUTTONUP | WM_CONTEXTMENU => show_menu(hwnd),
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        WM_TRAY_REFRESH | WM_TIMER => {
+            update_tooltip(hwnd);
+            LRESULT(0)
+        }
+        _ if msg == *TASKBAR_CREATED => {
+            add_icon(hwnd);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
@@ -0,0 +1,61 @@
+//! Periodic Memory Trim
+//! For long sessions on 8-16 GB machines: every few minutes re-trims background working sets
+//! and purges the standby list, so launchers and browsers that crept back don't push the game
+//! into paging. Uses the same exclusions as the start-of-session flush, so the game is never touched.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use crate::log_line;
+use crate::services::memory::MemoryService;
+
+struct Trim {
+    /// Set to true by stop(); the Condvar wakes the thread out of its wait
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: JoinHandle<()>,
+}
+
+static TRIM: Lazy<Mutex<Option<Trim>>> = Lazy::new(|| Mutex::new(None));
+
+/// Start trimming every `interval_minutes`; `game_pid` is read on every pass so a picked or
+/// relaunched game stays excluded
+pub fn start(game_pid: Arc<AtomicU32>, interval_minutes: u32, exclusions: Vec<String>) {
+    let mut trim = TRIM.lock().unwrap();
+    if trim.is_some() || interval_minutes == 0 {
+        return;
+    }
+
+    let stop = Arc::new((Mutex::new(false), Condvar::new()));
+    let stop_for_thread = stop.clone();
+    let interval = Duration::from_secs(interval_minutes as u64 * 60);
+    let handle = thread::spawn(move || {
+        let (stopped, signal) = &*stop_for_thread;
+        loop {
+            let guard = signal.wait_timeout_while(stopped.lock().unwrap(), interval, |s| !*s).unwrap().0;
+            if *guard {
+                break;
+            }
+            drop(guard);
+
+            let game = game_pid.load(Ordering::SeqCst);
+            MemoryService::flush_memory((game != 0).then_some(game), &exclusions);
+            if MemoryService::purge_standby_list() {
+                log_line!("[Trim] Standby list purged");
+            }
+        }
+    });
+
+    log_line!("[Trim] Trimming background memory every {} min", interval_minutes);
+    *trim = Some(Trim { stop, handle });
+}
+
+pub fn stop() {
+    if let Some(trim) = TRIM.lock().unwrap().take() {
+        let (stopped, signal) = &*trim.stop;
+        *stopped.lock().unwrap() = true;
+        signal.notify_all();
+        let _ = trim.handle.join();
+    }
+}
@@ -1,104 +1,374 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
 use std::env;
 use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::thread;
+use once_cell::sync::Lazy;
+use crate::services::{notify, session, settings};
+use crate::{log_line, tr};
 
-#[derive(Deserialize, Debug)]
+const RELEASES_URL: &str = "https://api.github.com/repos/xillyservices-code/GameMode/releases";
+/// Version of this build, compared against release tags
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Release notes longer than this are cut in the update prompt
+const NOTES_LIMIT: usize = 1200;
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct GitHubRelease {
     pub tag_name: String,
+    /// Release notes (markdown)
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
     pub assets: Vec<GitHubAsset>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct GitHubAsset {
     pub name: String,
     pub browser_download_url: String,
 }
 
+/// Release track the updater follows ("update_channel" in settings.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// Full releases only
+    #[default]
+    Stable,
+    /// Pre-releases too
+    Beta,
+}
+
+// Newer release found by the last check, installed only when the user asks
+static AVAILABLE: Lazy<Mutex<Option<GitHubRelease>>> = Lazy::new(|| Mutex::new(None));
+// Download/install in progress (a second click does nothing)
+static INSTALLING: AtomicBool = AtomicBool::new(false);
+
 pub struct UpdateService;
 
 impl UpdateService {
-    // 1:1 CheckForUpdatesAsync logic (Synchronous wrapper for thread usage)
-    pub fn check_for_updates() {
+    /// Look for a newer release on `channel` (blocking); remembers it for `install_available`
+    /// and returns its tag, without downloading anything
+    pub fn check(channel: UpdateChannel) -> Option<String> {
+        let release = match Self::latest_release(channel) {
+            Ok(Some(release)) => release,
+            Ok(None) => return None,
+            Err(e) => {
+                log_line!("[Update] Check failed: {}", e);
+                return None;
+            }
+        };
+        if Self::compare_versions(&release.tag_name, CURRENT_VERSION) != Ordering::Greater {
+            log_line!("[Update] Up to date (v{}, latest {:?} {})", CURRENT_VERSION, channel, release.tag_name);
+            return None;
+        }
+        log_line!("[Update] {} available (running v{})", release.tag_name, CURRENT_VERSION);
+        let tag = release.tag_name.clone();
+        *AVAILABLE.lock().unwrap() = Some(release);
+        Some(tag)
+    }
+
+    /// Tag of the update found by the last `check`, if any
+    pub fn available() -> Option<String> {
+        AVAILABLE.lock().unwrap().as_ref().map(|r| r.tag_name.clone())
+    }
+
+    /// Show the found update's notes and install it if confirmed ("Update" in the UI / tray);
+    /// `progress` gets the download fraction (0.0-1.0), then None once it is done or failed.
+    /// Refused while a game mode session runs: installing exits without restoring the system
+    pub fn install_available(progress: impl Fn(Option<f32>) + Send + 'static) {
+        let Some(release) = AVAILABLE.lock().unwrap().clone() else {
+            return;
+        };
+        if session::current().is_some() {
+            log_line!("[Update] Not installing {} during a session", release.tag_name);
+            notify::info(&tr!("Update Not Installed"), &tr!("End the game mode session first, then install the update."));
+            return;
+        }
+        if INSTALLING.swap(true, AtomicOrdering::SeqCst) {
+            return; // Already downloading
+        }
         thread::spawn(move || {
-            if let Ok(release) = Self::get_latest_release() {
-                // Version parsing logic from C#
-                // C# compares TagName with "1.0.0"
-                // Assuming current version is 1.0.0
-                let current_version = "1.0.0";
-                let tag = release.tag_name.trim_start_matches('v');
-                
-                // Simple string compare or semver? C# used Version.TryParse
-                // We'll simplisticly assume if tag != current, it's new for this MVP port
-                if tag != current_version {
-                    // Logic: Show Native MessageBox "Update Available"
-                    // In Slint, showing a message box from background thread is hard without callback.
-                    // But C# uses `ModernMessageBox.ShowDialog()`.
-                    // We can print or use a Win32 MessageBox.
-                    
-                    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_YESNO, MB_ICONQUESTION, IDYES};
-                    use windows::core::HSTRING;
-                    
+            use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_OK, MB_YESNO, MB_ICONQUESTION, MB_ICONWARNING, IDYES};
+            use windows::core::HSTRING;
+
+            let notes = Self::release_notes(&release);
+            let msg = if release.prerelease {
+                tr!(
+                    "A new beta version ({}) is available! You have v{}.\n\nWhat's new:\n{}\n\nDo you want to update now?",
+                    release.tag_name, CURRENT_VERSION, notes,
+                )
+            } else {
+                tr!(
+                    "A new version ({}) is available! You have v{}.\n\nWhat's new:\n{}\n\nDo you want to update now?",
+                    release.tag_name, CURRENT_VERSION, notes,
+                )
+            };
+            let result = unsafe {
+                MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(tr!("Update Available")), MB_YESNO | MB_ICONQUESTION)
+            };
+            if result == IDYES {
+                if let Err(e) = Self::perform_update(&release, &progress) {
+                    log_line!("[Update] Not installed: {}", e);
+                    progress(None);
                     unsafe {
-                        let msg = format!("A new version ({}) is available!\n\nDo you want to update now?", release.tag_name);
-                        let title = "Update Available";
-                        
-                        let result = MessageBoxW(None, &HSTRING::from(msg), &HSTRING::from(title), MB_YESNO | MB_ICONQUESTION);
-                        if result == IDYES {
-                             Self::perform_update(&release);
-                        }
+                        MessageBoxW(None, &HSTRING::from(e), &HSTRING::from(tr!("Update Not Installed")), MB_OK | MB_ICONWARNING);
                     }
                 }
             }
+            INSTALLING.store(false, AtomicOrdering::SeqCst);
         });
     }
 
-    fn get_latest_release() -> Result<GitHubRelease, Box<dyn std::error::Error>> {
-        let url = "https://api.github.com/repos/xillyservices-code/GameMode/releases/latest";
-        let agent = ureq::AgentBuilder::new().user_agent("XillyGameMode-Updater").build();
-        let resp = agent.get(url).call()?;
-        let release: GitHubRelease = resp.into_json()?;
-        Ok(release)
+    /// HTTP agent for GitHub requests (updates, community presets)
+    pub fn agent() -> ureq::Agent {
+        ureq::AgentBuilder::new().user_agent("XillyGameMode-Updater").build()
+    }
+
+    /// Newest release on the channel (None if nothing is published)
+    fn latest_release(channel: UpdateChannel) -> Result<Option<GitHubRelease>, Box<dyn std::error::Error>> {
+        match channel {
+            // /latest never returns pre-releases
+            UpdateChannel::Stable => {
+                let release: GitHubRelease = Self::agent().get(&format!("{}/latest", RELEASES_URL)).call()?.into_json()?;
+                Ok(Some(release))
+            }
+            UpdateChannel::Beta => {
+                let releases: Vec<GitHubRelease> = Self::agent()
+                    .get(&format!("{}?per_page=20", RELEASES_URL))
+                    .call()?
+                    .into_json()?;
+                Ok(releases.into_iter().max_by(|a, b| Self::compare_versions(&a.tag_name, &b.tag_name)))
+            }
+        }
+    }
+
+    /// Semver-style compare of "v1.2.3" / "1.2.3-beta.2" tags; a pre-release sorts before its release
+    fn compare_versions(a: &str, b: &str) -> Ordering {
+        fn split(tag: &str) -> (Vec<u64>, Option<&str>) {
+            let tag = tag.trim().trim_start_matches(['v', 'V']);
+            let (core, pre) = match tag.split_once('-') {
+                Some((core, pre)) => (core, Some(pre)),
+                None => (tag, None),
+            };
+            let mut numbers: Vec<u64> = core.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+            numbers.resize(3.max(numbers.len()), 0);
+            (numbers, pre)
+        }
+        fn compare_pre(a: &str, b: &str) -> Ordering {
+            for (x, y) in a.split('.').zip(b.split('.')) {
+                let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            a.split('.').count().cmp(&b.split('.').count())
+        }
+
+        let (a_core, a_pre) = split(a);
+        let (b_core, b_pre) = split(b);
+        a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(x), Some(y)) => compare_pre(x, y),
+        })
+    }
+
+    fn release_notes(release: &GitHubRelease) -> String {
+        let notes = release.body.as_deref().map(str::trim).unwrap_or_default();
+        if notes.is_empty() {
+            return "(no release notes)".to_string();
+        }
+        match notes.char_indices().nth(NOTES_LIMIT) {
+            Some((cut, _)) => format!("{}...", &notes[..cut]),
+            None => notes.to_string(),
+        }
     }
 
-    fn perform_update(release: &GitHubRelease) {
-         // Find exe asset
-         if let Some(asset) = release.assets.iter().find(|a| a.name.ends_with(".exe")) {
-             let url = &asset.browser_download_url;
-             if let Ok(bytes) = Self::download_file(url) {
-                  let current_exe = env::current_exe().unwrap_or(PathBuf::from("gamemode.exe"));
-                  let update_exe = current_exe.with_extension("update");
-                  
-                  if fs::write(&update_exe, bytes).is_ok() {
-                      // Create bat file
-                      let bat_file = env::temp_dir().join("gamemode_update.bat");
-                      let pid = std::process::id();
-                      let current_exe_str = current_exe.to_string_lossy();
-                      let update_exe_str = update_exe.to_string_lossy();
-                      
-                      // 1:1 Batch file content logic
-                      let script = format!(
-                          "@echo off\r\ntimeout /t 2 /nobreak\r\n:loop\r\ntasklist | find \"{}\" >nul\r\nif not errorlevel 1 (\r\n    timeout /t 1 /nobreak\r\n    goto loop\r\n)\r\nif exist \"{}.bak\" del \"{}.bak\"\r\nmove \"{}\" \"{}.bak\"\r\nmove \"{}\" \"{}\"\r\nstart \"\" \"{}\"\r\ndel \"%~f0\"\r\n",
-                          pid, current_exe_str, current_exe_str, current_exe_str, current_exe_str, update_exe_str, current_exe_str, current_exe_str
-                      );
-                      
-                      if fs::write(&bat_file, script).is_ok() {
-                          let _ = Command::new("cmd")
-                              .args(["/C", bat_file.to_str().unwrap()])
-                              .spawn();
-                          std::process::exit(0);
-                      }
-                  }
-             }
-         }
+    fn perform_update(release: &GitHubRelease, progress: &dyn Fn(Option<f32>)) -> Result<(), String> {
+        // Find exe asset
+        let asset = release.assets.iter()
+            .find(|a| a.name.ends_with(".exe"))
+            .ok_or("The release has no executable to install.")?;
+        let expected = Self::published_sha256(release, &asset.name)
+            .ok_or_else(|| format!("The release publishes no SHA-256 for {}, so it cannot be verified.", asset.name))?
+            .to_ascii_lowercase();
+
+        let current_exe = env::current_exe().unwrap_or(PathBuf::from("gamemode.exe"));
+        let update_exe = current_exe.with_extension("update");
+        // Named after the expected hash, so a partial download is only resumed for the same build
+        let partial = current_exe.with_extension(format!("{}.part", &expected[..12]));
+
+        Self::download_resumable(&asset.browser_download_url, &partial, progress)?;
+        let actual = Self::sha256_file(&partial).map_err(|e| format!("Could not read the download: {}", e))?;
+        if actual != expected {
+            let _ = fs::remove_file(&partial);
+            return Err(format!("The download does not match the published SHA-256 of {}. Nothing was changed.", asset.name));
+        }
+        log_line!("[Update] {} verified ({})", asset.name, actual);
+        let _ = fs::remove_file(&update_exe);
+        fs::rename(&partial, &update_exe).map_err(|e| format!("Could not save the update: {}", e))?;
+        progress(None);
+
+        // Create bat file
+        let bat_file = env::temp_dir().join("gamemode_update.bat");
+        let pid = std::process::id();
+        let current_exe_str = current_exe.to_string_lossy();
+        let update_exe_str = update_exe.to_string_lossy();
+
+        // Wait for this process to exit, keep the old exe as .bak and swap in the update.
+        // If the swap fails the .bak is moved back and the old version restarts with --update-failed
+        let script = format!(
+            "@echo off\r\n\
+             timeout /t 2 /nobreak\r\n\
+             :loop\r\n\
+             tasklist /FI \"PID eq {pid}\" | find \"{pid}\" >nul\r\n\
+             if not errorlevel 1 (\r\n    timeout /t 1 /nobreak\r\n    goto loop\r\n)\r\n\
+             if exist \"{exe}.bak\" del \"{exe}.bak\"\r\n\
+             move /y \"{exe}\" \"{exe}.bak\" || goto failed\r\n\
+             move /y \"{update}\" \"{exe}\" || goto rollback\r\n\
+             start \"\" \"{exe}\"\r\n\
+             goto done\r\n\
+             :rollback\r\n\
+             move /y \"{exe}.bak\" \"{exe}\"\r\n\
+             :failed\r\n\
+             start \"\" \"{exe}\" --update-failed\r\n\
+             :done\r\n\
+             del \"%~f0\"\r\n",
+            pid = pid,
+            exe = current_exe_str,
+            update = update_exe_str,
+        );
+
+        // A session started during the download would be left applied by the exit below
+        if session::current().is_some() {
+            return Err("A game mode session started during the download. End it, then install the update again.".to_string());
+        }
+        fs::write(&bat_file, script).map_err(|e| format!("Could not write the update script: {}", e))?;
+        // Only exit once the script is running; otherwise this version just keeps going
+        Command::new("cmd")
+            .args(["/C", &bat_file.to_string_lossy()])
+            .spawn()
+            .map_err(|e| format!("Could not start the update script: {}", e))?;
+        settings::flush_pending();
+        std::process::exit(0);
+    }
+
+    /// Stream `url` into `partial`, continuing after the bytes already there (HTTP Range)
+    fn download_resumable(url: &str, partial: &Path, progress: &dyn Fn(Option<f32>)) -> Result<(), String> {
+        let offset = fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+        let mut request = Self::agent().get(url);
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            // Range past the end: the partial file is complete or stale, start over
+            Err(ureq::Error::Status(416, _)) if offset > 0 => {
+                let _ = fs::remove_file(partial);
+                return Self::download_resumable(url, partial, progress);
+            }
+            Err(e) => return Err(format!("Download failed: {}", e)),
+        };
+
+        // 206 = server continues where we left off; 200 = it sent the whole file again
+        let resumed = offset > 0 && response.status() == 206;
+        let start = if resumed { offset } else { 0 };
+        let total = response.header("Content-Length")
+            .and_then(|len| len.parse::<u64>().ok())
+            .map(|len| len + start);
+        if resumed {
+            log_line!("[Update] Resuming download at {} bytes", offset);
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(partial)
+            .map_err(|e| format!("Could not save the download: {}", e))?;
+        let mut reader = response.into_reader();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut written = start;
+        let mut reported = -1.0f32;
+        loop {
+            // An interrupted download keeps its partial file for the next attempt
+            let read = reader.read(&mut buffer).map_err(|e| format!("Download interrupted: {}", e))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read]).map_err(|e| format!("Could not save the download: {}", e))?;
+            written += read as u64;
+            if let Some(total) = total.filter(|t| *t > 0) {
+                let fraction = (written as f32 / total as f32).min(1.0);
+                if fraction - reported >= 0.01 {
+                    reported = fraction;
+                    progress(Some(fraction));
+                }
+            }
+        }
+        file.flush().map_err(|e| format!("Could not save the download: {}", e))?;
+
+        if total.is_some_and(|t| written < t) {
+            return Err(format!("Download interrupted at {} of {} bytes; it resumes on the next try", written, total.unwrap_or(0)));
+        }
+        Ok(())
+    }
+
+    fn sha256_file(path: &Path) -> std::io::Result<String> {
+        let mut file = fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// SHA-256 for `asset_name` from "<asset>.sha256" or a SHA256SUMS / checksums.txt asset
+    fn published_sha256(release: &GitHubRelease, asset_name: &str) -> Option<String> {
+        let checksum_asset = release.assets.iter().find(|a| {
+            let name = a.name.to_ascii_lowercase();
+            name == format!("{}.sha256", asset_name.to_ascii_lowercase())
+                || name == "sha256sums"
+                || name == "sha256sums.txt"
+                || name == "checksums.txt"
+        })?;
+        let bytes = Self::download_file(&checksum_asset.browser_download_url).ok()?;
+        let text = String::from_utf8_lossy(&bytes);
+
+        // "<hash>  <file>" lines (sha256sum format, '*' = binary mode), or just "<hash>"
+        text.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            let hash = parts.next()?;
+            let is_hash = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+            match parts.next() {
+                Some(file) if file.trim_start_matches('*').eq_ignore_ascii_case(asset_name) => is_hash.then(|| hash.to_string()),
+                None if is_hash => Some(hash.to_string()),
+                _ => None,
+            }
+        })
     }
 
-    fn download_file(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let agent = ureq::AgentBuilder::new().user_agent("XillyGameMode-Updater").build();
-        let resp = agent.get(url).call()?;
+    pub fn download_file(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let resp = Self::agent().get(url).call()?;
         let mut reader = resp.into_reader();
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes)?;
@@ -0,0 +1,188 @@
+//! Core Isolation (expert)
+//! Reads whether virtualization-based security and Memory Integrity (HVCI) are actually running
+//! (Win32_DeviceGuard), and turns them off / back on through the same registry values the
+//! Windows Security toggle uses. Like the other boot settings this is permanent and applies at
+//! the next restart; the values from before are kept in vbs.json (data folder) for the revert.
+//! With a UEFI lock the registry values are ignored, which is reported instead of written.
+
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::services::settings;
+use crate::services::system::{Hive, RegistryBackend, Win32};
+use crate::{log_line, tr};
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+const DEVICE_GUARD: &str = r"SYSTEM\CurrentControlSet\Control\DeviceGuard";
+const HVCI: &str = r"SYSTEM\CurrentControlSet\Control\DeviceGuard\Scenarios\HypervisorEnforcedCodeIntegrity";
+/// Group Policy "Turn On Virtualization Based Security", wins over the values below
+const DEVICE_GUARD_POLICY: &str = r"SOFTWARE\Policies\Microsoft\Windows\DeviceGuard";
+
+/// (key, value) written to 0 by `disable`
+const VALUES: &[(&str, &str)] = &[
+    (HVCI, "Enabled"),
+    (DEVICE_GUARD, "EnableVirtualizationBasedSecurity"),
+];
+
+/// Win32_DeviceGuard SecurityServicesRunning entry for Memory Integrity
+const SERVICE_HVCI: u32 = 2;
+/// Win32_DeviceGuard VirtualizationBasedSecurityStatus: enabled and running
+const VBS_RUNNING: u32 = 2;
+
+/// What is running right now and what would stop the registry values from mattering
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VbsState {
+    pub vbs_running: bool,
+    pub hvci_running: bool,
+    /// Enabled with a UEFI lock: only the Device Guard readiness tool can turn it off
+    pub uefi_locked: bool,
+    /// Enforced by Group Policy (or an MDM), which rewrites the values
+    pub policy_enforced: bool,
+}
+
+impl VbsState {
+    /// Short status line for the UI
+    pub fn describe(&self) -> String {
+        let running = match (self.vbs_running, self.hvci_running) {
+            (true, true) => tr!("VBS and Memory Integrity running"),
+            (true, false) => tr!("VBS running, Memory Integrity off"),
+            (false, _) => tr!("VBS not running"),
+        };
+        if self.uefi_locked {
+            tr!("{}, locked in UEFI", running)
+        } else if self.policy_enforced {
+            tr!("{}, enforced by policy", running)
+        } else {
+            running
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VbsBackup {
+    /// (key, value, data before the change; None = not set)
+    originals: Vec<(String, String, Option<u32>)>,
+}
+
+/// Current state; None if Win32_DeviceGuard could not be read (pre-1607 or WMI broken)
+pub fn state() -> Option<VbsState> {
+    let output = Command::new("wmic")
+        .args([
+            r"/namespace:\\root\Microsoft\Windows\DeviceGuard",
+            "path", "Win32_DeviceGuard",
+            "get", "VirtualizationBasedSecurityStatus,SecurityServicesRunning", "/format:list",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let mut state = VbsState {
+        uefi_locked: uefi_locked(),
+        policy_enforced: policy_enforced(),
+        ..Default::default()
+    };
+    let mut found = false;
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        match key {
+            "VirtualizationBasedSecurityStatus" => {
+                found = true;
+                state.vbs_running = value.trim().parse::<u32>().ok() == Some(VBS_RUNNING);
+            }
+            // Array, shown as {1,2}
+            "SecurityServicesRunning" => {
+                state.hvci_running = value
+                    .trim_matches(|c| c == '{' || c == '}' || char::is_whitespace(c))
+                    .split(',')
+                    .any(|s| s.trim().parse::<u32>().ok() == Some(SERVICE_HVCI));
+            }
+            _ => {}
+        }
+    }
+    found.then_some(state)
+}
+
+/// The registry values are written by `disable` (possibly waiting for a reboot)
+pub fn is_disabled_by_app() -> bool {
+    backup_path().exists()
+}
+
+/// The session's VBS registry values would be ignored (see revi_tweaks)
+pub fn registry_ignored() -> bool {
+    uefi_locked() || policy_enforced()
+}
+
+fn uefi_locked() -> bool {
+    Win32.read_dword(Hive::LocalMachine, DEVICE_GUARD, "Locked") == Some(1) || Win32.read_dword(Hive::LocalMachine, HVCI, "Locked") == Some(1)
+}
+
+fn policy_enforced() -> bool {
+    Win32.read_dword(Hive::LocalMachine, DEVICE_GUARD_POLICY, "EnableVirtualizationBasedSecurity") == Some(1)
+}
+
+/// Record the current values and turn Memory Integrity and VBS off for the next boot
+pub fn disable(state: &VbsState) -> Result<(), String> {
+    if is_disabled_by_app() {
+        return Ok(());
+    }
+    if state.uefi_locked {
+        return Err("Core isolation is locked in UEFI. Use Microsoft's Device Guard readiness tool to turn it off.".into());
+    }
+    if state.policy_enforced {
+        return Err("Core isolation is enforced by Group Policy. Change \"Turn On Virtualization Based Security\" instead.".into());
+    }
+
+    let backup = VbsBackup {
+        originals: VALUES.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string(), Win32.read_dword(Hive::LocalMachine, key, value)))
+            .collect(),
+    };
+    save_backup(&backup);
+
+    for (key, value) in VALUES {
+        if !Win32.write_dword(Hive::LocalMachine, key, value, 0) {
+            return Err(format!("Could not write {}", value));
+        }
+        log_line!("[VBS] {}\\{} -> 0", key, value);
+    }
+    Ok(())
+}
+
+/// Put back the values from before `disable`; true if anything was restored
+pub fn restore() -> bool {
+    let Some(backup) = load_backup() else {
+        return false;
+    };
+    for (key, value, original) in &backup.originals {
+        match original {
+            Some(data) => { Win32.write_dword(Hive::LocalMachine, key, value, *data); }
+            None => Win32.delete_value(Hive::LocalMachine, key, value),
+        }
+        log_line!("[VBS] {}\\{} restored ({:?})", key, value, original);
+    }
+    let _ = fs::remove_file(backup_path());
+    true
+}
+
+/// vbs.json in settings::data_dir()
+fn backup_path() -> PathBuf {
+    settings::data_dir().join("vbs.json")
+}
+
+fn load_backup() -> Option<VbsBackup> {
+    serde_json::from_str(&fs::read_to_string(backup_path()).ok()?).ok()
+}
+
+fn save_backup(backup: &VbsBackup) {
+    if let Ok(json) = serde_json::to_string_pretty(backup) {
+        if let Err(e) = fs::write(backup_path(), json) {
+            log_line!("[VBS] Could not save the backup: {}", e);
+        }
+    }
+}
@@ -1,11 +1,28 @@
 use windows::Win32::System::Services::{
     OpenSCManagerW, OpenServiceW, ControlService, CloseServiceHandle, StartServiceW,
-    QueryServiceStatus, SC_MANAGER_CONNECT, SERVICE_STOP, SERVICE_START, 
-    SERVICE_CONTROL_STOP, SERVICE_STATUS, SERVICE_QUERY_STATUS, SERVICE_RUNNING,
+    QueryServiceStatus, SC_HANDLE, SC_MANAGER_CONNECT, SERVICE_STOP, SERVICE_START, 
+    SERVICE_CONTROL_STOP, SERVICE_STATUS, SERVICE_STATUS_CURRENT_STATE, SERVICE_QUERY_STATUS,
+    SERVICE_RUNNING, SERVICE_STOPPED,
 };
 use windows::core::{PCWSTR, HSTRING};
 use std::thread;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::services::notify;
+use crate::services::system::{Hive, RegistryBackend, ServiceControl};
+use crate::{log_line, tr};
+
+/// How long a service may take to report RUNNING after StartServiceW
+const START_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long a stop may stay STOP_PENDING before game mode carries on without it
+const STOP_TIMEOUT: Duration = Duration::from_secs(15);
+/// Start attempts per service; a dependency restored in parallel may not be up on the first one
+const START_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const SERVICES_KEY: &str = r"SYSTEM\CurrentControlSet\Services";
+/// Service Type bits: a per-user service template, and an instance created from one at sign-in
+const SERVICE_USER_SERVICE: u32 = 0x40;
+const SERVICE_USERSERVICE_INSTANCE: u32 = 0x80;
 
 pub struct WindowsServiceManager;
 
@@ -18,17 +35,20 @@ impl WindowsServiceManager {
     ];
 
     /// Stop optimization services - Parallel with thread-safe collection
-    pub fn stop_optimization_services() -> Vec<String> {
-        let stopped = Mutex::new(Vec::with_capacity(Self::OPTIMIZATION_SERVICES.len()));
+    pub fn stop_optimization_services<S: ServiceControl + RegistryBackend + Sync + ?Sized>(services: &S) -> Vec<String> {
+        let names: Vec<String> = Self::OPTIMIZATION_SERVICES.iter()
+            .flat_map(|name| Self::resolve_service_names(services, name))
+            .collect();
+        let stopped = Mutex::new(Vec::with_capacity(names.len()));
         
         thread::scope(|s| {
-            for &name in Self::OPTIMIZATION_SERVICES {
+            for name in &names {
                 let stopped_ref = &stopped;
                 
                 s.spawn(move || {
-                    if Self::stop_single_service(name) {
+                    if services.stop(name) {
                         if let Ok(mut guard) = stopped_ref.lock() {
-                            guard.push(name.to_string());
+                            guard.push(name.clone());
                         }
                     }
                 });
@@ -38,9 +58,42 @@ impl WindowsServiceManager {
         stopped.into_inner().unwrap_or_default()
     }
 
-    /// Stop a single service - returns true if stopped
+    /// Stop the given services; returns the ones that were running and got stopped
+    pub fn stop_services<S: ServiceControl + RegistryBackend + ?Sized>(services: &S, names: &[&str]) -> Vec<String> {
+        names.iter()
+            .flat_map(|name| Self::resolve_service_names(services, name))
+            .filter(|name| services.stop(name))
+            .collect()
+    }
+
+    /// Services to act on for `name`: the name itself, plus for a per-user service template
+    /// (CDPUserSvc) its instances (CDPUserSvc_4a1b2), whose suffix changes with every sign-in
+    /// The template never runs, but its startup type is what new instances inherit
+    pub fn resolve_service_names<R: RegistryBackend + ?Sized>(registry: &R, name: &str) -> Vec<String> {
+        let service_type = |service: &str| {
+            registry.read_dword(Hive::LocalMachine, &format!(r"{}\{}", SERVICES_KEY, service), "Type").unwrap_or(0)
+        };
+        let template_type = service_type(name);
+        if template_type & SERVICE_USER_SERVICE == 0 || template_type & SERVICE_USERSERVICE_INSTANCE != 0 {
+            return vec![name.to_string()];
+        }
+        
+        let prefix = format!("{}_", name);
+        let mut names = vec![name.to_string()];
+        names.extend(
+            registry.subkeys(Hive::LocalMachine, SERVICES_KEY)
+                .into_iter()
+                .filter(|key| key.len() > prefix.len())
+                .filter(|key| key.get(..prefix.len()).is_some_and(|p| p.eq_ignore_ascii_case(&prefix)))
+                .filter(|key| service_type(key) & SERVICE_USERSERVICE_INSTANCE != 0),
+        );
+        names
+    }
+
+    /// Stop a single service - returns true if it accepted the stop (and so needs restoring)
+    /// Waits out STOP_PENDING so slow services (WSearch, SysMain) are down before the game starts
     #[inline]
-    fn stop_single_service(name: &str) -> bool {
+    pub(crate) fn stop_single_service(name: &str) -> bool {
         unsafe {
             let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else { 
                 return false; 
@@ -57,7 +110,11 @@ impl WindowsServiceManager {
                     && status.dwCurrentState == SERVICE_RUNNING 
                 {
                     let mut new_status = SERVICE_STATUS::default();
-                    ControlService(service, SERVICE_CONTROL_STOP, &mut new_status).is_ok()
+                    let accepted = ControlService(service, SERVICE_CONTROL_STOP, &mut new_status).is_ok();
+                    if accepted && !Self::wait_for_state(service, SERVICE_STOPPED, STOP_TIMEOUT) {
+                        log_line!("[Services] {} still stopping after {}s, continuing", name, STOP_TIMEOUT.as_secs());
+                    }
+                    accepted
                 } else {
                     false
                 };
@@ -72,40 +129,117 @@ impl WindowsServiceManager {
         }
     }
 
+    /// Whether a service is running; None if it doesn't exist or can't be queried
+    pub fn is_running(name: &str) -> Option<bool> {
+        unsafe {
+            let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT).ok()?;
+            let name_w = HSTRING::from(name);
+            let running = match OpenServiceW(scm, PCWSTR(name_w.as_ptr()), SERVICE_QUERY_STATUS) {
+                Ok(service) => {
+                    let mut status = SERVICE_STATUS::default();
+                    let running = QueryServiceStatus(service, &mut status).is_ok()
+                        .then(|| status.dwCurrentState == SERVICE_RUNNING);
+                    let _ = CloseServiceHandle(service);
+                    running
+                }
+                Err(_) => None,
+            };
+            let _ = CloseServiceHandle(scm);
+            running
+        }
+    }
+
     /// Restore services - Parallel
-    pub fn restore_services(service_names: &[String]) {
+    /// Returns the services that did not come back; they are logged and raised as a toast,
+    /// since a silently missing WSearch or wuauserv is only noticed much later
+    pub fn restore_services<S: ServiceControl + Sync + ?Sized>(services: &S, service_names: &[String]) -> Vec<String> {
+        let failed = Mutex::new(Vec::new());
+        
         thread::scope(|s| {
             for name in service_names {
+                let failed_ref = &failed;
+                
                 s.spawn(move || {
-                    Self::start_single_service(name);
+                    if !services.start(name) {
+                        if let Ok(mut guard) = failed_ref.lock() {
+                            guard.push(name.clone());
+                        }
+                    }
                 });
             }
         });
+        
+        let failed = failed.into_inner().unwrap_or_default();
+        if !failed.is_empty() {
+            log_line!("[Services] Not restarted: {}", failed.join(", "));
+            notify::attention(
+                &tr!("Services not restored"),
+                &tr!("{} could not be restarted. Start them from services.msc or restart the PC.", failed.join(", ")),
+            );
+        }
+        failed
     }
 
-    /// Start a single service
+    /// Start a single service - returns true once it is confirmed running
+    /// Retried a few times, since a dependency restored in parallel may still be starting
     #[inline]
-    fn start_single_service(name: &str) {
+    pub(crate) fn start_single_service(name: &str) -> bool {
         unsafe {
-            let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else { return };
+            let Ok(scm) = OpenSCManagerW(None, None, SC_MANAGER_CONNECT) else { return false };
             
             let name_w = HSTRING::from(name);
-            if let Ok(service) = OpenServiceW(
+            let result = if let Ok(service) = OpenServiceW(
                 scm, 
                 PCWSTR(name_w.as_ptr()), 
                 SERVICE_START | SERVICE_QUERY_STATUS
             ) {
-                let mut status = SERVICE_STATUS::default();
-                if QueryServiceStatus(service, &mut status).is_ok() {
-                    // SERVICE_STOPPED = 1
-                    if status.dwCurrentState.0 == 1 {
-                        let _ = StartServiceW(service, None);
+                let mut running = false;
+                for attempt in 1..=START_ATTEMPTS {
+                    let mut status = SERVICE_STATUS::default();
+                    if QueryServiceStatus(service, &mut status).is_err() {
+                        break;
+                    }
+                    if status.dwCurrentState == SERVICE_STOPPED {
+                        if let Err(e) = StartServiceW(service, None) {
+                            log_line!("[Services] {} start attempt {} failed: {}", name, attempt, e.message());
+                        }
+                    }
+                    // Also waits out a START_PENDING left by someone else
+                    if Self::wait_for_state(service, SERVICE_RUNNING, START_TIMEOUT) {
+                        running = true;
+                        break;
+                    }
+                    if attempt < START_ATTEMPTS {
+                        thread::sleep(RETRY_DELAY);
                     }
                 }
                 let _ = CloseServiceHandle(service);
-            }
+                running
+            } else {
+                false
+            };
             
             let _ = CloseServiceHandle(scm);
+            result
+        }
+    }
+
+    /// Poll until the service leaves its pending state; true if it ends up in `target`
+    /// The interval follows the service's wait hint (a tenth of it, 100 ms - 1 s) as the SCM docs suggest
+    fn wait_for_state(service: SC_HANDLE, target: SERVICE_STATUS_CURRENT_STATE, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut status = SERVICE_STATUS::default();
+            if unsafe { QueryServiceStatus(service, &mut status) }.is_err() {
+                return false;
+            }
+            // START_PENDING = 2, STOP_PENDING = 3, CONTINUE_PENDING = 5, PAUSE_PENDING = 6
+            let pending = matches!(status.dwCurrentState.0, 2 | 3 | 5 | 6);
+            if status.dwCurrentState == target || !pending || Instant::now() >= deadline {
+                return status.dwCurrentState == target;
+            }
+            let interval = (status.dwWaitHint / 10).clamp(100, 1000);
+            thread::sleep(Duration::from_millis(interval as u64));
         }
     }
 }
@@ -0,0 +1,69 @@
+//! Registry Write Audit
+//! Values are read back after every write; anything that didn't stick (Group Policy,
+//! Tamper Protection, key permissions) goes to the session log and the status page
+//! instead of being assumed applied
+
+use std::fmt::Display;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use crate::log_line;
+
+/// Writes that read back differently, since the current session started
+static MISMATCHES: Lazy<Mutex<Vec<WriteMismatch>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// A value that holds something else than what was written
+#[derive(Debug, Clone)]
+pub struct WriteMismatch {
+    pub hive: &'static str,
+    pub path: String,
+    pub name: String,
+    pub expected: String,
+    /// None = value absent or unreadable
+    pub actual: Option<String>,
+}
+
+impl WriteMismatch {
+    /// "HKLM\...\Value = 1 (wrote 0)"
+    pub fn describe(&self) -> String {
+        format!(
+            "{}\\{}\\{} = {} (wrote {})",
+            self.hive,
+            self.path,
+            self.name,
+            self.actual.as_deref().unwrap_or("not set"),
+            self.expected
+        )
+    }
+}
+
+/// Compare a read-back with what was written; records and logs a mismatch, true if it stuck
+pub fn check<T: PartialEq + Display>(hive: &'static str, path: &str, name: &str, written: T, actual: Option<T>) -> bool {
+    if actual.as_ref() == Some(&written) {
+        return true;
+    }
+
+    let mismatch = WriteMismatch {
+        hive,
+        path: path.to_string(),
+        name: name.to_string(),
+        expected: written.to_string(),
+        actual: actual.map(|v| v.to_string()),
+    };
+    log_line!("[Audit] Write did not stick: {}", mismatch.describe());
+
+    // Keep only the latest attempt per value
+    let mut mismatches = MISMATCHES.lock().unwrap();
+    mismatches.retain(|m| !(m.hive == hive && m.path.eq_ignore_ascii_case(path) && m.name.eq_ignore_ascii_case(name)));
+    mismatches.push(mismatch);
+    false
+}
+
+/// Mismatches recorded since the current (or last) session started
+pub fn mismatches() -> Vec<WriteMismatch> {
+    MISMATCHES.lock().unwrap().clone()
+}
+
+/// Forget earlier mismatches (called when a session starts)
+pub fn clear() {
+    MISMATCHES.lock().unwrap().clear();
+}